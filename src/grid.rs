@@ -0,0 +1,52 @@
+//! Grid math on the tiles of a world, shared by the GUI and the code using ragnarok.
+//!
+//! Tiles are addressed as `(row, col)`, like the robot's coordinates and `WorldSnapshot::world`.
+//! The robot moves in four directions, so the number of moves between two tiles is their
+//! manhattan distance, while the chebyshev distance tells whether a tile is within a square
+//! radius (e.g. the 3x3 area the robot sees around itself).
+//!
+//! ```
+//! use ragnarok::grid;
+//! assert_eq!(grid::manhattan_distance((1, 2), (4, 0)), 5);
+//! assert_eq!(grid::chebyshev_distance((1, 2), (4, 0)), 3);
+//! assert_eq!(grid::neighbors((0, 0), 10).count(), 2);
+//! ```
+
+/// Number of moves between the two tiles when moving in four directions.
+pub fn manhattan_distance(a: (usize, usize), b: (usize, usize)) -> usize {
+    a.0.abs_diff(b.0) + a.1.abs_diff(b.1)
+}
+
+/// Number of moves between the two tiles when moving in eight directions, i.e. the radius of the
+/// smallest square around one which contains the other.
+pub fn chebyshev_distance(a: (usize, usize), b: (usize, usize)) -> usize {
+    a.0.abs_diff(b.0).max(a.1.abs_diff(b.1))
+}
+
+/// Straight line distance between the centers of the two tiles.
+pub fn euclidean_distance(a: (usize, usize), b: (usize, usize)) -> f32 {
+    let (rows, cols) = (a.0.abs_diff(b.0) as f32, a.1.abs_diff(b.1) as f32);
+    (rows * rows + cols * cols).sqrt()
+}
+
+/// The tiles up, down, left and right of the given one which are in a world of the given size.
+pub fn neighbors(tile: (usize, usize), world_size: usize) -> impl Iterator<Item = (usize, usize)> {
+    let (row, col) = tile;
+    [row.checked_sub(1).map(|row| (row, col)), Some((row + 1, col)), col.checked_sub(1).map(|col| (row, col)), Some((row, col + 1))]
+        .into_iter()
+        .flatten()
+        .filter(move |(row, col)| *row < world_size && *col < world_size)
+}
+
+/// The tiles around the given one, diagonals included, which are in a world of the given size.
+pub fn neighbors_with_diagonals(tile: (usize, usize), world_size: usize) -> impl Iterator<Item = (usize, usize)> {
+    let (row, col) = tile;
+    (row.saturating_sub(1)..=row + 1)
+        .flat_map(move |r| (col.saturating_sub(1)..=col + 1).map(move |c| (r, c)))
+        .filter(move |neighbor| *neighbor != tile && neighbor.0 < world_size && neighbor.1 < world_size)
+}
+
+/// Whether the tile is in a world of the given size.
+pub fn in_bounds(tile: (usize, usize), world_size: usize) -> bool {
+    tile.0 < world_size && tile.1 < world_size
+}
@@ -1,9 +1,30 @@
 mod worker_thread;
 mod game_runner;
 mod gui_thread;
+mod builder;
+mod event_journal;
+mod thread_health;
+mod replay_player;
+mod snapshot_history;
+mod world_preview;
+mod marker_style;
+mod observer;
+mod breakpoints;
+mod tile_layers;
+mod telemetry;
+mod control_handle;
+mod tick_stats;
+mod run_statistics;
+mod run_summary;
+mod error;
+mod map_merge;
 
 use std::collections::{HashMap, HashSet};
-use std::{sync};
+use std::convert::Infallible;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use std::sync::mpsc::{Receiver, Sender, SyncSender};
+use std::{panic, sync};
 use nalgebra_glm::{UVec2};
 use robotics_lib::runner::{Runnable};
 use robotics_lib::utils::LibError;
@@ -13,6 +34,30 @@ use robotics_lib::world::world_generator::Generator;
 use gui_thread::GuiThread;
 use worker_thread::WorkerThread;
 use game_runner::GameRunner;
+use replay_player::ReplayPlayer;
+use snapshot_history::SnapshotHistory;
+use world_preview::WorldPreview;
+use crate::replay::{self, ReplayError};
+use builder::Config;
+use thread_health::{HealthMonitor, MonitoredThread};
+use event_journal::LoggedEvent;
+use observer::ObserversHandle;
+use breakpoints::Breakpoints;
+use control_handle::ControlRequest;
+use run_statistics::RunStatistics;
+use map_merge::RobotMap;
+pub use builder::GuiRunnerBuilder;
+pub use event_journal::{EventJournalConfig, JournalEntry};
+pub use marker_style::{MarkerIcon, MarkerStyle};
+pub use observer::GuiRunnerObserver;
+pub use tile_layers::TileLayers;
+pub use telemetry::Telemetry;
+pub use control_handle::ControlHandle;
+pub use tick_stats::TickStats;
+pub use run_summary::{ExitReason, RunSummary};
+pub use error::RagnarokError;
+pub use map_merge::MergePolicy;
+pub(crate) use gui_thread::gui::offscreen::OffscreenRenderer;
 
 // GuiRunner handles spawning and joining the GuiThread and the WorkerThread, and hijacks the main
 // thread to use as game thread for GameRunner.
@@ -57,41 +102,192 @@ use game_runner::GameRunner;
 ///}
 /// ```
 pub struct GuiRunner {
-    //necessary for running game loop:
-    game_runner: GameRunner,
+    //necessary for running game loop (or playing back a replay):
+    game: Game,
     //other threads
     worker_thread: WorkerThread,
     gui_thread: GuiThread,
+    control_handle: ControlHandle, // sends to the GUI thread
+}
+// Game is whatever runs on the game thread, feeding PartialWorlds to the worker thread
+enum Game {
+    Live(GameRunner),
+    Replay(ReplayPlayer),
+    Preview(WorldPreview),
 }
 impl GuiRunner {
     /// Constructs a GuiRunner, given a Runnable and a Generator (similarly to `Runner::new`).
     pub fn new(robot: Box<dyn Runnable>, generator: &mut impl Generator) -> Result<GuiRunner, LibError> {
+        Self::with_config(robot, generator, Config::default(), vec![])
+    }
+
+    /// Constructs a GuiRunner which runs several robots round-robin, one tick each per game tick.
+    /// Since robotics_lib gives every robot a world of its own, the Generator is asked for one
+    /// world per robot: the robots only see each other's discoveries in the GUI, where their maps
+    /// are merged (see `MergePolicy`), and that only makes sense if the Generator creates the same
+    /// world every time. The first robot is the one the replay, the event journal, the breakpoints
+    /// and the observers are about; the others are shown as markers and can be selected in the GUI.
+    ///
+    /// Fails with `RagnarokError::NoRobots` if `robots` is empty.
+    pub fn new_multi(robots: Vec<Box<dyn Runnable>>, generator: &mut impl Generator) -> Result<GuiRunner, RagnarokError> {
+        Self::with_config_multi(robots, generator, Config::default(), vec![])
+    }
+
+    /// Returns a GuiRunnerBuilder, which allows constructing a GuiRunner with non-default settings.
+    pub fn builder() -> GuiRunnerBuilder {
+        GuiRunnerBuilder::new()
+    }
+
+    /// Constructs a GuiRunner which plays back a replay (see the `replay` module) instead of
+    /// running the game: no robot or generator is needed, and the run controls of the GUI move
+    /// through the replay (backwards too).
+    pub fn replay(path: impl AsRef<Path>) -> Result<GuiRunner, ReplayError> {
+        Self::replay_with_config(path, Config::default())
+    }
+
+    /// Constructs a GuiRunner which only shows the world created by the given Generator, with
+    /// every tile visible and no robot, so that its output can be inspected with the camera and
+    /// the overlays of the GUI (e.g. while tuning the parameters of a generator).
+    pub fn preview(generator: &mut impl Generator) -> GuiRunner {
+        Self::preview_with_config(generator, Config::default())
+    }
+
+    fn with_config(robot: Box<dyn Runnable>, generator: &mut impl Generator, config: Config, observers: Vec<Box<dyn GuiRunnerObserver>>) -> Result<GuiRunner, LibError> {
+        Self::with_robots(robot, vec![], generator, config, observers)
+    }
+
+    fn with_config_multi(robots: Vec<Box<dyn Runnable>>, generator: &mut impl Generator, config: Config, observers: Vec<Box<dyn GuiRunnerObserver>>) -> Result<GuiRunner, RagnarokError> {
+        let mut robots = robots.into_iter();
+        let robot = robots.next().ok_or(RagnarokError::NoRobots)?;
+        Ok(Self::with_robots(robot, robots.collect(), generator, config, observers)?)
+    }
+
+    fn with_robots(robot: Box<dyn Runnable>, other_robots: Vec<Box<dyn Runnable>>, generator: &mut impl Generator, config: Config, observers: Vec<Box<dyn GuiRunnerObserver>>) -> Result<GuiRunner, LibError> {
+        let observers = ObserversHandle::new(observers.into());
+        Self::with_game(config, |game_to_worker_tx, gui_to_game_rx, event_log_tx, breakpoints, config, health| {
+            GameRunner::new(robot, other_robots, generator, game_to_worker_tx, gui_to_game_rx, event_log_tx, observers, breakpoints, config, health).map(Game::Live)
+        })
+    }
+
+    fn replay_with_config(path: impl AsRef<Path>, config: Config) -> Result<GuiRunner, ReplayError> {
+        // the snapshots are compressed on a background thread while the rest of the file is read
+        let history = SnapshotHistory::new(config.history_memory_budget);
+        let replay = replay::load_streaming(&path, |snapshot| history.push(snapshot))?;
+        Self::with_game(config, |game_to_worker_tx, gui_to_game_rx, _event_log_tx, _breakpoints, _config, health| {
+            Ok(Game::Replay(ReplayPlayer::new(path.as_ref().to_path_buf(), history, replay.annotations, game_to_worker_tx, gui_to_game_rx, health)))
+        })
+    }
+
+    fn preview_with_config(generator: &mut impl Generator, config: Config) -> GuiRunner {
+        let gui_runner = Self::with_game::<Infallible>(config, |game_to_worker_tx, gui_to_game_rx, _event_log_tx, _breakpoints, _config, health| {
+            Ok(Game::Preview(WorldPreview::new(generator, game_to_worker_tx, gui_to_game_rx, health)))
+        });
+        match gui_runner {
+            Ok(gui_runner) => gui_runner,
+            Err(never) => match never {},
+        }
+    }
+
+    fn with_game<E>(config: Config, make_game: impl FnOnce(SyncSender<PartialWorld>, Receiver<RunModeChange>, Sender<LoggedEvent>, Breakpoints, &Config, HealthMonitor) -> Result<Game, E>) -> Result<GuiRunner, E> {
         // we only allow 1 PartialWorld to be queued between in the game->worker channel to avoid
         // having world information become more and more dated as the execution goes, rather
         // discarding some messages (skipping world versions when the game is going really fast
         // from one to the other)
         let (game_to_worker_tx, game_to_worker_rx) = sync::mpsc::sync_channel::<PartialWorld>(1);
         let (worker_to_gui_tx, worker_to_gui_rx) = sync::mpsc::channel::<PartialWorld>();
-        let (gui_to_game_tx, gui_to_game_rx) = sync::mpsc::channel::<RunMode>();
+        let (gui_to_game_tx, gui_to_game_rx) = sync::mpsc::channel::<RunModeChange>();
+        // the events received by the robot go straight to the GUI's event log, skipping the worker
+        let (event_log_tx, event_log_rx) = sync::mpsc::channel::<LoggedEvent>();
+        // the run controls requested from outside go through the GUI, like those of its window
+        let (control_tx, control_rx) = sync::mpsc::channel::<ControlRequest>();
+
+        // every thread reports its heartbeats to the same monitor, so that a stalled or dead
+        // thread can be noticed and reported by the others
+        let health = HealthMonitor::new();
+        // the breakpoints are edited in the GUI and checked by the game
+        let breakpoints = Breakpoints::default();
+
+        let game = make_game(game_to_worker_tx, gui_to_game_rx, event_log_tx, breakpoints.clone(), &config, health.clone())?;
+        let replay_info = match &game {
+            Game::Live(_) | Game::Preview(_) => None,
+            Game::Replay(replay_player) => Some(replay_player.info()),
+        };
+        let is_preview = matches!(game, Game::Preview(_));
+        let true_world = match &game {
+            Game::Live(game_runner) => game_runner.true_world(),
+            Game::Replay(_) | Game::Preview(_) => None,
+        };
 
-        let game_runner = GameRunner::new(robot, generator, game_to_worker_tx, gui_to_game_rx)?;
+        // the worker thread keeps the history of a live run, which the GUI can rewind through
+        let rewind_history = (matches!(game, Game::Live(_)) && config.rewind_memory_budget > 0)
+            .then(|| SnapshotHistory::new(config.rewind_memory_budget));
 
-        let worker_thread = WorkerThread::new(game_to_worker_rx, worker_to_gui_tx);
-        let gui_thread = GuiThread::new(worker_to_gui_rx, gui_to_game_tx);
-        Ok(Self { game_runner, worker_thread, gui_thread })
+        // the worker thread accumulates the statistics of the run, which the GUI charts
+        let statistics = RunStatistics::new();
+
+        let worker_thread = WorkerThread::new(game_to_worker_rx, worker_to_gui_tx, config.vicinity_refresh_radius, health.clone(), rewind_history.clone(), statistics.clone(), config.map_merge.clone());
+        let gui_thread = GuiThread::new(worker_to_gui_rx, gui_to_game_tx, event_log_rx, breakpoints, config, health, replay_info, is_preview, true_world, rewind_history, statistics, control_rx);
+        Ok(Self { game, worker_thread, gui_thread, control_handle: ControlHandle::new(control_tx) })
+    }
+
+    /// Returns a handle which pauses, resumes and terminates the run, or changes its tick rate,
+    /// as the run controls of the GUI would. It can be cloned and sent to other threads before
+    /// calling `run`.
+    pub fn control_handle(&self) -> ControlHandle {
+        self.control_handle.clone()
     }
 
     /// Starts the game loop and the GUI, which will run on different threads. Consumes GuiRunner
-    /// and only returns when the user closes the window.
-    pub fn run(self) -> Result<(), LibError> {
+    /// and only returns when the user closes the window (or the run is terminated through a
+    /// `ControlHandle`), with a summary of how the run ended. A tick failing, the window failing to
+    /// open, or the GUI being closed from its diagnostics while a tick was stalled, is returned as
+    /// an error once the window is closed and the game stopped.
+    pub fn run(self) -> Result<RunSummary, RagnarokError> {
+        let started = Instant::now();
         let worker_thread_handle = self.worker_thread.start();
         let gui_thread_handle = self.gui_thread.start();
 
-        self.game_runner.run();
+        // if the game panics let the user see it in the GUI's diagnostics panel before unwinding
+        let game_result = panic::catch_unwind(panic::AssertUnwindSafe(|| match self.game {
+            Game::Live(game_runner) => game_runner.run(),
+            Game::Replay(replay_player) => Ok(replay_player.run()),
+            Game::Preview(world_preview) => Ok(world_preview.run()),
+        }));
 
-        gui_thread_handle.join().expect("failed to join GUI thread");
-        worker_thread_handle.join().expect("failed to join worker thread");
-        Ok(())
+        // the game stops when the GUI does (or is gone), and the worker thread when the game does
+        let gui_result = gui_thread_handle.join();
+        let worker_result = worker_thread_handle.join();
+
+        let mut summary = match game_result {
+            Ok(game_result) => game_result,
+            Err(panic_payload) => panic::resume_unwind(panic_payload),
+        };
+        // the GUI failing to start is why the game stopped, if it did
+        gui_result.map_err(|_| RagnarokError::ThreadPanicked(MonitoredThread::Gui.name()))??;
+        worker_result.map_err(|_| RagnarokError::ThreadPanicked(MonitoredThread::Worker.name()))?;
+        if let Ok(summary) = &mut summary {
+            summary.wall_time = started.elapsed();
+        }
+        summary
+    }
+
+    /// Runs the game for the given number of ticks as fast as possible without opening a window,
+    /// which is useful for testing or benchmarking a robot where no display is available. The
+    /// event journal, if enabled, is still written. Does nothing when playing back a replay or
+    /// previewing a world, returning an empty summary.
+    pub fn run_headless(self, ticks: usize) -> Result<RunSummary, RagnarokError> {
+        let started = Instant::now();
+        // dropping the other threads before starting them closes their channels: the robot wrapper
+        // ignores failed sends, so the game runs exactly as it would with the GUI open
+        let Self { game, worker_thread, gui_thread, .. } = self;
+        drop((worker_thread, gui_thread));
+
+        let mut summary = match game {
+            Game::Live(game_runner) => game_runner.run_ticks(ticks)?,
+            Game::Replay(_) | Game::Preview(_) => RunSummary::new(0, 0, HashMap::new(), ExitReason::TicksCompleted),
+        };
+        summary.wall_time = started.elapsed();
+        Ok(summary)
     }
 }
 
@@ -101,25 +297,96 @@ impl GuiRunner {
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub(crate) enum RunMode {
     SingleTick,
+    RunTicks(u32), // run the given number of ticks as fast as possible, then pause
     Continuous(Option<f32>), // if Some it indicates the number of ticks per second the game will be played at
     Paused,
     Terminate,
+    // only meaningful when playing back a replay:
+    StepBack,
+    Seek(usize), // jump to the given tick
+}
+
+// RunModeChange is what the gui->game channel carries: the RunMode requested, along with what
+// requested it and the tick and robot position the GUI was showing at the time, so that the game
+// thread can write the change to the event journal.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RunModeChange {
+    pub run_mode: RunMode,
+    pub source: RunModeSource,
+    pub tick: usize,
+    pub robot_position: UVec2,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum RunModeSource {
+    Key,
+    Gui,
+    AutoPause, // e.g. at the end of a replay
+    InputPlayback, // a recording of the user's input being played back
+    Breakpoint, // the game paused by itself, the GUI follows
+    RobotCrash, // likewise, because the robot panicked
+    WindowClosed,
+    ControlHandle, // requested through a ControlHandle
+}
+impl RunModeSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RunModeSource::Key => "key",
+            RunModeSource::Gui => "GUI",
+            RunModeSource::AutoPause => "auto-pause",
+            RunModeSource::InputPlayback => "input playback",
+            RunModeSource::Breakpoint => "breakpoint",
+            RunModeSource::RobotCrash => "robot crash",
+            RunModeSource::WindowClosed => "window closed",
+            RunModeSource::ControlHandle => "control handle",
+        }
+    }
 }
 
 // PartialWorld contains the partial world information available to the robot, including information
 // about discovered tiles, the robot itself and the environmental conditions. it also includes the
 // tiles_to_refresh field to simplify the job of the gui thread, which can avoid wasting computing
-// resources to refresh all other tiles.
+// resources to refresh all other tiles, and what the game and worker threads found out about the
+// tick, which narrows down where to look for changes.
 // It will be sent through channels between different threads: the game thread will send the raw
 // information to the worker thread, which will compute tiles_to_refresh (tiles whose vertices need
-// to be created or updated) and send that information, along with what it received from the game
-// thread to the gui thread.
+// to be created or updated), changed_tiles and content_changes, and send that information, along
+// with what it received from the game thread to the gui thread.
 #[derive(Clone)]
 pub(crate) struct PartialWorld {
-    pub world: Vec<Vec<Option<Tile>>>,
+    pub world: Vec<Vec<Option<Tile>>>, // of the first robot; the worker thread merges other_maps into it (see MapMerger)
     pub tiles_to_refresh: HashSet<UVec2>,
+    pub distant_changes: Vec<UVec2>, // changes signaled by events far from the robot (e.g. teleports), refreshed with a wider radius
+    pub touched_tiles: Option<Vec<UVec2>>, // where the events of the tick happened; None (e.g. in replays) diffs the whole map
+    pub stale_tiles: Vec<UVec2>, // discovered tiles which changed since the robot saw them, only known with the god view
+    pub tick: usize,
+    pub elapsed: Duration, // wall time since the game started, zero in replays and previews
     pub robot_position: UVec2,
     pub energy: usize,
     pub backpack: HashMap<Content, usize>,
+    pub backpack_size: Option<usize>, // None in replays, snapshots and previews, which don't record it
     pub env_cond: EnvironmentalConditions,
+    pub other_robots: Vec<RobotState>, // every robot but the first, empty when only one runs
+    pub other_maps: Vec<RobotMap>, // of the robots in other_robots, emptied by the worker thread once merged into world
+    pub changed_tiles: Vec<UVec2>, // since the previous world, filled by the worker thread
+    pub content_changes: Vec<(UVec2, ContentChange)>, // the known tiles among changed_tiles whose content changed
+}
+
+// ContentChange is the cause the worker thread attributes to the change of the content of a tile,
+// see PartialWorld::content_changes: a robot, or the world making a content appear (or replacing
+// it) or disappear on a tile no robot was next to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ContentChange {
+    ByRobot,
+    Respawned,
+    Decayed,
+}
+
+// RobotState is what PartialWorld carries about each of the robots after the first
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RobotState {
+    pub position: UVec2,
+    pub energy: usize,
+    pub backpack: HashMap<Content, usize>,
+    pub backpack_size: usize,
 }
@@ -0,0 +1,141 @@
+use std::sync::{Arc, Mutex};
+use nalgebra_glm::UVec2;
+use robotics_lib::event::events::Event;
+use robotics_lib::world::tile::Content;
+use super::PartialWorld;
+
+// Breakpoints is shared (cloned) between the GUI thread, where the user edits the breakpoints, and
+// the game thread, where the robot wrapper checks them against every event and at the end of every
+// tick. When one is hit the GameRunner pauses the game by itself (so that not even one more tick
+// runs, however fast the game is going), and the GUI is told through the list of hits, which it
+// shows and uses to sync its run mode.
+// The backpack breakpoints only trigger when their condition becomes true, not at every tick it
+// stays true, so that the game can be resumed.
+// Events and contents are identified by the name of their variant (the same as the kind of the
+// journal entries), so that their payload doesn't matter.
+// The robot wrappers also report the panics of the robots through here (see RobotCrash): the game
+// pauses the same way, and the GUI shows the crash.
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Breakpoint {
+    EnterTile(UVec2),
+    Event(String), // kind of event
+    BackpackAtLeast { content: String, amount: usize },
+}
+impl Breakpoint {
+    pub fn describe(&self) -> String {
+        match self {
+            Breakpoint::EnterTile(tile) => format!("robot enters ({}, {})", tile.x, tile.y),
+            Breakpoint::Event(kind) => format!("event {kind}"),
+            Breakpoint::BackpackAtLeast { content, amount } => format!("backpack contains {content} >= {amount}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct BreakpointHit {
+    pub tick: usize,
+    pub robot_position: UVec2,
+    pub description: String,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct RobotCrash {
+    pub tick: usize,
+    pub robot: usize, // 0 for the first robot, i + 1 for the other robot i
+    pub robot_position: UVec2,
+    pub message: String,
+}
+
+#[derive(Default)]
+struct Shared {
+    breakpoints: Vec<Breakpoint>,
+    was_satisfied: Vec<bool>, // whether the condition of each breakpoint held at the last tick
+    pause_requested: bool, // for the game thread
+    hits: Vec<BreakpointHit>, // for the GUI thread
+    crash: Option<RobotCrash>, // likewise, the last one
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Breakpoints {
+    shared: Arc<Mutex<Shared>>,
+}
+impl Breakpoints {
+    // replaces the breakpoints being checked
+    pub fn set(&self, breakpoints: Vec<Breakpoint>) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.was_satisfied = vec![false; breakpoints.len()];
+        shared.breakpoints = breakpoints;
+    }
+
+    pub fn check_event(&self, tick: usize, robot_position: UVec2, event: &Event) {
+        let mut shared = self.shared.lock().unwrap();
+        if shared.breakpoints.is_empty() {
+            return;
+        }
+        let kind = variant_name(event);
+        let hit = shared.breakpoints.iter().find(|breakpoint| match breakpoint {
+            Breakpoint::Event(k) => *k == kind,
+            Breakpoint::EnterTile(tile) => matches!(event, Event::Moved(_, (x, y)) if UVec2::new(*x as u32, *y as u32) == *tile),
+            Breakpoint::BackpackAtLeast { .. } => false,
+        });
+        if let Some(breakpoint) = hit {
+            let description = format!("{} ({event:?})", breakpoint.describe());
+            shared.hit(BreakpointHit { tick, robot_position, description });
+        }
+    }
+
+    // checks the state at the end of a tick
+    pub fn check_world(&self, world: &PartialWorld) {
+        let mut shared = self.shared.lock().unwrap();
+        let satisfied: Vec<bool> = shared.breakpoints.iter().map(|breakpoint| match breakpoint {
+            Breakpoint::BackpackAtLeast { content, amount } => {
+                let total: usize = world.backpack.iter()
+                    .filter(|(c, _)| variant_name::<Content>(c) == *content)
+                    .map(|(_, n)| *n)
+                    .sum();
+                total >= *amount
+            }
+            Breakpoint::EnterTile(_) | Breakpoint::Event(_) => false,
+        }).collect();
+        let hit = (0..satisfied.len()).find(|i| satisfied[*i] && !shared.was_satisfied[*i]);
+        shared.was_satisfied = satisfied;
+        if let Some(i) = hit {
+            let description = shared.breakpoints[i].describe();
+            shared.hit(BreakpointHit { tick: world.tick, robot_position: world.robot_position, description });
+        }
+    }
+
+    pub fn report_crash(&self, crash: RobotCrash) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.pause_requested = true;
+        shared.crash = Some(crash);
+    }
+
+    // whether a breakpoint was hit (or a robot crashed) since the last call; for the game thread
+    pub fn take_pause_request(&self) -> bool {
+        std::mem::take(&mut self.shared.lock().unwrap().pause_requested)
+    }
+
+    // the breakpoints hit since the last call; for the GUI thread
+    pub fn take_hits(&self) -> Vec<BreakpointHit> {
+        std::mem::take(&mut self.shared.lock().unwrap().hits)
+    }
+
+    // the last robot crash since the last call; for the GUI thread
+    pub fn take_crash(&self) -> Option<RobotCrash> {
+        self.shared.lock().unwrap().crash.take()
+    }
+}
+impl Shared {
+    fn hit(&mut self, hit: BreakpointHit) {
+        self.pause_requested = true;
+        self.hits.push(hit);
+    }
+}
+
+// name of the variant of an event or a content, from its Debug representation
+fn variant_name<T: std::fmt::Debug>(value: &T) -> String {
+    let debug = format!("{value:?}");
+    debug.split(|c: char| c == '(' || c == ' ' || c == '{').next().unwrap_or_default().to_string()
+}
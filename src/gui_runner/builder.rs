@@ -0,0 +1,300 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use robotics_lib::runner::Runnable;
+use robotics_lib::utils::LibError;
+use robotics_lib::world::world_generator::Generator;
+use super::{GuiRunner, EventJournalConfig, GuiRunnerObserver, MarkerStyle, RagnarokError, Telemetry, TickStats, TileLayers};
+use super::tick_stats::StatsObserver;
+use super::map_merge::{MapMerge, MergePolicy};
+use crate::replay::ReplayError;
+
+// GuiRunnerBuilder collects the optional settings of a GuiRunner into a Config, which is then
+// handed (or cloned) to whichever thread needs it when the GuiRunner is built. The observers are
+// kept apart from the Config, since they can't be cloned and only ever live on the game thread.
+
+#[derive(Clone)]
+pub(crate) struct Config {
+    pub event_journal: Option<EventJournalConfig>,
+    pub log_run_mode_changes: bool,
+    pub replay_path: Option<PathBuf>,
+    pub ghost_replay: Option<PathBuf>,
+    pub god_view: bool,
+    pub robot_style: MarkerStyle,
+    pub other_robot_styles: Vec<MarkerStyle>, // of the robots after the first, in their order
+    pub tile_layers: Option<TileLayers>,
+    pub telemetry: Option<Telemetry>,
+    pub key_bindings: Option<PathBuf>,
+    pub ghost_style: MarkerStyle,
+    pub history_memory_budget: usize,
+    pub rewind_memory_budget: usize,
+    pub stall_timeout: Duration,
+    pub tick_timeout: Duration,
+    pub stuck_warning_ticks: usize,
+    pub vicinity_refresh_radius: u32,
+    pub near_plane: f32,
+    pub far_plane: f32,
+    pub logarithmic_depth: bool,
+    pub fonts: Vec<PathBuf>,
+    pub autosave_interval: Duration,
+    pub map_merge: MapMerge, // shared between the worker thread and the GUI, which sets it
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            event_journal: None,
+            log_run_mode_changes: true,
+            replay_path: None,
+            ghost_replay: None,
+            god_view: false,
+            robot_style: MarkerStyle::robot(),
+            other_robot_styles: vec![],
+            tile_layers: None,
+            telemetry: None,
+            key_bindings: None,
+            ghost_style: MarkerStyle::ghost(),
+            history_memory_budget: 1024 * 1024 * 1024,
+            rewind_memory_budget: 256 * 1024 * 1024,
+            stall_timeout: Duration::from_secs(5),
+            tick_timeout: Duration::from_secs(2),
+            stuck_warning_ticks: 100,
+            vicinity_refresh_radius: 1,
+            near_plane: 1.0 / 32.0,
+            far_plane: 8192.0,
+            logarithmic_depth: true,
+            fonts: vec![],
+            autosave_interval: Duration::from_secs(5 * 60),
+            map_merge: MapMerge::default(),
+        }
+    }
+}
+
+/// Builds a GuiRunner with non-default settings. Obtained through `GuiRunner::builder`.
+///
+/// ```no_run
+///# fn f(robot: Box<dyn robotics_lib::runner::Runnable>, world_generator: &mut impl robotics_lib::world::world_generator::Generator) {
+/// let gui_runner = ragnarok::GuiRunner::builder()
+///     .event_journal(ragnarok::EventJournalConfig::new("events.jsonl"))
+///     .build(robot, world_generator)
+///     .unwrap();
+///# }
+/// ```
+#[derive(Default)]
+pub struct GuiRunnerBuilder {
+    config: Config,
+    observers: Vec<Box<dyn GuiRunnerObserver>>,
+}
+impl GuiRunnerBuilder {
+    /// Equivalent to `GuiRunner::builder`.
+    pub fn new() -> Self { Self::default() }
+
+    /// Streams every event received by the robot to an append-only journal file.
+    pub fn event_journal(mut self, journal: EventJournalConfig) -> Self {
+        self.config.event_journal = Some(journal);
+        self
+    }
+
+    /// Whether the changes of run mode (pausing, resuming, running single ticks, ...) are written
+    /// to the event journal along with what requested them. Enabled by default.
+    pub fn log_run_mode_changes(mut self, enable: bool) -> Self {
+        self.config.log_run_mode_changes = enable;
+        self
+    }
+
+    /// Registers an observer, which is called with every tick and event of the game (see
+    /// `GuiRunnerObserver`). Observers are called in the order they were registered.
+    pub fn observer(mut self, observer: impl GuiRunnerObserver + 'static) -> Self {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    /// Registers a callback which is called at the end of every tick with what changed for the
+    /// robot during it (see `TickStats`), to feed the run into other analysis tools. It is called
+    /// on the game thread like the observers (see `GuiRunnerObserver`), so it should return quickly.
+    pub fn on_stats(self, callback: impl FnMut(TickStats) + 'static) -> Self {
+        self.observer(StatsObserver::new(callback))
+    }
+
+    /// Records the run to a replay file (see the `replay` module), which can be loaded later with
+    /// `ragnarok::replay::load`.
+    pub fn record_replay(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.replay_path = Some(path.into());
+        self
+    }
+
+    /// Shows the robot of a previously recorded replay as a translucent ghost next to the live
+    /// robot, tick by tick. The replay can also be loaded from the GUI.
+    pub fn ghost_replay(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.ghost_replay = Some(path.into());
+        self
+    }
+
+    /// Initial color, icon and label of the marker (and trail) of the robot, which can also be
+    /// edited from the GUI. Defaults to `MarkerStyle::robot()`.
+    pub fn robot_marker_style(mut self, style: MarkerStyle) -> Self {
+        self.config.robot_style = style;
+        self
+    }
+
+    /// Initial styles of the markers of the robots of a run with several robots (see
+    /// `GuiRunner::new_multi`), one per robot in the same order, the first replacing the one set by
+    /// `robot_marker_style`. They can also be edited from the GUI. The robots left without one get
+    /// a diamond labelled after the marker of the first robot.
+    pub fn robot_marker_styles(mut self, styles: Vec<MarkerStyle>) -> Self {
+        let mut styles = styles.into_iter();
+        if let Some(style) = styles.next() {
+            self.config.robot_style = style;
+        }
+        self.config.other_robot_styles = styles.collect();
+        self
+    }
+
+    /// Initial color, icon and label of the marker (and trail) of the ghost replay, which can also
+    /// be edited from the GUI. Defaults to `MarkerStyle::ghost()`.
+    pub fn ghost_marker_style(mut self, style: MarkerStyle) -> Self {
+        self.config.ghost_style = style;
+        self
+    }
+
+    /// Keeps a copy of the world created by the Generator, which allows enabling the "god view" in
+    /// the GUI: the whole world is shown dimmed beneath the tiles discovered by the robot. The
+    /// view itself is off until enabled from the GUI. Disabled by default.
+    pub fn god_view(mut self, enable: bool) -> Self {
+        self.config.god_view = enable;
+        self
+    }
+
+    /// Per-tile values (e.g. the cost map of the robot's AI) and directions (e.g. the gradient of
+    /// a potential field) which the GUI can render as heatmaps and arrows, one layer at a time.
+    /// Keep a clone of the TileLayers to update them while the game runs.
+    pub fn tile_layers(mut self, layers: TileLayers) -> Self {
+        self.config.tile_layers = Some(layers);
+        self
+    }
+
+    /// Series of values published by the robot (see `Telemetry`), which the GUI can chart as time
+    /// series, histograms or scatter plots. Keep a clone of the Telemetry to publish values while
+    /// the game runs.
+    pub fn telemetry(mut self, telemetry: Telemetry) -> Self {
+        self.config.telemetry = Some(telemetry);
+        self
+    }
+
+    /// JSON file the key bindings are loaded from at startup, if it exists, and saved to from the
+    /// "Key bindings" window of the GUI. Defaults to `keybindings.json` in the working directory.
+    pub fn key_bindings(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.key_bindings = Some(path.into());
+        self
+    }
+
+    /// Memory (in bytes) the compressed snapshots of a replay being played back may take: when it
+    /// is exceeded the oldest ticks are dropped. It can also be changed from the GUI. Defaults to
+    /// 1 GiB.
+    pub fn history_memory_budget(mut self, bytes: usize) -> Self {
+        self.config.history_memory_budget = bytes;
+        self
+    }
+
+    /// Memory (in bytes) the compressed snapshots of a live run may take, which the GUI can rewind
+    /// through to see the world as it was at an earlier tick: when it is exceeded the oldest ticks
+    /// are dropped. 0 disables rewinding, saving the cost of copying the world at every tick.
+    /// Defaults to 256 MiB.
+    pub fn rewind_memory_budget(mut self, bytes: usize) -> Self {
+        self.config.rewind_memory_budget = bytes;
+        self
+    }
+
+    /// Time without heartbeats after which a thread is reported as stalled in the diagnostics
+    /// panel. Defaults to 5 seconds.
+    pub fn stall_timeout(mut self, stall_timeout: Duration) -> Self {
+        self.config.stall_timeout = stall_timeout;
+        self
+    }
+
+    /// Time a single tick may run for before the diagnostics panel reports it as running for too
+    /// long (which usually means the robot's AI is stuck in a loop), offering to terminate the run
+    /// once the tick returns or to close the GUI at once. The GUI stays responsive meanwhile.
+    /// Defaults to 2 seconds.
+    pub fn tick_timeout(mut self, tick_timeout: Duration) -> Self {
+        self.config.tick_timeout = tick_timeout;
+        self
+    }
+
+    /// Number of ticks after which the GUI warns that the robot appears stuck, if its position,
+    /// energy and backpack didn't change in the meantime while the game was running. It can also be
+    /// changed from the GUI. 0 disables the warning. Defaults to 100.
+    pub fn stuck_warning(mut self, ticks: usize) -> Self {
+        self.config.stuck_warning_ticks = ticks;
+        self
+    }
+
+    /// Radius (in tiles) of the neighbourhood refreshed around every changed tile. The radius is
+    /// automatically widened around changes the robot didn't cause by walking, such as teleports.
+    /// Defaults to 1, which is the minimum needed for the terrain mesh to stay seamless.
+    pub fn vicinity_refresh_radius(mut self, radius: u32) -> Self {
+        self.config.vicinity_refresh_radius = radius.max(1);
+        self
+    }
+
+    /// Distances of the near and far clipping planes of the camera. Defaults to 1/32 and 8192.
+    pub fn depth_planes(mut self, near: f32, far: f32) -> Self {
+        assert!(0.0 < near && near < far, "the planes must satisfy 0 < near < far");
+        self.config.near_plane = near;
+        self.config.far_plane = far;
+        self
+    }
+
+    /// Whether to use a logarithmic depth buffer, which avoids z-fighting on distant terrain at
+    /// the cost of disabling early depth testing. Enabled by default.
+    pub fn logarithmic_depth(mut self, enable: bool) -> Self {
+        self.config.logarithmic_depth = enable;
+        self
+    }
+
+    /// Adds a TTF or OTF font to draw the characters the GUI's bundled font lacks (which covers
+    /// the Latin, Greek and Cyrillic scripts and common symbols), e.g. CJK text in the names of
+    /// markers. The fonts are tried in the order they were added, before the common Unicode and
+    /// emoji fonts of the system. Every glyph of the font is loaded, so large fonts slow down the
+    /// startup and take more video memory.
+    pub fn font(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.fonts.push(path.into());
+        self
+    }
+
+    /// How often the GUI saves a snapshot of the world shown into the configuration directory, to
+    /// be offered back the next time the GUI starts if it crashed. It can also be changed from the
+    /// GUI. `Duration::ZERO` disables autosaving. Defaults to 5 minutes.
+    pub fn autosave_interval(mut self, interval: Duration) -> Self {
+        self.config.autosave_interval = interval;
+        self
+    }
+
+    /// How the maps of the robots are merged into the one shown in the GUI when several run (see
+    /// `GuiRunner::new_multi`), which can also be changed from the GUI. Defaults to
+    /// `MergePolicy::Union`.
+    pub fn map_merge_policy(self, policy: MergePolicy) -> Self {
+        self.config.map_merge.set_policy(policy);
+        self
+    }
+
+    /// Constructs the GuiRunner, given a Runnable and a Generator (similarly to `Runner::new`).
+    pub fn build(self, robot: Box<dyn Runnable>, generator: &mut impl Generator) -> Result<GuiRunner, LibError> {
+        GuiRunner::with_config(robot, generator, self.config, self.observers)
+    }
+
+    /// Constructs a GuiRunner which runs several robots, similarly to `GuiRunner::new_multi`.
+    pub fn build_multi(self, robots: Vec<Box<dyn Runnable>>, generator: &mut impl Generator) -> Result<GuiRunner, RagnarokError> {
+        GuiRunner::with_config_multi(robots, generator, self.config, self.observers)
+    }
+
+    /// Constructs a GuiRunner which plays back a replay, similarly to `GuiRunner::replay`. Settings
+    /// which only affect the game, such as the event journal and the observers, are ignored.
+    pub fn build_replay(self, path: impl AsRef<Path>) -> Result<GuiRunner, ReplayError> {
+        GuiRunner::replay_with_config(path, self.config)
+    }
+
+    /// Constructs a GuiRunner which previews the world created by a Generator, similarly to
+    /// `GuiRunner::preview`.
+    pub fn build_preview(self, generator: &mut impl Generator) -> GuiRunner {
+        GuiRunner::preview_with_config(generator, self.config)
+    }
+}
@@ -0,0 +1,67 @@
+use std::sync::mpsc::Sender;
+
+// ControlHandle sends ControlRequests to the GUI thread, which carries them out as it does the
+// run controls of its window (so that what it shows stays in sync with the run mode), tagging the
+// changes with their own RunModeSource in the event journal. Requests sent while the GUI isn't
+// running (e.g. by a headless run) are dropped.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ControlRequest {
+    Pause,
+    Resume,
+    SetTickCap(Option<f32>), // None for uncapped
+    SingleTick,
+    RunTicks(u32),
+    Terminate,
+}
+
+/// Drives the run from outside the GUI (e.g. from automation or tests) the same way its run
+/// controls do, without simulating keyboard input. Obtained through `GuiRunner::control_handle`;
+/// cloning it gives another handle to the same run, which can be moved to another thread.
+///
+/// ```no_run
+///# fn f(gui_runner: &ragnarok::GuiRunner) {
+/// let control = gui_runner.control_handle();
+/// std::thread::spawn(move || {
+///     control.set_tick_cap(50.0);
+///     control.resume();
+///     std::thread::sleep(std::time::Duration::from_secs(10));
+///     control.request_terminate();
+/// });
+///# }
+/// ```
+#[derive(Clone)]
+pub struct ControlHandle {
+    tx: Sender<ControlRequest>,
+}
+impl ControlHandle {
+    pub(crate) fn new(tx: Sender<ControlRequest>) -> Self {
+        Self { tx }
+    }
+
+    fn send(&self, request: ControlRequest) {
+        // the GUI may have exited already, in which case there is nothing to control
+        let _ = self.tx.send(request);
+    }
+
+    /// Pauses the run, if it is running continuously.
+    pub fn pause(&self) { self.send(ControlRequest::Pause) }
+
+    /// Runs the game continuously, at the current tick cap.
+    pub fn resume(&self) { self.send(ControlRequest::Resume) }
+
+    /// Sets the number of ticks per second the game runs at when running continuously.
+    pub fn set_tick_cap(&self, ticks_per_second: f32) { self.send(ControlRequest::SetTickCap(Some(ticks_per_second))) }
+
+    /// Lets the game run as fast as possible when running continuously.
+    pub fn set_uncapped(&self) { self.send(ControlRequest::SetTickCap(None)) }
+
+    /// Runs a single tick, if the game is paused.
+    pub fn single_tick(&self) { self.send(ControlRequest::SingleTick) }
+
+    /// Runs the given number of ticks as fast as possible and then pauses, if the game is paused.
+    pub fn run_ticks(&self, ticks: u32) { self.send(ControlRequest::RunTicks(ticks)) }
+
+    /// Terminates the run and closes the window, as closing it by hand would.
+    pub fn request_terminate(&self) { self.send(ControlRequest::Terminate) }
+}
@@ -0,0 +1,48 @@
+use std::fmt;
+use robotics_lib::utils::LibError;
+
+// RagnarokError is what GuiRunner::run and GuiRunner::run_headless return when the run could not
+// go on: rather than a thread panicking (and the others waiting on it forever), the thread which
+// fails returns its error, which GuiRunner returns after joining the threads. The failures of the
+// game thread are also reported to the GUI through the HealthMonitor, which shows them in the
+// diagnostics window until the user closes it. It is also what the constructors of runs
+// with several robots return, since those can fail before any tick for lack of robots.
+
+/// Error returned by `GuiRunner::run` and `GuiRunner::run_headless`, and by the constructors of
+/// runs with several robots.
+#[derive(Debug)]
+pub enum RagnarokError {
+    /// A tick of the game failed, or the game could not be created.
+    Lib(LibError),
+    /// A run with several robots was given none.
+    NoRobots,
+    /// The map of a robot (0 is the first) could not be read from its world at the end of a tick.
+    MissingMap(usize),
+    /// The window, or what the GUI draws it with, could not be created (e.g. because no display
+    /// is available).
+    Window(String),
+    /// A thread stopped before the run ended, closing the channel the others were waiting on.
+    Disconnected(&'static str),
+    /// A thread of the GuiRunner panicked.
+    ThreadPanicked(&'static str),
+    /// The user closed the GUI from its diagnostics while the game was stalled inside a tick. It is
+    /// returned once the tick returns, since the game can't be stopped in the middle of a tick.
+    Aborted,
+}
+impl fmt::Display for RagnarokError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lib(e) => write!(f, "the game failed: {e:?}"),
+            Self::NoRobots => write!(f, "no robot was given"),
+            Self::MissingMap(robot) => write!(f, "the map of robot {} could not be read", robot + 1),
+            Self::Window(e) => write!(f, "could not create the window: {e}"),
+            Self::Disconnected(thread) => write!(f, "the {thread} stopped before the run ended"),
+            Self::ThreadPanicked(thread) => write!(f, "the {thread} panicked"),
+            Self::Aborted => write!(f, "the GUI was closed while a tick was stalled"),
+        }
+    }
+}
+impl std::error::Error for RagnarokError {}
+impl From<LibError> for RagnarokError {
+    fn from(e: LibError) -> Self { Self::Lib(e) }
+}
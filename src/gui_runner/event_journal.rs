@@ -0,0 +1,173 @@
+use std::cell::RefCell;
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use nalgebra_glm::UVec2;
+use robotics_lib::event::events::Event;
+use serde::{Deserialize, Serialize};
+use super::{RunMode, RunModeChange};
+
+// EventJournal appends every event received by the robot to a JSON-lines file (one JournalEntry
+// per line). The journal is flushed at the end of every tick, so that it can still be analyzed if
+// the process dies during a very long run. When the file grows past max_file_size it is rotated:
+// "path" is renamed to "path.1", "path.1" to "path.2" and so on, deleting the files beyond
+// max_rotated_files.
+// The journal is appended to across runs, and the ticks of every run start from 0: so that the runs
+// can be told apart, opening the journal writes a "RunStarted" entry whose description holds the
+// run id (the time the run started at, in milliseconds since the Unix epoch). Readers (e.g. the
+// JournalViewer) take the entries following it, up to the next one, as that run's.
+// Besides the events, the journal records the changes of RunMode requested from the GUI (when
+// enabled with GuiRunnerBuilder::log_run_mode_changes), as entries of kind "RunModeChanged"
+// carrying what requested the change and a timestamp. The journal is shared (EventJournalHandle)
+// between the robot wrapper, which writes the events, and the GameRunner, which writes the changes.
+// LoggedEvent is a JournalEntry sent to the GUI's event log through the game->gui channel as soon
+// as the event is received, along with the time elapsed since the start of the run.
+
+/// Configuration of the event journal, an append-only JSON-lines file to which every event
+/// received by the robot is written during the run.
+#[derive(Clone, Debug)]
+pub struct EventJournalConfig {
+    /// Path of the journal file. Rotated files are stored next to it as `path.1`, `path.2`, ...
+    pub path: PathBuf,
+    /// Size in bytes past which the journal is rotated; `None` never rotates it.
+    pub max_file_size: Option<u64>,
+    /// Number of rotated files kept around; older ones are deleted.
+    pub max_rotated_files: usize,
+}
+impl EventJournalConfig {
+    /// Journal at `path`, rotated every 64 MiB keeping the 4 most recent rotated files.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), max_file_size: Some(64 * 1024 * 1024), max_rotated_files: 4 }
+    }
+}
+
+/// A single line of the event journal.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Tick during which the event was received (the initialization tick is tick 0).
+    pub tick: usize,
+    /// Name of the event variant, e.g. `"Moved"` or `"EnergyConsumed"`.
+    pub kind: String,
+    /// Position of the robot when the event was received.
+    pub robot_position: (u32, u32),
+    /// Tile the event refers to, for events which refer to one.
+    pub coordinate: Option<(usize, usize)>,
+    /// Debug representation of the event.
+    pub description: String,
+    /// Wall-clock time at which the entry was written, in milliseconds since the Unix epoch. Only
+    /// set for run mode changes and the start of a run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unix_time_ms: Option<u64>,
+}
+impl JournalEntry {
+    /// Kind of the entry written when a run starts, before the entries of that run.
+    pub const RUN_STARTED: &'static str = "RunStarted";
+
+    pub fn new(tick: usize, robot_position: UVec2, event: &Event) -> Self {
+        let description = format!("{event:?}");
+        let kind = description.split(|c: char| c == '(' || c == ' ').next().unwrap_or_default().to_string();
+        let coordinate = match event {
+            Event::Moved(_, coord) | Event::TileContentUpdated(_, coord) => Some(*coord),
+            _ => None,
+        };
+        Self { tick, kind, robot_position: (robot_position.x, robot_position.y), coordinate, description, unix_time_ms: None }
+    }
+
+    pub(crate) fn run_mode_change(previous: RunMode, change: &RunModeChange) -> Self {
+        let unix_time_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).ok();
+        Self {
+            tick: change.tick,
+            kind: "RunModeChanged".into(),
+            robot_position: (change.robot_position.x, change.robot_position.y),
+            coordinate: None,
+            description: format!("{previous:?} -> {:?} (source: {})", change.run_mode, change.source.name()),
+            unix_time_ms,
+        }
+    }
+
+    // the run id is the time the run started at
+    fn run_started() -> Self {
+        let unix_time_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or_default();
+        Self {
+            tick: 0,
+            kind: Self::RUN_STARTED.into(),
+            robot_position: (0, 0),
+            coordinate: None,
+            description: format!("run {unix_time_ms} started"),
+            unix_time_ms: Some(unix_time_ms),
+        }
+    }
+}
+
+pub(crate) type EventJournalHandle = Rc<RefCell<Option<EventJournal>>>;
+
+pub(crate) struct LoggedEvent {
+    pub elapsed: Duration,
+    pub entry: JournalEntry,
+}
+
+pub struct EventJournal {
+    config: EventJournalConfig,
+    writer: BufWriter<File>,
+    current_file_size: u64,
+}
+impl EventJournal {
+    pub fn open(config: EventJournalConfig) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&config.path)?;
+        let current_file_size = file.metadata()?.len();
+        let mut journal = Self { config, writer: BufWriter::new(file), current_file_size };
+        journal.append(&JournalEntry::run_started())?;
+        journal.flush()?;
+        Ok(journal)
+    }
+
+    pub fn append(&mut self, entry: &JournalEntry) -> io::Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())?;
+        self.current_file_size += line.len() as u64;
+
+        match self.config.max_file_size {
+            Some(max_file_size) if self.current_file_size >= max_file_size => self.rotate(),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+
+        let path = &self.config.path;
+        let n = self.config.max_rotated_files;
+        if n == 0 {
+            fs::remove_file(path)?;
+        } else {
+            let _ = fs::remove_file(rotated_path(path, n)); // it may not exist yet
+            for i in (1..n).rev() {
+                let from = rotated_path(path, i);
+                if from.exists() {
+                    fs::rename(from, rotated_path(path, i + 1))?;
+                }
+            }
+            fs::rename(path, rotated_path(path, 1))?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        self.writer = BufWriter::new(file);
+        self.current_file_size = 0;
+        Ok(())
+    }
+}
+
+pub fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(format!(".{n}"));
+    PathBuf::from(path)
+}
@@ -1,43 +1,132 @@
-use std::sync::mpsc::{Receiver, SyncSender};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::mpsc::{Receiver, Sender, SyncSender, TryRecvError};
 use std::thread;
 use std::time::Duration;
 use robotics_lib::runner::{Runnable, Runner};
 use robotics_lib::utils::LibError;
+use robotics_lib::world::environmental_conditions::EnvironmentalConditions;
+use robotics_lib::world::tile::{Content, Tile};
 use robotics_lib::world::world_generator::Generator;
-use robot_wrapper::RobotWrapper;
-use super::{PartialWorld, RunMode};
+use robot_wrapper::{LastStateHandle, MissingMapHandle, OtherRobotsHandle, Role, RobotWrapper};
+use replay_recorder::ReplayRecorder;
+use true_world::{TrueWorld, TrueWorldHandle};
+use super::{PartialWorld, RunMode, RunModeChange};
+use super::run_summary::{ExitReason, RunSummary};
+use super::error::RagnarokError;
+use super::builder::Config;
+use super::event_journal::{EventJournal, EventJournalHandle, JournalEntry, LoggedEvent};
+use super::observer::ObserversHandle;
+use super::breakpoints::Breakpoints;
+use super::thread_health::{HealthMonitor, MonitoredThread, ThreadStatus};
 
 pub mod robot_wrapper;
+pub mod replay_recorder;
+pub mod true_world;
 
 // GameRunner handles creating the Runner and running it at the correct rate based on the RunMode
-// last received through the gui->game channel
+// last received through the gui->game channel. when the god view is enabled it also keeps a copy
+// of the world created by the generator (see TrueWorld), for the GUI to take. the changes of
+// RunMode it receives are written to the event journal, if enabled. the observers registered on
+// the builder are shared with the robot wrapper, which calls them at every tick, and are told
+// when the game stops. the robot wrapper also checks the breakpoints set in the GUI, and when one
+// is hit the game pauses by itself. when the game stops it returns a RunSummary of the last tick
+// of the first robot, telling why it stopped by the source of the Terminate received. when a tick
+// fails (including when the map of a robot can't be read at its end) the game stops, reporting the
+// error to the GUI (which shows it until it is closed) and returning it.
+// When several robots run, each has a Runner (and a world) of its own: the others are ticked before
+// the first, whose robot wrapper then sends what they all know (see RobotWrapper).
 
 pub struct GameRunner {
     runner: Runner,
-    gui_to_game_rx: Receiver<RunMode>,
+    other_runners: Vec<Runner>,
+    gui_to_game_rx: Receiver<RunModeChange>,
+    health: HealthMonitor,
+    stall_timeout: Duration,
+    true_world: Option<TrueWorldHandle>,
+    journal: EventJournalHandle,
+    log_run_mode_changes: bool,
+    observers: ObserversHandle,
+    breakpoints: Breakpoints,
+    last_state: LastStateHandle, // shared with the robot wrapper of the first robot
+    missing_map: MissingMapHandle, // shared with every robot wrapper
+    exit_reason: ExitReason,
 }
 impl GameRunner {
-    pub fn new(robot: Box<dyn Runnable>, world_generator: &mut impl Generator, game_to_worker_tx: SyncSender<PartialWorld>, gui_to_game_rx: Receiver<RunMode>) -> Result<Self, LibError> {
-        let robot_wrapper = RobotWrapper::new(robot, game_to_worker_tx);
+    pub fn new(robot: Box<dyn Runnable>, other_robots: Vec<Box<dyn Runnable>>, world_generator: &mut impl Generator, game_to_worker_tx: SyncSender<PartialWorld>, gui_to_game_rx: Receiver<RunModeChange>, event_log_tx: Sender<LoggedEvent>, observers: ObserversHandle, breakpoints: Breakpoints, config: &Config, health: HealthMonitor) -> Result<Self, LibError> {
+        let journal = config.event_journal.clone().and_then(|journal_config| {
+            let path = journal_config.path.clone();
+            EventJournal::open(journal_config)
+                .map_err(|e| eprintln!("could not open event journal {path:?}: {e}"))
+                .ok()
+        });
+        let journal = Rc::new(RefCell::new(journal));
+        let replay = config.replay_path.clone().map(ReplayRecorder::new);
+        let true_world = config.god_view.then(TrueWorldHandle::default);
+        let other_robots_handle: OtherRobotsHandle = Rc::new(RefCell::new(vec![None; other_robots.len()]));
+        let last_state = LastStateHandle::default();
+        let missing_map = MissingMapHandle::default();
+        let mut other_runners = vec![];
+        for (i, other_robot) in other_robots.into_iter().enumerate() {
+            let role = Role::Other(i, other_robots_handle.clone());
+            // the other robots only report their crashes to the breakpoints, they aren't checked against them
+            let robot_wrapper = RobotWrapper::new(other_robot, game_to_worker_tx.clone(), event_log_tx.clone(), journal.clone(), ObserversHandle::default(), breakpoints.clone(), None, None, None, role, missing_map.clone());
+            let mut runner = Runner::new(Box::new(robot_wrapper), world_generator)?;
+            runner.game_tick()?;
+            other_runners.push(runner);
+        }
+
+        let robot_wrapper = RobotWrapper::new(robot, game_to_worker_tx, event_log_tx, journal.clone(), observers.clone(), breakpoints.clone(), replay, true_world.clone(), config.telemetry.clone(), Role::First(other_robots_handle, last_state.clone()), missing_map.clone());
 
-        let mut runner = Runner::new(Box::new(robot_wrapper), world_generator)?;
+        let mut runner = match &true_world {
+            Some(true_world) => {
+                let mut retaining_generator = RetainingGenerator { inner: world_generator, true_world: true_world.clone() };
+                Runner::new(Box::new(robot_wrapper), &mut retaining_generator)?
+            }
+            None => Runner::new(Box::new(robot_wrapper), world_generator)?,
+        };
         runner.game_tick()?; // first tick needed to fully init partial_world
 
-        Ok(Self{ runner, gui_to_game_rx })
+        Ok(Self{ runner, other_runners, gui_to_game_rx, health, stall_timeout: config.stall_timeout, true_world, journal, log_run_mode_changes: config.log_run_mode_changes, observers, breakpoints, last_state, missing_map, exit_reason: ExitReason::WindowClosed })
     }
 
-    pub fn run(mut self) {
+    // a copy of the real world, if the god view is enabled
+    pub fn true_world(&self) -> Option<Vec<Vec<Tile>>> {
+        self.true_world.as_ref()?.borrow().as_ref().map(|true_world| true_world.tiles.clone())
+    }
+
+    pub fn run(mut self) -> Result<RunSummary, RagnarokError> {
+        let _heartbeat_guard = self.health.guard(MonitoredThread::Game);
+        let mut gui_stall_reported = false;
+
         let mut last_tick_begin = std::time::Instant::now();
         let mut run_mode = RunMode::Paused;
         'main_game_loop:
         loop {
             loop {
-                run_mode = self.gui_to_game_rx.try_iter().last().unwrap_or(run_mode);
+                self.health.beat(MonitoredThread::Game);
+                run_mode = self.receive_run_mode(run_mode);
+
+                // the GUI can't report its own stalls, so do it here
+                match self.health.status(MonitoredThread::Gui, self.stall_timeout) {
+                    ThreadStatus::Stalled(d) if !gui_stall_reported => {
+                        eprintln!("the GUI thread has not responded for {:.1}s", d.as_secs_f32());
+                        gui_stall_reported = true;
+                    }
+                    ThreadStatus::Alive => gui_stall_reported = false,
+                    _ => {}
+                }
+
                 match run_mode {
-                    RunMode::SingleTick => {
+                    RunMode::SingleTick | RunMode::RunTicks(0..=1) => {
                         run_mode = RunMode::Paused;
                         break;
                     }
+                    RunMode::RunTicks(ticks) => {
+                        run_mode = RunMode::RunTicks(ticks - 1);
+                        break;
+                    }
                     RunMode::Continuous(None) => break,
                     RunMode::Terminate => break 'main_game_loop,
                     RunMode::Continuous(Some(cap)) => {
@@ -51,12 +140,118 @@ impl GameRunner {
                     RunMode::Paused => {
                         thread::sleep(Duration::from_millis(5));
                     }
+                    RunMode::StepBack | RunMode::Seek(_) => run_mode = RunMode::Paused, // the game can't go back in time
+
                 }
             }
 
             last_tick_begin = std::time::Instant::now();
-            self.runner.game_tick().unwrap();
+            self.health.begin_tick();
+            let result = self.game_tick();
+            self.health.end_tick();
+            if let Err(e) = result {
+                eprintln!("{e}");
+                self.health.report_error(MonitoredThread::Game, e.to_string());
+                self.notify_terminate();
+                return Err(e);
+            }
+            if self.breakpoints.take_pause_request() {
+                run_mode = RunMode::Paused;
+            }
+        }
+        self.notify_terminate();
+        Ok(self.summary(self.exit_reason))
+    }
+
+    // runs the given number of ticks back to back, ignoring the gui->game channel
+    pub fn run_ticks(mut self, ticks: usize) -> Result<RunSummary, RagnarokError> {
+        let _heartbeat_guard = self.health.guard(MonitoredThread::Game);
+        let mut result = Ok(());
+        for _ in 0..ticks {
+            self.health.beat(MonitoredThread::Game);
+            self.health.begin_tick();
+            result = self.game_tick();
+            self.health.end_tick();
+            if result.is_err() {
+                break;
+            }
+        }
+        self.notify_terminate();
+        result.map(|()| self.summary(ExitReason::TicksCompleted))
+    }
+
+    fn summary(&self, exit_reason: ExitReason) -> RunSummary {
+        match self.last_state.borrow().as_ref() {
+            Some((tick, state)) => RunSummary::new(*tick, state.energy, state.backpack.clone(), exit_reason),
+            None => RunSummary::new(0, 0, HashMap::new(), exit_reason),
+        }
+    }
+
+    // ticks every robot, the first one last. a map missing at the end of the ticks run by new is
+    // reported by the first call
+    fn game_tick(&mut self) -> Result<(), RagnarokError> {
+        for runner in self.other_runners.iter_mut() {
+            runner.game_tick()?;
+        }
+        self.runner.game_tick()?;
+        match self.missing_map.take() {
+            Some(robot) => Err(RagnarokError::MissingMap(robot)),
+            None => Ok(()),
+        }
+    }
+
+    fn notify_terminate(&self) {
+        for observer in self.observers.borrow_mut().iter_mut() {
+            observer.on_terminate();
+        }
+    }
+
+    // writes the change to the event journal (flushing it, since the game may stay paused for long)
+    fn log_run_mode_change(&self, previous: RunMode, change: &RunModeChange) {
+        let mut journal = self.journal.borrow_mut();
+        let entry = JournalEntry::run_mode_change(previous, change);
+        if let Some(Err(e)) = journal.as_mut().map(|journal| journal.append(&entry).and_then(|()| journal.flush())) {
+            eprintln!("could not write to the event journal, disabling it: {e}");
+            *journal = None;
+        }
+    }
+
+    // returns the last RunMode received from the GUI, or Terminate if the GUI is gone (keeping why
+    // the game is terminated in exit_reason)
+    fn receive_run_mode(&mut self, mut run_mode: RunMode) -> RunMode {
+        loop {
+            match self.gui_to_game_rx.try_recv() {
+                Ok(change) => {
+                    if self.log_run_mode_changes && change.run_mode != run_mode {
+                        self.log_run_mode_change(run_mode, &change);
+                    }
+                    if change.run_mode == RunMode::Terminate {
+                        self.exit_reason = ExitReason::from_source(change.source);
+                    }
+                    run_mode = change.run_mode;
+                }
+                Err(TryRecvError::Empty) => return run_mode,
+                Err(TryRecvError::Disconnected) => {
+                    if run_mode != RunMode::Terminate {
+                        self.exit_reason = ExitReason::GuiExited;
+                    }
+                    return RunMode::Terminate;
+                }
+            }
         }
     }
 }
 
+
+// RetainingGenerator wraps the generator given by the user, keeping a copy of the world it creates
+struct RetainingGenerator<'a, G: Generator> {
+    inner: &'a mut G,
+    true_world: TrueWorldHandle,
+}
+impl<G: Generator> Generator for RetainingGenerator<'_, G> {
+    fn gen(&mut self) -> (Vec<Vec<Tile>>, (usize, usize), EnvironmentalConditions, f32, Option<HashMap<Content, f32>>) {
+        let generated = self.inner.gen();
+        *self.true_world.borrow_mut() = Some(TrueWorld::new(generated.0.clone()));
+        generated
+    }
+}
@@ -0,0 +1,33 @@
+use std::path::PathBuf;
+use crate::replay::{ReplayError, ReplayMetadata, ReplayWriter};
+use crate::{JournalEntry, WorldSnapshot};
+use super::PartialWorld;
+
+// ReplayRecorder records the run to a replay file, writing one snapshot (plus the events received
+// during the tick) at the end of every tick. The file is only created on the first tick, when the
+// size of the world is known.
+
+pub struct ReplayRecorder {
+    path: PathBuf,
+    writer: Option<ReplayWriter>,
+    tick_events: Vec<JournalEntry>,
+}
+impl ReplayRecorder {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, writer: None, tick_events: vec![] }
+    }
+
+    pub fn record_event(&mut self, entry: JournalEntry) {
+        self.tick_events.push(entry);
+    }
+
+    pub fn record_tick(&mut self, world: &PartialWorld) -> Result<(), ReplayError> {
+        if self.writer.is_none() {
+            self.writer = Some(ReplayWriter::create(&self.path, &ReplayMetadata::new(world.world.len()))?);
+        }
+        let writer = self.writer.as_mut().unwrap();
+        writer.write_tick(&WorldSnapshot::from_partial_world(world), &self.tick_events)?;
+        self.tick_events.clear();
+        writer.flush()
+    }
+}
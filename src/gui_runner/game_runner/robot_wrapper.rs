@@ -1,5 +1,10 @@
+use std::any::Any;
+use std::cell::{Cell, RefCell};
 use std::collections::HashSet;
-use std::sync::mpsc::{SyncSender};
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::sync::mpsc::{Sender, SyncSender};
+use std::time::Instant;
 use nalgebra_glm::UVec2;
 use robotics_lib::energy::Energy;
 use robotics_lib::event::events::Event;
@@ -8,51 +13,248 @@ use robotics_lib::runner::Runnable;
 use robotics_lib::world::coordinates::Coordinate;
 use robotics_lib::world::World;
 use super::PartialWorld;
+use crate::gui_runner::RobotState;
+use crate::gui_runner::event_journal::{EventJournal, EventJournalHandle, JournalEntry, LoggedEvent};
+use crate::gui_runner::observer::ObserversHandle;
+use crate::gui_runner::breakpoints::{Breakpoints, RobotCrash};
+use crate::gui_runner::map_merge::RobotMap;
+use crate::gui_runner::Telemetry;
+use crate::snapshot::WorldSnapshot;
+use super::replay_recorder::ReplayRecorder;
+use super::true_world::TrueWorldHandle;
 
 // RobotWrapper is a wrapper around Runnable, which itself implements Runnable. It serves the
 // purpose of sending world information through the gui->worker channel, since robotics_lib offers
 // no way of doing this that wouldn't involve calling gui code in the Runnable. This is simply a
 // workaround which allows me to "have a full wine barrel and a drunk wife" (as they would say in
 // Italy) by seamlessly wrapping the user's robot in this struct which does all the ugly things
-// necessary to communicate with the gui.
+// necessary to communicate with the gui. Since it sees every tick and every event, it is also the
+// one calling the observers registered by the user.
+// A panic of the robot's process_tick or handle_event is caught by its wrapper, which reports it
+// through the Breakpoints (pausing the game) and carries on with the tick as if the robot had
+// returned, so that the world stays visible in the GUI rather than the whole visualizer dying with
+// the robot.
+// When several robots run, each in a Runner of its own, the wrappers of the robots after the first
+// (see Role) only leave their map, state and the tiles their events touched in an OtherRobotsHandle,
+// and do nothing else with their ticks and events; the wrapper of the first robot, which ticks last,
+// sends them along with its own map, which the worker thread merges them into (see MapMerger).
+// The wrapper of the first robot also leaves its tick and state in a LastStateHandle, from which the
+// GameRunner makes the RunSummary when the game stops. A wrapper which can't read the map of its
+// robot at the end of a tick leaves the robot in a MissingMapHandle instead, and the GameRunner
+// fails the tick with it.
+
+// the map and state left by each of the robots after the first, in order, until the first takes them
+pub type OtherRobotsHandle = Rc<RefCell<Vec<Option<(RobotMap, RobotState)>>>>;
+// the tick and state of the first robot at the end of its last tick
+pub type LastStateHandle = Rc<RefCell<Option<(usize, RobotState)>>>;
+// the robot (0 is the first) whose map could not be read at the end of its tick, if any
+pub type MissingMapHandle = Rc<Cell<Option<usize>>>;
+
+pub enum Role {
+    First(OtherRobotsHandle, LastStateHandle),
+    Other(usize, OtherRobotsHandle), // index in the handle
+}
 
 pub struct RobotWrapper {
     ai: Box<dyn Runnable>,
     to_worker_tx: SyncSender<PartialWorld>,
     is_first_tick: bool,
+    tick: usize,
+    journal: EventJournalHandle, // shared with the GameRunner
+    observers: ObserversHandle, // shared with the GameRunner
+    breakpoints: Breakpoints,
+    replay: Option<ReplayRecorder>,
+    event_log_tx: Option<Sender<LoggedEvent>>, // None once the GUI is gone
+    started: Instant,
+    last_position: Option<UVec2>,
+    distant_changes: Vec<UVec2>,
+    touched_tiles: Vec<UVec2>,
+    true_world: Option<TrueWorldHandle>, // kept up to date with the events, if the god view is enabled
+    telemetry: Option<Telemetry>, // told the tick being run, to stamp the values published
+    role: Role,
+    missing_map: MissingMapHandle, // shared with the GameRunner
 }
 impl RobotWrapper {
-    pub fn new(ai: Box<dyn Runnable>, to_worker_tx: SyncSender<PartialWorld>) -> Self {
-        Self { ai, to_worker_tx, is_first_tick: true }
+    pub fn new(ai: Box<dyn Runnable>, to_worker_tx: SyncSender<PartialWorld>, event_log_tx: Sender<LoggedEvent>, journal: EventJournalHandle, observers: ObserversHandle, breakpoints: Breakpoints, replay: Option<ReplayRecorder>, true_world: Option<TrueWorldHandle>, telemetry: Option<Telemetry>, role: Role, missing_map: MissingMapHandle) -> Self {
+        Self { ai, to_worker_tx, is_first_tick: true, tick: 0, journal, observers, breakpoints, replay, event_log_tx: Some(event_log_tx), started: Instant::now(), last_position: None, distant_changes: vec![], touched_tiles: vec![], true_world, telemetry, role, missing_map }
+    }
+
+    // 0 is the first robot
+    fn robot(&self) -> usize {
+        match self.role {
+            Role::First(..) => 0,
+            Role::Other(i, _) => i + 1,
+        }
+    }
+
+    fn state(&self) -> RobotState {
+        RobotState {
+            position: coord_to_robot_position(self.get_coordinate()),
+            energy: self.get_energy().get_energy_level(),
+            backpack: self.get_backpack().get_contents().clone(),
+            backpack_size: self.get_backpack().get_size(),
+        }
+    }
+
+    fn report_crash(&self, payload: Box<dyn Any + Send>) {
+        let message = payload.downcast_ref::<&str>().map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "(the panic has no message)".to_string());
+        let robot_position = coord_to_robot_position(self.get_coordinate());
+        self.breakpoints.report_crash(RobotCrash { tick: self.tick, robot: self.robot(), robot_position, message });
+    }
+
+    // the events are also sent outside of process_tick (e.g. when the energy recharges), so a
+    // panic of the robot's handle_event is caught and reported the same way
+    fn ai_handle_event(&mut self, event: Event) {
+        if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| self.ai.handle_event(event))) {
+            self.report_crash(payload);
+        }
+    }
+
+    // records the positions touched by the event, around which the worker thread looks for
+    // changes, and among them those of the changes which did not happen next to the robot, which
+    // the worker thread would otherwise refresh with the default (narrow) radius
+    fn track_changes(&mut self, event: &Event) {
+        let robot_position = coord_to_robot_position(self.ai.get_coordinate());
+        match event {
+            Event::Moved(_, (x, y)) => {
+                let new_position = UVec2::new(*x as u32, *y as u32);
+                self.touched_tiles.push(new_position);
+                if let Some(last_position) = self.last_position {
+                    if chebyshev_distance(last_position, new_position) > 1 {
+                        self.distant_changes.push(new_position); // teleported
+                    }
+                }
+                self.last_position = Some(new_position);
+            }
+            Event::TileContentUpdated(_, (x, y)) => {
+                let position = UVec2::new(*x as u32, *y as u32);
+                self.touched_tiles.push(position);
+                if chebyshev_distance(robot_position, position) > 1 {
+                    self.distant_changes.push(position);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // writes the event to the journal and/or the replay, if they are enabled, and sends it to the
+    // GUI's event log
+    fn record_event(&mut self, event: &Event) {
+        if self.journal.borrow().is_none() && self.replay.is_none() && self.event_log_tx.is_none() {
+            return;
+        }
+        let entry = JournalEntry::new(self.tick, coord_to_robot_position(self.ai.get_coordinate()), event);
+        {
+            let mut journal = self.journal.borrow_mut();
+            if let Some(Err(e)) = journal.as_mut().map(|journal| journal.append(&entry)) {
+                eprintln!("could not write to the event journal, disabling it: {e}");
+                *journal = None;
+            }
+        }
+        if let Some(event_log_tx) = &self.event_log_tx {
+            let logged_event = LoggedEvent { elapsed: self.started.elapsed(), entry: entry.clone() };
+            if event_log_tx.send(logged_event).is_err() {
+                self.event_log_tx = None; // the GUI was closed (or never opened, when running headless)
+            }
+        }
+        if let Some(replay) = &mut self.replay {
+            replay.record_event(entry);
+        }
     }
 }
 impl Runnable for RobotWrapper {
     fn process_tick(&mut self, world: &mut World) {
         if !self.is_first_tick {
-            self.ai.process_tick(world);
+            self.tick += 1;
+            if let Some(telemetry) = &self.telemetry {
+                telemetry.set_tick(self.tick);
+            }
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| self.ai.process_tick(world))) {
+                self.report_crash(payload);
+            }
         } else {
             robotics_lib::interface::robot_view(self, world);
             self.is_first_tick = false;
         }
 
+        let Some(robot_map) = robotics_lib::interface::robot_map(world) else {
+            self.missing_map.set(Some(self.robot()));
+            return;
+        };
+        let state = self.state();
+        let (other_maps, other_robots): (Vec<RobotMap>, Vec<RobotState>) = match &self.role {
+            Role::First(other_robots, last_state) => {
+                *last_state.borrow_mut() = Some((self.tick, state));
+                other_robots.borrow_mut().iter_mut().filter_map(Option::take).unzip()
+            }
+            Role::Other(i, other_robots) => {
+                self.distant_changes.clear();
+                let robot_map = RobotMap { map: robot_map, touched_tiles: std::mem::take(&mut self.touched_tiles) };
+                other_robots.borrow_mut()[*i] = Some((robot_map, state));
+                return;
+            }
+        };
+        let stale_tiles = self.true_world.as_ref()
+            .and_then(|true_world| true_world.borrow().as_ref().map(|true_world| true_world.stale_tiles(&robot_map)))
+            .unwrap_or_default();
         let world_data = PartialWorld {
-            world: robotics_lib::interface::robot_map(world).unwrap(),
+            world: robot_map,
             tiles_to_refresh: HashSet::new(),
+            distant_changes: std::mem::take(&mut self.distant_changes),
+            touched_tiles: Some(std::mem::take(&mut self.touched_tiles)),
+            stale_tiles,
+            tick: self.tick,
+            elapsed: self.started.elapsed(),
             robot_position: coord_to_robot_position(self.get_coordinate()),
             energy: self.get_energy().get_energy_level(),
             backpack: self.get_backpack().get_contents().clone(),
+            backpack_size: Some(self.get_backpack().get_size()),
             env_cond: robotics_lib::interface::look_at_sky(&world),
+            other_robots,
+            other_maps,
+            changed_tiles: vec![],
+            content_changes: vec![],
         };
+        self.last_position = Some(world_data.robot_position);
+        if let Some(Err(e)) = self.replay.as_mut().map(|replay| replay.record_tick(&world_data)) {
+            eprintln!("could not write to the replay, disabling it: {e}");
+            self.replay = None;
+        }
+        self.breakpoints.check_world(&world_data);
+        if !self.observers.borrow().is_empty() {
+            let snapshot = WorldSnapshot::from_partial_world(&world_data);
+            for observer in self.observers.borrow_mut().iter_mut() {
+                observer.on_tick(&snapshot);
+            }
+        }
         let _ = self.to_worker_tx.send(world_data); // do not unwrap, since Err simply means the GUI was closed and this thread is also about to exit
+
+        let mut journal = self.journal.borrow_mut();
+        if let Some(Err(e)) = journal.as_mut().map(EventJournal::flush) {
+            eprintln!("could not flush the event journal, disabling it: {e}");
+            *journal = None;
+        }
     }
 
     fn handle_event(&mut self, event: Event) {
-        self.ai.handle_event(event.clone());
-
-        match &event {
-            //ignore these events
-            Event::Ready | Event::Moved(_,_) | Event::DayChanged(_) | Event::EnergyRecharged(_)  | Event::TimeChanged(_) => {}
-            e => println!("RobotWrapper caught event {e:?}"),
+        if let Role::Other(..) = self.role {
+            self.ai_handle_event(event.clone());
+            self.track_changes(&event);
+            return;
+        }
+        self.ai_handle_event(event.clone());
+        for observer in self.observers.borrow_mut().iter_mut() {
+            observer.on_event(self.tick, &event);
+        }
+        self.record_event(&event);
+        self.breakpoints.check_event(self.tick, coord_to_robot_position(self.ai.get_coordinate()), &event);
+        self.track_changes(&event);
+        if let Some(true_world) = self.true_world.as_ref() {
+            if let Some(true_world) = true_world.borrow_mut().as_mut() {
+                true_world.apply_event(&event);
+            }
         }
     }
 
@@ -66,3 +268,6 @@ impl Runnable for RobotWrapper {
 fn coord_to_robot_position(c: &Coordinate) -> UVec2 {
     UVec2::new(c.get_row() as u32, c.get_col() as u32)
 }
+fn chebyshev_distance(a: UVec2, b: UVec2) -> u32 {
+    a.x.abs_diff(b.x).max(a.y.abs_diff(b.y))
+}
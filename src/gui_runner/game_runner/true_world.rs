@@ -0,0 +1,45 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+use nalgebra_glm::UVec2;
+use robotics_lib::event::events::Event;
+use robotics_lib::world::tile::Tile;
+
+// TrueWorld keeps a copy of the world as created by the generator (when the god view is enabled),
+// kept up to date with the TileContentUpdated events. Since robotics_lib only updates the robot's
+// map of a tile when the robot sees it, the map can go stale: the tiles whose content changed
+// after being discovered are compared against the map at every tick, which tells which ones are.
+// It is shared (through a TrueWorldHandle) between the generator wrapper which fills it, the robot
+// wrapper which updates it, and the GameRunner which hands a copy of it to the GUI.
+
+pub type TrueWorldHandle = Rc<RefCell<Option<TrueWorld>>>;
+
+pub struct TrueWorld {
+    pub tiles: Vec<Vec<Tile>>,
+    changed: HashSet<UVec2>, // tiles changed since the world was generated
+}
+impl TrueWorld {
+    pub fn new(tiles: Vec<Vec<Tile>>) -> Self {
+        Self { tiles, changed: HashSet::new() }
+    }
+
+    pub fn apply_event(&mut self, event: &Event) {
+        if let Event::TileContentUpdated(tile, (x, y)) = event {
+            if let Some(true_tile) = self.tiles.get_mut(*x).and_then(|row| row.get_mut(*y)) {
+                *true_tile = tile.clone();
+                self.changed.insert(UVec2::new(*x as u32, *y as u32));
+            }
+        }
+    }
+
+    // the discovered tiles whose content in the robot's map differs from the real one
+    pub fn stale_tiles(&self, robot_map: &Vec<Vec<Option<Tile>>>) -> Vec<UVec2> {
+        self.changed.iter().copied().filter(|tile_pos| {
+            let (x, y) = (tile_pos.x as usize, tile_pos.y as usize);
+            match &robot_map[x][y] {
+                Some(known) => known.content != self.tiles[x][y].content,
+                None => false,
+            }
+        }).collect()
+    }
+}
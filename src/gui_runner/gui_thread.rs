@@ -1,25 +1,52 @@
 use std::sync::mpsc::{Receiver, Sender};
 use std::thread;
-use super::{PartialWorld, RunMode};
+use robotics_lib::world::tile::Tile;
+use super::{PartialWorld, RunModeChange};
+use super::event_journal::LoggedEvent;
+use super::breakpoints::Breakpoints;
+use super::builder::Config;
+use super::thread_health::{HealthMonitor, MonitoredThread};
+use super::replay_player::ReplayInfo;
+use super::snapshot_history::SnapshotHistory;
+use super::run_statistics::RunStatistics;
+use super::control_handle::ControlRequest;
+use super::error::RagnarokError;
 use gui::GUI;
 
 pub mod gui;
 
-// GuiThread handles spawning a thread which will run the GUI
+// GuiThread handles spawning a thread which will run the GUI, returning the error which kept the
+// GUI from starting, if any
 
 pub struct GuiThread {
     worker_to_gui_rx: Receiver<PartialWorld>,
-    gui_to_game_tx: Sender<RunMode>,
+    gui_to_game_tx: Sender<RunModeChange>,
+    event_log_rx: Receiver<LoggedEvent>,
+    breakpoints: Breakpoints,
+    config: Config,
+    health: HealthMonitor,
+    replay: Option<ReplayInfo>,
+    is_preview: bool,
+    true_world: Option<Vec<Vec<Tile>>>, // for the god view
+    rewind_history: Option<SnapshotHistory>, // filled by the worker thread
+    statistics: RunStatistics, // as above
+    control_rx: Receiver<ControlRequest>,
 }
 impl GuiThread {
-    pub fn new(worker_to_gui_rx: Receiver<PartialWorld>, gui_to_game_tx: Sender<RunMode>) -> Self {
-        Self { worker_to_gui_rx, gui_to_game_tx }
+    pub fn new(worker_to_gui_rx: Receiver<PartialWorld>, gui_to_game_tx: Sender<RunModeChange>, event_log_rx: Receiver<LoggedEvent>, breakpoints: Breakpoints, config: Config, health: HealthMonitor, replay: Option<ReplayInfo>, is_preview: bool, true_world: Option<Vec<Vec<Tile>>>, rewind_history: Option<SnapshotHistory>, statistics: RunStatistics, control_rx: Receiver<ControlRequest>) -> Self {
+        Self { worker_to_gui_rx, gui_to_game_tx, event_log_rx, breakpoints, config, health, replay, is_preview, true_world, rewind_history, statistics, control_rx }
     }
-    pub fn start(self) -> thread::JoinHandle<()> {
+    pub fn start(self) -> thread::JoinHandle<Result<(), RagnarokError>> {
         thread::spawn(move || {
+            let _heartbeat_guard = self.health.guard(MonitoredThread::Gui);
             // GUI is not Send :(
-            let gui = GUI::new("Ragnarok", self.worker_to_gui_rx, self.gui_to_game_tx);
-            gui.run();
+            let window_title = match (&self.replay, self.is_preview) {
+                (Some(_), _) => "Ragnarok (replay)",
+                (None, true) => "Ragnarok (world preview)",
+                (None, false) => "Ragnarok",
+            };
+            let gui = GUI::new(window_title, self.worker_to_gui_rx, self.gui_to_game_tx, self.event_log_rx, self.breakpoints, &self.config, self.health.clone(), self.replay, self.is_preview, self.true_world, self.rewind_history, self.statistics, self.control_rx)?;
+            gui.run()
         })
     }
 }
@@ -3,25 +3,179 @@ mod shaders;
 mod keyboard_event_handler;
 mod frame_delta_timer;
 mod compute_mvp;
-
+pub(crate) mod charts;
+mod journal_viewer;
+mod diagnostics;
+mod weather_timeline;
+mod sundial;
+mod trail;
+mod picking;
+mod pinned_panels;
+mod metronome;
+mod render_stats;
+mod markers;
+mod annotations;
+mod ghost;
+mod frustum;
+mod daylight;
+mod teleports;
+mod streets;
+mod water_bodies;
+mod robot_model;
+mod god_view;
+mod stale_tiles;
+mod content_icons;
+mod minimap;
+mod robot_panels;
+mod map_camera;
+mod idle_detector;
+mod event_log;
+mod run_mode_log;
+mod simulation_clock;
+mod robot_history;
+mod screenshot;
+mod input_recorder;
+mod breakpoint_editor;
+mod tile_layers_overlay;
+mod vector_field_overlay;
+mod telemetry_panel;
+mod snapshot_file;
+mod session;
+mod phase_timeline;
+mod detached_view;
+mod rewind;
+mod key_bindings;
+mod layouts;
+mod settings;
+mod status_bar;
+mod speed_dial;
+mod change_heatmap;
+mod pinned_world;
+mod content_changes;
+mod discovery_age;
+mod fog;
+mod chunk_rebuilds;
+mod skybox;
+mod fonts;
+mod clipboard;
+mod file_drop;
+mod shadows;
+mod autosave;
+mod energy_planner;
+mod on_demand;
+mod run_comparison;
+mod themes;
+mod goto_box;
+mod tile_search;
+mod measure_tool;
+mod statistics_window;
+mod tick_export;
+mod crash_modal;
+mod interaction_trace;
+pub mod offscreen;
+
+use std::cell::Cell;
 use std::collections::HashSet;
-use std::sync::mpsc::{Receiver, Sender};
+use std::ops::RangeInclusive;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
 use glium::index::PrimitiveType;
 use glium::Surface;
-use imgui::{Condition, SliderFlags, StyleColor, TreeNodeFlags};
+use imgui::{Condition, MouseButton, SliderFlags, StyleColor, TreeNodeFlags};
 use imgui_winit_support::HiDpiMode;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event_loop::EventLoopWindowTarget;
 use winit::window::WindowBuilder;
 use nalgebra_glm as glm;
-use glm::{Vec3, vec3};
+use glm::{UVec2, Vec3, vec3};
+use robotics_lib::world::tile::Tile;
 use world_mesh::WorldMesh;
 use frame_delta_timer::FrameDeltaTimer;
-use keyboard_event_handler::{KeyboardEventHandler, ProcessedKeyboardInput};
-use super::{PartialWorld, RunMode};
+use charts::{Chart, DownsampledHistory};
+use journal_viewer::JournalViewer;
+use weather_timeline::WeatherTimeline;
+use trail::Trail;
+use pinned_panels::PinnedPanels;
+use metronome::Metronome;
+use render_stats::RenderStats;
+use keyboard_event_handler::{ChordAction, KeyAction, KeyboardEventHandler, ProcessedKeyboardInput};
+use key_bindings::KeyBinding;
+use input_recorder::InputRecorder;
+use breakpoint_editor::BreakpointEditor;
+use annotations::{AnnotationsEditor, AnnotationsRequest};
+use ghost::GhostOverlay;
+use frustum::Frustum;
+use daylight::Daylight;
+use teleports::TeleportNetwork;
+use streets::StreetNetwork;
+use water_bodies::WaterBodies;
+use robot_model::RobotModel;
+use god_view::GodView;
+use stale_tiles::StaleTilesOverlay;
+use tile_layers_overlay::TileLayersOverlay;
+use vector_field_overlay::VectorFieldOverlay;
+use telemetry_panel::TelemetryPanel;
+use snapshot_file::SnapshotFile;
+use phase_timeline::PhaseTimeline;
+use detached_view::{DetachedView, ViewRequest};
+use session::MarkerStyles;
+use rewind::Rewind;
+use key_bindings::KeyBindingsEditor;
+use layouts::{HudWindows, Layouts};
+use settings::{Settings, WindowGeometry};
+use status_bar::StatusInfo;
+use change_heatmap::ChangeHeatmap;
+use pinned_world::PinnedWorld;
+use content_changes::ContentChanges;
+use discovery_age::DiscoveryAge;
+use fog::FogOfWar;
+use chunk_rebuilds::ChunkRebuilds;
+use skybox::{SkyMode, Skybox};
+use clipboard::SystemClipboard;
+use file_drop::{FileDrop, FileKind};
+use shadows::{ShadowMap, Shadowed};
+use autosave::Autosave;
+use energy_planner::EnergyPlanner;
+use on_demand::OnDemand;
+use run_comparison::RunComparison;
+use fonts::Fonts;
+use goto_box::GoToBox;
+use tile_search::TileSearch;
+use measure_tool::MeasureTool;
+use statistics_window::StatisticsWindow;
+use tick_export::TickExport;
+use crash_modal::CrashModal;
+use interaction_trace::InteractionTrace;
+use content_icons::ContentIcons;
+use minimap::Minimap;
+use robot_panels::{BackpackDeltas, FollowTarget};
+use map_camera::MapCamera;
+use idle_detector::IdleDetector;
+use event_log::EventLog;
+use run_mode_log::RunModeLog;
+use diagnostics::DiagnosticsAction;
+use simulation_clock::SimulationClock;
+use robot_history::RobotHistory;
+use super::PartialWorld;
+use crate::gui_runner::{RunMode, RunModeChange, RunModeSource};
+use crate::gui_runner::event_journal::LoggedEvent;
+use crate::gui_runner::builder::Config;
+use crate::gui_runner::breakpoints::Breakpoints;
+use crate::gui_runner::replay_player::ReplayInfo;
+use crate::gui_runner::snapshot_history::SnapshotHistory;
+use crate::gui_runner::control_handle::ControlRequest;
+use crate::gui_runner::error::RagnarokError;
+use crate::gui_runner::thread_health::{HealthMonitor, MonitoredThread};
+use crate::gui_runner::map_merge::{MapMerge, MergePolicy};
+use crate::gui_runner::run_statistics::RunStatistics;
+use crate::gui_runner::{MarkerIcon, MarkerStyle, RobotState};
 
 //extension that allows running winit on a thread that isn't the main thread. necessary since it's hard to run runner outside of main thread (it's not Send)
 #[cfg(target_os = "linux")] use winit::platform::unix::EventLoopBuilderExtUnix;
 #[cfg(target_os = "windows")] use winit::platform::windows::EventLoopBuilderExtWindows;
 #[cfg(target_os = "macos")] use winit::platform::macos::EventLoopBuilderExtMacOS;
+// running the event loop with run_return gives the thread back when the window is closed, rather than exiting the process
+use winit::platform::run_return::EventLoopExtRunReturn;
 
 // GUI handles:
 // - rendering:
@@ -32,121 +186,438 @@ use super::{PartialWorld, RunMode};
 //   - graphical input through imgui
 // - inter thread communication:
 //   - receives PartialWorld through worker->gui (uses feeds it to WorldMesh to turn it into a mesh)
-//   - sends RunMode through gui->game (when the user requests it with keyboard or graphical input)
+//   - sends RunModeChange through gui->game (when the user requests it with keyboard or graphical
+//     input), via RunModeLog
+// failing to create the window, or the worker thread exiting before sending the first world, is
+// returned by GUI::new as a RagnarokError, which the GUI thread returns.
 
 pub struct GUI {
     rx_from_worker: Receiver<PartialWorld>,
-    tx_to_game: Sender<RunMode>,
+    rx_control: Receiver<ControlRequest>,
+    run_mode_log: RunModeLog,
     world_copy: PartialWorld,
 
-    event_loop: winit::event_loop::EventLoop<()>,
+    event_loop: Option<winit::event_loop::EventLoop<()>>, // taken by run
     display: glium::Display,
     imgui_ctx: imgui::Context,
     imgui_platform: imgui_winit_support::WinitPlatform,
     imgui_renderer: imgui_glium_renderer::Renderer,
+    layouts: Layouts,
+    fonts: Fonts,
+    default_style: imgui::Style, // imgui's, which the themes are applied to
 
     world_mesh: WorldMesh,
     shader_program: glium::Program,
+    liquid_shader_program: glium::Program,
 
     kbd_event_handler: KeyboardEventHandler,
+    key_bindings_editor: KeyBindingsEditor,
+    journal_viewer: JournalViewer,
+    event_log: EventLog,
+    breakpoint_editor: BreakpointEditor,
+    crash_modal: CrashModal,
+    map_merge: MapMerge, // shared with the worker thread, which merges the maps of the robots as chosen here
+    ghost_overlay: GhostOverlay,
+
+    health: HealthMonitor,
+    stall_timeout: Duration,
+    tick_timeout: Duration,
+    stuck_warning_ticks: usize,
+
+    near_plane: f32,
+    far_plane: f32,
+    logarithmic_depth: bool,
+    msaa_samples: u16, // of the context, which is rebuilt when the setting changes
+    vsync: bool, // as above
+    window_title: String, // to create the window again when the context is rebuilt
+    autosave_interval: Duration,
+
+    // Some when playing back a replay:
+    replay_ticks: Option<RangeInclusive<usize>>,
+    replay_history: Option<SnapshotHistory>,
+    annotations_editor: Option<AnnotationsEditor>,
+    // true when previewing a world generator, in which case there is no robot to show or control
+    is_preview: bool,
+    god_view: Option<GodView>, // Some if the world created by the generator was kept
+    tile_layers: Option<TileLayersOverlay>, // Some if the host code set tile layers
+    vector_fields: Option<VectorFieldOverlay>, // as above
+    telemetry_panel: Option<TelemetryPanel>, // Some if the host code publishes telemetry
+    phase_timeline: PhaseTimeline,
+    energy_planner: EnergyPlanner,
+    statistics_window: StatisticsWindow,
+    rewind: Option<Rewind>, // Some in live runs, unless disabled
+    robot_style: MarkerStyle,
+    other_robot_styles: Vec<MarkerStyle>, // of the robots after the first, those left out get a default one
+    settings: Settings, // as loaded, updated when saved
 }
 impl GUI {
-    pub fn new(window_title: &str, rx_from_worker: Receiver<PartialWorld>, tx_to_game: Sender<RunMode>) -> Self {
+    pub fn new(window_title: &str, rx_from_worker: Receiver<PartialWorld>, tx_to_game: Sender<RunModeChange>, rx_event_log: Receiver<LoggedEvent>, breakpoints: Breakpoints, config: &Config, health: HealthMonitor, replay: Option<ReplayInfo>, is_preview: bool, true_world: Option<Vec<Vec<Tile>>>, rewind_history: Option<SnapshotHistory>, statistics: RunStatistics, rx_control: Receiver<ControlRequest>) -> Result<Self, RagnarokError> {
         let event_loop =
             winit::event_loop::EventLoopBuilder::new()
             .with_any_thread(true)
             .build();
 
-        let window_builder =
+        let mut settings = Settings::load();
+        let mut window_builder =
             WindowBuilder::new()
                 .with_title(window_title);
-
-        let display = glium::Display::new(window_builder, glium::glutin::ContextBuilder::new(), &event_loop).unwrap();
+        if let Some(window) = settings.window {
+            window_builder = window_builder.with_inner_size(PhysicalSize::new(window.size[0], window.size[1]));
+            if let Some([x, y]) = window.position {
+                window_builder = window_builder.with_position(PhysicalPosition::new(x, y));
+            }
+        }
+
+        // not every platform supports every sample count (or v-sync), in which case the context is created without them
+        let context_builder = glium::glutin::ContextBuilder::new().with_multisampling(settings.msaa_samples).with_vsync(settings.vsync);
+        let (display, msaa_samples, vsync) = match glium::Display::new(window_builder.clone(), context_builder, &event_loop) {
+            Ok(display) => (display, settings.msaa_samples, settings.vsync),
+            Err(e) => {
+                eprintln!("could not create a context with {} samples and v-sync {}, disabling them: {e}", settings.msaa_samples, if settings.vsync { "on" } else { "off" });
+                let display = glium::Display::new(window_builder, glium::glutin::ContextBuilder::new(), &event_loop)
+                    .map_err(|e| RagnarokError::Window(e.to_string()))?;
+                // rather than trying to rebuild the context with them at the first frame
+                (settings.msaa_samples, settings.vsync) = (0, false);
+                (display, 0, false)
+            }
+        };
 
         let mut imgui_ctx = imgui::Context::create();
-        imgui_ctx.set_ini_filename(None); //for some reason loading imgui.ini files sometimes causes crashes
+        imgui_ctx.set_ini_filename(None); // the settings are loaded and saved by Layouts, which checks them first
+        match SystemClipboard::new() {
+            Ok(clipboard) => imgui_ctx.set_clipboard_backend(clipboard),
+            Err(e) => eprintln!("could not access the clipboard, copying and pasting will only work within the GUI: {e}"),
+        }
+        imgui_ctx.io_mut().config_flags |= imgui::ConfigFlags::DOCKING_ENABLE;
+        let layouts = Layouts::new(&mut imgui_ctx);
+        let mut fonts = Fonts::new(&config.fonts);
+        fonts.load(&mut imgui_ctx, display.gl_window().window().scale_factor() as f32, settings.ui_scale);
         imgui_ctx.fonts().build_alpha8_texture();
+        let default_style = *imgui_ctx.style();
+        settings.theme.apply(settings.accent_color, settings.ui_scale, &default_style, imgui_ctx.style_mut());
 
         let mut imgui_platform = imgui_winit_support::WinitPlatform::init(&mut imgui_ctx);
         imgui_platform.attach_window(imgui_ctx.io_mut(), &display.gl_window().window(), HiDpiMode::Default);
 
-        let imgui_renderer = imgui_glium_renderer::Renderer::init(&mut imgui_ctx, &display).unwrap();
-        let world_copy = rx_from_worker.recv().unwrap();
-        let world_mesh = WorldMesh::new(world_copy.world.len(), 10, &display);
-        let shader_program = shaders::make_program(&display).unwrap();
+        let imgui_renderer = imgui_glium_renderer::Renderer::init(&mut imgui_ctx, &display)
+            .map_err(|e| RagnarokError::Window(e.to_string()))?;
+        let world_copy = loop {
+            health.beat(MonitoredThread::Gui);
+            match rx_from_worker.recv_timeout(HealthMonitor::HEARTBEAT_INTERVAL) {
+                Ok(w) => break w,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return Err(RagnarokError::Disconnected(MonitoredThread::Worker.name())),
+            }
+        };
+        let world_mesh = WorldMesh::new(&display);
+        let shader_program = shaders::make_program(&display).map_err(|e| RagnarokError::Window(e.to_string()))?;
+        let liquid_shader_program = shaders::make_liquid_program(&display).map_err(|e| RagnarokError::Window(e.to_string()))?;
+
+        let mut kbd_event_handler = KeyboardEventHandler::new(settings.movement_speed, settings.look_speed);
+        if let Some(bindings) = settings.key_bindings.clone() {
+            kbd_event_handler.set_bindings(bindings);
+        }
+        let mut key_bindings_editor = KeyBindingsEditor::new(config.key_bindings.clone());
+        key_bindings_editor.load_into(&mut kbd_event_handler);
+        let journal_viewer = JournalViewer::new(config.event_journal.as_ref().map(|j| j.path.clone()));
+        let event_log = EventLog::new(rx_event_log);
+        let crash_modal = CrashModal::new(breakpoints.clone());
+        let breakpoint_editor = BreakpointEditor::new(breakpoints);
+        let run_mode_log = RunModeLog::new(tx_to_game, &world_copy);
+        let ghost_overlay = GhostOverlay::new(config.ghost_replay.clone(), config.ghost_style.clone());
+        let god_view = true_world.map(|true_world| GodView::new(true_world, world_copy.env_cond.clone()));
+
+        Ok(Self {
+            rx_from_worker, rx_control, run_mode_log, world_copy, event_loop: Some(event_loop), display, imgui_ctx, imgui_platform, imgui_renderer, layouts, fonts, default_style,
+            world_mesh, shader_program, liquid_shader_program, kbd_event_handler, key_bindings_editor, journal_viewer, event_log, breakpoint_editor, crash_modal, map_merge: config.map_merge.clone(), ghost_overlay, health, stall_timeout: config.stall_timeout, tick_timeout: config.tick_timeout, stuck_warning_ticks: config.stuck_warning_ticks,
+            near_plane: config.near_plane, far_plane: config.far_plane, logarithmic_depth: config.logarithmic_depth, msaa_samples, vsync, window_title: window_title.to_string(), autosave_interval: config.autosave_interval,
+            replay_ticks: replay.as_ref().map(|replay| replay.ticks.clone()),
+            replay_history: replay.as_ref().map(|replay| replay.history.clone()),
+            annotations_editor: replay.map(|replay| AnnotationsEditor::new(replay.path, replay.annotations)),
+            is_preview,
+            god_view,
+            tile_layers: config.tile_layers.clone().map(TileLayersOverlay::new),
+            vector_fields: config.tile_layers.clone().map(VectorFieldOverlay::new),
+            telemetry_panel: config.telemetry.clone().map(TelemetryPanel::new),
+            phase_timeline: PhaseTimeline::new(config.telemetry.clone()),
+            energy_planner: EnergyPlanner::new(config.telemetry.clone()),
+            statistics_window: StatisticsWindow::new(statistics),
+            rewind: rewind_history.map(Rewind::new),
+            robot_style: config.robot_style.clone(),
+            other_robot_styles: config.other_robot_styles.clone(),
+            settings,
+        })
+    }
 
-        let kbd_event_handler = KeyboardEventHandler::new(50.0, 1.0);
+    // applies the sample count and v-sync of the settings, which only a new context can change
+    fn rebuild_context(&mut self, window_target: &EventLoopWindowTarget<()>) -> Result<(), String> {
+        // the new window takes the place of the current one, and shares its resources (meshes,
+        // textures, programs)
+        let window_builder = {
+            let gl_window = self.display.gl_window();
+            let window = gl_window.window();
+            let mut window_builder = WindowBuilder::new()
+                .with_title(&self.window_title)
+                .with_inner_size(window.inner_size())
+                .with_maximized(window.is_maximized())
+                .with_fullscreen(window.fullscreen());
+            if let Ok(position) = window.outer_position() {
+                window_builder = window_builder.with_position(position);
+            }
+            window_builder
+        };
+        let context_builder = glium::glutin::ContextBuilder::new().with_multisampling(self.settings.msaa_samples).with_vsync(self.settings.vsync);
+        // when that fails the current context is kept, and the settings reverted
+        if let Err(e) = self.display.rebuild(window_builder, context_builder, window_target) {
+            self.settings.msaa_samples = self.msaa_samples;
+            self.settings.vsync = self.vsync;
+            return Err(e.to_string());
+        }
+        self.msaa_samples = self.settings.msaa_samples;
+        self.vsync = self.settings.vsync;
+        self.imgui_platform.attach_window(self.imgui_ctx.io_mut(), &self.display.gl_window().window(), HiDpiMode::Default);
+        Ok(())
+    }
 
-        Self { rx_from_worker, tx_to_game, world_copy, event_loop, display, imgui_ctx, imgui_platform, imgui_renderer, world_mesh, shader_program, kbd_event_handler }
+    // saves the preferences of the user, to be restored by the next run
+    fn save_settings(&mut self, ticks_per_second_cap: f32, uncapped: bool, sky: SkyMode) {
+        let window = self.display.gl_window().window();
+        let size = window.inner_size();
+        self.settings = Settings {
+            movement_speed: self.kbd_event_handler.movement_speed,
+            look_speed: self.kbd_event_handler.look_speed,
+            ticks_per_second_cap,
+            uncapped,
+            sky,
+            theme: self.settings.theme,
+            accent_color: self.settings.accent_color,
+            ui_scale: self.settings.ui_scale,
+            clear_color: self.settings.clear_color,
+            vignette: self.settings.vignette,
+            msaa_samples: self.settings.msaa_samples,
+            vsync: self.settings.vsync,
+            frame_rate_cap: self.settings.frame_rate_cap,
+            low_power: self.settings.low_power,
+            window: Some(WindowGeometry {
+                size: [size.width, size.height],
+                position: window.outer_position().ok().map(|position| [position.x, position.y]),
+            }),
+            key_bindings: Some(self.kbd_event_handler.bindings().clone()),
+        };
+        if let Err(e) = self.settings.save() {
+            eprintln!("could not save the settings: {e}");
+        }
     }
 
-    fn toggle_continuous_mode(run_mode: &mut RunMode, tx_to_game: &Sender<RunMode>, last_was_uncapped: bool, last_ticks_per_second_cap: f32) {
-        *run_mode = match run_mode {
+    // returns the point on top of the given tile
+    fn tile_top(world: &PartialWorld, tile: UVec2) -> Vec3 {
+        let elevation = world.world.get(tile.x as usize)
+            .and_then(|row| row.get(tile.y as usize))
+            .and_then(|t| t.as_ref())
+            .map(|t| t.elevation)
+            .unwrap_or(0);
+        vec3(tile.x as f32, world_mesh::elevation_to_mesh_space_y(elevation as f32), tile.y as f32)
+    }
+
+    // returns the camera position which frames the given tile, keeping the camera direction
+    fn cam_pos_looking_at(world: &PartialWorld, tile: UVec2, cam_dir: Vec3) -> Vec3 {
+        Self::tile_top(world, tile) - cam_dir * 30.0
+    }
+
+    // the robot selected in the Robot panel, if it isn't the first one (which has its own model)
+    fn selected_other_robot(world: &PartialWorld, selected_robot: usize) -> Option<&RobotState> {
+        selected_robot.checked_sub(1).and_then(|i| world.other_robots.get(i))
+    }
+
+    // the style of the marker of the i-th of the other robots, if neither the host code nor the
+    // user set one: a diamond labelled after the marker of the first robot
+    fn default_other_robot_style(robot_style: &MarkerStyle, i: usize) -> MarkerStyle {
+        MarkerStyle { icon: MarkerIcon::Diamond, label: format!("{} {}", robot_style.label, i + 2), ..robot_style.clone() }
+    }
+
+    // returns the camera position and direction which overlook the whole world: above the middle
+    // of its western edge, as high as the camera may go, looking at its center
+    fn cam_overlooking_world(world_size: usize) -> (Vec3, Vec3) {
+        let half = world_size as f32 / 2.0;
+        let center = vec3(half, world_mesh::elevation_to_mesh_space_y(0.0), half);
+        let cam_pos = vec3(half, half + 10.0, -10.0);
+        (cam_pos, (center - cam_pos).normalize())
+    }
+
+    fn toggle_continuous_mode(run_mode: &mut RunMode, run_mode_log: &mut RunModeLog, source: RunModeSource, last_was_uncapped: bool, last_ticks_per_second_cap: f32) {
+        let new_run_mode = match run_mode {
             RunMode::Continuous(_) => RunMode::Paused,
             _ => {
                 let cap = if last_was_uncapped { None } else { Some(last_ticks_per_second_cap) };
                 RunMode::Continuous(cap)
             }
         };
-        let _ = tx_to_game.send(*run_mode);
+        run_mode_log.request(run_mode, new_run_mode, source);
     }
-    fn request_single_tick(run_mode: &mut RunMode, tx_to_game: &Sender<RunMode>) {
-        *run_mode = RunMode::SingleTick;
-        let _ = tx_to_game.send(*run_mode);
+    fn request_single_tick(run_mode: &mut RunMode, run_mode_log: &mut RunModeLog, source: RunModeSource) {
+        run_mode_log.request(run_mode, RunMode::SingleTick, source);
     }
-    pub fn run(mut self) -> () {
+    fn apply_annotations_request(request: AnnotationsRequest, cam_pos: &mut Vec3, cam_dir: &mut Vec3, run_mode: &mut RunMode, run_mode_log: &mut RunModeLog) {
+        if let Some((pos, dir)) = request.camera {
+            *cam_pos = pos;
+            *cam_dir = dir;
+        }
+        if let Some(tick) = request.seek {
+            run_mode_log.request(run_mode, RunMode::Seek(tick), RunModeSource::Gui);
+        }
+    }
+    fn request_ticks(run_mode: &mut RunMode, run_mode_log: &mut RunModeLog, source: RunModeSource, ticks: u32) {
+        run_mode_log.request(run_mode, RunMode::RunTicks(ticks), source);
+    }
+    fn request_step_back(run_mode: &mut RunMode, run_mode_log: &mut RunModeLog, source: RunModeSource) {
+        run_mode_log.request(run_mode, RunMode::StepBack, source);
+    }
+    // returns an error if the user closed the GUI from the diagnostics while the game thread was stalled
+    pub fn run(mut self) -> Result<(), RagnarokError> {
+        let Some(mut event_loop) = self.event_loop.take() else { return Ok(()) };
         let mut kbd_input = ProcessedKeyboardInput::default();
         let (mut cam_dir, mut cam_pos) = {
-            let robot_pos = self.world_copy.robot_position;
-            let elevation = {
-                let w = &self.world_copy;
-                w.world[w.robot_position.x as usize][w.robot_position.y as usize].as_ref().unwrap().elevation
-            };
             let cam_dir = vec3(-1.0, -1.0, -1.0).normalize();
-            let cam_pos = vec3(robot_pos.x as f32, world_mesh::elevation_to_mesh_space_y(elevation as f32), robot_pos.y as f32) - cam_dir * 30.0;
+            let cam_pos = Self::cam_pos_looking_at(&self.world_copy, self.world_copy.robot_position, cam_dir);
             (cam_dir, cam_pos)
         };
 
         let mut frame_delta_timer = FrameDeltaTimer::new();
+        let mut frame_started = Instant::now();
+        let mut on_demand = OnDemand::new();
+        let mut fps_history = DownsampledHistory::new(256);
+        let mut fps_chart = Chart::new(48, [120, 220, 120]);
 
-        let mut last_ticks_per_second_cap = 5.0_f32;
-        let mut last_was_uncapped = false;
+        let mut last_ticks_per_second_cap = self.settings.ticks_per_second_cap;
+        let mut last_was_uncapped = self.settings.uncapped;
         let mut follow_robot = false;
+        let mut follow_target = FollowTarget::Robot;
+        let mut selected_robot = 0; // when several robots run, the one found, followed and shown in the Robot panel
         let mut find_robot = false;
-        let mut enable_skybox = true;
-
+        let mut sky_mode = self.settings.sky;
+        let mut skybox = Skybox::new(&self.display)
+            .map_err(|e| eprintln!("could not create the skybox: {e}"))
+            .ok();
+        let mut shadow_map = ShadowMap::new(&self.display)
+            .map_err(|e| eprintln!("could not create the shadow map, rendering without shadows: {e}"))
+            .ok();
+        let mut applied_style = (self.settings.theme, self.settings.accent_color, self.settings.ui_scale);
+        let mut ui_scaling = false; // while the UI scale is dragged, in which case it isn't applied yet
+        let mut day_night_lighting = true;
+        let mut animate_liquids = true;
+        let animation_start = Instant::now();
+        let mut liquids_time = 0.0;
+        let mut show_clock = true;
+        let mut show_trail = true;
+        let mut constant_size_robot = false;
+        let mut frustum_culling = true;
+        let mut level_of_detail = true;
+        let mut lod_distance = 256.0;
+        let mut show_robot_marker = !self.is_preview;
+        let mut color_trail_by_energy = true;
+        let mut trail = Trail::new(self.robot_style.color);
+        let mut robot_model = RobotModel::new(&self.world_copy);
+        robot_model.show = !self.is_preview;
+        let mut teleport_network = TeleportNetwork::new();
+        let mut tile_search = TileSearch::new();
+        let mut measure_tool = MeasureTool::new();
+        let mut tick_export = TickExport::new();
+        let mut street_network = StreetNetwork::new();
+        let mut water_bodies = WaterBodies::new();
+        let mut stale_tiles = StaleTilesOverlay::new();
+        let mut content_icons = ContentIcons::new();
+        let mut minimap = Minimap::new(self.world_copy.world.len());
+        let mut map_camera = MapCamera::new(self.world_copy.world.len());
+        let mut idle_detector = IdleDetector::new(self.stuck_warning_ticks);
+        idle_detector.record(&self.world_copy);
+        let mut pinned_panels = PinnedPanels::new();
+        let mut metronome = Metronome::new();
+        let mut render_stats = RenderStats::new();
+        trail.record(&self.world_copy);
+        let mut go_to_tile = Option::<UVec2>::None;
+        let mut goto_box = GoToBox::new();
+        let mut overlook_world = false;
+        let mut take_screenshot = false;
+        let mut ticks_to_run: u32 = 10;
+        let mut key_actions = Vec::<(KeyAction, RunModeSource)>::new(); // handled once per frame
+        let mut input_recorder = InputRecorder::new();
+        let mut snapshot_file = SnapshotFile::new();
+        let mut file_drop = FileDrop::new();
+        let mut autosave = Autosave::new(self.autosave_interval);
+        let mut run_comparison = RunComparison::new();
+        let mut show_controls = false;
+        let mut show_settings = false;
+        let mut hud_windows = HudWindows::new();
+        let mut detached_view = DetachedView::new();
+        let mut change_heatmap = ChangeHeatmap::new(self.world_copy.world.len());
+        let mut pinned_world = PinnedWorld::new();
+        let mut content_changes = ContentChanges::new();
+        let mut discovery_age = DiscoveryAge::new(&self.world_copy);
+        let mut fog = FogOfWar::new();
+        let mut chunk_rebuilds = ChunkRebuilds::new();
+        let mut weather_timeline = WeatherTimeline::new();
+        weather_timeline.record(self.world_copy.tick, &self.world_copy.env_cond);
+
+        let mut simulation_clock = SimulationClock::new();
+        let mut robot_history = RobotHistory::new(500);
+        robot_history.record(&self.world_copy);
+        let mut interaction_trace = InteractionTrace::new();
+        interaction_trace.record(&self.world_copy);
+        self.energy_planner.record(&self.world_copy);
+        let mut backpack_deltas = BackpackDeltas::new();
+        backpack_deltas.record(&self.world_copy);
         let mut run_mode = RunMode::Paused;
+        let mut status_message: Option<(String, Instant)> = None; // shown in the status bar for STATUS_MESSAGE_DURATION
+        let aborted = Cell::new(false);
+        let aborted_ref = &aborted; // the closure only borrows it, so that it can be read once the loop returns
 
-        self.event_loop.run(move |ev, _window_target, _control_flow| {
+        event_loop.run_return(move |ev, window_target, _control_flow| {
             self.imgui_platform.handle_event(self.imgui_ctx.io_mut(), &self.display.gl_window().window(), &ev);
+            if let winit::event::Event::WindowEvent { .. } = ev {
+                on_demand.wake();
+            }
             match ev {
                 //close requests and keyboard input
                 winit::event::Event::WindowEvent { event, .. } => match event {
                     winit::event::WindowEvent::CloseRequested => {
-                        run_mode = RunMode::Terminate;
-                        let _ = self.tx_to_game.send(run_mode);
+                        self.run_mode_log.request(&mut run_mode, RunMode::Terminate, RunModeSource::WindowClosed);
 
                         _control_flow.set_exit();
                     },
+                    winit::event::WindowEvent::ModifiersChanged(modifiers) => {
+                        self.kbd_event_handler.process_modifiers(modifiers);
+                    }
                     winit::event::WindowEvent::KeyboardInput{ input, .. } => {
                         kbd_input = self.kbd_event_handler.process_input(input);
 
-                        if kbd_input.toggle_continuous_mode {
-                            Self::toggle_continuous_mode(&mut run_mode, &self.tx_to_game, last_was_uncapped, last_ticks_per_second_cap);
-                        } else if kbd_input.single_tick {
-                            Self::request_single_tick(&mut run_mode, &self.tx_to_game);
-                        }
-
-                        if kbd_input.toggle_follow_robot {
-                            follow_robot = !follow_robot;
-                        }
-                        find_robot = find_robot || kbd_input.find_robot;
+                        let actions = kbd_input.actions();
+                        input_recorder.record_actions(&actions);
+                        key_actions.extend(actions.into_iter().map(|action| (action, RunModeSource::Key)));
+                    }
+                    event @ (winit::event::WindowEvent::HoveredFile(_) | winit::event::WindowEvent::HoveredFileCancelled | winit::event::WindowEvent::DroppedFile(_)) => {
+                        file_drop.handle_event(&event);
                     }
                     _ => {}
                 },
-                // MainEventsCleared can be used for rendering since we don't lock the framerate
+                // MainEventsCleared can be used for rendering since we redraw continuously (unless in low power mode, see OnDemand)
                 winit::event::Event::MainEventsCleared => {
+                    if self.settings.msaa_samples != self.msaa_samples || self.settings.vsync != self.vsync {
+                        if let Err(e) = self.rebuild_context(window_target) {
+                            eprintln!("could not change the anti-aliasing or v-sync: {e}");
+                            status_message = Some((format!("could not change the anti-aliasing or v-sync: {e}"), Instant::now()));
+                        }
+                    }
+                    on_demand.set_control_flow(_control_flow, self.settings.low_power);
+                    if let Some(frame_rate_cap) = self.settings.frame_rate_cap {
+                        let frame_time = Duration::from_secs_f32(1.0 / frame_rate_cap);
+                        if let Some(left) = frame_time.checked_sub(frame_started.elapsed()) {
+                            std::thread::sleep(left);
+                        }
+                    }
+                    frame_started = Instant::now();
+                    self.health.beat(MonitoredThread::Gui);
                     let delta = frame_delta_timer.get_delta_and_reset();
+                    fps_history.push(frame_delta_timer.get_average_fps());
 
                     // update world_copy
                     {
@@ -154,38 +625,235 @@ impl GUI {
                         let mut new_world = None;
                         for mut received_world in self.rx_from_worker.try_iter() {
                             tiles_to_refresh.extend(received_world.tiles_to_refresh.drain());
+                            weather_timeline.record(received_world.tick, &received_world.env_cond);
+                            trail.record(&received_world);
+                            robot_model.record(&received_world);
+                            idle_detector.record(&received_world);
+                            simulation_clock.record(&received_world);
+                            robot_history.record(&received_world);
+                            backpack_deltas.record(&received_world);
+                            self.phase_timeline.record(&received_world);
+                            self.energy_planner.record(&received_world);
+                            change_heatmap.record(&received_world);
+                            content_changes.record(&received_world);
+                            discovery_age.record(&received_world);
+                            tick_export.record(&received_world);
+                            interaction_trace.record(&received_world);
 
                             new_world = Some(received_world);
                         }
 
+                        // while a loaded snapshot or an earlier tick is shown the worlds received aren't
+                        let mut new_world = detached_view.filter(new_world, &self.world_copy);
+                        if detached_view.is_detached() {
+                            tiles_to_refresh.clear();
+                        }
+                        if let Some(new_world) = &mut new_world {
+                            tiles_to_refresh.extend(new_world.tiles_to_refresh.drain());
+                        }
+
+                        let events = self.event_log.receive();
+                        self.energy_planner.receive(events);
+                        interaction_trace.receive(events);
+                        self.statistics_window.update();
+                        tick_export.flush();
+                        if let Some(telemetry_panel) = &mut self.telemetry_panel {
+                            telemetry_panel.receive();
+                        }
+
+                        // the game paused by itself when the breakpoint was hit
+                        if !self.breakpoint_editor.take_hits().is_empty() {
+                            self.run_mode_log.request(&mut run_mode, RunMode::Paused, RunModeSource::Breakpoint);
+                            self.breakpoint_editor.open = true;
+                        }
+                        // and when a robot crashed
+                        if self.crash_modal.receive() {
+                            self.run_mode_log.request(&mut run_mode, RunMode::Paused, RunModeSource::RobotCrash);
+                        }
+
                         if let Some(new_world) = new_world {
+                            on_demand.wake();
                             self.world_copy = new_world;
                             self.world_copy.tiles_to_refresh = tiles_to_refresh;
+                            teleport_network.update(&self.world_copy);
+                            tile_search.update(&self.world_copy);
+                            street_network.update(&self.world_copy);
+                            water_bodies.update(&self.world_copy);
+                            stale_tiles.update(&self.world_copy);
+                            fog.update(&self.world_copy);
+                            content_icons.update(&self.world_copy);
+                            minimap.update(&self.world_copy);
+                            self.run_mode_log.update(&self.world_copy);
+                            metronome.on_tick(self.world_copy.tick);
+
+                            // the replay player pauses by itself at the end of the replay
+                            if let Some(replay_ticks) = &self.replay_ticks {
+                                if self.world_copy.tick == *replay_ticks.end() {
+                                    if let RunMode::Continuous(_) = run_mode {
+                                        self.run_mode_log.request(&mut run_mode, RunMode::Paused, RunModeSource::AutoPause);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    // the snapshots shown by the detached view are already saved
+                    if !self.is_preview && !detached_view.is_detached() {
+                        autosave.update(&self.world_copy);
+                    }
+
+                    // carry out the requests of the ControlHandles, as the run controls of the window would
+                    for request in self.rx_control.try_iter() {
+                        let source = RunModeSource::ControlHandle;
+                        let continuous = matches!(run_mode, RunMode::Continuous(_));
+                        match request {
+                            ControlRequest::Pause if continuous => self.run_mode_log.request(&mut run_mode, RunMode::Paused, source),
+                            ControlRequest::Resume if !continuous => {
+                                Self::toggle_continuous_mode(&mut run_mode, &mut self.run_mode_log, source, last_was_uncapped, last_ticks_per_second_cap);
+                            }
+                            ControlRequest::SetTickCap(cap) => {
+                                match cap {
+                                    Some(cap) => {
+                                        last_ticks_per_second_cap = cap.max(1.0);
+                                        last_was_uncapped = false;
+                                    }
+                                    None => last_was_uncapped = true,
+                                }
+                                if continuous {
+                                    let cap = if last_was_uncapped { None } else { Some(last_ticks_per_second_cap) };
+                                    self.run_mode_log.request(&mut run_mode, RunMode::Continuous(cap), source);
+                                }
+                            }
+                            ControlRequest::SingleTick if !continuous => Self::request_single_tick(&mut run_mode, &mut self.run_mode_log, source),
+                            ControlRequest::RunTicks(ticks) if !continuous => Self::request_ticks(&mut run_mode, &mut self.run_mode_log, source, ticks),
+                            ControlRequest::Terminate => {
+                                self.run_mode_log.request(&mut run_mode, RunMode::Terminate, source);
+                                _control_flow.set_exit();
+                            }
+                            ControlRequest::Pause | ControlRequest::Resume | ControlRequest::SingleTick | ControlRequest::RunTicks(_) => {}
                         }
                     }
 
+                    // play back the recorded input, if any, and handle the key actions
+                    let playback = input_recorder.update(cam_pos, cam_dir, self.world_copy.tick);
+                    key_actions.extend(playback.actions.into_iter().map(|action| (action, RunModeSource::InputPlayback)));
+                    for (action, source) in key_actions.drain(..) {
+                        match action {
+                            KeyAction::ToggleContinuousMode => {
+                                Self::toggle_continuous_mode(&mut run_mode, &mut self.run_mode_log, source, last_was_uncapped, last_ticks_per_second_cap);
+                            }
+                            KeyAction::SingleTick => Self::request_single_tick(&mut run_mode, &mut self.run_mode_log, source),
+                            KeyAction::StepBack if self.replay_ticks.is_some() => {
+                                Self::request_step_back(&mut run_mode, &mut self.run_mode_log, source);
+                            }
+                            KeyAction::StepBack => {}
+                            KeyAction::FindRobot => find_robot = true,
+                            KeyAction::ToggleFollowRobot => follow_robot = !follow_robot,
+                            KeyAction::CycleFollowTarget => {
+                                let ghost_available = self.ghost_overlay.position(self.world_copy.tick).is_some();
+                                follow_target = follow_target.next(ghost_available);
+                                follow_robot = true;
+                            }
+                            KeyAction::ToggleMapView => map_camera.toggle(&mut cam_pos, cam_dir),
+                            KeyAction::Chord(ChordAction::RunTicks) => {
+                                Self::request_ticks(&mut run_mode, &mut self.run_mode_log, source, ticks_to_run);
+                            }
+                            KeyAction::Chord(ChordAction::Screenshot) => take_screenshot = true,
+                            KeyAction::Chord(ChordAction::OverlookWorld) => overlook_world = true,
+                            KeyAction::Chord(ChordAction::ToggleMinimap) => minimap.open = !minimap.open,
+                            KeyAction::Chord(ChordAction::ToggleEventLog) => {
+                                if !self.is_preview && self.replay_ticks.is_none() {
+                                    self.event_log.open = !self.event_log.open;
+                                }
+                            }
+                            KeyAction::Chord(ChordAction::ToggleGodView) => {
+                                if let Some(god_view) = &mut self.god_view {
+                                    god_view.enabled = !god_view.enabled;
+                                }
+                            }
+                        }
+                    }
 
                     // move/rotate camera
-                    kbd_input.update_cam_dir_and_pos(&mut cam_dir, &mut cam_pos, delta);
+                    if map_camera.enabled {
+                        kbd_input.update_map_view(&mut map_camera.center, &mut map_camera.half_height, delta);
+                    } else {
+                        kbd_input.update_cam_dir_and_pos(&mut cam_dir, &mut cam_pos, delta);
+                    }
+                    if let Some(annotations_editor) = &mut self.annotations_editor {
+                        let request = annotations_editor.update(cam_pos, cam_dir, self.world_copy.tick);
+                        Self::apply_annotations_request(request, &mut cam_pos, &mut cam_dir, &mut run_mode, &mut self.run_mode_log);
+                    }
+                    if let Some((pos, dir)) = playback.camera {
+                        cam_pos = pos;
+                        cam_dir = dir;
+                    }
+                    if let Some(tick) = playback.seek.filter(|_| self.replay_ticks.is_some()) {
+                        self.run_mode_log.request(&mut run_mode, RunMode::Seek(tick), RunModeSource::InputPlayback);
+                    }
 
                     // make the camera go to the robot if needed
-                    if find_robot || follow_robot {
-                        let w = &self.world_copy;
-                        let elevation = w.world[w.robot_position.x as usize][w.robot_position.y as usize].as_ref().unwrap().elevation;
-                        cam_pos = vec3(self.world_copy.robot_position.x as f32, world_mesh::elevation_to_mesh_space_y(elevation as f32), self.world_copy.robot_position.y as f32) - cam_dir * 30.0;
+                    let camera_moved_to_target = if follow_robot {
+                        // follow the model rather than the tile, so that the camera glides along with it
+                        let target = match (follow_target, self.ghost_overlay.position(self.world_copy.tick)) {
+                            (FollowTarget::Ghost, Some(ghost_position)) => ghost_position,
+                            _ => {
+                                follow_target = FollowTarget::Robot; // the ghost was hidden or unloaded
+                                match Self::selected_other_robot(&self.world_copy, selected_robot) {
+                                    Some(other_robot) => Self::tile_top(&self.world_copy, other_robot.position),
+                                    None => robot_model.position(),
+                                }
+                            }
+                        };
+                        cam_pos = target - cam_dir * 30.0;
+                        find_robot = false;
+                        true
+                    } else if find_robot {
+                        let robot_position = Self::selected_other_robot(&self.world_copy, selected_robot)
+                            .map_or(self.world_copy.robot_position, |other_robot| other_robot.position);
+                        cam_pos = Self::cam_pos_looking_at(&self.world_copy, robot_position, cam_dir);
 
                         find_robot = false;
+                        true
+                    } else if let Some(tile) = go_to_tile.take() {
+                        cam_pos = Self::cam_pos_looking_at(&self.world_copy, tile, cam_dir);
+                        true
+                    } else if std::mem::take(&mut overlook_world) {
+                        if map_camera.enabled {
+                            map_camera.overlook(self.world_copy.world.len());
+                        } else {
+                            (cam_pos, cam_dir) = Self::cam_overlooking_world(self.world_copy.world.len());
+                        }
+                        false
+                    } else { false };
+                    if map_camera.enabled && camera_moved_to_target {
+                        map_camera.center_on(cam_pos + cam_dir * 30.0);
                     }
+                    map_camera.clamp(self.world_copy.world.len());
                     let world_size = self.world_copy.world.len() as f32;
                     cam_pos.x = cam_pos.x.clamp(-10.0, world_size+10.0);
                     cam_pos.y = cam_pos.y.clamp(-world_size / 2.0 - 10.0, world_size / 2.0 + 10.0);
                     cam_pos.z = cam_pos.z.clamp(-10.0, world_size+10.0);
+                    // in the map view everything which needs the camera position uses the map camera's stand-in
+                    let eye_pos = if map_camera.enabled { map_camera.equivalent_cam_pos() } else { cam_pos };
+
+                    if self.settings.low_power && !on_demand.must_draw(eye_pos, cam_dir) {
+                        return;
+                    }
 
                     // rendering
                     {
                         let mut target = self.display.draw();
 
-                        let mvp = compute_mvp::compute_mvp(target.get_dimensions(), cam_pos, cam_dir);
+                        let mvp = if map_camera.enabled {
+                            map_camera.mvp(target.get_dimensions(), self.far_plane)
+                        } else {
+                            compute_mvp::compute_mvp(target.get_dimensions(), cam_pos, cam_dir, self.near_plane, self.far_plane)
+                        };
+                        // the sky is drawn around a camera at the origin, so that it never gets closer
+                        let sky_view_projection = compute_mvp::compute_mvp(target.get_dimensions(), vec3(0.0, 0.0, 0.0), cam_dir, self.near_plane, self.far_plane);
+                        // the logarithmic depth relies on the perspective division, which an orthographic projection doesn't have
+                        let logarithmic_depth = self.logarithmic_depth && !map_camera.enabled;
+                        let log_depth_coef = compute_mvp::log_depth_coefficient(self.far_plane);
 
                         let draw_params = glium::DrawParameters {
                             depth: glium::Depth {
@@ -193,35 +861,529 @@ impl GUI {
                                 write: true,
                                 .. Default::default()
                             },
-                            multisampling: false,
+                            multisampling: self.msaa_samples > 0,
                             dithering: false,
 
                             .. Default::default()
                         };
 
-                        target.clear_color_and_depth((0.2, 0.2, 0.2, 1.0), 1.0);
+                        let daylight = if day_night_lighting { Daylight::from_env_cond(&self.world_copy.env_cond) } else { Daylight::NEUTRAL };
+                        let unlit_uniforms = shaders::uniforms(&mvp, logarithmic_depth, log_depth_coef, &daylight, false);
+                        if animate_liquids {
+                            liquids_time = animation_start.elapsed().as_secs_f32();
+                        }
+                        let liquid_uniforms = shaders::liquid_uniforms(&mvp, logarithmic_depth, log_depth_coef, &daylight, liquids_time);
+
+                        let clear_color = self.settings.clear_color.map(|[r, g, b]| (r, g, b, 1.0)).unwrap_or(daylight.sky_color);
+                        target.clear_color_and_depth(clear_color, 1.0);
 
                         //render world
                         {
                             // update vbo with new world information
-                            self.world_mesh.update(&mut self.world_copy, &self.display, enable_skybox);
+                            let robot_scale = if constant_size_robot {
+                                // keep the robot (2.5 units tall) at least 40 pixels tall
+                                let viewport_height = target.get_dimensions().1 as f32;
+                                let size = if map_camera.enabled {
+                                    map_camera.world_size_of_pixels(40.0, viewport_height)
+                                } else {
+                                    let distance = glm::distance(&cam_pos, &robot_model.position());
+                                    picking::world_size_of_pixels(distance, 40.0, viewport_height)
+                                };
+                                (size / 2.5).max(1.0)
+                            } else { 1.0 };
+                            self.world_mesh.update(&mut self.world_copy, &self.display, robot_model.pose(), robot_scale);
                             self.world_copy.tiles_to_refresh.clear();
 
-                            target.draw(&self.world_mesh.vbo, &glium::index::NoIndices(PrimitiveType::TrianglesList),
-                                        &self.shader_program,&uniform! { mvp:  *mvp.as_ref() }, &draw_params).unwrap();
+                            // the shadows fall around the point the camera looks at (or the center of the map)
+                            let shadow_focus = if map_camera.enabled {
+                                vec3(map_camera.center.x, 0.0, map_camera.center.y)
+                            } else {
+                                eye_pos + cam_dir * 48.0
+                            };
+                            let shadow_triangles = shadow_map.as_mut()
+                                .map_or(0, |shadow_map| shadow_map.render(&self.display, self.world_mesh.chunks().map(|(_, chunk)| chunk), &daylight, shadow_focus));
+                            if shadow_triangles > 0 {
+                                render_stats.record_draw(shadow_triangles);
+                            }
+                            let uniforms = Shadowed { uniforms: shaders::uniforms(&mvp, logarithmic_depth, log_depth_coef, &daylight, true), shadow_map: shadow_map.as_ref() };
+                            let liquid_uniforms = Shadowed { uniforms: shaders::liquid_uniforms(&mvp, logarithmic_depth, log_depth_coef, &daylight, liquids_time), shadow_map: shadow_map.as_ref() };
+                            // the robot has its own colors, which mustn't be shaded
+                            target.draw(&self.world_mesh.misc_vbo, &glium::index::NoIndices(PrimitiveType::TrianglesList),
+                                        &self.shader_program, &unlit_uniforms, &draw_params).unwrap();
+                            render_stats.record_draw(self.world_mesh.misc_vbo.len() / 3);
+                            let frustum = Frustum::from_mvp(&mvp);
+                            let (mut chunks_drawn, mut chunks_simplified, mut chunks_total) = (0, 0, 0);
+                            for (_chunk_pos, chunk) in self.world_mesh.chunks() {
+                                chunks_total += 1;
+                                if frustum_culling && !frustum.intersects_aabb(chunk.min, chunk.max) {
+                                    continue;
+                                }
+                                // distance between the camera and the closest point of the chunk
+                                let distance = glm::distance(&eye_pos, &eye_pos.sup(&chunk.min).inf(&chunk.max));
+                                if level_of_detail && distance > lod_distance {
+                                    target.draw(&chunk.lod_vbo, &glium::index::NoIndices(PrimitiveType::TrianglesList),
+                                                &self.shader_program, &uniforms, &draw_params).unwrap();
+                                    render_stats.record_draw(chunk.lod_vbo.len() / 3);
+                                    chunks_simplified += 1;
+                                } else {
+                                    target.draw(&chunk.vbo, &glium::index::NoIndices(PrimitiveType::TrianglesList),
+                                                &self.shader_program, &uniforms, &draw_params).unwrap();
+                                    render_stats.record_draw(chunk.vbo.len() / 3);
+                                    if let Some(liquid_vbo) = &chunk.liquid_vbo {
+                                        target.draw(liquid_vbo, &glium::index::NoIndices(PrimitiveType::TrianglesList),
+                                                    &self.liquid_shader_program, &liquid_uniforms, &draw_params).unwrap();
+                                        render_stats.record_draw(liquid_vbo.len() / 3);
+                                    }
+                                }
+                                chunks_drawn += 1;
+                            }
+                            render_stats.record_chunks_drawn(chunks_drawn, chunks_simplified, chunks_total);
+                            render_stats.record_upload(self.world_mesh.bytes_uploaded);
+                            render_stats.record_chunks_rebuilt(self.world_mesh.rebuilt_chunks.len());
+                            chunk_rebuilds.record(&self.world_mesh);
+                        }
+
+                        //render the sky where no geometry was drawn. seen from above, it would hide the world
+                        if let Some(skybox) = skybox.as_mut().filter(|_| !map_camera.enabled) {
+                            let triangles = skybox.draw(&mut target, &self.display, &sky_view_projection, sky_mode, &self.world_copy.env_cond);
+                            render_stats.record_draw(triangles);
+                        }
+
+                        //render the whole world, dimmed, beneath the discovered one
+                        if let Some(god_view) = self.god_view.as_mut().filter(|god_view| god_view.enabled) {
+                            let god_view_mvp = GodView::mvp(&mvp);
+                            let uniforms = shaders::uniforms(&god_view_mvp, logarithmic_depth, log_depth_coef, &daylight, true);
+                            let liquid_uniforms = shaders::liquid_uniforms(&god_view_mvp, logarithmic_depth, log_depth_coef, &daylight, liquids_time);
+                            let god_view_draw_params = glium::DrawParameters {
+                                blend: glium::Blend {
+                                    color: glium::BlendingFunction::Addition {
+                                        source: glium::LinearBlendingFactor::ConstantAlpha,
+                                        destination: glium::LinearBlendingFactor::OneMinusConstantAlpha,
+                                    },
+                                    constant_value: (0.0, 0.0, 0.0, GodView::ALPHA),
+                                    .. Default::default()
+                                },
+                                .. draw_params.clone()
+                            };
+                            let frustum = Frustum::from_mvp(&god_view_mvp);
+                            for (_chunk_pos, chunk) in god_view.mesh(&self.display).chunks() {
+                                if frustum_culling && !frustum.intersects_aabb(chunk.min, chunk.max) {
+                                    continue;
+                                }
+                                target.draw(&chunk.vbo, &glium::index::NoIndices(PrimitiveType::TrianglesList),
+                                            &self.shader_program, &uniforms, &god_view_draw_params).unwrap();
+                                render_stats.record_draw(chunk.vbo.len() / 3);
+                                if let Some(liquid_vbo) = &chunk.liquid_vbo {
+                                    target.draw(liquid_vbo, &glium::index::NoIndices(PrimitiveType::TrianglesList),
+                                                &self.liquid_shader_program, &liquid_uniforms, &god_view_draw_params).unwrap();
+                                    render_stats.record_draw(liquid_vbo.len() / 3);
+                                }
+                            }
+                        }
+
+                        //render robot trail
+                        if show_trail {
+                            trail.set_color_by_energy(color_trail_by_energy);
+                            trail.set_color(self.robot_style.color);
+                            render_stats.record_upload(trail.update_vbo(&self.display));
+                            if let Some(trail_vbo) = &trail.vbo {
+                                target.draw(trail_vbo, &glium::index::NoIndices(PrimitiveType::LineStrip),
+                                            &self.shader_program, &unlit_uniforms, &draw_params).unwrap();
+                                render_stats.record_draw(0);
+                            }
+                        }
+
+                        //render the arcs of the teleport network
+                        if teleport_network.show_arcs {
+                            render_stats.record_upload(teleport_network.update_vbo(&self.display));
+                            if let Some(arcs_vbo) = &teleport_network.vbo {
+                                target.draw(arcs_vbo, &glium::index::NoIndices(PrimitiveType::LinesList),
+                                            &self.shader_program, &unlit_uniforms, &draw_params).unwrap();
+                                render_stats.record_draw(0);
+                            }
+                        }
+
+                        //render the street network overlay
+                        if street_network.show_overlay {
+                            render_stats.record_upload(street_network.update_vbo(&self.display));
+                            if let Some(streets_vbo) = &street_network.vbo {
+                                target.draw(streets_vbo, &glium::index::NoIndices(PrimitiveType::LinesList),
+                                            &self.shader_program, &unlit_uniforms, &draw_params).unwrap();
+                                render_stats.record_draw(0);
+                            }
+                        }
+
+                        //render the outlines of the tiles found by the tile search
+                        if tile_search.show() {
+                            render_stats.record_upload(tile_search.update_vbo(&self.display));
+                            if let Some(tile_search_vbo) = &tile_search.vbo {
+                                target.draw(tile_search_vbo, &glium::index::NoIndices(PrimitiveType::LinesList),
+                                            &self.shader_program, &unlit_uniforms, &draw_params).unwrap();
+                                render_stats.record_draw(0);
+                            }
+                        }
+
+                        //render the tiles where the robot's map is out of date
+                        if self.god_view.is_some() && stale_tiles.show {
+                            render_stats.record_upload(stale_tiles.update_vbo(&self.display));
+                            if let Some(stale_tiles_vbo) = &stale_tiles.vbo {
+                                target.draw(stale_tiles_vbo, &glium::index::NoIndices(PrimitiveType::LinesList),
+                                            &self.shader_program, &unlit_uniforms, &draw_params).unwrap();
+                                render_stats.record_draw(0);
+                            }
+                        }
+
+                        //render the rebuild statistics of the chunks
+                        if chunk_rebuilds.show {
+                            render_stats.record_upload(chunk_rebuilds.update_vbo(&self.display, &self.world_mesh));
+                            if let Some(chunk_rebuilds_vbo) = &chunk_rebuilds.vbo {
+                                let chunk_rebuilds_draw_params = glium::DrawParameters {
+                                    blend: glium::Blend {
+                                        color: glium::BlendingFunction::Addition {
+                                            source: glium::LinearBlendingFactor::ConstantAlpha,
+                                            destination: glium::LinearBlendingFactor::OneMinusConstantAlpha,
+                                        },
+                                        constant_value: (0.0, 0.0, 0.0, ChunkRebuilds::ALPHA),
+                                        .. Default::default()
+                                    },
+                                    .. draw_params.clone()
+                                };
+                                target.draw(chunk_rebuilds_vbo, &glium::index::NoIndices(PrimitiveType::TrianglesList),
+                                            &self.shader_program, &unlit_uniforms, &chunk_rebuilds_draw_params).unwrap();
+                                render_stats.record_draw(chunk_rebuilds_vbo.len() / 3);
+                            }
+                        }
+
+                        //render the fog over the undiscovered tiles, unless the god view shows them
+                        if fog.enabled && !self.god_view.as_ref().is_some_and(|god_view| god_view.enabled) {
+                            render_stats.record_upload(fog.update_vbo(&self.display, &self.world_copy));
+                            if let Some(fog_vbo) = &fog.vbo {
+                                let fog_draw_params = glium::DrawParameters {
+                                    blend: glium::Blend {
+                                        color: glium::BlendingFunction::Addition {
+                                            source: glium::LinearBlendingFactor::ConstantAlpha,
+                                            destination: glium::LinearBlendingFactor::OneMinusConstantAlpha,
+                                        },
+                                        constant_value: (0.0, 0.0, 0.0, FogOfWar::ALPHA),
+                                        .. Default::default()
+                                    },
+                                    .. draw_params.clone()
+                                };
+                                target.draw(fog_vbo, &glium::index::NoIndices(PrimitiveType::TrianglesList),
+                                            &self.shader_program, &unlit_uniforms, &fog_draw_params).unwrap();
+                                render_stats.record_draw(fog_vbo.len() / 3);
+                            }
+                        }
+
+                        //render the highlights of the contents which changed
+                        content_changes.update(&self.world_copy);
+                        render_stats.record_upload(content_changes.update_vbo(&self.display));
+                        if let Some(content_changes_vbo) = &content_changes.vbo {
+                            target.draw(content_changes_vbo, &glium::index::NoIndices(PrimitiveType::LinesList),
+                                        &self.shader_program, &unlit_uniforms, &draw_params).unwrap();
+                            render_stats.record_draw(0);
+                        }
+
+                        //render the heatmap of the selected tile layer
+                        if let Some(tile_layers) = self.tile_layers.as_mut().filter(|tile_layers| tile_layers.show) {
+                            tile_layers.update(&self.world_copy);
+                            render_stats.record_upload(tile_layers.update_vbo(&self.display));
+                            if let Some(heatmap_vbo) = &tile_layers.vbo {
+                                let heatmap_draw_params = glium::DrawParameters {
+                                    blend: glium::Blend {
+                                        color: glium::BlendingFunction::Addition {
+                                            source: glium::LinearBlendingFactor::ConstantAlpha,
+                                            destination: glium::LinearBlendingFactor::OneMinusConstantAlpha,
+                                        },
+                                        constant_value: (0.0, 0.0, 0.0, tile_layers.opacity),
+                                        .. Default::default()
+                                    },
+                                    .. draw_params.clone()
+                                };
+                                target.draw(heatmap_vbo, &glium::index::NoIndices(PrimitiveType::TrianglesList),
+                                            &self.shader_program, &unlit_uniforms, &heatmap_draw_params).unwrap();
+                                render_stats.record_draw(heatmap_vbo.len() / 3);
+                            }
+                        }
+
+                        //render the heatmap of the tile changes
+                        if change_heatmap.show {
+                            change_heatmap.update(&self.world_copy);
+                            render_stats.record_upload(change_heatmap.update_vbo(&self.display));
+                            if let Some(heatmap_vbo) = &change_heatmap.vbo {
+                                let heatmap_draw_params = glium::DrawParameters {
+                                    blend: glium::Blend {
+                                        color: glium::BlendingFunction::Addition {
+                                            source: glium::LinearBlendingFactor::ConstantAlpha,
+                                            destination: glium::LinearBlendingFactor::OneMinusConstantAlpha,
+                                        },
+                                        constant_value: (0.0, 0.0, 0.0, change_heatmap.opacity),
+                                        .. Default::default()
+                                    },
+                                    .. draw_params.clone()
+                                };
+                                target.draw(heatmap_vbo, &glium::index::NoIndices(PrimitiveType::TrianglesList),
+                                            &self.shader_program, &unlit_uniforms, &heatmap_draw_params).unwrap();
+                                render_stats.record_draw(heatmap_vbo.len() / 3);
+                            }
+                        }
+
+                        //render the age of the discovered tiles
+                        if discovery_age.show {
+                            discovery_age.update(&self.world_copy);
+                            render_stats.record_upload(discovery_age.update_vbo(&self.display));
+                            if let Some(discovery_vbo) = &discovery_age.vbo {
+                                let discovery_draw_params = glium::DrawParameters {
+                                    blend: glium::Blend {
+                                        color: glium::BlendingFunction::Addition {
+                                            source: glium::LinearBlendingFactor::ConstantAlpha,
+                                            destination: glium::LinearBlendingFactor::OneMinusConstantAlpha,
+                                        },
+                                        constant_value: (0.0, 0.0, 0.0, discovery_age.opacity),
+                                        .. Default::default()
+                                    },
+                                    .. draw_params.clone()
+                                };
+                                target.draw(discovery_vbo, &glium::index::NoIndices(PrimitiveType::TrianglesList),
+                                            &self.shader_program, &unlit_uniforms, &discovery_draw_params).unwrap();
+                                render_stats.record_draw(discovery_vbo.len() / 3);
+                            }
+                        }
+
+                        //render the arrows of the selected vector field
+                        if let Some(vector_fields) = self.vector_fields.as_mut().filter(|vector_fields| vector_fields.show) {
+                            vector_fields.update(&self.world_copy);
+                            render_stats.record_upload(vector_fields.update_vbo(&self.display));
+                            if let Some(arrows_vbo) = &vector_fields.vbo {
+                                target.draw(arrows_vbo, &glium::index::NoIndices(PrimitiveType::LinesList),
+                                            &self.shader_program, &unlit_uniforms, &draw_params).unwrap();
+                                render_stats.record_draw(0);
+                            }
+                        }
+
+                        //render the outline of the selected water body
+                        {
+                            render_stats.record_upload(water_bodies.update_vbo(&self.display));
+                            if let Some(outline_vbo) = &water_bodies.vbo {
+                                target.draw(outline_vbo, &glium::index::NoIndices(PrimitiveType::LinesList),
+                                            &self.shader_program, &unlit_uniforms, &draw_params).unwrap();
+                                render_stats.record_draw(0);
+                            }
+                        }
+
+                        //render the ghost of the loaded replay, if any
+                        {
+                            let (ghost_vbo, bytes_uploaded) = self.ghost_overlay.update_vbo(&self.display, self.world_copy.tick);
+                            render_stats.record_upload(bytes_uploaded);
+                            if let Some(ghost_vbo) = ghost_vbo {
+                                let ghost_draw_params = glium::DrawParameters {
+                                    blend: glium::Blend {
+                                        color: glium::BlendingFunction::Addition {
+                                            source: glium::LinearBlendingFactor::ConstantAlpha,
+                                            destination: glium::LinearBlendingFactor::OneMinusConstantAlpha,
+                                        },
+                                        constant_value: (0.0, 0.0, 0.0, GhostOverlay::ALPHA),
+                                        .. Default::default()
+                                    },
+                                    .. draw_params.clone()
+                                };
+                                target.draw(ghost_vbo, &glium::index::NoIndices(PrimitiveType::LineStrip),
+                                            &self.shader_program, &unlit_uniforms, &ghost_draw_params).unwrap();
+                                render_stats.record_draw(0);
+                            }
                         }
 
                         //render imgui
                         {
+                            fps_chart.update(&fps_history, &self.display, &mut self.imgui_renderer);
+                            render_stats.update_chart(&self.display, &mut self.imgui_renderer);
+                            if let Some(telemetry_panel) = &mut self.telemetry_panel {
+                                telemetry_panel.update_charts(&self.display, &mut self.imgui_renderer);
+                            }
+                            robot_history.update_charts(&self.display, &mut self.imgui_renderer);
+                            self.statistics_window.update_charts(&self.display, &mut self.imgui_renderer);
+                            minimap.update_texture(&self.display, &mut self.imgui_renderer);
+                            let pinned_triangles = pinned_world.render(&self.display, &mut self.imgui_renderer, target.get_dimensions(), &mvp,
+                                                                       (&self.shader_program, &self.liquid_shader_program), logarithmic_depth, log_depth_coef,
+                                                                       day_night_lighting, skybox.as_mut().filter(|_| !map_camera.enabled).map(|skybox| (skybox, sky_mode, &sky_view_projection)), liquids_time);
+                            render_stats.record_draw(pinned_triangles);
+
+                            self.layouts.before_frame(&mut self.imgui_ctx);
+                            if !ui_scaling {
+                                let scale_factor = self.imgui_platform.hidpi_factor() as f32;
+                                self.fonts.update(&mut self.imgui_ctx, &mut self.imgui_renderer, scale_factor, self.settings.ui_scale);
+                                if applied_style != (self.settings.theme, self.settings.accent_color, self.settings.ui_scale) {
+                                    applied_style = (self.settings.theme, self.settings.accent_color, self.settings.ui_scale);
+                                    applied_style.0.apply(applied_style.1, applied_style.2, &self.default_style, self.imgui_ctx.style_mut());
+                                }
+                            }
                             self.imgui_platform.prepare_frame(self.imgui_ctx.io_mut(), self.display.gl_window().window()).unwrap();
                             let ui = self.imgui_ctx.new_frame();
                             self.imgui_platform.prepare_render(&ui, self.display.gl_window().window());
 
-                            ui.window("Ragnarok")
-                                .size([300.0, 550.0], Condition::FirstUseEver)
-                                .build(|| {
-                                    if ui.collapsing_header("Simulation settings", TreeNodeFlags::DEFAULT_OPEN) {
-                                        ui.indent();
+                            // the menu bar queues key actions, handled in the next frame like those from the keyboard
+                            ui.main_menu_bar(|| {
+                                let bindings = self.kbd_event_handler.bindings().clone();
+                                ui.menu("File", || {
+                                    ui.menu_item_config("Save/load snapshot...").build_with_ref(&mut snapshot_file.open);
+                                    if ui.menu_item("Save session") {
+                                        let markers = MarkerStyles { robot: self.robot_style.clone(), other_robots: self.other_robot_styles.clone(), ghost: self.ghost_overlay.style.clone() };
+                                        let message = match session::save_session(&self.world_copy, cam_pos, cam_dir, markers) {
+                                            Ok(path) => format!("session saved to {}", path.display()),
+                                            Err(e) => format!("could not save the session: {e}"),
+                                        };
+                                        status_message = Some((message, Instant::now()));
+                                    }
+                                    if ui.menu_item("Open session") {
+                                        let message = match session::load_session(self.world_copy.world.len()) {
+                                            Ok((world, session_cam_pos, session_cam_dir, markers)) => {
+                                                let message = format!("showing the session saved at tick {}", world.tick);
+                                                detached_view.request(ViewRequest::Show(world));
+                                                (cam_pos, cam_dir) = (session_cam_pos, session_cam_dir);
+                                                if let Some(markers) = markers {
+                                                    self.robot_style = markers.robot;
+                                                    self.other_robot_styles = markers.other_robots;
+                                                    self.ghost_overlay.style = markers.ghost;
+                                                }
+                                                message
+                                            }
+                                            Err(e) => format!("could not open the session: {e}"),
+                                        };
+                                        status_message = Some((message, Instant::now()));
+                                    }
+                                    ui.menu_item_config("Input recording...").build_with_ref(&mut input_recorder.open);
+                                    if ui.menu_item("Pin the current state") {
+                                        pinned_world.pin(&self.world_copy, &self.display);
+                                    }
+                                    if pinned_world.is_pinned() {
+                                        ui.menu_item_config("Pinned state").build_with_ref(&mut pinned_world.open);
+                                    }
+                                    if ui.menu_item_config("Screenshot").shortcut(bindings.chord_shortcut(ChordAction::Screenshot).unwrap_or_default()).build() {
+                                        key_actions.push((KeyAction::Chord(ChordAction::Screenshot), RunModeSource::Gui));
+                                    }
+                                    ui.separator();
+                                    if ui.menu_item("Exit") {
+                                        self.run_mode_log.request(&mut run_mode, RunMode::Terminate, RunModeSource::Gui);
+                                        _control_flow.set_exit();
+                                    }
+                                });
+                                ui.menu("View", || {
+                                    hud_windows.draw_menu(&ui, self.is_preview);
+                                    ui.menu("Panels", || {
+                                        if !self.is_preview && self.replay_ticks.is_none() {
+                                            ui.menu_item_config("Event log")
+                                                .shortcut(bindings.chord_shortcut(ChordAction::ToggleEventLog).unwrap_or_default())
+                                                .build_with_ref(&mut self.event_log.open);
+                                            ui.menu_item_config("Breakpoints").build_with_ref(&mut self.breakpoint_editor.open);
+                                            ui.menu_item_config("Energy planner").build_with_ref(&mut self.energy_planner.open);
+                                        }
+                                        ui.menu_item_config("Event journal").build_with_ref(&mut self.journal_viewer.open);
+                                        if let Some(annotations_editor) = &mut self.annotations_editor {
+                                            ui.menu_item_config("Annotations").build_with_ref(&mut annotations_editor.open);
+                                        }
+                                        if let Some(telemetry_panel) = &mut self.telemetry_panel {
+                                            ui.menu_item_config("Telemetry").build_with_ref(&mut telemetry_panel.open);
+                                        }
+                                        ui.menu_item_config("Content changes").build_with_ref(&mut content_changes.open);
+                                        ui.menu_item_config("Run comparison").build_with_ref(&mut run_comparison.open);
+                                        ui.menu_item_config("Measure").build_with_ref(&mut measure_tool.open);
+                                        ui.menu_item_config("Statistics").build_with_ref(&mut self.statistics_window.open);
+                                        ui.menu_item_config("Tick export").build_with_ref(&mut tick_export.open);
+                                        ui.menu_item_config("Interaction trace").build_with_ref(&mut interaction_trace.open);
+                                        ui.menu_item_config("Minimap")
+                                            .shortcut(bindings.chord_shortcut(ChordAction::ToggleMinimap).unwrap_or_default())
+                                            .build_with_ref(&mut minimap.open);
+                                    });
+                                    ui.menu("Overlays", || {
+                                        ui.menu_item_config("Ghost replay").build_with_ref(&mut self.ghost_overlay.open);
+                                        ui.menu_item_config("Teleport network").build_with_ref(&mut teleport_network.open);
+                                        ui.menu_item_config("Tile search").build_with_ref(&mut tile_search.open);
+                                        ui.menu_item_config("Street network").build_with_ref(&mut street_network.open);
+                                        ui.menu_item_config("Content icons").build_with_ref(&mut content_icons.open);
+                                        ui.menu_item_config("Change heatmap").build_with_ref(&mut change_heatmap.show);
+                                        ui.menu_item_config("Discovery age").build_with_ref(&mut discovery_age.show);
+                                        if let Some(tile_layers) = &mut self.tile_layers {
+                                            ui.menu_item_config("Tile layers").build_with_ref(&mut tile_layers.open);
+                                        }
+                                        if let Some(vector_fields) = &mut self.vector_fields {
+                                            ui.menu_item_config("Vector fields").build_with_ref(&mut vector_fields.open);
+                                        }
+                                        if let Some(god_view) = &mut self.god_view {
+                                            ui.menu_item_config("God view")
+                                                .shortcut(bindings.chord_shortcut(ChordAction::ToggleGodView).unwrap_or_default())
+                                                .build_with_ref(&mut god_view.enabled);
+                                            ui.menu_item_config("Stale tiles").build_with_ref(&mut stale_tiles.show);
+                                        }
+                                    });
+                                    ui.separator();
+                                    if ui.menu_item_config("Top-down map view").shortcut(bindings.key_shortcut(KeyBinding::ToggleMapView).unwrap_or_default()).selected(map_camera.enabled).build() {
+                                        key_actions.push((KeyAction::ToggleMapView, RunModeSource::Gui));
+                                    }
+                                    if ui.menu_item_config("Find the robot").shortcut(bindings.key_shortcut(KeyBinding::FindRobot).unwrap_or_default()).build() {
+                                        key_actions.push((KeyAction::FindRobot, RunModeSource::Gui));
+                                    }
+                                    ui.text("Go to tile");
+                                    ui.same_line();
+                                    if let Some(tile) = goto_box.draw(&ui, self.world_copy.world.len()) {
+                                        go_to_tile = Some(tile);
+                                        follow_robot = false;
+                                        ui.close_current_popup();
+                                    }
+                                    if ui.menu_item_config("Follow the robot").shortcut(bindings.key_shortcut(KeyBinding::ToggleFollowRobot).unwrap_or_default()).selected(follow_robot).build() {
+                                        key_actions.push((KeyAction::ToggleFollowRobot, RunModeSource::Gui));
+                                    }
+                                    if ui.menu_item_config("Overlook the world").shortcut(bindings.chord_shortcut(ChordAction::OverlookWorld).unwrap_or_default()).build() {
+                                        key_actions.push((KeyAction::Chord(ChordAction::OverlookWorld), RunModeSource::Gui));
+                                    }
+                                    if self.crash_modal.has_crashed() && ui.menu_item("Last robot crash") {
+                                        self.crash_modal.reopen();
+                                    }
+                                    ui.separator();
+                                    ui.menu_item_config("Settings").build_with_ref(&mut show_settings);
+                                    self.layouts.draw_menu(&ui);
+                                });
+                                if !self.is_preview {
+                                    ui.menu("Simulation", || {
+                                        let continuous = matches!(run_mode, RunMode::Continuous(_));
+                                        let shortcut = bindings.key_shortcut(KeyBinding::ToggleContinuousMode).unwrap_or_default();
+                                        if ui.menu_item_config(if continuous { "Pause" } else { "Run" }).shortcut(shortcut).build() {
+                                            key_actions.push((KeyAction::ToggleContinuousMode, RunModeSource::Gui));
+                                        }
+                                        if ui.menu_item_config("Step").shortcut(bindings.key_shortcut(KeyBinding::SingleTick).unwrap_or_default()).enabled(!continuous).build() {
+                                            key_actions.push((KeyAction::SingleTick, RunModeSource::Gui));
+                                        }
+                                        if ui.menu_item_config(format!("Run for {ticks_to_run} ticks")).shortcut(bindings.chord_shortcut(ChordAction::RunTicks).unwrap_or_default()).enabled(!continuous).build() {
+                                            key_actions.push((KeyAction::Chord(ChordAction::RunTicks), RunModeSource::Gui));
+                                        }
+                                        if self.replay_ticks.is_some() && ui.menu_item_config("Step back").shortcut(bindings.key_shortcut(KeyBinding::StepBack).unwrap_or_default()).enabled(!continuous).build() {
+                                            key_actions.push((KeyAction::StepBack, RunModeSource::Gui));
+                                        }
+                                    });
+                                }
+                                ui.menu("Help", || {
+                                    ui.menu_item_config("Controls").build_with_ref(&mut show_controls);
+                                    ui.menu_item_config("Key bindings").build_with_ref(&mut self.key_bindings_editor.open);
+                                });
+                            });
+                            if show_controls {
+                                ui.window("Controls")
+                                    .opened(&mut show_controls)
+                                    .size([420.0, 400.0], Condition::FirstUseEver)
+                                    .build(|| ui.text_wrapped(self.kbd_event_handler.get_explanation()));
+                            }
+
+                            // the HUD is split into windows which can be docked around the 3D view (or to one another)
+                            ui.dockspace_over_main_viewport();
+                            let [display_width, _] = ui.io().display_size;
+                            if !self.is_preview && hud_windows.simulation {
+                                ui.window("Simulation")
+                                    .opened(&mut hud_windows.simulation)
+                                    .position([10.0, 30.0], Condition::FirstUseEver)
+                                    .size([300.0, 400.0], Condition::FirstUseEver)
+                                    .build(|| {
+                                        simulation_clock.draw(&ui, &self.world_copy);
+                                        self.phase_timeline.draw(&ui, self.world_copy.tick);
+                                        ui.separator();
 
                                         let continuous = match run_mode {
                                             RunMode::Continuous(_) => true,
@@ -230,17 +1392,57 @@ impl GUI {
 
                                         let btn_text = if continuous {"Stop"} else {"Run"};
                                         if ui.button(btn_text) {
-                                            Self::toggle_continuous_mode(&mut run_mode, &self.tx_to_game, last_was_uncapped, last_ticks_per_second_cap);
+                                            Self::toggle_continuous_mode(&mut run_mode, &mut self.run_mode_log, RunModeSource::Gui, last_was_uncapped, last_ticks_per_second_cap);
                                         }
 
                                         ui.same_line();
 
                                         ui.disabled(continuous, || {
+                                            if self.replay_ticks.is_some() {
+                                                if ui.button("Step back") {
+                                                    Self::request_step_back(&mut run_mode, &mut self.run_mode_log, RunModeSource::Gui);
+                                                }
+                                                ui.same_line();
+                                            }
                                             if ui.button("Run single tick") {
-                                                Self::request_single_tick(&mut run_mode, &self.tx_to_game);
+                                                Self::request_single_tick(&mut run_mode, &mut self.run_mode_log, RunModeSource::Gui);
                                             }
+                                            if ui.button("Run for") {
+                                                Self::request_ticks(&mut run_mode, &mut self.run_mode_log, RunModeSource::Gui, ticks_to_run);
+                                            }
+                                            ui.same_line();
+                                            ui.set_next_item_width(80.0);
+                                            ui.input_scalar("ticks##ticks_to_run", &mut ticks_to_run).step(1).build();
+                                            ticks_to_run = ticks_to_run.max(1);
                                         });
 
+                                        if let Some(replay_ticks) = &self.replay_ticks {
+                                            let mut tick = self.world_copy.tick;
+                                            if ui.slider("tick", *replay_ticks.start(), *replay_ticks.end(), &mut tick) {
+                                                self.run_mode_log.request(&mut run_mode, RunMode::Seek(tick), RunModeSource::Gui);
+                                            }
+                                        }
+                                        if let Some(rewind) = &mut self.rewind {
+                                            let live_tick = detached_view.live_tick().unwrap_or(self.world_copy.tick);
+                                            if let Some(request) = rewind.draw(&ui, live_tick, detached_view.is_detached()) {
+                                                detached_view.request(request);
+                                            }
+                                        }
+                                        if let Some(history) = &self.replay_history {
+                                            const MIB: usize = 1024 * 1024;
+                                            let (used, budget) = history.memory_usage();
+                                            ui.text(format!("History: {:.1} MiB", used as f32 / MIB as f32));
+                                            let mut budget_mib = budget / MIB;
+                                            if ui.slider_config("history budget (MiB)", 64, 16384)
+                                                .flags(SliderFlags::LOGARITHMIC)
+                                                .build(&mut budget_mib) {
+                                                history.set_budget(budget_mib * MIB);
+                                            }
+                                            if ui.is_item_hovered() {
+                                                ui.tooltip_text("the oldest ticks of the replay are dropped when the budget is exceeded");
+                                            }
+                                        }
+
                                         let mut changed = false;
 
                                         let greyed_out_text_if_not_continuous = if !continuous {
@@ -253,96 +1455,536 @@ impl GUI {
                                             Some(ui.push_style_color(StyleColor::Text, [0.4, 0.4, 0.4, 1.0]))
                                         } else { None };
 
-                                       changed = changed || ui.slider_config("speed", 1.0, 200.0)
-                                           .flags(SliderFlags::LOGARITHMIC)
-                                           .build(&mut last_ticks_per_second_cap);
+                                        changed |= speed_dial::speed_dial(&ui, "speed", &mut last_ticks_per_second_cap);
                                         if let Some(t) = greyed_out_text_if_uncapped { t.pop(); }
                                         if let Some(t) = greyed_out_text_if_not_continuous { t.pop(); }
 
                                         let cap = if last_was_uncapped { None } else { Some(last_ticks_per_second_cap) };
                                         if changed && continuous {
-                                            run_mode = RunMode::Continuous(cap);
-                                            let _ = self.tx_to_game.send(run_mode);
+                                            self.run_mode_log.request(&mut run_mode, RunMode::Continuous(cap), RunModeSource::Gui);
                                         }
 
-                                        ui.unindent();
-                                    }
-
-                                    ui.separator();
+                                        ui.slider_config("stuck warning (ticks)", 0, 10000)
+                                            .flags(SliderFlags::LOGARITHMIC)
+                                            .build(&mut idle_detector.threshold);
+                                        if ui.is_item_hovered() {
+                                            ui.tooltip_text("warn when the robot's position, energy and backpack don't change for this many ticks (0 disables the warning)");
+                                        }
 
-                                    if ui.collapsing_header("Robot", TreeNodeFlags::DEFAULT_OPEN) {
-                                        ui.indent();
+                                        self.run_mode_log.draw(&ui);
 
-                                        ui.checkbox("Follow robot", &mut follow_robot);
-                                        ui.disabled(follow_robot, || {
-                                            ui.same_line();
-                                            find_robot = find_robot || ui.button("Find robot");
+                                        ui.checkbox("Metronome pulse", &mut metronome.visual);
+                                        ui.same_line();
+                                        ui.disabled(!Metronome::audio_is_supported(), || {
+                                            ui.checkbox("Sound", &mut metronome.audible);
                                         });
+                                        if !Metronome::audio_is_supported() && ui.is_item_hovered() {
+                                            ui.tooltip_text("ragnarok was built without the \"audio\" feature");
+                                        }
+                                    });
+                            }
+
+                            if hud_windows.robot && (!self.is_preview || self.ghost_overlay.is_loaded()) {
+                                ui.window("Robot")
+                                    .opened(&mut hud_windows.robot)
+                                    .position([10.0, 440.0], Condition::FirstUseEver)
+                                    .size([300.0, 300.0], Condition::FirstUseEver)
+                                    .build(|| {
+                                        if !self.is_preview {
+                                            if !self.world_copy.other_robots.is_empty() {
+                                                let names: Vec<String> = (1..=self.world_copy.other_robots.len() + 1).map(|i| format!("robot {i}")).collect();
+                                                selected_robot = selected_robot.min(names.len() - 1);
+                                                ui.combo_simple_string("selected robot", &mut selected_robot, &names);
+
+                                                // the maps of the robots disagree on the tiles they saw at different times
+                                                let current = self.map_merge.policy();
+                                                if let Some(_combo) = ui.begin_combo("map view", current.name()) {
+                                                    let policies = [MergePolicy::Union, MergePolicy::LatestWriter].into_iter()
+                                                        .chain((0..names.len()).map(MergePolicy::Robot));
+                                                    for policy in policies {
+                                                        if ui.selectable_config(policy.name()).selected(policy == current).build() {
+                                                            self.map_merge.set_policy(policy);
+                                                        }
+                                                    }
+                                                }
+                                                if ui.is_item_hovered() {
+                                                    ui.tooltip_text("how the maps of the robots are merged, from the next tick");
+                                                }
+                                            }
 
-                                        ui.text_wrapped(format!("Position: {:?}", self.world_copy.robot_position.as_ref()));
+                                            let mut following = follow_robot && follow_target == FollowTarget::Robot;
+                                            if ui.checkbox("Follow robot", &mut following) {
+                                                follow_robot = following;
+                                                follow_target = FollowTarget::Robot;
+                                            }
+                                            ui.disabled(following, || {
+                                                ui.same_line();
+                                                find_robot = find_robot || ui.button("Find robot");
+                                            });
+                                            if let Some(tile) = goto_box.draw(&ui, self.world_copy.world.len()) {
+                                                go_to_tile = Some(tile);
+                                                follow_robot = false;
+                                            }
 
-                                        ui.text_wrapped("Energy:");
-                                        ui.same_line();
-                                        imgui::ProgressBar::new(self.world_copy.energy as f32 / 1000.0)
-                                            .overlay_text(format!("{}", self.world_copy.energy))
-                                            .build(&ui);
+                                            ui.checkbox("Robot model", &mut robot_model.show);
+                                            ui.same_line();
+                                            ui.checkbox("Smooth movement", &mut robot_model.smooth);
+                                            if robot_model.smooth {
+                                                ui.checkbox("Match tick rate", &mut robot_model.match_tick_rate);
+                                                ui.same_line();
+                                                ui.disabled(robot_model.match_tick_rate, || {
+                                                    ui.slider_config("duration (s)", 0.02, 2.0)
+                                                        .flags(SliderFlags::LOGARITHMIC)
+                                                        .build(&mut robot_model.duration);
+                                                });
+                                            }
 
-                                        let mut backpack_is_empty = true;
-                                        if ui.collapsing_header("Backpack:", TreeNodeFlags::DEFAULT_OPEN) {
-                                            ui.indent();
+                                            ui.checkbox("Robot marker", &mut show_robot_marker);
+                                            ui.same_line();
+                                            ui.checkbox("Constant size robot", &mut constant_size_robot);
 
-                                            for (k, v) in self.world_copy.backpack.iter() {
-                                                if *v != 0 {
-                                                    ui.text_wrapped(format!("{k}: {v}"));
-                                                    backpack_is_empty = false;
+                                            ui.checkbox("Show trail", &mut show_trail);
+                                            ui.same_line();
+                                            ui.disabled(!show_trail, || {
+                                                ui.checkbox("Color by energy", &mut color_trail_by_energy);
+                                            });
+
+                                            if let Some(_node) = ui.tree_node("Marker style") {
+                                                match selected_robot.checked_sub(1).filter(|i| *i < self.world_copy.other_robots.len()) {
+                                                    Some(i) => {
+                                                        while self.other_robot_styles.len() <= i {
+                                                            let style = Self::default_other_robot_style(&self.robot_style, self.other_robot_styles.len());
+                                                            self.other_robot_styles.push(style);
+                                                        }
+                                                        markers::edit_marker_style(&ui, &format!("other robot {i}"), &mut self.other_robot_styles[i]);
+                                                    }
+                                                    None => markers::edit_marker_style(&ui, "robot", &mut self.robot_style),
                                                 }
                                             }
-                                            if backpack_is_empty {
-                                                ui.text_wrapped("(empty)");
-                                            }
 
-                                            ui.unindent();
+                                            match Self::selected_other_robot(&self.world_copy, selected_robot) {
+                                                Some(other_robot) => robot_panels::draw_robot_status(&ui, "other robot", *other_robot.position.as_ref(), other_robot.energy, &other_robot.backpack,
+                                                                                                     Some(other_robot.backpack_size), backpack_deltas.of_robot(selected_robot, self.world_copy.tick)),
+                                                None => robot_panels::draw_robot_status(&ui, "robot", *self.world_copy.robot_position.as_ref(), self.world_copy.energy, &self.world_copy.backpack,
+                                                                                        self.world_copy.backpack_size, backpack_deltas.of_robot(0, self.world_copy.tick)),
+                                            }
+                                            robot_history.draw(&ui);
                                         }
+                                        if !self.is_preview && self.ghost_overlay.is_loaded() {
+                                            ui.separator();
+                                        }
+                                        if self.ghost_overlay.is_loaded() {
+                                            if ui.collapsing_header("Ghost", TreeNodeFlags::DEFAULT_OPEN) {
+                                                ui.indent();
+
+                                                let mut following = follow_robot && follow_target == FollowTarget::Ghost;
+                                                if ui.checkbox("Follow ghost", &mut following) {
+                                                    follow_robot = following;
+                                                    follow_target = if following { FollowTarget::Ghost } else { FollowTarget::Robot };
+                                                }
+                                                self.ghost_overlay.draw_status(&ui, self.world_copy.tick);
 
-                                        ui.unindent()
-                                    }
-
-                                    ui.separator();
-
-                                    if ui.collapsing_header("Environmental conditions", TreeNodeFlags::DEFAULT_OPEN) {
-                                        ui.indent();
+                                                ui.unindent()
+                                            }
+                                        }
+                                    });
+                            }
+
+                            if hud_windows.environment {
+                                ui.window("Environment")
+                                    .opened(&mut hud_windows.environment)
+                                    .position([display_width - 310.0, 30.0], Condition::FirstUseEver)
+                                    .size([300.0, 320.0], Condition::FirstUseEver)
+                                    .build(|| {
                                         let env = &self.world_copy.env_cond;
                                         ui.text_wrapped(format!("Time of day: {}, {:?}", env.get_time_of_day_string(), env.get_time_of_day()));
                                         ui.text_wrapped(format!("Weather: {:?}", env.get_weather_condition()));
-                                        ui.checkbox("Enable skybox", &mut enable_skybox);
+                                        weather_timeline.draw(&ui);
+                                        Skybox::draw_mode_combo(&ui, &mut sky_mode);
+                                        ui.same_line();
+                                        ui.checkbox("Show clock", &mut show_clock);
+                                        ui.checkbox("Day/night lighting", &mut day_night_lighting);
+                                        if let Some(shadow_map) = &mut shadow_map {
+                                            ui.same_line();
+                                            ui.disabled(!day_night_lighting, || { ui.checkbox("Shadows", &mut shadow_map.enabled); });
+                                            if ui.is_item_hovered() {
+                                                ui.tooltip_text("the terrain and the contents cast shadows from the sun and the moon (disable on slow GPUs)");
+                                            }
+                                        }
+                                        ui.checkbox("Animated water and lava", &mut animate_liquids);
+                                        let mut ambient_occlusion = self.world_mesh.ambient_occlusion();
+                                        ui.set_next_item_width(120.0);
+                                        if ui.slider_config("Ambient occlusion", 0.0, 1.0).display_format("%.2f").build(&mut ambient_occlusion) {
+                                            self.world_mesh.set_ambient_occlusion(ambient_occlusion);
+                                        }
+                                        if ui.is_item_hovered() {
+                                            ui.tooltip_text("darkens the terrain at the foot of cliffs and steps in elevation");
+                                        }
+                                        ui.checkbox("Fog of war", &mut fog.enabled);
+                                        if ui.is_item_hovered() {
+                                            ui.tooltip_text("covers the undiscovered tiles with a dark plane at sea level");
+                                        }
 
-                                        ui.unindent();
-                                    }
+                                        ui.separator();
 
-                                    ui.separator();
+                                        if ui.collapsing_header("Water bodies", TreeNodeFlags::empty()) {
+                                            ui.indent();
+                                            if let Some(tile) = water_bodies.draw(&ui, &self.world_copy) {
+                                                go_to_tile = Some(tile);
+                                            }
+                                            ui.unindent();
+                                        }
+                                    });
+                            }
+
+                            if hud_windows.tools {
+                                ui.window("Tools")
+                                    .opened(&mut hud_windows.tools)
+                                    .position([display_width - 310.0, 360.0], Condition::FirstUseEver)
+                                    .size([300.0, 400.0], Condition::FirstUseEver)
+                                    .build(|| {
+                                        if ui.button(if map_camera.enabled { "Back to the fly camera" } else { "Top-down map view" }) {
+                                            map_camera.toggle(&mut cam_pos, cam_dir);
+                                        }
+                                        if !self.is_preview && self.replay_ticks.is_none() {
+                                            ui.checkbox("Event log", &mut self.event_log.open);
+                                            ui.checkbox("Breakpoints", &mut self.breakpoint_editor.open);
+                                            ui.checkbox("Energy planner", &mut self.energy_planner.open);
+                                            if ui.is_item_hovered() {
+                                                ui.tooltip_text("projects when the energy runs out and whether it suffices for the robot's plan");
+                                            }
+                                        }
+                                        ui.checkbox("Event journal", &mut self.journal_viewer.open);
+                                        ui.checkbox("Input recording", &mut input_recorder.open);
+                                        ui.checkbox("Save/load snapshot", &mut snapshot_file.open);
+                                        ui.checkbox("Run comparison", &mut run_comparison.open);
+                                        if ui.is_item_hovered() {
+                                            ui.tooltip_text("compares the replays or CSV exports of several runs");
+                                        }
+                                        ui.checkbox("Statistics", &mut self.statistics_window.open);
+                                        if ui.is_item_hovered() {
+                                            ui.tooltip_text("charts the tiles discovered, the distance traveled, the energy spent and the items collected");
+                                        }
+                                        ui.checkbox("Tick export", &mut tick_export.open);
+                                        if ui.is_item_hovered() {
+                                            ui.tooltip_text("streams the position, energy, backpack, weather and time of day of every tick to a CSV or JSON lines file");
+                                        }
+                                        ui.checkbox("Interaction trace", &mut interaction_trace.open);
+                                        if ui.is_item_hovered() {
+                                            ui.tooltip_text("tells what the robot did at every tick: moves, teleports, contents destroyed, placed or deposited");
+                                        }
+                                        ui.checkbox("Measure", &mut measure_tool.open);
+                                        if ui.is_item_hovered() {
+                                            ui.tooltip_text("left click two tiles to measure the distances and the elevation profile between them");
+                                        }
+                                        if !self.is_preview {
+                                            autosave.draw_settings(&ui);
+                                        }
+                                        if ui.button("Pin the current state") {
+                                            pinned_world.pin(&self.world_copy, &self.display);
+                                        }
+                                        if ui.is_item_hovered() {
+                                            ui.tooltip_text("shows the world as it is now next to the live one, with the same camera");
+                                        }
+                                        if pinned_world.is_pinned() {
+                                            ui.same_line();
+                                            ui.checkbox("show##pinned world", &mut pinned_world.open);
+                                        }
+                                        if let Some(annotations_editor) = &mut self.annotations_editor {
+                                            ui.checkbox("Annotations", &mut annotations_editor.open);
+                                        }
+                                        ui.checkbox("Ghost replay", &mut self.ghost_overlay.open);
+                                        ui.checkbox("Teleport network", &mut teleport_network.open);
+                                        ui.checkbox("Tile search", &mut tile_search.open);
+                                        if ui.is_item_hovered() {
+                                            ui.tooltip_text("lists and outlines the discovered tiles with a content or a tile type");
+                                        }
+                                        ui.checkbox("Street network", &mut street_network.open);
+                                        ui.checkbox("Content icons", &mut content_icons.open);
+                                        ui.checkbox("Minimap", &mut minimap.open);
+                                        ui.checkbox("Content changes", &mut content_changes.open);
+                                        ui.checkbox("Change heatmap", &mut change_heatmap.show);
+                                        if ui.is_item_hovered() {
+                                            ui.tooltip_text("colors the tiles by how many times they changed");
+                                        }
+                                        change_heatmap.draw_settings(&ui);
+                                        ui.checkbox("Discovery age", &mut discovery_age.show);
+                                        if ui.is_item_hovered() {
+                                            ui.tooltip_text("colors the tiles by how long ago they were discovered, the newest being the brightest");
+                                        }
+                                        discovery_age.draw_settings(&ui);
+                                        if let Some(tile_layers) = &mut self.tile_layers {
+                                            ui.checkbox("Tile layers", &mut tile_layers.open);
+                                        }
+                                        if let Some(vector_fields) = &mut self.vector_fields {
+                                            ui.checkbox("Vector fields", &mut vector_fields.open);
+                                        }
+                                        if let Some(telemetry_panel) = &mut self.telemetry_panel {
+                                            ui.checkbox("Telemetry", &mut telemetry_panel.open);
+                                        }
+                                        if let Some(god_view) = &mut self.god_view {
+                                            let red_text = ui.push_style_color(StyleColor::Text, [1.0, 0.4, 0.4, 1.0]);
+                                            ui.checkbox("God view", &mut god_view.enabled);
+                                            red_text.pop();
+                                            if ui.is_item_hovered() {
+                                                ui.tooltip_text("shows the whole world, including the tiles the robot hasn't discovered");
+                                            }
+                                            ui.checkbox(format!("Stale tiles ({})###stale_tiles", stale_tiles.count()), &mut stale_tiles.show);
+                                            if ui.is_item_hovered() {
+                                                ui.tooltip_text("crosses out the discovered tiles whose content changed after the robot last saw them");
+                                            }
+                                        }
 
-                                    if ui.collapsing_header("Controls", TreeNodeFlags::empty()) {
-                                        ui.indent();
-                                        ui.text_wrapped(self.kbd_event_handler.get_explanation());
-                                        ui.unindent();
-                                    }
+                                        ui.separator();
 
-                                    ui.separator();
+                                        if ui.collapsing_header("Controls", TreeNodeFlags::empty()) {
+                                            ui.indent();
+                                            ui.text_wrapped(self.kbd_event_handler.get_explanation());
+                                            ui.slider_config("camera speed", 5.0, 500.0)
+                                                .flags(SliderFlags::LOGARITHMIC)
+                                                .build(&mut self.kbd_event_handler.movement_speed);
+                                            ui.slider_config("look speed", 0.1, 10.0)
+                                                .flags(SliderFlags::LOGARITHMIC)
+                                                .build(&mut self.kbd_event_handler.look_speed);
+                                            ui.checkbox("Key bindings", &mut self.key_bindings_editor.open);
+                                            ui.text(format!("Camera: {}", clipboard::describe_camera(cam_pos, cam_dir)));
+                                            ui.same_line();
+                                            clipboard::copy_button(&ui, "camera", || clipboard::describe_camera(cam_pos, cam_dir));
+                                            ui.text_disabled("Ctrl+C over a tile copies its description");
+                                            ui.unindent();
+                                        }
+                                    });
+                            }
+
+                            if hud_windows.stats {
+                                ui.window("Stats")
+                                    .opened(&mut hud_windows.stats)
+                                    .position([320.0, 30.0], Condition::FirstUseEver)
+                                    .size([300.0, 400.0], Condition::FirstUseEver)
+                                    .build(|| {
+                                        ui.checkbox("Frustum culling", &mut frustum_culling);
+                                        ui.checkbox("Level of detail", &mut level_of_detail);
+                                        if level_of_detail {
+                                            ui.slider_config("LOD distance", 32.0, 2048.0)
+                                                .flags(SliderFlags::LOGARITHMIC)
+                                                .display_format("%.0f")
+                                                .build(&mut lod_distance);
+                                        }
+                                        render_stats.draw(&ui);
+                                        clipboard::copy_button(&ui, "render stats", || format!("{}\nFPS: {:.1}", render_stats.summary(), frame_delta_timer.get_average_fps()));
+                                        ui.checkbox("Chunk rebuilds overlay", &mut chunk_rebuilds.show);
+                                        if ui.is_item_hovered() {
+                                            ui.tooltip_text("tints the chunks by how many times they were rebuilt recently");
+                                        }
+                                        chunk_rebuilds.draw_stats(&ui);
+                                        ui.text("FPS");
+                                        fps_chart.draw(&ui, [ui.content_region_avail()[0], 48.0]);
 
-                                    ui.text_wrapped(format!("FPS: {}", frame_delta_timer.get_average_fps() as u32));
-                                });
+                                        ui.separator();
+
+                                        if ui.collapsing_header("Graphics", TreeNodeFlags::empty()) {
+                                            ui.indent();
+                                            settings::draw_antialiasing_settings(&ui, &mut self.settings);
+                                            settings::draw_frame_rate_settings(&ui, &mut self.settings);
+                                            ui.unindent();
+                                        }
+                                    });
+                            }
+
+                            settings::draw_vignette(&ui, self.settings.vignette);
+
+                            let hovered_tile = (!ui.io().want_capture_mouse)
+                                .then(|| picking::pick_tile(&mvp, ui.io().mouse_pos, ui.io().display_size, &self.world_copy.world))
+                                .flatten();
+                            if let Some(tile) = hovered_tile {
+                                if !ui.io().want_capture_keyboard && ui.io().key_ctrl && ui.is_key_pressed_no_repeat(imgui::Key::C) {
+                                    ui.set_clipboard_text(clipboard::describe_tile(tile, self.world_copy.world[tile.x as usize][tile.y as usize].as_ref()));
+                                }
+                            }
+                            measure_tool.handle_click(&ui, hovered_tile);
+                            measure_tool.draw_line(&ui, &mvp, &self.world_copy.world);
+                            if let Some(discovered_at) = hovered_tile.filter(|_| discovery_age.show).and_then(|tile| discovery_age.discovered_at(tile)) {
+                                ui.tooltip_text(format!("discovered at tick {discovered_at}"));
+                            }
+                            status_bar::draw_status_bar(&ui, &StatusInfo {
+                                tick: self.world_copy.tick,
+                                run_mode,
+                                is_preview: self.is_preview,
+                                is_detached: detached_view.is_detached(),
+                                fps: frame_delta_timer.get_average_fps(),
+                                cam_pos,
+                                hovered_tile,
+                                message: status_message.as_ref().filter(|(_, shown)| shown.elapsed() < STATUS_MESSAGE_DURATION).map(|(message, _)| message.clone()),
+                            });
+
+                            pinned_panels.draw(&ui, &mvp, &self.world_copy.world);
+                            if show_robot_marker {
+                                markers::draw_robot_marker(&ui, &mvp, robot_model.position() + vec3(0.0, 1.0, 0.0), &self.robot_style, 0.8);
+                                for (i, other_robot) in self.world_copy.other_robots.iter().enumerate() {
+                                    let style = self.other_robot_styles.get(i).cloned().unwrap_or_else(|| Self::default_other_robot_style(&self.robot_style, i));
+                                    markers::draw_robot_marker(&ui, &mvp, Self::tile_top(&self.world_copy, other_robot.position) + vec3(0.0, 1.0, 0.0), &style, 0.8);
+                                }
+                            }
+                            if map_camera.enabled && !ui.io().want_capture_mouse && ui.io().mouse_wheel != 0.0 {
+                                map_camera.zoom(0.85_f32.powf(ui.io().mouse_wheel));
+                            }
+                            if !ui.io().want_capture_mouse && ui.is_mouse_clicked(MouseButton::Right) {
+                                if let Some(tile) = picking::pick_tile(&mvp, ui.io().mouse_pos, ui.io().display_size, &self.world_copy.world) {
+                                    pinned_panels.pin(tile);
+                                }
+                            }
+
+                            if show_clock {
+                                sundial::draw_sundial(&ui, &self.world_copy.env_cond);
+                            }
+                            metronome.draw(&ui);
+
+                            if let Some(tile) = self.journal_viewer.draw(&ui) {
+                                go_to_tile = Some(tile);
+                            }
+
+                            if let Some(tile) = self.event_log.draw(&ui) {
+                                go_to_tile = Some(tile);
+                            }
+                            if let Some(tile) = self.breakpoint_editor.draw(&ui, self.world_copy.robot_position) {
+                                go_to_tile = Some(tile);
+                            }
+                            self.energy_planner.draw(&ui);
+                            measure_tool.draw(&ui, &self.world_copy.world);
+                            self.statistics_window.draw(&ui, self.world_copy.world.len());
+                            if let Some(tile) = self.crash_modal.draw(&ui) {
+                                go_to_tile = Some(tile);
+                                follow_robot = false;
+                            }
+                            tick_export.draw(&ui);
+                            if let Some(tile) = interaction_trace.draw(&ui) {
+                                go_to_tile = Some(tile);
+                                follow_robot = false;
+                            }
+
+                            if let Some(tile) = tile_search.draw(&ui, &self.world_copy) {
+                                go_to_tile = Some(tile);
+                            }
+                            if let Some(tile) = teleport_network.draw(&ui, self.world_copy.world.len()) {
+                                go_to_tile = Some(tile);
+                            }
+
+                            if let Some(god_view) = &self.god_view {
+                                god_view.draw_label(&ui);
+                            }
+
+                            if let Some(tile) = minimap.draw(&ui, &mvp, self.world_copy.robot_position) {
+                                go_to_tile = Some(tile);
+                            }
+
+                            if let Some(tile_layers) = &mut self.tile_layers {
+                                tile_layers.draw(&ui);
+                            }
+                            if let Some(vector_fields) = &mut self.vector_fields {
+                                vector_fields.draw(&ui);
+                            }
+                            if let Some(telemetry_panel) = &mut self.telemetry_panel {
+                                telemetry_panel.draw(&ui);
+                            }
+
+                            content_icons.draw_icons(&ui, &mvp, eye_pos, &self.world_copy);
+                            content_icons.draw(&ui);
+
+                            street_network.draw_junctions(&ui, &mvp, &self.world_copy);
+                            street_network.draw(&ui);
+
+                            self.ghost_overlay.draw_marker(&ui, &mvp, self.world_copy.tick);
+                            self.ghost_overlay.draw(&ui, self.world_copy.world.len());
+
+                            if let Some(annotations_editor) = &mut self.annotations_editor {
+                                annotations_editor.draw_markers(&ui, &mvp, &self.world_copy.world, self.world_copy.tick);
+                                let request = annotations_editor.draw(&ui, cam_pos, cam_dir, self.world_copy.tick, self.world_copy.robot_position);
+                                Self::apply_annotations_request(request, &mut cam_pos, &mut cam_dir, &mut run_mode, &mut self.run_mode_log);
+                            }
+                            input_recorder.draw(&ui, cam_pos, cam_dir, self.world_copy.tick, self.replay_ticks.is_some());
+                            self.key_bindings_editor.draw(&ui, &mut self.kbd_event_handler);
+                            for (path, kind) in file_drop.take_dropped() {
+                                match kind {
+                                    FileKind::Replay => self.ghost_overlay.open_file(path),
+                                    FileKind::Snapshot => {
+                                        if let Some(request) = snapshot_file.open_file(&path, self.world_copy.world.len()) {
+                                            detached_view.request(request);
+                                        }
+                                    }
+                                    FileKind::InputRecording => input_recorder.open_file(&path),
+                                    FileKind::Journal => self.journal_viewer.open_file(path),
+                                }
+                            }
+                            file_drop.draw(&ui);
+                            if let Some(request) = autosave.draw_prompt(&ui, self.world_copy.world.len()) {
+                                detached_view.request(request);
+                            }
+                            if let Some(request) = snapshot_file.draw(&ui, &self.world_copy, detached_view.is_detached()) {
+                                detached_view.request(request);
+                            }
+                            pinned_world.draw(&ui, self.world_copy.tick);
+                            run_comparison.draw(&ui);
+                            ui_scaling = settings::draw_settings_window(&ui, &mut self.settings, &mut show_settings);
+                            if let Some(tile) = content_changes.draw(&ui) {
+                                go_to_tile = Some(tile);
+                            }
+
+                            let running = matches!(run_mode, RunMode::Continuous(_)) && !self.is_preview;
+                            if idle_detector.draw_banner(&ui, running) {
+                                self.run_mode_log.request(&mut run_mode, RunMode::Paused, RunModeSource::Gui);
+                            }
+
+                            if run_mode != RunMode::Terminate {
+                                match diagnostics::draw_diagnostics(&ui, &self.health, self.stall_timeout, self.tick_timeout) {
+                                    DiagnosticsAction::None => {}
+                                    DiagnosticsAction::Terminate => {
+                                        self.run_mode_log.request(&mut run_mode, RunMode::Terminate, RunModeSource::Gui);
+                                        _control_flow.set_exit();
+                                    }
+                                    // the game thread terminates once the stalled tick returns
+                                    DiagnosticsAction::CloseGui => {
+                                        self.run_mode_log.request(&mut run_mode, RunMode::Terminate, RunModeSource::Gui);
+                                        aborted_ref.set(true);
+                                        _control_flow.set_exit();
+                                    }
+                                }
+                            }
 
                             let draw_data = self.imgui_ctx.render();
+                            render_stats.record_imgui(draw_data);
                             self.imgui_renderer.render(&mut target, draw_data).unwrap();
                         }
 
                         target.finish().unwrap();
+                        if std::mem::take(&mut take_screenshot) {
+                            match screenshot::save_screenshot(&self.display) {
+                                Ok(path) => status_message = Some((format!("screenshot saved to {}", path.display()), Instant::now())),
+                                Err(e) => {
+                                    eprintln!("could not save the screenshot: {e}");
+                                    status_message = Some((format!("could not save the screenshot: {e}"), Instant::now()));
+                                }
+                            }
+                            on_demand.wake(); // to show the message
+                        }
+                        render_stats.end_frame();
                     }
                 },
+                // whether the window was closed or the run terminated from the GUI
+                winit::event::Event::LoopDestroyed => {
+                    autosave.clean_exit();
+                    self.layouts.save_settings(&mut self.imgui_ctx);
+                    self.save_settings(last_ticks_per_second_cap, last_was_uncapped, sky_mode);
+                }
                 _ => {}
             }
         });
+
+        match aborted.get() {
+            true => Err(RagnarokError::Aborted),
+            false => Ok(()),
+        }
     }
 }
 
+const STATUS_MESSAGE_DURATION: Duration = Duration::from_secs(5);
 const UP : Vec3 = Vec3::new(0.0, 1.0, 0.0);
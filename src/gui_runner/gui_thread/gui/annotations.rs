@@ -0,0 +1,228 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use imgui::{Condition, TreeNodeFlags, Ui};
+use nalgebra_glm::{Mat4, UVec2, Vec3, vec3};
+use robotics_lib::world::tile::Tile;
+use crate::replay::{self, CameraBookmark, CameraKeyframe, CameraPath, CameraPose, Marker, ReplayAnnotations};
+use super::picking;
+
+// AnnotationsEditor is an imgui window, available while playing back a replay, which edits the
+// annotations stored in the replay file: markers (labels attached to a tile from a given tick
+// onwards, drawn over the 3D view), camera bookmarks and camera paths. Camera paths are recorded by
+// sampling the camera while the user flies around, and played back along with the replay ticks
+// shown during the recording. Annotations are only written to the file when the user saves them.
+
+// AnnotationsRequest is what the editor asks the GUI to do in the current frame
+#[derive(Default)]
+pub struct AnnotationsRequest {
+    pub camera: Option<(Vec3, Vec3)>, // position, direction
+    pub seek: Option<usize>,
+}
+
+pub struct AnnotationsEditor {
+    pub open: bool,
+    pub show_markers: bool,
+    replay_path: PathBuf,
+    annotations: ReplayAnnotations,
+    is_modified: bool,
+    save_error: Option<String>,
+
+    label_input: String,
+    recording: Option<(Instant, CameraPath)>,
+    playing: Option<(usize, Instant)>, // index of the path being played, start of the playback
+    last_seek: Option<usize>,
+}
+impl AnnotationsEditor {
+    const RECORDING_INTERVAL: Duration = Duration::from_millis(33);
+
+    pub fn new(replay_path: PathBuf, annotations: ReplayAnnotations) -> Self {
+        Self {
+            open: !annotations.is_empty(),
+            show_markers: true,
+            replay_path,
+            annotations,
+            is_modified: false,
+            save_error: None,
+            label_input: String::new(),
+            recording: None,
+            playing: None,
+            last_seek: None,
+        }
+    }
+
+    // records or plays back camera paths; must be called once per frame
+    pub fn update(&mut self, cam_pos: Vec3, cam_dir: Vec3, tick: usize) -> AnnotationsRequest {
+        if let Some((start, path)) = &mut self.recording {
+            let time = start.elapsed().as_secs_f32();
+            let is_due = path.keyframes.last().map(|k| time - k.time >= Self::RECORDING_INTERVAL.as_secs_f32()).unwrap_or(true);
+            if is_due {
+                path.keyframes.push(CameraKeyframe { time, tick, pose: to_pose(cam_pos, cam_dir) });
+            }
+        }
+
+        let Some((path_index, start)) = self.playing else { return AnnotationsRequest::default() };
+        let Some((pose, tick)) = self.annotations.camera_paths[path_index].sample(start.elapsed().as_secs_f32()) else {
+            self.playing = None;
+            return AnnotationsRequest::default();
+        };
+
+        let seek = if self.last_seek != Some(tick) { Some(tick) } else { None };
+        self.last_seek = Some(tick);
+        AnnotationsRequest { camera: Some(from_pose(&pose)), seek }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, cam_pos: Vec3, cam_dir: Vec3, tick: usize, robot_position: UVec2) -> AnnotationsRequest {
+        let mut request = AnnotationsRequest::default();
+        if !self.open {
+            return request;
+        }
+
+        let mut open = self.open;
+        ui.window("Annotations")
+            .opened(&mut open)
+            .size([340.0, 420.0], Condition::FirstUseEver)
+            .build(|| {
+                ui.input_text("label", &mut self.label_input).hint("marker label or bookmark name").build();
+                if ui.button("Mark robot position") {
+                    let label = self.take_label("marker");
+                    self.annotations.markers.push(Marker { tick, tile: (robot_position.x, robot_position.y), label });
+                    self.is_modified = true;
+                }
+                ui.same_line();
+                if ui.button("Bookmark camera") {
+                    let name = self.take_label("bookmark");
+                    self.annotations.camera_bookmarks.push(CameraBookmark { name, tick, pose: to_pose(cam_pos, cam_dir) });
+                    self.is_modified = true;
+                }
+
+                if self.recording.is_none() {
+                    if ui.button("Record camera path") {
+                        self.playing = None;
+                        let name = self.take_label("camera path");
+                        self.recording = Some((Instant::now(), CameraPath { name, keyframes: vec![] }));
+                    }
+                } else if ui.button("Stop recording") {
+                    if let Some((_, path)) = self.recording.take() {
+                        self.annotations.camera_paths.push(path);
+                        self.is_modified = true;
+                    }
+                }
+                ui.checkbox("Show markers", &mut self.show_markers);
+
+                ui.separator();
+                if ui.collapsing_header("Markers", TreeNodeFlags::DEFAULT_OPEN) {
+                    let mut to_remove = None;
+                    for (i, marker) in self.annotations.markers.iter().enumerate() {
+                        let _id = ui.push_id_usize(i);
+                        if ui.small_button("go") {
+                            request.seek = Some(marker.tick);
+                        }
+                        ui.same_line();
+                        if ui.small_button("x") {
+                            to_remove = Some(i);
+                        }
+                        ui.same_line();
+                        ui.text(format!("tick {}: {} at ({}, {})", marker.tick, marker.label, marker.tile.0, marker.tile.1));
+                    }
+                    if let Some(i) = to_remove {
+                        self.annotations.markers.remove(i);
+                        self.is_modified = true;
+                    }
+                }
+                if ui.collapsing_header("Camera bookmarks", TreeNodeFlags::DEFAULT_OPEN) {
+                    let mut to_remove = None;
+                    for (i, bookmark) in self.annotations.camera_bookmarks.iter().enumerate() {
+                        let _id = ui.push_id_usize(i);
+                        if ui.small_button("go") {
+                            request.camera = Some(from_pose(&bookmark.pose));
+                            request.seek = Some(bookmark.tick);
+                        }
+                        ui.same_line();
+                        if ui.small_button("x") {
+                            to_remove = Some(i);
+                        }
+                        ui.same_line();
+                        ui.text(format!("{} (tick {})", bookmark.name, bookmark.tick));
+                    }
+                    if let Some(i) = to_remove {
+                        self.annotations.camera_bookmarks.remove(i);
+                        self.is_modified = true;
+                    }
+                }
+                if ui.collapsing_header("Camera paths", TreeNodeFlags::DEFAULT_OPEN) {
+                    let mut to_remove = None;
+                    for (i, path) in self.annotations.camera_paths.iter().enumerate() {
+                        let _id = ui.push_id_usize(i);
+                        let is_playing = self.playing.map(|(p, _)| p == i).unwrap_or(false);
+                        if ui.small_button(if is_playing { "stop" } else { "play" }) {
+                            self.playing = if is_playing { None } else { Some((i, Instant::now())) };
+                            self.last_seek = None;
+                        }
+                        ui.same_line();
+                        if ui.small_button("x") {
+                            to_remove = Some(i);
+                        }
+                        ui.same_line();
+                        ui.text(format!("{} ({:.1}s)", path.name, path.duration()));
+                    }
+                    if let Some(i) = to_remove {
+                        self.annotations.camera_paths.remove(i);
+                        self.playing = None;
+                        self.is_modified = true;
+                    }
+                }
+
+                ui.separator();
+                ui.disabled(!self.is_modified, || {
+                    if ui.button("Save to replay") {
+                        match replay::append_annotations(&self.replay_path, &self.annotations) {
+                            Ok(()) => {
+                                self.is_modified = false;
+                                self.save_error = None;
+                            }
+                            Err(e) => self.save_error = Some(e.to_string()),
+                        }
+                    }
+                });
+                if self.is_modified {
+                    ui.same_line();
+                    ui.text_disabled("(unsaved changes)");
+                }
+                if let Some(e) = &self.save_error {
+                    ui.text_colored([1.0, 0.4, 0.4, 1.0], format!("could not save: {e}"));
+                }
+            });
+        self.open = open;
+        request
+    }
+
+    // draws the label of every marker placed at or before the current tick over its tile
+    pub fn draw_markers(&self, ui: &Ui, mvp: &Mat4, world: &Vec<Vec<Option<Tile>>>, tick: usize) {
+        if !self.show_markers {
+            return;
+        }
+        let display_size = ui.io().display_size;
+        let draw_list = ui.get_background_draw_list();
+        for marker in self.annotations.markers.iter().filter(|m| m.tick <= tick) {
+            let tile = UVec2::new(marker.tile.0, marker.tile.1);
+            if let Some(anchor) = picking::project_to_screen(mvp, picking::tile_anchor(tile, world), display_size) {
+                draw_list.add_circle(anchor, 5.0, [1.0, 0.8, 0.2, 1.0]).filled(true).build();
+                draw_list.add_text([anchor[0] + 8.0, anchor[1] - 8.0], [1.0, 0.8, 0.2, 1.0], &marker.label);
+            }
+        }
+    }
+
+    fn take_label(&mut self, default: &str) -> String {
+        let label = std::mem::take(&mut self.label_input);
+        if label.is_empty() { default.to_string() } else { label }
+    }
+}
+
+pub fn to_pose(position: Vec3, direction: Vec3) -> CameraPose {
+    CameraPose { position: [position.x, position.y, position.z], direction: [direction.x, direction.y, direction.z] }
+}
+pub fn from_pose(pose: &CameraPose) -> (Vec3, Vec3) {
+    let [px, py, pz] = pose.position;
+    let [dx, dy, dz] = pose.direction;
+    (vec3(px, py, pz), vec3(dx, dy, dz).normalize())
+}
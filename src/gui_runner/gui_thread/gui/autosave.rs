@@ -0,0 +1,160 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use imgui::{Condition, Ui};
+use crate::gui_runner::PartialWorld;
+use crate::snapshot::WorldSnapshot;
+use super::detached_view::ViewRequest;
+use super::settings;
+
+// Autosave periodically saves the world being shown (as a WorldSnapshot, like SnapshotFile) into the
+// autosave directory of the configuration directory, cycling through SLOTS files so that a save
+// interrupted halfway never costs more than one interval. The snapshot is serialized on a thread of
+// its own, since big worlds take a while, and written to a temporary file which then replaces the
+// slot, so that a slot is never left half written.
+// A lock file is created when the GUI starts and removed when it exits cleanly: finding it at the
+// next start means that the previous session crashed (or was killed), in which case a prompt
+// offers to show its newest autosave through the DetachedView. The run itself can't be restored,
+// since the state of the robot and of the world generator aren't saved.
+
+pub struct Autosave {
+    pub interval: Duration, // zero disables autosaving
+    dir: Option<PathBuf>,
+    last_save: Instant,
+    next_slot: usize,
+    saving: Option<(usize, thread::JoinHandle<Result<(), String>>)>, // tick being saved, thread saving it
+    status: Option<String>,
+    recovered: Option<PathBuf>, // newest autosave of a session which didn't exit cleanly, until the prompt is answered
+}
+impl Autosave {
+    const SLOTS: usize = 3;
+
+    pub fn new(interval: Duration) -> Self {
+        let dir = settings::config_dir().map(|dir| dir.join("autosave"));
+        let mut autosave = Self { interval, dir, last_save: Instant::now(), next_slot: 0, saving: None, status: None, recovered: None };
+        let Some(dir) = autosave.dir.clone() else { return autosave };
+        if let Err(e) = fs::create_dir_all(&dir) {
+            eprintln!("could not create the autosave directory {dir:?}: {e}");
+            autosave.dir = None;
+            return autosave;
+        }
+
+        let modified = |slot: usize| fs::metadata(Self::slot_path(&dir, slot)).and_then(|metadata| metadata.modified()).ok();
+        let slots: Vec<(usize, Option<SystemTime>)> = (0..Self::SLOTS).map(|slot| (slot, modified(slot))).collect();
+        let lock = dir.join("session.lock");
+        if lock.exists() {
+            autosave.recovered = slots.iter().filter_map(|(slot, modified)| Some((*slot, (*modified)?)))
+                .max_by_key(|(_, modified)| *modified)
+                .map(|(slot, _)| Self::slot_path(&dir, slot));
+        }
+        // overwrite the oldest slot first, keeping the newest ones for as long as possible
+        autosave.next_slot = slots.iter().min_by_key(|(_, modified)| *modified).map(|(slot, _)| *slot).unwrap_or(0);
+        if let Err(e) = fs::write(&lock, std::process::id().to_string()) {
+            eprintln!("could not create {lock:?}, a crash won't be detected: {e}");
+        }
+        autosave
+    }
+
+    fn slot_path(dir: &Path, slot: usize) -> PathBuf {
+        dir.join(format!("autosave-{slot}.json"))
+    }
+
+    // must be called once per frame
+    pub fn update(&mut self, world: &PartialWorld) {
+        if let Some((tick, saving)) = self.saving.take() {
+            if !saving.is_finished() {
+                self.saving = Some((tick, saving));
+                return;
+            }
+            self.status = Some(match saving.join() {
+                Ok(Ok(())) => format!("autosaved tick {tick}"),
+                Ok(Err(e)) => format!("could not autosave: {e}"),
+                Err(_) => "the autosave thread panicked".into(),
+            });
+        }
+        let Some(dir) = &self.dir else { return };
+        if self.interval.is_zero() || self.last_save.elapsed() < self.interval {
+            return;
+        }
+        self.last_save = Instant::now();
+
+        let snapshot = WorldSnapshot::from_partial_world(world);
+        let path = Self::slot_path(dir, self.next_slot);
+        self.next_slot = (self.next_slot + 1) % Self::SLOTS;
+        self.saving = Some((world.tick, thread::spawn(move || {
+            let temporary = path.with_extension("json.tmp");
+            let file = File::create(&temporary).map_err(|e| e.to_string())?;
+            serde_json::to_writer(BufWriter::new(file), &snapshot).map_err(|e| e.to_string())?;
+            fs::rename(&temporary, &path).map_err(|e| e.to_string())
+        })));
+    }
+
+    // must be called when the GUI exits normally
+    pub fn clean_exit(&mut self) {
+        if let Some((_, saving)) = self.saving.take() {
+            let _ = saving.join();
+        }
+        if let Some(dir) = &self.dir {
+            let _ = fs::remove_file(dir.join("session.lock"));
+        }
+    }
+
+    fn load(path: &Path, world_size: usize) -> Result<PartialWorld, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let snapshot: WorldSnapshot = serde_json::from_reader(BufReader::new(file)).map_err(|e| e.to_string())?;
+        if snapshot.world.len() != world_size {
+            return Err(format!("the autosave is of a {0}x{0} world, this one is {1}x{1}", snapshot.world.len(), world_size));
+        }
+        Ok(snapshot.to_partial_world())
+    }
+
+    // draws the prompt offering to restore the autosave of a session which didn't exit cleanly
+    pub fn draw_prompt(&mut self, ui: &Ui, world_size: usize) -> Option<ViewRequest> {
+        let path = self.recovered.clone()?;
+        let mut request = None;
+        let [width, height] = ui.io().display_size;
+        ui.window("Restore the last autosave?")
+            .position([width / 2.0, height / 3.0], Condition::Appearing)
+            .position_pivot([0.5, 0.5])
+            .always_auto_resize(true)
+            .collapsible(false)
+            .build(|| {
+                ui.text("The last session didn't exit cleanly.");
+                ui.text_disabled(path.display().to_string());
+                if ui.button("Show the autosave") {
+                    match Self::load(&path, world_size) {
+                        Ok(world) => {
+                            self.status = Some(format!("showing tick {} from the autosave", world.tick));
+                            request = Some(ViewRequest::Show(world));
+                        }
+                        Err(e) => self.status = Some(format!("could not load the autosave: {e}")),
+                    }
+                    self.recovered = None;
+                }
+                ui.same_line();
+                if ui.button("Dismiss") {
+                    self.recovered = None;
+                }
+            });
+        request
+    }
+
+    // draws the interval and the outcome of the last save, in the Tools section
+    pub fn draw_settings(&mut self, ui: &Ui) {
+        let mut minutes = self.interval.as_secs_f32() / 60.0;
+        ui.set_next_item_width(120.0);
+        if ui.slider_config("Autosave every", 0.0, 30.0).display_format("%.1f min").build(&mut minutes) {
+            self.interval = Duration::from_secs_f32(minutes * 60.0);
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text("saves a snapshot of the world, offered back if the GUI crashes (0 disables it)");
+        }
+        if self.dir.is_none() {
+            ui.text_disabled("the configuration directory is unknown, autosaving is disabled");
+        } else if let Some(status) = &self.status {
+            ui.text_disabled(status);
+        }
+    }
+}
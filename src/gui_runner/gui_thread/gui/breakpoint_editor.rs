@@ -0,0 +1,135 @@
+use imgui::{Condition, Ui};
+use nalgebra_glm::UVec2;
+use crate::gui_runner::breakpoints::{Breakpoint, BreakpointHit, Breakpoints};
+
+// BreakpointEditor is an imgui window listing the breakpoints of a live run, where they can be
+// added (on a tile, on a kind of event, or on the amount of a content in the backpack), disabled
+// and removed. Every edit replaces the breakpoints checked by the game thread with the enabled
+// ones. It also keeps the last hits, which the GUI takes from the shared Breakpoints every frame,
+// and returns the location of a hit when it is clicked.
+
+pub struct BreakpointEditor {
+    pub open: bool,
+    shared: Breakpoints,
+    breakpoints: Vec<(Breakpoint, bool)>, // enabled
+    hits: Vec<BreakpointHit>, // the most recent last
+
+    tile: [u32; 2],
+    event_kind: usize,
+    content: usize,
+    amount: u32,
+}
+impl BreakpointEditor {
+    const MAX_HITS: usize = 20;
+    const EVENT_KINDS: [&'static str; 10] = [
+        "Moved", "TileContentUpdated", "AddedToBackpack", "RemovedFromBackpack", "EnergyConsumed",
+        "EnergyRecharged", "DayChanged", "TimeChanged", "Ready", "Terminated",
+    ];
+    const CONTENTS: [&'static str; 15] = [
+        "Rock", "Tree", "Garbage", "Fire", "Coin", "Bin", "Crate", "Bank", "Water", "Market", "Fish",
+        "Building", "Bush", "JollyBlock", "Scarecrow",
+    ];
+
+    pub fn new(shared: Breakpoints) -> Self {
+        Self { open: false, shared, breakpoints: vec![], hits: vec![], tile: [0, 0], event_kind: 0, content: 4, amount: 10 }
+    }
+
+    // returns the breakpoints hit since the last call, which must be made every frame
+    pub fn take_hits(&mut self) -> Vec<BreakpointHit> {
+        let hits = self.shared.take_hits();
+        self.hits.extend(hits.iter().cloned());
+        let excess = self.hits.len().saturating_sub(Self::MAX_HITS);
+        self.hits.drain(..excess);
+        hits
+    }
+
+    fn add(&mut self, breakpoint: Breakpoint) {
+        if !self.breakpoints.iter().any(|(b, _)| *b == breakpoint) {
+            self.breakpoints.push((breakpoint, true));
+        }
+    }
+
+    // returns the location of the hit clicked this frame, if any
+    pub fn draw(&mut self, ui: &Ui, robot_position: UVec2) -> Option<UVec2> {
+        if !self.open {
+            return None;
+        }
+        let mut clicked = None;
+        let mut changed = false;
+        let mut open = self.open;
+        ui.window("Breakpoints")
+            .opened(&mut open)
+            .size([360.0, 380.0], Condition::FirstUseEver)
+            .build(|| {
+                ui.text("Pause when...");
+                ui.set_next_item_width(120.0);
+                ui.input_scalar_n("##tile", &mut self.tile).build();
+                ui.same_line();
+                if ui.button("the robot enters the tile") {
+                    self.add(Breakpoint::EnterTile(UVec2::new(self.tile[0], self.tile[1])));
+                    changed = true;
+                }
+                ui.same_line();
+                if ui.small_button("here") {
+                    self.tile = [robot_position.x, robot_position.y];
+                }
+
+                ui.set_next_item_width(160.0);
+                ui.combo_simple_string("##event_kind", &mut self.event_kind, &Self::EVENT_KINDS);
+                ui.same_line();
+                if ui.button("is received") {
+                    self.add(Breakpoint::Event(Self::EVENT_KINDS[self.event_kind].to_string()));
+                    changed = true;
+                }
+
+                ui.text("the backpack contains");
+                ui.set_next_item_width(110.0);
+                ui.combo_simple_string("##content", &mut self.content, &Self::CONTENTS);
+                ui.same_line();
+                ui.set_next_item_width(60.0);
+                ui.input_scalar(">=##amount", &mut self.amount).build();
+                ui.same_line();
+                if ui.button("Add") {
+                    self.add(Breakpoint::BackpackAtLeast { content: Self::CONTENTS[self.content].to_string(), amount: self.amount as usize });
+                    changed = true;
+                }
+
+                ui.separator();
+                let mut to_remove = None;
+                for (i, (breakpoint, enabled)) in self.breakpoints.iter_mut().enumerate() {
+                    let _id = ui.push_id_usize(i);
+                    changed |= ui.checkbox("##enabled", enabled);
+                    ui.same_line();
+                    if ui.small_button("x") {
+                        to_remove = Some(i);
+                    }
+                    ui.same_line();
+                    ui.text(breakpoint.describe());
+                }
+                if let Some(i) = to_remove {
+                    self.breakpoints.remove(i);
+                    changed = true;
+                }
+                if self.breakpoints.is_empty() {
+                    ui.text_disabled("(no breakpoints)");
+                }
+
+                if !self.hits.is_empty() {
+                    ui.separator();
+                    ui.text("Last hits:");
+                    for (i, hit) in self.hits.iter().enumerate().rev() {
+                        let label = format!("tick {}: {}##{i}", hit.tick, hit.description);
+                        if ui.selectable(label) {
+                            clicked = Some(hit.robot_position);
+                        }
+                    }
+                }
+            });
+        self.open = open;
+
+        if changed {
+            self.shared.set(self.breakpoints.iter().filter(|(_, enabled)| *enabled).map(|(b, _)| b.clone()).collect());
+        }
+        clicked
+    }
+}
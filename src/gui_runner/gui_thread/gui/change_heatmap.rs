@@ -0,0 +1,116 @@
+use glium::{Display, VertexBuffer};
+use imgui::Ui;
+use nalgebra_glm::{vec3, UVec2};
+use super::picking;
+use super::tile_layers_overlay::Palette;
+use super::world_mesh::Vertex;
+use crate::gui_runner::PartialWorld;
+
+// ChangeHeatmap counts how many times every tile changed (as found by the worker thread, which
+// lists the changed tiles of every world it diffs; a tile being discovered counts as a change) and
+// renders the counts as a heatmap, like the TileLayersOverlay does with the layers of the host
+// code, to show where the robot does most of its work. The colors follow the logarithm of the
+// counts, since a few tiles (e.g. those the robot keeps walking over) usually change far more often
+// than the rest. The counts are kept while the heatmap is hidden, and the quads are only rebuilt
+// when a count changes.
+
+pub struct ChangeHeatmap {
+    pub show: bool,
+    pub opacity: f32,
+    counts: Vec<Vec<u32>>,
+    max_count: u32,
+    is_outdated: bool,
+    built_tick: usize,
+
+    triangles: Vec<Vertex>,
+    pub vbo: Option<VertexBuffer<Vertex>>,
+    vbo_is_outdated: bool,
+}
+impl ChangeHeatmap {
+    const HEIGHT_ABOVE_TERRAIN: f32 = 0.1;
+
+    pub fn new(world_size: usize) -> Self {
+        Self {
+            show: false,
+            opacity: 0.6,
+            counts: vec![vec![0; world_size]; world_size],
+            max_count: 0,
+            is_outdated: true,
+            built_tick: 0,
+            triangles: vec![],
+            vbo: None,
+            vbo_is_outdated: false,
+        }
+    }
+
+    // must be called with every world received from the worker
+    pub fn record(&mut self, world: &PartialWorld) {
+        for tile in world.changed_tiles.iter() {
+            if let Some(count) = self.counts.get_mut(tile.x as usize).and_then(|row| row.get_mut(tile.y as usize)) {
+                *count += 1;
+                self.max_count = self.max_count.max(*count);
+                self.is_outdated = true;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.counts.iter_mut().for_each(|row| row.fill(0));
+        self.max_count = 0;
+        self.is_outdated = true;
+    }
+
+    // must be called once per frame
+    pub fn update(&mut self, world: &PartialWorld) {
+        // the terrain the quads are laid on may have changed too
+        if !self.show || (!self.is_outdated && self.built_tick == world.tick) {
+            return;
+        }
+        self.triangles.clear();
+        let log_max = (self.max_count as f32).ln_1p();
+        for (x, row) in self.counts.iter().enumerate() {
+            for (y, count) in row.iter().enumerate() {
+                if *count == 0 {
+                    continue;
+                }
+                let tile_pos = UVec2::new(x as u32, y as u32);
+                let color = Palette::Heat.color((*count as f32).ln_1p() / log_max);
+                let y = picking::tile_anchor(tile_pos, &world.world).y + Self::HEIGHT_ABOVE_TERRAIN;
+                let corner = |dx: f32, dz: f32| vec3(tile_pos.x as f32 + dx, y, tile_pos.y as f32 + dz);
+                let [a, b, c, d] = [corner(0.0, 0.0), corner(1.0, 0.0), corner(1.0, 1.0), corner(0.0, 1.0)];
+                for p in [a, b, c, a, c, d] {
+                    self.triangles.push(Vertex { position: *p.as_ref(), color });
+                }
+            }
+        }
+        self.is_outdated = false;
+        self.built_tick = world.tick;
+        self.vbo_is_outdated = true;
+    }
+
+    // returns the number of bytes uploaded to the gpu
+    pub fn update_vbo(&mut self, display: &Display) -> usize {
+        if !self.vbo_is_outdated {
+            return 0;
+        }
+        self.vbo_is_outdated = false;
+        self.vbo = if self.triangles.is_empty() { None } else { VertexBuffer::new(display, &self.triangles).ok() };
+        self.triangles.len() * std::mem::size_of::<Vertex>()
+    }
+
+    // draws the settings, below the checkbox showing the heatmap
+    pub fn draw_settings(&mut self, ui: &Ui) {
+        if !self.show {
+            return;
+        }
+        ui.indent();
+        ui.set_next_item_width(120.0);
+        ui.slider("opacity##change_heatmap", 0.1, 1.0, &mut self.opacity);
+        ui.same_line();
+        if ui.button("Reset counts") {
+            self.reset();
+        }
+        ui.text_disabled(format!("the most changed tile changed {} times", self.max_count));
+        ui.unindent();
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::VecDeque;
+use std::rc::Rc;
+use glium::Display;
+use glium::texture::{RawImage2d, Texture2d};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerBehavior};
+use imgui::{TextureId, Ui};
+use imgui_glium_renderer::{Renderer, Texture};
+
+// DownsampledHistory is a ring buffer of fixed capacity which stores samples grouped in buckets,
+// each keeping the min, max and average of the samples it covers. It can be used in two ways:
+// - DownsampledHistory::new keeps the whole history: when all buckets are in use adjacent buckets
+//   are merged pairwise, halving the resolution, so that any run length fits in the same memory.
+// - DownsampledHistory::with_window only keeps the last `window` samples: each bucket covers a
+//   fixed number of samples and the oldest bucket is dropped when the buffer is full.
+// Either way drawing a chart of the history costs O(capacity), which is meant to be roughly the
+// number of horizontal pixels of the chart, regardless of how many samples were pushed.
+//
+// Chart in turn rasterizes a DownsampledHistory into a texture registered in the imgui renderer,
+// so that drawing it is a single textured quad. The history is rasterized again only when new
+// samples were pushed, and the texture re-uploaded only when that changed what the chart shows:
+// a sample pushed every frame (like the FPS) mostly lands in a bucket without moving any pixel.
+// The vertical range of a chart fits the history, unless it is set by hand (see Chart::set_range).
+
+#[derive(Clone, Copy, Debug)]
+pub struct Bucket {
+    pub min: f32,
+    pub max: f32,
+    sum: f64,
+    count: u32,
+}
+impl Bucket {
+    fn new(value: f32) -> Self {
+        Self { min: value, max: value, sum: value as f64, count: 1 }
+    }
+    fn push(&mut self, value: f32) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value as f64;
+        self.count += 1;
+    }
+    fn merge(&self, other: &Bucket) -> Bucket {
+        Bucket {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+            sum: self.sum + other.sum,
+            count: self.count + other.count,
+        }
+    }
+    pub fn avg(&self) -> f32 { (self.sum / self.count as f64) as f32 }
+}
+
+#[derive(Clone)]
+pub struct DownsampledHistory {
+    buckets: VecDeque<Bucket>,
+    capacity: usize,
+    samples_per_bucket: u32,
+    is_windowed: bool,
+    total_samples: u64,
+}
+impl DownsampledHistory {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity >= 2);
+        Self { buckets: VecDeque::with_capacity(capacity), capacity, samples_per_bucket: 1, is_windowed: false, total_samples: 0 }
+    }
+    pub fn with_window(capacity: usize, window: usize) -> Self {
+        assert!(capacity >= 2);
+        let samples_per_bucket = ((window + capacity - 1) / capacity).max(1) as u32;
+        Self { buckets: VecDeque::with_capacity(capacity), capacity, samples_per_bucket, is_windowed: true, total_samples: 0 }
+    }
+
+    pub fn push(&mut self, value: f32) {
+        self.total_samples += 1;
+        match self.buckets.back_mut() {
+            Some(bucket) if bucket.count < self.samples_per_bucket => bucket.push(value),
+            _ => {
+                if self.buckets.len() == self.capacity {
+                    if self.is_windowed {
+                        self.buckets.pop_front();
+                    } else {
+                        self.halve_resolution();
+                    }
+                }
+                self.buckets.push_back(Bucket::new(value));
+            }
+        }
+    }
+
+    fn halve_resolution(&mut self) {
+        let old_buckets = std::mem::take(&mut self.buckets);
+        let mut iter = old_buckets.iter();
+        while let Some(first) = iter.next() {
+            let merged = match iter.next() {
+                Some(second) => first.merge(second),
+                None => *first,
+            };
+            self.buckets.push_back(merged);
+        }
+        self.samples_per_bucket *= 2;
+    }
+
+    pub fn buckets(&self) -> impl Iterator<Item=&Bucket> + '_ { self.buckets.iter() }
+    pub fn capacity(&self) -> usize { self.capacity }
+    pub fn total_samples(&self) -> u64 { self.total_samples }
+
+    // returns the (min, max) range of all the samples in the history, if there are any
+    pub fn range(&self) -> Option<(f32, f32)> {
+        self.buckets.iter().fold(None, |acc, b| match acc {
+            None => Some((b.min, b.max)),
+            Some((min, max)) => Some((min.min(b.min), max.max(b.max))),
+        })
+    }
+}
+
+pub struct Chart {
+    texture: Option<(TextureId, Rc<Texture2d>)>,
+    uploaded_samples: Option<u64>,
+    uploaded_pixels: Vec<u8>,
+    height: u32,
+    color: [u8; 3],
+    range: Option<(f32, f32)>, // None fits the history
+}
+impl Chart {
+    pub fn new(height: u32, color: [u8; 3]) -> Self {
+        Self { texture: None, uploaded_samples: None, uploaded_pixels: vec![], height, color, range: None }
+    }
+
+    pub fn set_range(&mut self, range: Option<(f32, f32)>) {
+        if range != self.range {
+            self.range = range;
+            self.invalidate();
+        }
+    }
+
+    // must be called when the chart is given a different history, which may have as many samples
+    pub fn invalidate(&mut self) {
+        self.uploaded_samples = None;
+    }
+
+    pub fn update(&mut self, history: &DownsampledHistory, display: &Display, renderer: &mut Renderer) {
+        if self.uploaded_samples == Some(history.total_samples()) {
+            return;
+        }
+        self.uploaded_samples = Some(history.total_samples());
+
+        let width = history.capacity() as u32;
+        let pixels = Self::rasterize(history, width, self.height, self.color, self.range);
+        if self.texture.is_some() && pixels == self.uploaded_pixels {
+            return;
+        }
+        self.uploaded_pixels = pixels.clone();
+        let image = RawImage2d::from_raw_rgba(pixels, (width, self.height));
+
+        match &self.texture {
+            Some((_id, texture)) if texture.width() == width && texture.height() == self.height => {
+                texture.write(glium::Rect { left: 0, bottom: 0, width, height: self.height }, image);
+            }
+            _ => {
+                let texture = match Texture2d::new(display, image) {
+                    Ok(t) => Rc::new(t),
+                    Err(_) => return,
+                };
+                let imgui_texture = Texture {
+                    texture: texture.clone(),
+                    sampler: SamplerBehavior {
+                        magnify_filter: MagnifySamplerFilter::Linear,
+                        minify_filter: MinifySamplerFilter::Linear,
+                        ..Default::default()
+                    },
+                };
+                let id = match &self.texture {
+                    Some((id, _)) => {
+                        renderer.textures().replace(*id, imgui_texture);
+                        *id
+                    }
+                    None => renderer.textures().insert(imgui_texture),
+                };
+                self.texture = Some((id, texture));
+            }
+        }
+    }
+
+    pub fn draw(&self, ui: &Ui, size: [f32; 2]) {
+        if let Some((id, _)) = &self.texture {
+            imgui::Image::new(*id, size).build(ui);
+        }
+    }
+
+    // rows are written top to bottom, which is how imgui samples the texture with default uvs.
+    // the min..max band of each bucket is drawn translucent, and its average opaque
+    fn rasterize(history: &DownsampledHistory, width: u32, height: u32, color: [u8; 3], range: Option<(f32, f32)>) -> Vec<u8> {
+        let [r, g, b] = color;
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+        for px in pixels.chunks_mut(4) {
+            px.copy_from_slice(&[0, 0, 0, 40]);
+        }
+
+        let (min, max) = match range.or(history.range()) {
+            Some((min, max)) if max > min => (min, max),
+            Some((min, max)) => (min - 1.0, max + 1.0),
+            None => return pixels,
+        };
+        let to_row = |v: f32| -> u32 {
+            let normalized = (max - v) / (max - min);
+            ((normalized * (height - 1) as f32).round() as u32).min(height - 1)
+        };
+
+        for (x, bucket) in history.buckets().enumerate().take(width as usize) {
+            let x = x as u32;
+            let (top, bottom) = (to_row(bucket.max), to_row(bucket.min));
+            for y in top..=bottom {
+                let i = ((y * width + x) * 4) as usize;
+                pixels[i..i + 4].copy_from_slice(&[r, g, b, 110]);
+            }
+            let y = to_row(bucket.avg());
+            let i = ((y * width + x) * 4) as usize;
+            pixels[i..i + 4].copy_from_slice(&[r, g, b, 255]);
+        }
+
+        pixels
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use glium::{Display, VertexBuffer};
+use imgui::Ui;
+use nalgebra_glm::{UVec2, vec3};
+use super::tile_layers_overlay::Palette;
+use super::world_mesh::{Vertex, WorldMesh};
+
+// ChunkRebuilds is a debug overlay which tints every chunk of the WorldMesh by how many times it
+// was rebuilt in the last WINDOW, relative to the most rebuilt one, with a quad just above the top
+// of its bounding box; it helps telling apart the chunks rebuilt because the robot works there from
+// pathological refresh patterns (e.g. a change of the weather refreshing the whole map). Frames in
+// which every chunk was rebuilt are counted as whole-map rebuilds. The rebuilds are recorded even
+// while the overlay is hidden, so that the statistics are ready when it is shown.
+
+pub struct ChunkRebuilds {
+    pub show: bool,
+    history: VecDeque<(Instant, Vec<UVec2>)>,
+    counts: HashMap<UVec2, usize>,
+    whole_map_rebuilds: VecDeque<Instant>,
+    triangles: Vec<Vertex>,
+    pub vbo: Option<VertexBuffer<Vertex>>,
+    vbo_is_outdated: bool,
+}
+impl ChunkRebuilds {
+    pub const ALPHA: f32 = 0.45;
+    const WINDOW: Duration = Duration::from_secs(5);
+    const HEIGHT_ABOVE_CHUNK: f32 = 0.5;
+
+    pub fn new() -> Self {
+        Self {
+            show: false,
+            history: VecDeque::new(),
+            counts: HashMap::new(),
+            whole_map_rebuilds: VecDeque::new(),
+            triangles: vec![],
+            vbo: None,
+            vbo_is_outdated: false,
+        }
+    }
+
+    // must be called once per frame, after the world mesh is updated
+    pub fn record(&mut self, world_mesh: &WorldMesh) {
+        let now = Instant::now();
+        if !world_mesh.rebuilt_chunks.is_empty() {
+            for chunk in world_mesh.rebuilt_chunks.iter() {
+                *self.counts.entry(*chunk).or_insert(0) += 1;
+            }
+            if world_mesh.rebuilt_chunks.len() > 1 && world_mesh.rebuilt_chunks.len() >= world_mesh.chunks().count() {
+                self.whole_map_rebuilds.push_back(now);
+            }
+            self.history.push_back((now, world_mesh.rebuilt_chunks.clone()));
+            self.vbo_is_outdated = true;
+        }
+        while let Some((time, chunks)) = self.history.front() {
+            if now.duration_since(*time) < Self::WINDOW {
+                break;
+            }
+            for chunk in chunks.iter() {
+                if let Some(count) = self.counts.get_mut(chunk) {
+                    *count -= 1;
+                    if *count == 0 {
+                        self.counts.remove(chunk);
+                    }
+                }
+            }
+            self.history.pop_front();
+            self.vbo_is_outdated = true;
+        }
+        while self.whole_map_rebuilds.front().is_some_and(|time| now.duration_since(*time) >= Self::WINDOW) {
+            self.whole_map_rebuilds.pop_front();
+        }
+    }
+
+    // returns the number of bytes uploaded to the gpu
+    pub fn update_vbo(&mut self, display: &Display, world_mesh: &WorldMesh) -> usize {
+        if !self.vbo_is_outdated {
+            return 0;
+        }
+        self.vbo_is_outdated = false;
+
+        self.triangles.clear();
+        let max_count = self.counts.values().copied().max().unwrap_or(1) as f32;
+        for (chunk_pos, chunk) in world_mesh.chunks() {
+            let Some(count) = self.counts.get(&chunk_pos) else { continue };
+            let color = Palette::Heat.color(*count as f32 / max_count);
+            let y = chunk.max.y + Self::HEIGHT_ABOVE_CHUNK;
+            let [a, b, c, d] = [
+                vec3(chunk.min.x, y, chunk.min.z), vec3(chunk.max.x, y, chunk.min.z),
+                vec3(chunk.max.x, y, chunk.max.z), vec3(chunk.min.x, y, chunk.max.z),
+            ];
+            for p in [a, b, c, a, c, d] {
+                self.triangles.push(Vertex { position: *p.as_ref(), color });
+            }
+        }
+        self.vbo = if self.triangles.is_empty() { None } else { VertexBuffer::new(display, &self.triangles).ok() };
+        self.triangles.len() * std::mem::size_of::<Vertex>()
+    }
+
+    pub fn draw_stats(&self, ui: &Ui) {
+        let seconds = Self::WINDOW.as_secs();
+        match self.counts.iter().max_by_key(|(_, count)| **count) {
+            Some((chunk, count)) => ui.text(format!("Most rebuilt chunk: ({}, {}), {count} times in {seconds}s", chunk.x, chunk.y)),
+            None => ui.text(format!("No chunk rebuilt in {seconds}s")),
+        }
+        ui.text(format!("Whole-map rebuilds in {seconds}s: {}", self.whole_map_rebuilds.len()));
+    }
+}
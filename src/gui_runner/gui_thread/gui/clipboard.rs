@@ -0,0 +1,51 @@
+use copypasta::{ClipboardContext, ClipboardProvider};
+use imgui::{ClipboardBackend, Ui};
+use nalgebra_glm::{UVec2, Vec3};
+use robotics_lib::world::tile::Tile;
+
+// SystemClipboard connects imgui to the clipboard of the system, which imgui can't reach by itself
+// (without a backend copying and pasting only work within the GUI): once it is set as the backend
+// of the context every text input can be pasted into, and ui.set_clipboard_text copies out of the
+// GUI. When the clipboard can't be reached (e.g. without a display server) imgui's own is kept.
+// copy_button is the small button next to the values which can be copied, and the functions below
+// format the values copied, so that they read the same wherever they are copied from.
+
+pub struct SystemClipboard(ClipboardContext);
+impl SystemClipboard {
+    pub fn new() -> Result<Self, String> {
+        ClipboardContext::new().map(Self).map_err(|e| e.to_string())
+    }
+}
+impl ClipboardBackend for SystemClipboard {
+    fn get(&mut self) -> Option<String> {
+        self.0.get_contents().ok()
+    }
+
+    fn set(&mut self, value: &str) {
+        if let Err(e) = self.0.set_contents(value.to_owned()) {
+            eprintln!("could not copy to the clipboard: {e}");
+        }
+    }
+}
+
+// draws a small button copying the text (only built when clicked) to the clipboard. id must be
+// unique within the window
+pub fn copy_button(ui: &Ui, id: &str, text: impl FnOnce() -> String) {
+    if ui.small_button(format!("Copy##{id}")) {
+        ui.set_clipboard_text(text());
+    }
+    if ui.is_item_hovered() {
+        ui.tooltip_text("copy to the clipboard");
+    }
+}
+
+pub fn describe_tile(tile_pos: UVec2, tile: Option<&Tile>) -> String {
+    match tile {
+        Some(tile) => format!("tile ({}, {}): {:?}, content: {:?}, elevation: {}", tile_pos.x, tile_pos.y, tile.tile_type, tile.content, tile.elevation),
+        None => format!("tile ({}, {}): undiscovered", tile_pos.x, tile_pos.y),
+    }
+}
+
+pub fn describe_camera(cam_pos: Vec3, cam_dir: Vec3) -> String {
+    format!("position: [{:.3}, {:.3}, {:.3}], direction: [{:.4}, {:.4}, {:.4}]", cam_pos.x, cam_pos.y, cam_pos.z, cam_dir.x, cam_dir.y, cam_dir.z)
+}
@@ -3,13 +3,35 @@ use nalgebra_glm as glm;
 use glm::{Mat4, Vec3, vec3};
 use super::UP;
 
-// compute_mvp is a simple utility function which, given the frame(buffer) size, the camera position
-// and the camera direction returns the mvp (model-view-projection) matrix for the world. Note that
-// the model matrix is simply the identity matrix since the world is the only rendered object.
+// compute_mvp is a simple utility function which, given the frame(buffer) size, the camera position,
+// the camera direction and the distances of the clipping planes returns the mvp
+// (model-view-projection) matrix for the world. Note that the model matrix is simply the identity
+// matrix since the world is the only rendered object.
+// compute_ortho_mvp does the same for the top-down map view: an orthographic projection looking
+// straight down from cam_pos, with rows going downwards and columns rightwards on the screen, which
+// shows half_height tiles above and below the center.
+// log_depth_coefficient returns the coefficient the shaders need to compute a logarithmic depth
+// with the same far plane.
+
+pub const FOV: f32 = PI / 3.0;
+
+pub fn compute_mvp(frame_size: (u32, u32), cam_pos: Vec3, cam_dir: Vec3, near: f32, far: f32) -> Mat4 {
+    let model = Mat4::identity();
+    proj_matrix(frame_size, FOV, near, far) * view_matrix(cam_pos, cam_dir, UP) * model
+}
 
-pub fn compute_mvp(frame_size: (u32, u32), cam_pos: Vec3, cam_dir: Vec3) -> Mat4 {
+pub fn compute_ortho_mvp(frame_size: (u32, u32), cam_pos: Vec3, half_height: f32, far: f32) -> Mat4 {
     let model = Mat4::identity();
-    proj_matrix(frame_size, PI / 3.0) * view_matrix(cam_pos, cam_dir, UP) * model
+    let (width, height) = frame_size;
+    let half_width = half_height * width as f32 / height as f32;
+    // looking down, "up" on the screen is towards lower rows
+    let up = vec3(-1.0, 0.0, 0.0);
+    let proj = glm::ortho_lh(-half_width, half_width, -half_height, half_height, 1.0, far);
+    proj * view_matrix(cam_pos, vec3(0.0, -1.0, 0.0), up) * model
+}
+
+pub fn log_depth_coefficient(far: f32) -> f32 {
+    1.0 / (far + 1.0).log2()
 }
 
 fn view_matrix(cam_pos: Vec3, cam_dir: Vec3, up: Vec3) -> Mat4 {
@@ -30,9 +52,9 @@ fn view_matrix(cam_pos: Vec3, cam_dir: Vec3, up: Vec3) -> Mat4 {
         0.0, 0.0,      0.0,    1.0,
     )
 }
-fn proj_matrix(frame_size: (u32, u32), fov: f32) -> Mat4 {
+fn proj_matrix(frame_size: (u32, u32), fov: f32, near: f32, far: f32) -> Mat4 {
     let (width, height) = frame_size;
     let aspect_ratio = width as f32 / height as f32;
 
-    glm::perspective_lh(aspect_ratio, fov, 1.0/32.0, 8192.0)
+    glm::perspective_lh(aspect_ratio, fov, near, far)
 }
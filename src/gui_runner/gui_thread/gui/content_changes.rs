@@ -0,0 +1,178 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use glium::{Display, VertexBuffer};
+use imgui::{Condition, Ui};
+use nalgebra_glm::{UVec2, vec3};
+use robotics_lib::world::tile::Content;
+use super::picking;
+use super::world_mesh::Vertex;
+use crate::gui_runner::{ContentChange, PartialWorld};
+
+// ContentChanges animates the changes of the contents of the known tiles, as classified by the
+// worker thread (see PartialWorld::content_changes): every change is outlined by a square which
+// shrinks towards the center of the tile over HIGHLIGHT_DURATION, colored by its cause, so that
+// contents respawning or decaying by themselves stand out from those the robot collected or
+// placed. The changes made by the world are also listed in a window (the robot's are already in
+// the event log), the oldest being dropped past MAX_ENTRIES; selecting one returns its tile.
+// While highlights are running the LinesList vertex buffer is rebuilt every frame.
+
+struct LogEntry {
+    tick: usize,
+    tile: UVec2,
+    change: ContentChange,
+    content: Option<Content>, // the new content, for respawns
+}
+
+pub struct ContentChanges {
+    pub open: bool,
+    pub highlight_robot_changes: bool,
+    pub highlight_world_changes: bool,
+    highlights: Vec<(UVec2, ContentChange, Instant)>,
+    log: VecDeque<LogEntry>,
+    respawned: usize,
+    decayed: usize,
+
+    lines: Vec<Vertex>,
+    pub vbo: Option<VertexBuffer<Vertex>>,
+    vbo_is_outdated: bool,
+}
+impl ContentChanges {
+    const HIGHLIGHT_DURATION: Duration = Duration::from_millis(1500);
+    const MAX_ENTRIES: usize = 1000;
+    const HEIGHT_ABOVE_TERRAIN: f32 = 0.15;
+    const RISE: f32 = 0.6;
+
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            highlight_robot_changes: true,
+            highlight_world_changes: true,
+            highlights: vec![],
+            log: VecDeque::new(),
+            respawned: 0,
+            decayed: 0,
+            lines: vec![],
+            vbo: None,
+            vbo_is_outdated: false,
+        }
+    }
+
+    fn color(change: ContentChange) -> [f32; 3] {
+        match change {
+            ContentChange::ByRobot => [0.3, 0.8, 1.0],
+            ContentChange::Respawned => [0.3, 1.0, 0.3],
+            ContentChange::Decayed => [1.0, 0.5, 0.1],
+        }
+    }
+
+    fn describe(change: ContentChange) -> &'static str {
+        match change {
+            ContentChange::ByRobot => "changed by the robot",
+            ContentChange::Respawned => "respawned",
+            ContentChange::Decayed => "decayed",
+        }
+    }
+
+    // must be called with every world received from the worker
+    pub fn record(&mut self, world: &PartialWorld) {
+        let now = Instant::now();
+        for (tile, change) in world.content_changes.iter() {
+            self.highlights.push((*tile, *change, now));
+            let content = match change {
+                ContentChange::ByRobot => continue,
+                ContentChange::Respawned => {
+                    self.respawned += 1;
+                    world.world[tile.x as usize][tile.y as usize].as_ref().map(|tile| tile.content.clone())
+                }
+                ContentChange::Decayed => {
+                    self.decayed += 1;
+                    None
+                }
+            };
+            if self.log.len() == Self::MAX_ENTRIES {
+                self.log.pop_front();
+            }
+            self.log.push_back(LogEntry { tick: world.tick, tile: *tile, change: *change, content });
+        }
+    }
+
+    // must be called once per frame
+    pub fn update(&mut self, world: &PartialWorld) {
+        // one last rebuild clears the lines of the highlights which just ended
+        if self.highlights.is_empty() && self.lines.is_empty() {
+            return;
+        }
+        let now = Instant::now();
+        self.highlights.retain(|(_, _, start)| now.duration_since(*start) < Self::HIGHLIGHT_DURATION);
+
+        self.lines.clear();
+        for (tile_pos, change, start) in self.highlights.iter() {
+            let shown = match change {
+                ContentChange::ByRobot => self.highlight_robot_changes,
+                _ => self.highlight_world_changes,
+            };
+            if !shown {
+                continue;
+            }
+            let t = now.duration_since(*start).as_secs_f32() / Self::HIGHLIGHT_DURATION.as_secs_f32();
+            let inset = 0.5 * t;
+            let y = picking::tile_anchor(*tile_pos, &world.world).y + Self::HEIGHT_ABOVE_TERRAIN + Self::RISE * t;
+            let corner = |dx: f32, dz: f32| vec3(tile_pos.x as f32 + dx, y, tile_pos.y as f32 + dz);
+            let [a, b, c, d] = [corner(inset, inset), corner(1.0 - inset, inset), corner(1.0 - inset, 1.0 - inset), corner(inset, 1.0 - inset)];
+            let color = Self::color(*change);
+            for (from, to) in [(a, b), (b, c), (c, d), (d, a)] {
+                self.lines.push(Vertex { position: *from.as_ref(), color });
+                self.lines.push(Vertex { position: *to.as_ref(), color });
+            }
+        }
+        self.vbo_is_outdated = true;
+    }
+
+    // returns the number of bytes uploaded to the gpu
+    pub fn update_vbo(&mut self, display: &Display) -> usize {
+        if !self.vbo_is_outdated {
+            return 0;
+        }
+        self.vbo_is_outdated = false;
+        self.vbo = if self.lines.is_empty() { None } else { VertexBuffer::new(display, &self.lines).ok() };
+        self.lines.len() * std::mem::size_of::<Vertex>()
+    }
+
+    // draws the log window, returning the tile of the change the user selected, if any
+    pub fn draw(&mut self, ui: &Ui) -> Option<UVec2> {
+        if !self.open {
+            return None;
+        }
+        let mut selected = None;
+        let mut open = self.open;
+        ui.window("Content changes")
+            .opened(&mut open)
+            .size([340.0, 300.0], Condition::FirstUseEver)
+            .build(|| {
+                ui.text(format!("{} respawned, {} decayed", self.respawned, self.decayed));
+                ui.checkbox("highlight the world's changes", &mut self.highlight_world_changes);
+                ui.checkbox("highlight the robot's changes", &mut self.highlight_robot_changes);
+                for change in [ContentChange::Respawned, ContentChange::Decayed, ContentChange::ByRobot] {
+                    let [r, g, b] = Self::color(change);
+                    ui.text_colored([r, g, b, 1.0], Self::describe(change));
+                    ui.same_line();
+                }
+                ui.new_line();
+                ui.separator();
+
+                ui.child_window("content changes").build(|| {
+                    for (i, entry) in self.log.iter().enumerate().rev() {
+                        let content = entry.content.as_ref().map(|content| format!(" ({content:?})")).unwrap_or_default();
+                        let label = format!("tick {}: ({}, {}) {}{}##{}", entry.tick, entry.tile.x, entry.tile.y, Self::describe(entry.change), content, i);
+                        let [r, g, b] = Self::color(entry.change);
+                        let _color = ui.push_style_color(imgui::StyleColor::Text, [r, g, b, 1.0]);
+                        if ui.selectable(label) {
+                            selected = Some(entry.tile);
+                        }
+                    }
+                });
+            });
+        self.open = open;
+        selected
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use imgui::{Condition, DrawListMut, Ui};
+use nalgebra_glm::{Mat4, UVec2, Vec3, vec3};
+use robotics_lib::world::tile::Content;
+use super::picking;
+use crate::gui_runner::PartialWorld;
+
+// ContentIcons draws an icon (a colored disc with a glyph) over every discovered tile with some
+// content near the camera, as a billboard which always faces the screen and keeps the same size,
+// so that contents can be told apart even where their meshes are too small or hidden by terrain.
+// Hovering an icon shows the content along with its amount. The legend window maps every icon to
+// its Content variant, along with the number of tiles with that content discovered so far.
+// Contents are only searched for among the tiles which changed, like the world mesh does.
+// The same icons are drawn inline, as tall as a line of text, next to the contents of the backpacks.
+
+struct ContentKind {
+    name: &'static str,
+    glyph: &'static str,
+    color: [f32; 3],
+}
+
+const KINDS: [ContentKind; 15] = [
+    ContentKind { name: "Rock", glyph: "R", color: [0.55, 0.55, 0.55] },
+    ContentKind { name: "Tree", glyph: "T", color: [0.15, 0.6, 0.15] },
+    ContentKind { name: "Garbage", glyph: "G", color: [0.5, 0.35, 0.2] },
+    ContentKind { name: "Fire", glyph: "F", color: [1.0, 0.4, 0.0] },
+    ContentKind { name: "Coin", glyph: "$", color: [0.95, 0.8, 0.1] },
+    ContentKind { name: "Bin", glyph: "B", color: [0.3, 0.45, 0.3] },
+    ContentKind { name: "Crate", glyph: "C", color: [0.65, 0.45, 0.25] },
+    ContentKind { name: "Bank", glyph: "$$", color: [0.2, 0.5, 0.8] },
+    ContentKind { name: "Water", glyph: "W", color: [0.2, 0.4, 0.95] },
+    ContentKind { name: "Market", glyph: "M", color: [0.8, 0.3, 0.6] },
+    ContentKind { name: "Fish", glyph: "Fi", color: [0.3, 0.75, 0.85] },
+    ContentKind { name: "Building", glyph: "H", color: [0.7, 0.6, 0.5] },
+    ContentKind { name: "Bush", glyph: "b", color: [0.35, 0.75, 0.25] },
+    ContentKind { name: "JollyBlock", glyph: "J", color: [0.9, 0.3, 0.3] },
+    ContentKind { name: "Scarecrow", glyph: "S", color: [0.85, 0.75, 0.45] },
+];
+
+// index in KINDS of the kind of content, None for Content::None
+fn kind_index(content: &Content) -> Option<usize> {
+    Some(match content {
+        Content::Rock(_) => 0,
+        Content::Tree(_) => 1,
+        Content::Garbage(_) => 2,
+        Content::Fire => 3,
+        Content::Coin(_) => 4,
+        Content::Bin(_) => 5,
+        Content::Crate(_) => 6,
+        Content::Bank(_) => 7,
+        Content::Water(_) => 8,
+        Content::Market(_) => 9,
+        Content::Fish(_) => 10,
+        Content::Building => 11,
+        Content::Bush(_) => 12,
+        Content::JollyBlock(_) => 13,
+        Content::Scarecrow => 14,
+        Content::None => return None,
+    })
+}
+
+pub struct ContentIcons {
+    pub open: bool, // whether the legend window is open
+    pub show: bool,
+    max_distance: f32,
+    contents: HashMap<UVec2, Content>,
+}
+impl ContentIcons {
+    const RADIUS: f32 = 8.0;
+    const HEIGHT_ABOVE_TERRAIN: f32 = 1.6;
+
+    pub fn new() -> Self {
+        Self { open: false, show: false, max_distance: 48.0, contents: HashMap::new() }
+    }
+
+    // must be called with every new world, before its tiles_to_refresh are cleared
+    pub fn update(&mut self, world: &PartialWorld) {
+        for tile_pos in world.tiles_to_refresh.iter() {
+            match world.world[tile_pos.x as usize][tile_pos.y as usize].as_ref().map(|tile| &tile.content) {
+                Some(content) if kind_index(content).is_some() => { self.contents.insert(*tile_pos, content.clone()); }
+                _ => { self.contents.remove(tile_pos); }
+            }
+        }
+    }
+
+    fn draw_icon(ui: &Ui, draw_list: &DrawListMut, center: [f32; 2], kind: &ContentKind, radius: f32) {
+        let [r, g, b] = kind.color;
+        draw_list.add_circle(center, radius, [r, g, b, 0.9]).filled(true).build();
+        draw_list.add_circle(center, radius, [0.0, 0.0, 0.0, 0.9]).build();
+        let text_size = ui.calc_text_size(kind.glyph);
+        draw_list.add_text([center[0] - text_size[0] / 2.0, center[1] - text_size[1] / 2.0], [1.0, 1.0, 1.0, 1.0], kind.glyph);
+    }
+
+    // draws the icons over the contents within max_distance of the camera
+    pub fn draw_icons(&self, ui: &Ui, mvp: &Mat4, cam_pos: Vec3, world: &PartialWorld) {
+        if !self.show {
+            return;
+        }
+        let display_size = ui.io().display_size;
+        let mouse_pos = ui.io().mouse_pos;
+        let draw_list = ui.get_background_draw_list();
+        let mut hovered = None;
+        for (tile_pos, content) in self.contents.iter() {
+            let anchor = picking::tile_anchor(*tile_pos, &world.world) + vec3(0.0, Self::HEIGHT_ABOVE_TERRAIN, 0.0);
+            if nalgebra_glm::distance(&anchor, &cam_pos) > self.max_distance {
+                continue;
+            }
+            let Some(kind) = kind_index(content).map(|i| &KINDS[i]) else { continue };
+            let Some(center) = picking::project_to_screen(mvp, anchor, display_size) else { continue };
+            Self::draw_icon(ui, &draw_list, center, kind, Self::RADIUS);
+
+            let is_hovered = (mouse_pos[0] - center[0]).abs() <= Self::RADIUS && (mouse_pos[1] - center[1]).abs() <= Self::RADIUS;
+            if is_hovered {
+                hovered = Some((*tile_pos, content));
+            }
+        }
+        if let Some((tile_pos, content)) = hovered.filter(|_| !ui.io().want_capture_mouse) {
+            ui.tooltip_text(format!("{content:?} at ({}, {})", tile_pos.x, tile_pos.y));
+        }
+    }
+
+    // draws the legend window
+    pub fn draw(&mut self, ui: &Ui) {
+        if !self.open {
+            return;
+        }
+
+        let mut counts = [0; KINDS.len()];
+        for content in self.contents.values() {
+            if let Some(i) = kind_index(content) {
+                counts[i] += 1;
+            }
+        }
+
+        let mut open = self.open;
+        ui.window("Contents")
+            .opened(&mut open)
+            .size([220.0, 420.0], Condition::FirstUseEver)
+            .build(|| {
+                ui.checkbox("Show icons", &mut self.show);
+                ui.slider("max distance", 8.0, 256.0, &mut self.max_distance);
+                ui.separator();
+                for (kind, count) in KINDS.iter().zip(counts) {
+                    draw_inline_kind(ui, kind);
+                    ui.same_line();
+                    ui.text(format!("{}: {count} tiles", kind.name));
+                }
+            });
+        self.open = open;
+    }
+}
+
+fn draw_inline_kind(ui: &Ui, kind: &ContentKind) {
+    let cursor = ui.cursor_screen_pos();
+    let line_height = ui.text_line_height_with_spacing();
+    let center = [cursor[0] + line_height / 2.0, cursor[1] + line_height / 2.0];
+    ContentIcons::draw_icon(ui, &ui.get_window_draw_list(), center, kind, line_height / 2.0 - 1.0);
+    ui.dummy([line_height, line_height]);
+}
+
+// draws the icon of the content at the cursor, as tall as a line of text; nothing for Content::None
+pub fn draw_inline_icon(ui: &Ui, content: &Content) {
+    if let Some(i) = kind_index(content) {
+        draw_inline_kind(ui, &KINDS[i]);
+    }
+}
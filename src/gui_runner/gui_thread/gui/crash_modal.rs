@@ -0,0 +1,81 @@
+use imgui::Ui;
+use nalgebra_glm::UVec2;
+use crate::gui_runner::breakpoints::{Breakpoints, RobotCrash};
+
+// CrashModal is the modal popup shown when a robot panics during a tick: the robot wrapper catches
+// the panic and the game pauses by itself (see RobotCrash), so that the world can still be
+// inspected, and the popup tells which robot panicked, at which tick and where, with the message of
+// the panic. Once it's closed the last crash stays available from the View menu. Resuming the game
+// runs the robot again, however the panic left it.
+
+pub struct CrashModal {
+    shared: Breakpoints,
+    crash: Option<RobotCrash>, // the last one
+    show: bool,
+}
+impl CrashModal {
+    const POPUP_ID: &'static str = "Robot crashed";
+    const ERROR_COLOR: [f32; 4] = [1.0, 0.4, 0.4, 1.0];
+
+    pub fn new(shared: Breakpoints) -> Self {
+        Self { shared, crash: None, show: false }
+    }
+
+    // returns whether a robot crashed since the last call, which must be made every frame
+    pub fn receive(&mut self) -> bool {
+        let Some(crash) = self.shared.take_crash() else { return false };
+        eprintln!("robot {} panicked at tick {}: {}", crash.robot, crash.tick, crash.message);
+        self.crash = Some(crash);
+        self.show = true;
+        true
+    }
+
+    pub fn has_crashed(&self) -> bool {
+        self.crash.is_some()
+    }
+
+    // shows the last crash again
+    pub fn reopen(&mut self) {
+        self.show = self.crash.is_some();
+    }
+
+    // returns the tile the robot crashed at when the user asks to go there
+    pub fn draw(&mut self, ui: &Ui) -> Option<UVec2> {
+        let Some(crash) = &self.crash else { return None };
+        if self.show {
+            ui.open_popup(Self::POPUP_ID);
+            self.show = false;
+        }
+
+        let mut go_to_crash = None;
+        ui.modal_popup_config(Self::POPUP_ID)
+            .always_auto_resize(true)
+            .build(|| {
+                let robot = match crash.robot {
+                    0 => "The robot".to_string(),
+                    robot => format!("Robot {robot}"),
+                };
+                ui.text_colored(Self::ERROR_COLOR, format!("{robot} panicked at tick {}, at ({}, {}):", crash.tick, crash.robot_position.x, crash.robot_position.y));
+                let wrap = ui.push_text_wrap_pos_with_pos(ui.cursor_pos()[0] + 480.0);
+                ui.text(&crash.message);
+                wrap.end();
+                ui.separator();
+                ui.text_disabled("The game was paused, so the world can still be inspected.");
+                ui.text_disabled("Resuming it runs the robot again.");
+
+                if ui.button("Copy message") {
+                    ui.set_clipboard_text(&crash.message);
+                }
+                ui.same_line();
+                if ui.button("Go to the robot") {
+                    go_to_crash = Some(crash.robot_position);
+                    ui.close_current_popup();
+                }
+                ui.same_line();
+                if ui.button("Close") {
+                    ui.close_current_popup();
+                }
+            });
+        go_to_crash
+    }
+}
@@ -0,0 +1,55 @@
+use std::f32::consts::PI;
+use nalgebra_glm::{lerp, Vec3, vec3};
+use robotics_lib::world::environmental_conditions::EnvironmentalConditions;
+use super::sundial;
+
+// Daylight describes the lighting of the scene at the time of day of the EnvironmentalConditions:
+// the sun travels from the -x side of the world (rising at 6:00) to the +x side (setting at 18:00),
+// its light turning from warm to white as it rises; the moon does the same from 18:00 to 6:00 with a
+// dim blue light. The sky (i.e. the clear color) goes from orange at dawn and dusk to blue at noon
+// and to almost black at night. Lighting fades in and out with the elevation of the sun and the
+// moon, so that the scene changes smoothly as time goes by.
+
+pub struct Daylight {
+    pub light_direction: Vec3, // direction towards the sun (or moon)
+    pub light_color: Vec3,
+    pub ambient: Vec3,
+    pub sky_color: (f32, f32, f32, f32),
+}
+impl Daylight {
+    // unshaded colors, as if there was no lighting at all
+    pub const NEUTRAL: Self = Self {
+        light_direction: Vec3::new(0.0, 1.0, 0.0),
+        light_color: Vec3::new(0.0, 0.0, 0.0),
+        ambient: Vec3::new(1.0, 1.0, 1.0),
+        sky_color: (0.2, 0.2, 0.2, 1.0),
+    };
+
+    pub fn from_env_cond(env_cond: &EnvironmentalConditions) -> Self {
+        let hours = sundial::hours_since_midnight(env_cond);
+        let is_day = (6.0..18.0).contains(&hours);
+        // 0 at rise, 1 at set
+        let progress = if is_day { (hours - 6.0) / 12.0 } else { ((hours + 6.0) % 24.0) / 12.0 };
+        let angle = PI * progress;
+        let elevation = angle.sin();
+        let light_direction = vec3(-angle.cos(), elevation, 0.35).normalize();
+
+        let moon_light = vec3(0.25, 0.3, 0.45) * elevation;
+        let night_ambient = vec3(0.2, 0.22, 0.32);
+        let night_sky = vec3(0.03, 0.04, 0.08);
+        // how much of the daylight is in the mix: none at night, all once the sun is high enough
+        let day = if is_day { (elevation * 4.0).min(1.0) } else { 0.0 };
+
+        let sun_light = lerp(&vec3(1.0, 0.6, 0.35), &vec3(1.0, 0.97, 0.9), elevation) * (0.3 + 0.4 * elevation);
+        let day_ambient = vec3(0.5, 0.52, 0.55) * (0.7 + 0.3 * elevation);
+        let day_sky = lerp(&vec3(0.8, 0.5, 0.35), &vec3(0.45, 0.62, 0.85), elevation);
+
+        let sky = lerp(&night_sky, &day_sky, day);
+        Self {
+            light_direction,
+            light_color: lerp(&moon_light, &sun_light, day),
+            ambient: lerp(&night_ambient, &day_ambient, day),
+            sky_color: (sky.x, sky.y, sky.z, 1.0),
+        }
+    }
+}
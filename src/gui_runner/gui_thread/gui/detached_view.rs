@@ -0,0 +1,77 @@
+use crate::gui_runner::PartialWorld;
+use crate::gui_runner::worker_thread::tiles_to_refresh;
+
+// DetachedView lets the GUI show a world other than the one of the run (a snapshot loaded from a
+// file, or an earlier tick of the run) while the run goes on: the worlds received meanwhile are
+// stashed rather than shown, and the last one is shown again when returning to the run. Since the
+// world shown may differ anywhere from the one it replaces, the two are diffed whole to find the
+// tiles to refresh (the worker thread only diffs the worlds of the run with each other).
+
+pub enum ViewRequest {
+    Show(PartialWorld),
+    ReturnToRun,
+}
+
+pub struct DetachedView {
+    live_world: Option<PartialWorld>, // Some while detached
+    requested: Option<ViewRequest>,
+}
+impl DetachedView {
+    const REFRESH_RADIUS: u32 = 1;
+
+    pub fn new() -> Self {
+        Self { live_world: None, requested: None }
+    }
+
+    pub fn is_detached(&self) -> bool {
+        self.live_world.is_some()
+    }
+
+    // tick of the run, while detached
+    pub fn live_tick(&self) -> Option<usize> {
+        self.live_world.as_ref().map(|live_world| live_world.tick)
+    }
+
+    // the request is carried out by the next call to filter
+    pub fn request(&mut self, request: ViewRequest) {
+        self.requested = Some(request);
+    }
+
+    // must be called once per frame with the last world received from the worker, if any (whose
+    // tiles_to_refresh are ignored) and the world being shown; returns the world to show instead
+    pub fn filter(&mut self, received: Option<PartialWorld>, shown: &PartialWorld) -> Option<PartialWorld> {
+        let world = match self.requested.take() {
+            Some(ViewRequest::Show(world)) => {
+                if self.live_world.is_none() {
+                    self.live_world = Some(shown.clone());
+                }
+                if let Some(received) = received {
+                    self.live_world = Some(received);
+                }
+                world
+            }
+            // a world received in this frame is newer than the stashed one
+            Some(ViewRequest::ReturnToRun) => match received.or_else(|| self.live_world.take()) {
+                Some(live_world) => {
+                    self.live_world = None;
+                    live_world
+                }
+                None => return None,
+            },
+            None if self.is_detached() => {
+                if let Some(received) = received {
+                    self.live_world = Some(received);
+                }
+                return None;
+            }
+            None => return received,
+        };
+        Some(Self::with_tiles_to_refresh(world, shown))
+    }
+
+    fn with_tiles_to_refresh(mut world: PartialWorld, shown: &PartialWorld) -> PartialWorld {
+        world.touched_tiles = None;
+        world.tiles_to_refresh = tiles_to_refresh(&mut Some(shown.world.clone()), &world, Self::REFRESH_RADIUS, true);
+        world
+    }
+}
@@ -0,0 +1,66 @@
+use std::time::Duration;
+use imgui::{Condition, Ui};
+use crate::gui_runner::thread_health::{HealthMonitor, MonitoredThread, ThreadStatus};
+
+// draw_diagnostics shows a window naming the threads which stalled or died (if any, along with the
+// error they stopped because of), or telling for how long the current tick has been running if it
+// exceeded tick_timeout, offering to terminate the run. The game runs on the thread which called
+// GuiRunner::run, so nothing can end the run before the current tick returns: when the game thread
+// is stalled inside a tick the user is also offered to close the GUI right away, and GuiRunner::run
+// returns RagnarokError::Aborted once the tick returns.
+
+pub enum DiagnosticsAction {
+    None,
+    Terminate,
+    CloseGui,
+}
+
+pub fn draw_diagnostics(ui: &Ui, health: &HealthMonitor, stall_timeout: Duration, tick_timeout: Duration) -> DiagnosticsAction {
+    let long_tick = health.tick_duration().filter(|d| *d > tick_timeout);
+    let problems = [MonitoredThread::Game, MonitoredThread::Worker]
+        .into_iter()
+        .map(|t| (t, health.status(t, stall_timeout)))
+        .filter(|(_, status)| *status != ThreadStatus::Alive)
+        // a game thread stalled by a long tick is already reported as such
+        .filter(|(t, status)| !(long_tick.is_some() && *t == MonitoredThread::Game && matches!(status, ThreadStatus::Stalled(_))))
+        .collect::<Vec<_>>();
+
+    if problems.is_empty() && long_tick.is_none() {
+        return DiagnosticsAction::None;
+    }
+
+    let mut action = DiagnosticsAction::None;
+    ui.window("Diagnostics")
+        .size([360.0, 180.0], Condition::FirstUseEver)
+        .position([400.0, 40.0], Condition::FirstUseEver)
+        .build(|| {
+            if let Some(d) = long_tick {
+                ui.text_colored([1.0, 0.5, 0.3, 1.0], format!("The current tick has been running for {:.1}s", d.as_secs_f32()));
+            }
+            for (thread, status) in problems.iter() {
+                let description = match (status, health.error(*thread)) {
+                    (ThreadStatus::Stalled(d), _) => format!("has not responded for {:.1}s", d.as_secs_f32()),
+                    (ThreadStatus::Panicked, _) => "panicked".to_string(),
+                    (ThreadStatus::Finished, Some(error)) => format!("stopped: {error}"),
+                    (ThreadStatus::Finished, None) => "exited unexpectedly".to_string(),
+                    (ThreadStatus::Alive, _) => unreachable!(),
+                };
+                ui.text_colored([1.0, 0.5, 0.3, 1.0], format!("The {} {description}", thread.name()));
+            }
+            ui.separator();
+
+            let game_is_stalled = long_tick.is_some() || problems.iter().any(|(t, s)| *t == MonitoredThread::Game && matches!(s, ThreadStatus::Stalled(_)));
+            if ui.button("Terminate") {
+                action = DiagnosticsAction::Terminate;
+            }
+            if game_is_stalled {
+                ui.same_line();
+                if ui.button("Close GUI") {
+                    action = DiagnosticsAction::CloseGui;
+                }
+                ui.text_wrapped("The run can only terminate once the current tick returns, closing the GUI doesn't stop it.");
+            }
+        });
+
+    action
+}
@@ -0,0 +1,126 @@
+use std::time::{Duration, Instant};
+use glium::{Display, VertexBuffer};
+use imgui::Ui;
+use nalgebra_glm::{UVec2, vec3};
+use super::picking;
+use super::tile_layers_overlay::Palette;
+use super::world_mesh::Vertex;
+use crate::gui_runner::PartialWorld;
+
+// DiscoveryAge records the tick at which every tile was first discovered (the tiles known from the
+// start count as discovered at the first tick shown, the others are found among the changed tiles
+// of the worlds received) and colors the discovered tiles by how long ago that was, the newest
+// being the brightest, to show the exploration frontier of the robot. The age fades out over
+// fade_ticks ticks, past which tiles keep the dimmest color. Since the colors depend on the current
+// tick the quads are rebuilt whenever it changes, at most every REBUILD_INTERVAL. Going back in
+// time (stepping back or seeking in a replay) forgets the discoveries made after the tick shown.
+
+pub struct DiscoveryAge {
+    pub show: bool,
+    pub opacity: f32,
+    pub fade_ticks: u32,
+    discovered_at: Vec<Vec<Option<usize>>>,
+    last_tick: usize,
+    built: Option<(usize, u32, Instant)>, // tick and fade_ticks the quads were built for, and when
+
+    triangles: Vec<Vertex>,
+    pub vbo: Option<VertexBuffer<Vertex>>,
+    vbo_is_outdated: bool,
+}
+impl DiscoveryAge {
+    const HEIGHT_ABOVE_TERRAIN: f32 = 0.1;
+    const REBUILD_INTERVAL: Duration = Duration::from_millis(250);
+
+    pub fn new(world: &PartialWorld) -> Self {
+        let discovered_at = world.world.iter()
+            .map(|row| row.iter().map(|tile| tile.as_ref().map(|_| world.tick)).collect())
+            .collect();
+        Self {
+            show: false,
+            opacity: 0.6,
+            fade_ticks: 500,
+            discovered_at,
+            last_tick: world.tick,
+            built: None,
+            triangles: vec![],
+            vbo: None,
+            vbo_is_outdated: false,
+        }
+    }
+
+    // must be called with every world received from the worker
+    pub fn record(&mut self, world: &PartialWorld) {
+        if world.tick < self.last_tick {
+            self.discovered_at.iter_mut().flatten()
+                .filter(|discovered_at| discovered_at.is_some_and(|tick| tick > world.tick))
+                .for_each(|discovered_at| *discovered_at = None);
+        }
+        self.last_tick = world.tick;
+        for tile in world.changed_tiles.iter() {
+            let discovered_at = &mut self.discovered_at[tile.x as usize][tile.y as usize];
+            if discovered_at.is_none() && world.world[tile.x as usize][tile.y as usize].is_some() {
+                *discovered_at = Some(world.tick);
+            }
+        }
+    }
+
+    // must be called once per frame
+    pub fn update(&mut self, world: &PartialWorld) {
+        if !self.show {
+            return;
+        }
+        if let Some((tick, fade_ticks, built_at)) = self.built {
+            if (tick == world.tick && fade_ticks == self.fade_ticks) || built_at.elapsed() < Self::REBUILD_INTERVAL {
+                return;
+            }
+        }
+        self.triangles.clear();
+        for (x, row) in self.discovered_at.iter().enumerate() {
+            for (y, discovered_at) in row.iter().enumerate() {
+                let Some(discovered_at) = discovered_at else { continue };
+                let tile_pos = UVec2::new(x as u32, y as u32);
+                let age = world.tick.saturating_sub(*discovered_at) as f32;
+                let color = Palette::Viridis.color(1.0 - (age / self.fade_ticks.max(1) as f32).min(1.0));
+                let y = picking::tile_anchor(tile_pos, &world.world).y + Self::HEIGHT_ABOVE_TERRAIN;
+                let corner = |dx: f32, dz: f32| vec3(tile_pos.x as f32 + dx, y, tile_pos.y as f32 + dz);
+                let [a, b, c, d] = [corner(0.0, 0.0), corner(1.0, 0.0), corner(1.0, 1.0), corner(0.0, 1.0)];
+                for p in [a, b, c, a, c, d] {
+                    self.triangles.push(Vertex { position: *p.as_ref(), color });
+                }
+            }
+        }
+        self.built = Some((world.tick, self.fade_ticks, Instant::now()));
+        self.vbo_is_outdated = true;
+    }
+
+    // returns the number of bytes uploaded to the gpu
+    pub fn update_vbo(&mut self, display: &Display) -> usize {
+        if !self.vbo_is_outdated {
+            return 0;
+        }
+        self.vbo_is_outdated = false;
+        self.vbo = if self.triangles.is_empty() { None } else { VertexBuffer::new(display, &self.triangles).ok() };
+        self.triangles.len() * std::mem::size_of::<Vertex>()
+    }
+
+    // the tick the tile was first discovered at, if it was
+    pub fn discovered_at(&self, tile: UVec2) -> Option<usize> {
+        self.discovered_at.get(tile.x as usize)?.get(tile.y as usize).copied().flatten()
+    }
+
+    // draws the settings, below the checkbox showing the overlay
+    pub fn draw_settings(&mut self, ui: &Ui) {
+        if !self.show {
+            return;
+        }
+        ui.indent();
+        ui.set_next_item_width(120.0);
+        ui.slider("opacity##discovery_age", 0.1, 1.0, &mut self.opacity);
+        ui.set_next_item_width(120.0);
+        ui.slider_config("fade over##discovery_age", 10, 10_000)
+            .flags(imgui::SliderFlags::LOGARITHMIC)
+            .display_format("%d ticks")
+            .build(&mut self.fade_ticks);
+        ui.unindent();
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::{BTreeMap, VecDeque};
+use imgui::{Condition, TableFlags, Ui};
+use crate::gui_runner::{PartialWorld, Telemetry};
+use crate::gui_runner::event_journal::LoggedEvent;
+
+// EnergyPlanner estimates how long the robot's energy will last and whether it suffices for the
+// plan the robot published through the Telemetry handle (if any). The cost of every action is
+// learnt from the events of the event log: an EnergyConsumed is attributed to the action told by
+// the event following it in the same tick (Moved for go, AddedToBackpack for destroy,
+// RemovedFromBackpack for put) or to "other" when none tells it, and the last SAMPLES costs of
+// every action are averaged. The energy consumed and recharged over the last WINDOW ticks give the
+// rate at which the energy goes down, from which the tick it runs out at is projected.
+// The plan is walked action by action, spending the average cost of each (that of all the actions
+// for the ones never seen) and recharging at the recent rate spread over the actions, to find the
+// first one the energy won't suffice for: when a plan becomes infeasible the window opens by
+// itself, while the robot still has the energy to change its plan. Since the events only come
+// from the robot being run, the planner is only available in live runs.
+
+struct TickEnergy {
+    tick: usize,
+    consumed: usize,
+    recharged: usize,
+    actions: usize,
+}
+
+struct Rates {
+    ticks: usize,
+    consumed: f32, // per tick
+    recharged: f32, // per tick
+    actions: f32, // per tick
+}
+
+struct PlanEstimate {
+    cost: f32,
+    runs_out_at: Option<usize>, // index of the first action the energy won't suffice for
+    energy_left: f32,
+    ticks: Option<f32>, // to carry the plan out, if the robot acted recently
+}
+
+pub struct EnergyPlanner {
+    pub open: bool,
+    telemetry: Option<Telemetry>,
+    plan_cursor: u64,
+    plan: Option<(usize, Vec<String>)>, // tick it was published at, actions
+    estimate: Option<PlanEstimate>,
+    costs: BTreeMap<String, VecDeque<usize>>, // the last costs of every action
+    pending: Option<(usize, usize)>, // tick and amount of the last EnergyConsumed, until the next event tells its action
+    ticks: VecDeque<TickEnergy>, // the ticks with events among the last WINDOW ticks
+    energy: usize,
+    tick: usize,
+}
+impl EnergyPlanner {
+    const WINDOW: usize = 200;
+    const SAMPLES: usize = 100;
+    const MAX_ENERGY: f32 = 1000.0;
+    const WARNING_COLOR: [f32; 4] = [1.0, 0.4, 0.4, 1.0];
+
+    pub fn new(telemetry: Option<Telemetry>) -> Self {
+        Self {
+            open: false,
+            telemetry,
+            plan_cursor: 0,
+            plan: None,
+            estimate: None,
+            costs: BTreeMap::new(),
+            pending: None,
+            ticks: VecDeque::new(),
+            energy: 0,
+            tick: 0,
+        }
+    }
+
+    // must be called with every new world
+    pub fn record(&mut self, world: &PartialWorld) {
+        self.energy = world.energy;
+        self.tick = world.tick;
+    }
+
+    // the amount of an EnergyConsumed or EnergyRecharged event, e.g. "EnergyConsumed(5)"
+    fn amount(description: &str) -> usize {
+        description.trim_end_matches(')').rsplit('(').next().and_then(|amount| amount.parse().ok()).unwrap_or(0)
+    }
+
+    fn tick_energy(&mut self, tick: usize) -> &mut TickEnergy {
+        if self.ticks.back().map_or(true, |last| last.tick != tick) {
+            self.ticks.push_back(TickEnergy { tick, consumed: 0, recharged: 0, actions: 0 });
+        }
+        while self.ticks.front().map_or(false, |first| tick.saturating_sub(first.tick) >= Self::WINDOW) {
+            self.ticks.pop_front();
+        }
+        self.ticks.back_mut().unwrap()
+    }
+
+    fn record_cost(&mut self, tick: usize, action: &str, cost: usize) {
+        let tick_energy = self.tick_energy(tick);
+        tick_energy.consumed += cost;
+        tick_energy.actions += 1;
+        let costs = self.costs.entry(action.to_string()).or_default();
+        costs.push_back(cost);
+        if costs.len() > Self::SAMPLES {
+            costs.pop_front();
+        }
+    }
+
+    // must be called every frame with the events received, even when the window is closed
+    pub fn receive(&mut self, events: &[LoggedEvent]) {
+        for event in events {
+            let entry = &event.entry;
+            let action = match entry.kind.as_str() {
+                "Moved" => Some("go"),
+                "AddedToBackpack" => Some("destroy"),
+                "RemovedFromBackpack" => Some("put"),
+                _ => None,
+            };
+            if let Some((tick, cost)) = self.pending.take() {
+                let action = action.filter(|_| entry.tick == tick).unwrap_or("other");
+                self.record_cost(tick, action, cost);
+            }
+            match entry.kind.as_str() {
+                "EnergyConsumed" => self.pending = Some((entry.tick, Self::amount(&entry.description))),
+                "EnergyRecharged" => self.tick_energy(entry.tick).recharged += Self::amount(&entry.description),
+                _ => {}
+            }
+        }
+
+        let new_plan = self.telemetry.as_ref().and_then(|telemetry| telemetry.read_new_plan(&mut self.plan_cursor));
+        if let Some((tick, actions)) = new_plan {
+            self.plan = Some((tick, actions)).filter(|(_, actions)| !actions.is_empty());
+        }
+        let was_feasible = self.estimate.as_ref().map_or(true, |estimate| estimate.runs_out_at.is_none());
+        self.estimate = self.plan.as_ref().and_then(|(_, actions)| self.estimate_plan(actions));
+        if was_feasible && self.estimate.as_ref().is_some_and(|estimate| estimate.runs_out_at.is_some()) {
+            self.open = true;
+        }
+    }
+
+    fn rates(&self) -> Option<Rates> {
+        let first_tick = self.ticks.front()?.tick;
+        let ticks = self.tick.max(self.ticks.back()?.tick) + 1 - first_tick;
+        let per_tick = |value: fn(&TickEnergy) -> usize| self.ticks.iter().map(value).sum::<usize>() as f32 / ticks as f32;
+        Some(Rates {
+            ticks,
+            consumed: per_tick(|tick| tick.consumed),
+            recharged: per_tick(|tick| tick.recharged),
+            actions: per_tick(|tick| tick.actions),
+        })
+    }
+
+    fn estimate_plan(&self, actions: &[String]) -> Option<PlanEstimate> {
+        let average = |costs: &VecDeque<usize>| costs.iter().sum::<usize>() as f32 / costs.len() as f32;
+        let samples: usize = self.costs.values().map(VecDeque::len).sum();
+        if samples == 0 {
+            return None;
+        }
+        let overall_average = self.costs.values().flatten().sum::<usize>() as f32 / samples as f32;
+        let rates = self.rates().filter(|rates| rates.actions > 0.0);
+        let recharge_per_action = rates.as_ref().map_or(0.0, |rates| rates.recharged / rates.actions);
+
+        let mut estimate = PlanEstimate { cost: 0.0, runs_out_at: None, energy_left: self.energy as f32, ticks: None };
+        for (i, action) in actions.iter().enumerate() {
+            let cost = self.costs.get(action).map_or(overall_average, average);
+            estimate.cost += cost;
+            if estimate.runs_out_at.is_none() {
+                if cost > estimate.energy_left {
+                    estimate.runs_out_at = Some(i);
+                } else {
+                    estimate.energy_left = (estimate.energy_left - cost + recharge_per_action).min(Self::MAX_ENERGY);
+                }
+            }
+        }
+        estimate.ticks = rates.map(|rates| actions.len() as f32 / rates.actions);
+        Some(estimate)
+    }
+
+    pub fn draw(&mut self, ui: &Ui) {
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        ui.window("Energy planner")
+            .opened(&mut open)
+            .size([340.0, 320.0], Condition::FirstUseEver)
+            .build(|| {
+                ui.text(format!("energy: {} at tick {}", self.energy, self.tick));
+                match self.rates() {
+                    Some(rates) => {
+                        ui.text(format!("over the last {} ticks: -{:.2} +{:.2} per tick", rates.ticks, rates.consumed, rates.recharged));
+                        let drain = rates.consumed - rates.recharged;
+                        if drain > 0.0 {
+                            let ticks_left = (self.energy as f32 / drain).floor() as usize;
+                            ui.text(format!("projected to run out at tick {} (in {ticks_left} ticks)", self.tick + ticks_left));
+                        } else {
+                            ui.text("not running out at this rate");
+                        }
+                    }
+                    None => ui.text_disabled("no energy consumed or recharged yet"),
+                }
+
+                ui.separator();
+                match (&self.plan, &self.estimate) {
+                    (None, _) => ui.text_wrapped("No plan was published: the robot can publish one through Telemetry::publish_plan."),
+                    (Some((tick, actions)), estimate) => {
+                        ui.text(format!("plan of {} actions, published at tick {tick}", actions.len()));
+                        match estimate {
+                            None => ui.text_disabled("its cost can't be estimated before any action is seen"),
+                            Some(estimate) => {
+                                ui.text(format!("estimated cost: {:.0}", estimate.cost));
+                                if let Some(ticks) = estimate.ticks {
+                                    ui.text(format!("estimated to end at tick {}", tick + ticks.ceil() as usize));
+                                }
+                                match estimate.runs_out_at {
+                                    Some(i) => {
+                                        let runs_out_tick = estimate.ticks.map(|ticks| tick + (ticks * i as f32 / actions.len() as f32) as usize);
+                                        let when = runs_out_tick.map(|tick| format!(", around tick {tick}")).unwrap_or_default();
+                                        ui.text_colored(Self::WARNING_COLOR, format!("infeasible: the energy runs out at action {} ({}){when}", i + 1, actions[i]));
+                                    }
+                                    None => ui.text(format!("feasible, {:.0} energy left at its end", estimate.energy_left)),
+                                }
+                            }
+                        }
+                    }
+                }
+
+                ui.separator();
+                let flags = TableFlags::BORDERS | TableFlags::ROW_BG | TableFlags::SIZING_FIXED_FIT;
+                if let Some(_table) = ui.begin_table_with_flags("action costs", 4, flags) {
+                    for header in ["action", "average cost", "last", "samples"] {
+                        ui.table_setup_column(header);
+                    }
+                    ui.table_headers_row();
+                    for (action, costs) in self.costs.iter() {
+                        ui.table_next_row();
+                        ui.table_next_column();
+                        ui.text(action);
+                        ui.table_next_column();
+                        ui.text(format!("{:.1}", costs.iter().sum::<usize>() as f32 / costs.len() as f32));
+                        ui.table_next_column();
+                        ui.text(costs.back().map(ToString::to_string).unwrap_or_default());
+                        ui.table_next_column();
+                        ui.text(costs.len().to_string());
+                    }
+                }
+            });
+        self.open = open;
+    }
+}
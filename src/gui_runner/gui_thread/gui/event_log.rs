@@ -0,0 +1,144 @@
+use std::sync::mpsc::Receiver;
+use imgui::{Condition, ListClipper, Ui};
+use nalgebra_glm::UVec2;
+use crate::gui_runner::event_journal::LoggedEvent;
+use super::journal_viewer::JournalViewer;
+
+// EventLog is an imgui window listing the events received by the robot as they happen, which the
+// game thread sends through the game->gui channel (unlike the event journal, nothing is written to
+// disk). Every event shows the time since the start of the run and the tick it was received at,
+// and the list can be filtered by event type and by text; the most frequent events (moves, time
+// changes, ...) are hidden by default. The oldest events are dropped past MAX_EVENTS. Selecting
+// an event returns the location it refers to, like the journal viewer does.
+
+pub struct EventLog {
+    pub open: bool,
+    rx: Receiver<LoggedEvent>,
+    events: Vec<LoggedEvent>,
+
+    kind_filter: String,
+    text_filter: String,
+    hide_frequent: bool,
+    auto_scroll: bool,
+    filtered: Vec<usize>,
+    selected: Option<usize>,
+}
+impl EventLog {
+    const MAX_EVENTS: usize = 100_000;
+    const FREQUENT_KINDS: [&'static str; 6] = ["Ready", "Moved", "DayChanged", "TimeChanged", "EnergyRecharged", "EnergyConsumed"];
+
+    pub fn new(rx: Receiver<LoggedEvent>) -> Self {
+        Self {
+            open: false,
+            rx,
+            events: vec![],
+            kind_filter: String::new(),
+            text_filter: String::new(),
+            hide_frequent: true,
+            auto_scroll: true,
+            filtered: vec![],
+            selected: None,
+        }
+    }
+
+    // must be called every frame, even when the window is closed, so that the channel doesn't grow.
+    // returns the events received
+    pub fn receive(&mut self) -> &[LoggedEvent] {
+        let first_new_event = self.events.len();
+        self.events.extend(self.rx.try_iter());
+        let received = self.events.len() - first_new_event;
+
+        if self.events.len() > Self::MAX_EVENTS {
+            // drop a quarter at once, so that the indices don't have to be shifted at every event
+            self.events.drain(..Self::MAX_EVENTS / 4);
+            self.selected = None;
+            self.apply_filters();
+        } else {
+            for i in first_new_event..self.events.len() {
+                if self.matches_filters(&self.events[i]) {
+                    self.filtered.push(i);
+                }
+            }
+        }
+        &self.events[self.events.len().saturating_sub(received)..]
+    }
+
+    fn matches_filters(&self, event: &LoggedEvent) -> bool {
+        let entry = &event.entry;
+        let frequent_matches = !self.hide_frequent || !Self::FREQUENT_KINDS.contains(&entry.kind.as_str());
+        let kind_matches = self.kind_filter.is_empty()
+            || entry.kind.to_lowercase().contains(&self.kind_filter.to_lowercase());
+        let text_matches = self.text_filter.is_empty()
+            || entry.description.to_lowercase().contains(&self.text_filter.to_lowercase());
+        frequent_matches && kind_matches && text_matches
+    }
+
+    fn apply_filters(&mut self) {
+        self.filtered = (0..self.events.len()).filter(|i| self.matches_filters(&self.events[*i])).collect();
+    }
+
+    // returns the location of the event selected this frame, if any
+    pub fn draw(&mut self, ui: &Ui) -> Option<UVec2> {
+        if !self.open {
+            return None;
+        }
+
+        let mut selected_location = None;
+        let mut open = self.open;
+        ui.window("Event log")
+            .opened(&mut open)
+            .size([560.0, 420.0], Condition::FirstUseEver)
+            .build(|| {
+                let mut filters_changed = false;
+                filters_changed |= ui.input_text("Event type", &mut self.kind_filter).build();
+                filters_changed |= ui.input_text("Text", &mut self.text_filter).build();
+                filters_changed |= ui.checkbox("Hide frequent events", &mut self.hide_frequent);
+                if ui.is_item_hovered() {
+                    ui.tooltip_text(Self::FREQUENT_KINDS.join(", "));
+                }
+                ui.same_line();
+                ui.checkbox("Auto-scroll", &mut self.auto_scroll);
+                ui.same_line();
+                if ui.button("Clear") {
+                    self.events.clear();
+                    self.selected = None;
+                    filters_changed = true;
+                }
+                if filters_changed {
+                    self.apply_filters();
+                }
+
+                ui.separator();
+                ui.text(format!("{} of {} events", self.filtered.len(), self.events.len()));
+
+                ui.child_window("logged events").size([0.0, -60.0]).build(|| {
+                    let mut clipper = ListClipper::new(self.filtered.len() as i32).begin(ui);
+                    while clipper.step() {
+                        for row in clipper.display_start()..clipper.display_end() {
+                            let index = self.filtered[row as usize];
+                            let event = &self.events[index];
+                            let seconds = event.elapsed.as_secs_f32();
+                            let label = format!(
+                                "{:02}:{:06.3}  tick {:>6}  {:<20} {}##{index}",
+                                (seconds / 60.0) as u32, seconds % 60.0, event.entry.tick, event.entry.kind, event.entry.description
+                            );
+                            if ui.selectable_config(label).selected(self.selected == Some(index)).build() {
+                                self.selected = Some(index);
+                                selected_location = Some(JournalViewer::entry_location(&event.entry));
+                            }
+                        }
+                    }
+                    if self.auto_scroll && ui.scroll_y() >= ui.scroll_max_y() - 1.0 {
+                        ui.set_scroll_here_y_with_ratio(1.0);
+                    }
+                });
+
+                if let Some(event) = self.selected.and_then(|i| self.events.get(i)) {
+                    ui.text_wrapped(&event.entry.description);
+                }
+            });
+        self.open = open;
+
+        selected_location
+    }
+}
@@ -0,0 +1,114 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use imgui::{Condition, Ui};
+use crate::replay;
+
+// FileDrop receives the files dropped onto the window and tells what they are, so that the GUI can
+// open each in the window which shows that kind of file: replays are loaded as the ghost (the run
+// being shown can't be swapped for a replay), snapshots are shown through the DetachedView, input
+// recordings are loaded to be played back and event journals are opened in the journal viewer.
+// Replays are told apart by their magic number, the others by their JSON: a journal has an object
+// per line, a snapshot has the world and an input recording the camera and the key actions.
+// While a file is dragged over the window a hint is drawn; files which can't be opened are reported
+// in a small window.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileKind {
+    Replay,
+    Snapshot,
+    InputRecording,
+    Journal,
+}
+
+pub struct FileDrop {
+    hovering: bool,
+    dropped: Vec<PathBuf>,
+    errors: Vec<String>,
+}
+impl FileDrop {
+    pub fn new() -> Self {
+        Self { hovering: false, dropped: vec![], errors: vec![] }
+    }
+
+    // must be called with every window event
+    pub fn handle_event(&mut self, event: &winit::event::WindowEvent) {
+        match event {
+            winit::event::WindowEvent::HoveredFile(_) => self.hovering = true,
+            winit::event::WindowEvent::HoveredFileCancelled => self.hovering = false,
+            winit::event::WindowEvent::DroppedFile(path) => {
+                self.hovering = false;
+                self.dropped.push(path.clone());
+            }
+            _ => {}
+        }
+    }
+
+    // the files dropped since the last call, with their kind; those which aren't recognized are
+    // reported by draw
+    pub fn take_dropped(&mut self) -> Vec<(PathBuf, FileKind)> {
+        let mut recognized = vec![];
+        for path in std::mem::take(&mut self.dropped) {
+            match Self::kind_of(&path) {
+                Ok(kind) => recognized.push((path, kind)),
+                Err(e) => self.errors.push(format!("{}: {e}", path.display())),
+            }
+        }
+        recognized
+    }
+
+    fn kind_of(path: &Path) -> Result<FileKind, String> {
+        if path.is_dir() {
+            return Err("is a directory".into());
+        }
+        if replay::is_replay(path) {
+            return Ok(FileKind::Replay);
+        }
+        let not_recognized = || "not a replay, snapshot, input recording or event journal".to_string();
+        let mut lines = BufReader::new(File::open(path).map_err(|e| e.to_string())?).lines();
+        let first_line = lines.next().ok_or_else(not_recognized)?.map_err(|_| not_recognized())?;
+        let value = match serde_json::from_str::<serde_json::Value>(&first_line) {
+            Ok(value) if lines.next().is_some() || path.extension().is_some_and(|extension| extension == "jsonl") => {
+                return if value.is_object() { Ok(FileKind::Journal) } else { Err(not_recognized()) };
+            }
+            Ok(value) => value,
+            Err(_) => {
+                // snapshots and recordings are a single object, not necessarily on a single line
+                let file = File::open(path).map_err(|e| e.to_string())?;
+                serde_json::from_reader(BufReader::new(file)).map_err(|_| not_recognized())?
+            }
+        };
+        match value.as_object() {
+            Some(object) if object.contains_key("world") => Ok(FileKind::Snapshot),
+            Some(object) if object.contains_key("camera") && object.contains_key("actions") => Ok(FileKind::InputRecording),
+            _ => Err(not_recognized()),
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui) {
+        if self.hovering {
+            let [width, height] = ui.io().display_size;
+            let text = "drop to open the replay, snapshot, input recording or event journal";
+            let [text_width, _] = ui.calc_text_size(text);
+            let draw_list = ui.get_foreground_draw_list();
+            draw_list.add_rect([0.0, 0.0], [width, height], [0.1, 0.3, 0.6, 0.25]).filled(true).build();
+            draw_list.add_text([(width - text_width) / 2.0, height / 2.0], [1.0, 1.0, 1.0, 1.0], text);
+        }
+
+        if self.errors.is_empty() {
+            return;
+        }
+        let mut open = true;
+        ui.window("Could not open the dropped files")
+            .opened(&mut open)
+            .size([400.0, 120.0], Condition::FirstUseEver)
+            .build(|| {
+                for error in self.errors.iter() {
+                    ui.text_wrapped(error);
+                }
+            });
+        if !open {
+            self.errors.clear();
+        }
+    }
+}
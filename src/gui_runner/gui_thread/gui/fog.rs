@@ -0,0 +1,78 @@
+use glium::{Display, VertexBuffer};
+use nalgebra_glm::vec3;
+use super::world_mesh::{self, Vertex};
+use crate::gui_runner::PartialWorld;
+
+// FogOfWar covers the undiscovered tiles with a dark plane at sea level (elevation 0, the lowest a
+// tile can be), drawn translucent, so that the extent of the world and the boundary of what the
+// robot discovered are visible at a glance rather than the undiscovered tiles simply not being
+// there. Each row of the world gets a quad per run of consecutive undiscovered tiles, rebuilt
+// when a tile to refresh turns out to have been discovered (or forgotten, e.g. when a snapshot
+// is shown). While the fog is disabled nothing is kept, and it is rebuilt whole when enabled.
+
+pub struct FogOfWar {
+    pub enabled: bool,
+    undiscovered: Vec<Vec<bool>>,
+    triangles: Vec<Vertex>,
+    pub vbo: Option<VertexBuffer<Vertex>>,
+    vbo_is_outdated: bool,
+}
+impl FogOfWar {
+    pub const ALPHA: f32 = 0.75;
+    const COLOR: [f32; 3] = [0.04, 0.04, 0.07];
+
+    pub fn new() -> Self {
+        Self { enabled: false, undiscovered: vec![], triangles: vec![], vbo: None, vbo_is_outdated: false }
+    }
+
+    // must be called with every new world, before its tiles_to_refresh are cleared
+    pub fn update(&mut self, world: &PartialWorld) {
+        if !self.enabled {
+            self.undiscovered.clear();
+            return;
+        }
+        let outdated = self.undiscovered.len() != world.world.len() || world.tiles_to_refresh.iter()
+            .any(|tile| self.undiscovered[tile.x as usize][tile.y as usize] != world.world[tile.x as usize][tile.y as usize].is_none());
+        if outdated {
+            self.rebuild(world);
+        }
+    }
+
+    fn rebuild(&mut self, world: &PartialWorld) {
+        self.undiscovered = world.world.iter().map(|row| row.iter().map(|tile| tile.is_none()).collect()).collect();
+        self.triangles.clear();
+        let y = world_mesh::elevation_to_mesh_space_y(0.0);
+        for (x, row) in self.undiscovered.iter().enumerate() {
+            let mut z = 0;
+            while z < row.len() {
+                if !row[z] {
+                    z += 1;
+                    continue;
+                }
+                let start = z;
+                while z < row.len() && row[z] {
+                    z += 1;
+                }
+                let corner = |dx: f32, z: usize| vec3(x as f32 + dx, y, z as f32);
+                let [a, b, c, d] = [corner(0.0, start), corner(1.0, start), corner(1.0, z), corner(0.0, z)];
+                for p in [a, b, c, a, c, d] {
+                    self.triangles.push(Vertex { position: *p.as_ref(), color: Self::COLOR });
+                }
+            }
+        }
+        self.vbo_is_outdated = true;
+    }
+
+    // returns the number of bytes uploaded to the gpu. must be called once per frame while enabled
+    pub fn update_vbo(&mut self, display: &Display, world: &PartialWorld) -> usize {
+        if self.undiscovered.len() != world.world.len() {
+            self.rebuild(world);
+        }
+        if !self.vbo_is_outdated {
+            return 0;
+        }
+        self.vbo_is_outdated = false;
+        self.vbo = if self.triangles.is_empty() { None } else { VertexBuffer::new(display, &self.triangles).ok() };
+        self.triangles.len() * std::mem::size_of::<Vertex>()
+    }
+}
@@ -0,0 +1,131 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use imgui::{FontConfig, FontGlyphRanges, FontSource};
+use imgui_glium_renderer::Renderer;
+
+// Fonts sets up the font of the GUI. imgui's default font is a bitmap font, which gets blurry when
+// scaled and is unreadably small on HiDPI displays, so the GUI uses the bundled DejaVu Sans instead,
+// rasterized at FONT_SIZE times the scale factor of the window times the UI scale of the settings:
+// imgui lays the windows out in logical pixels, so the atlas is built in physical pixels and scaled
+// back by the scale factor, which keeps the text sharp. The atlas is rebuilt whenever either factor
+// changes, e.g. when the window is dragged to a display with another scale factor.
+// The fonts given to the builder and the first of the TEXT_FALLBACKS and of the EMOJI_FALLBACKS
+// found on the system are merged into the bundled font, in this order, each only contributing the
+// glyphs the previous ones lack (the symbols and emoji students put in their notes, ...). The
+// fallbacks only load FALLBACK_RANGES and EMOJI_RANGES, so that the atlas stays small, while the
+// fonts given to the builder load all the glyphs they have. Color emoji fonts can't be rasterized
+// by imgui, which is why the monochrome ones are looked up instead.
+
+const FONT_SIZE: f32 = 15.0; // in logical pixels
+const BUNDLED_FONT: &[u8] = include_bytes!("../../../../resources/fonts/DejaVuSans.ttf");
+
+// Latin Extended, Greek, Cyrillic, punctuation, arrows, math, box drawing, shapes, symbols, dingbats
+const FALLBACK_RANGES: &[u32] = &[
+    0x0020, 0x024F,
+    0x0370, 0x03FF,
+    0x0400, 0x052F,
+    0x2000, 0x206F,
+    0x20A0, 0x20CF,
+    0x2100, 0x214F,
+    0x2190, 0x21FF,
+    0x2200, 0x22FF,
+    0x2300, 0x23FF,
+    0x2500, 0x25FF,
+    0x2600, 0x27BF,
+    0x2B00, 0x2BFF,
+    0,
+];
+const EMOJI_RANGES: &[u32] = &[
+    0x1F300, 0x1F5FF,
+    0x1F600, 0x1F64F,
+    0x1F680, 0x1F6FF,
+    0x1F900, 0x1F9FF,
+    0,
+];
+const ALL_RANGES: &[u32] = &[0x0020, 0xFFFF, 0x1F000, 0x1FAFF, 0];
+
+// paths of common fonts, the text fonts first and the emoji ones last; one of each is loaded
+const TEXT_FALLBACKS: &[&str] = &[
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSans-Regular.ttf",
+    "/usr/share/fonts/noto/NotoSans-Regular.ttf",
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+    "C:\\Windows\\Fonts\\segoeui.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+const EMOJI_FALLBACKS: &[&str] = &[
+    "/usr/share/fonts/truetype/noto/NotoEmoji-Regular.ttf",
+    "/usr/share/fonts/noto/NotoEmoji-Regular.ttf",
+    "/usr/share/fonts/truetype/ancient-scripts/Symbola_hint.ttf",
+    "/usr/share/fonts/TTF/Symbola.ttf",
+    "C:\\Windows\\Fonts\\seguisym.ttf",
+    "C:\\Windows\\Fonts\\seguiemj.ttf",
+];
+
+pub struct Fonts {
+    user_fonts: Vec<PathBuf>,
+    built_for: (f32, f32), // scale factor and UI scale the atlas was built for
+}
+impl Fonts {
+    pub fn new(user_fonts: &[PathBuf]) -> Self {
+        Self { user_fonts: user_fonts.to_vec(), built_for: (0.0, 0.0) }
+    }
+
+    // adds the font of the GUI, with the fallbacks merged into it, to the atlas of the context.
+    // must be called before the atlas is built
+    pub fn load(&mut self, imgui_ctx: &mut imgui::Context, scale_factor: f32, ui_scale: f32) {
+        let first_found = |paths: &[&str]| paths.iter().find_map(|path| fs::read(Path::new(path)).ok());
+
+        let mut fonts: Vec<(Vec<u8>, &'static [u32])> = vec![];
+        for path in &self.user_fonts {
+            match fs::read(path) {
+                Ok(data) => fonts.push((data, ALL_RANGES)),
+                Err(e) => eprintln!("could not read the font {}: {e}", path.display()),
+            }
+        }
+        if let Some(data) = first_found(TEXT_FALLBACKS) {
+            fonts.push((data, FALLBACK_RANGES));
+        }
+        if let Some(data) = first_found(EMOJI_FALLBACKS) {
+            fonts.push((data, EMOJI_RANGES));
+        }
+
+        let size_pixels = (FONT_SIZE * scale_factor * ui_scale).round().max(6.0);
+        let mut sources = vec![FontSource::TtfData {
+            data: BUNDLED_FONT,
+            size_pixels,
+            config: Some(FontConfig {
+                glyph_ranges: FontGlyphRanges::from_slice(FALLBACK_RANGES),
+                pixel_snap_h: true,
+                ..FontConfig::default()
+            }),
+        }];
+        sources.extend(fonts.iter().map(|(data, ranges)| FontSource::TtfData {
+            data,
+            size_pixels,
+            config: Some(FontConfig {
+                glyph_ranges: FontGlyphRanges::from_slice(ranges),
+                pixel_snap_h: true,
+                ..FontConfig::default()
+            }),
+        }));
+        imgui_ctx.fonts().clear();
+        imgui_ctx.fonts().add_font(&sources);
+        imgui_ctx.io_mut().font_global_scale = 1.0 / scale_factor;
+        self.built_for = (scale_factor, ui_scale);
+    }
+
+    // rebuilds the atlas if the scale factor or the UI scale changed since it was built. must be
+    // called before the frame
+    pub fn update(&mut self, imgui_ctx: &mut imgui::Context, renderer: &mut Renderer, scale_factor: f32, ui_scale: f32) {
+        if (scale_factor, ui_scale) == self.built_for {
+            return;
+        }
+        self.load(imgui_ctx, scale_factor, ui_scale);
+        if let Err(e) = renderer.reload_font_texture(imgui_ctx) {
+            eprintln!("could not rebuild the font atlas: {e}");
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+
+// Frustum holds the 6 clipping planes of the camera, extracted from the mvp matrix, and can tell
+// whether an axis aligned box is (at least partially) inside them. It is used to skip drawing the
+// world mesh chunks which are out of view.
+
+pub struct Frustum {
+    planes: [Vec4; 6], // (a, b, c, d) such that the inside satisfies a*x + b*y + c*z + d >= 0
+}
+impl Frustum {
+    pub fn from_mvp(mvp: &Mat4) -> Self {
+        let row = |i: usize| -> Vec4 { mvp.row(i).transpose() };
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+        Self { planes: [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r3 + r2, r3 - r2] }
+    }
+
+    // conservative test: may return true for some boxes which are actually outside
+    pub fn intersects_aabb(&self, min: Vec3, max: Vec3) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner of the box furthest along the normal of the plane
+            let corner = Vec3::new(
+                if plane.x >= 0.0 { max.x } else { min.x },
+                if plane.y >= 0.0 { max.y } else { min.y },
+                if plane.z >= 0.0 { max.z } else { min.z },
+            );
+            plane.x * corner.x + plane.y * corner.y + plane.z * corner.z + plane.w >= 0.0
+        })
+    }
+}
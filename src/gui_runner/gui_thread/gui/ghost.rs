@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use glium::{Display, VertexBuffer};
+use imgui::{Condition, Ui};
+use nalgebra_glm::{Mat4, Vec3, vec3};
+use robotics_lib::world::tile::Content;
+use crate::replay;
+use crate::gui_runner::MarkerStyle;
+use super::markers;
+use super::robot_panels;
+use super::world_mesh::{self, Vertex};
+
+// GhostOverlay shows the robot of a previously recorded replay next to the live one: the replay is
+// loaded on a background thread (keeping only the robot's position, energy and backpack at every
+// tick), and at every frame the ghost is drawn where the replayed robot was at the current live tick (shifted by a
+// user chosen tick offset), along with the trail it walked until then. Meant to compare a new
+// version of an AI against an old one on the same world.
+
+struct GhostSample {
+    tick: usize,
+    tile: [u32; 2],
+    position: Vec3, // in mesh space, slightly above the tile
+    energy: usize,
+    backpack: Vec<(Content, usize)>,
+}
+
+struct LoadedGhost {
+    path: PathBuf,
+    world_size: usize,
+    samples: Vec<GhostSample>, // in tick order
+}
+
+pub struct GhostOverlay {
+    pub open: bool,
+    pub show: bool,
+    pub style: MarkerStyle,
+    path_input: String,
+    tick_offset: i32,
+    loading: Option<Receiver<Result<LoadedGhost, String>>>,
+    ghost: Option<LoadedGhost>,
+    error: Option<String>,
+
+    vbo: Option<VertexBuffer<Vertex>>,
+    vbo_len: usize, // number of samples in the vbo
+    vbo_color: [f32; 3],
+}
+impl GhostOverlay {
+    pub const ALPHA: f32 = 0.45;
+
+    pub fn new(initial_path: Option<PathBuf>, style: MarkerStyle) -> Self {
+        let mut overlay = Self {
+            open: false,
+            show: true,
+            vbo_color: style.color,
+            style,
+            path_input: String::new(),
+            tick_offset: 0,
+            loading: None,
+            ghost: None,
+            error: None,
+            vbo: None,
+            vbo_len: 0,
+        };
+        if let Some(path) = initial_path {
+            overlay.path_input = path.display().to_string();
+            overlay.load(path);
+        }
+        overlay
+    }
+
+    fn load(&mut self, path: PathBuf) {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut samples = vec![];
+            let result = replay::load_streaming(&path, |snapshot| {
+                let (x, y) = snapshot.robot_position;
+                let elevation = snapshot.world[x as usize][y as usize].as_ref().map(|t| t.elevation).unwrap_or(0);
+                let position = vec3(x as f32 + 0.5, world_mesh::elevation_to_mesh_space_y(elevation as f32) + 0.35, y as f32 + 0.5);
+                samples.push(GhostSample { tick: snapshot.tick, tile: [x, y], position, energy: snapshot.energy, backpack: snapshot.backpack.clone() });
+            }).map_err(|e| e.to_string()).map(|replay| {
+                LoadedGhost { path, world_size: replay.metadata.world_size, samples }
+            });
+            let _ = tx.send(result); // the GUI may have been closed in the meantime
+        });
+        self.loading = Some(rx);
+        self.error = None;
+    }
+
+    // loads the replay at path as the ghost (e.g. a file dropped onto the window), opening the window
+    pub fn open_file(&mut self, path: PathBuf) {
+        self.open = true;
+        self.path_input = path.display().to_string();
+        self.load(path);
+    }
+
+    fn poll_loading(&mut self) {
+        let Some(loading) = &self.loading else { return };
+        match loading.try_recv() {
+            Ok(Ok(ghost)) => {
+                self.ghost = Some(ghost);
+                self.vbo = None;
+                self.vbo_len = 0;
+                self.loading = None;
+            }
+            Ok(Err(e)) => {
+                self.error = Some(e);
+                self.loading = None;
+            }
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => {
+                self.error = Some("the loading thread panicked".into());
+                self.loading = None;
+            }
+        }
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.ghost.is_some()
+    }
+
+    // number of samples at or before the live tick (shifted by the offset)
+    fn visible_samples(&self, live_tick: usize) -> usize {
+        let Some(ghost) = &self.ghost else { return 0 };
+        let tick = live_tick as i64 + self.tick_offset as i64;
+        if tick < 0 {
+            return 0;
+        }
+        ghost.samples.partition_point(|s| s.tick <= tick as usize)
+    }
+
+    // the sample of the ghost at the live tick, if it is shown and already started
+    fn current_sample(&self, live_tick: usize) -> Option<&GhostSample> {
+        if !self.show {
+            return None;
+        }
+        let ghost = self.ghost.as_ref()?;
+        let visible_samples = self.visible_samples(live_tick);
+        if visible_samples == 0 {
+            return None;
+        }
+        Some(&ghost.samples[visible_samples - 1])
+    }
+
+    // where the ghost is at the live tick, in mesh space
+    pub fn position(&self, live_tick: usize) -> Option<Vec3> {
+        self.current_sample(live_tick).map(|s| s.position)
+    }
+
+    // returns the trail of the ghost up to the live tick, and the number of bytes uploaded to the gpu
+    pub fn update_vbo(&mut self, display: &Display, live_tick: usize) -> (Option<&VertexBuffer<Vertex>>, usize) {
+        self.poll_loading();
+        if !self.show {
+            return (None, 0);
+        }
+        let Some(ghost) = &self.ghost else { return (None, 0) };
+
+        let mut bytes_uploaded = 0;
+        let visible_samples = self.visible_samples(live_tick);
+        if visible_samples != self.vbo_len || self.style.color != self.vbo_color {
+            let color = self.style.color;
+            let verts = ghost.samples[..visible_samples].iter()
+                .map(|s| Vertex { position: *s.position.as_ref(), color })
+                .collect::<Vec<_>>();
+            bytes_uploaded = verts.len() * std::mem::size_of::<Vertex>();
+            self.vbo = if verts.len() < 2 { None } else { VertexBuffer::new(display, &verts).ok() };
+            self.vbo_len = visible_samples;
+            self.vbo_color = color;
+        }
+        (self.vbo.as_ref(), bytes_uploaded)
+    }
+
+    pub fn draw_marker(&self, ui: &Ui, mvp: &Mat4, live_tick: usize) {
+        let Some(sample) = self.current_sample(live_tick) else { return };
+        markers::draw_robot_marker(ui, mvp, sample.position + vec3(0.0, 1.5, 0.0), &self.style, Self::ALPHA + 0.2);
+    }
+
+    // draws the status of the ghost at the live tick, for its panel in the main window
+    pub fn draw_status(&self, ui: &Ui, live_tick: usize) {
+        match self.current_sample(live_tick) {
+            Some(sample) => {
+                let backpack: HashMap<Content, usize> = sample.backpack.iter().cloned().collect();
+                // the samples are those of consecutive ticks, unless the replay skipped some
+                let visible_samples = self.visible_samples(live_tick);
+                let previous = self.ghost.as_ref()
+                    .and_then(|ghost| ghost.samples[..visible_samples - 1].last())
+                    .filter(|previous| previous.tick + 1 == sample.tick)
+                    .map(|previous| previous.backpack.iter().cloned().collect::<HashMap<_, _>>());
+                let deltas = previous.map(|previous| robot_panels::backpack_delta(&previous, &backpack));
+                robot_panels::draw_robot_status(ui, "ghost", sample.tile, sample.energy, &backpack, None, deltas.as_ref());
+            }
+            None if !self.show => ui.text_disabled("(hidden)"),
+            None => ui.text_disabled("(not started yet)"),
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, world_size: usize) {
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        ui.window("Ghost replay")
+            .opened(&mut open)
+            .size([360.0, 240.0], Condition::FirstUseEver)
+            .build(|| {
+                ui.input_text("##path", &mut self.path_input).hint("path of a replay").build();
+                ui.same_line();
+                ui.disabled(self.loading.is_some(), || {
+                    if ui.button("Load") {
+                        self.load(PathBuf::from(self.path_input.clone()));
+                    }
+                });
+
+                if self.loading.is_some() {
+                    ui.text_disabled("loading...");
+                } else if let Some(e) = &self.error {
+                    ui.text_colored([1.0, 0.4, 0.4, 1.0], format!("could not load the replay: {e}"));
+                }
+
+                if let Some(ghost) = &self.ghost {
+                    ui.text_wrapped(format!("{} ({} ticks)", ghost.path.display(), ghost.samples.len()));
+                    if ghost.world_size != world_size {
+                        ui.text_colored([1.0, 0.8, 0.3, 1.0], "the replay was recorded on a world of a different size");
+                    }
+                    ui.checkbox("Show ghost", &mut self.show);
+                    ui.input_int("tick offset", &mut self.tick_offset).build();
+                }
+
+                if let Some(_node) = ui.tree_node("Marker style") {
+                    markers::edit_marker_style(ui, "ghost", &mut self.style);
+                }
+            });
+        self.open = open;
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use glium::Display;
+use imgui::Ui;
+use nalgebra_glm::{Mat4, UVec2, vec3};
+use robotics_lib::world::environmental_conditions::EnvironmentalConditions;
+use robotics_lib::world::tile::Tile;
+use super::world_mesh::WorldMesh;
+use crate::gui_runner::PartialWorld;
+
+// GodView shows the whole world as created by the generator, including the tiles the robot hasn't
+// discovered, to see how much of the world the robot has explored. It is drawn translucent (so it
+// looks dimmed) and slightly lower than the discovered world, which thus covers it wherever the
+// robot has been. Since it spoils the undiscovered parts of the world it's off by default, and a
+// label is shown over the view while it's on.
+// Its WorldMesh is only built the first time the view is enabled.
+
+pub struct GodView {
+    pub enabled: bool,
+    world: Option<PartialWorld>, // taken when the mesh is built
+    mesh: Option<WorldMesh>,
+}
+impl GodView {
+    pub const ALPHA: f32 = 0.35;
+    const DEPTH_OFFSET: f32 = 0.05;
+
+    pub fn new(true_world: Vec<Vec<Tile>>, env_cond: EnvironmentalConditions) -> Self {
+        let size = true_world.len() as u32;
+        let world = PartialWorld {
+            world: true_world.into_iter().map(|row| row.into_iter().map(Some).collect()).collect(),
+            tiles_to_refresh: (0..size).flat_map(|x| (0..size).map(move |y| UVec2::new(x, y))).collect(),
+            distant_changes: vec![],
+            touched_tiles: None,
+            stale_tiles: vec![],
+            tick: 0,
+            elapsed: Duration::ZERO,
+            robot_position: UVec2::zeros(),
+            energy: 0,
+            backpack: HashMap::new(),
+            backpack_size: None,
+            env_cond,
+            other_robots: vec![],
+            other_maps: vec![],
+            changed_tiles: vec![],
+            content_changes: vec![],
+        };
+        Self { enabled: false, world: Some(world), mesh: None }
+    }
+
+    pub fn mesh(&mut self, display: &Display) -> &WorldMesh {
+        let world = &mut self.world;
+        self.mesh.get_or_insert_with(|| {
+            let mut world = world.take().expect("the world is only taken when building the mesh");
+            let mut mesh = WorldMesh::new(display);
+            mesh.update(&mut world, display, None, 1.0);
+            mesh
+        })
+    }
+
+    // mvp of the god view, which is drawn slightly lower than the discovered world
+    pub fn mvp(mvp: &Mat4) -> Mat4 {
+        mvp * nalgebra_glm::translation(&vec3(0.0, -Self::DEPTH_OFFSET, 0.0))
+    }
+
+    pub fn draw_label(&self, ui: &Ui) {
+        if !self.enabled {
+            return;
+        }
+        let text = "GOD VIEW: undiscovered tiles are visible";
+        let text_size = ui.calc_text_size(text);
+        let position = [(ui.io().display_size[0] - text_size[0]) / 2.0, 8.0];
+        ui.get_foreground_draw_list().add_text(position, [1.0, 0.4, 0.4, 1.0], text);
+    }
+}
@@ -0,0 +1,69 @@
+use imgui::{InputTextFlags, Ui};
+use nalgebra_glm::UVec2;
+
+// GoToBox is the input where the user types the coordinates of a tile to fly the camera to, the
+// counterpart of "Find robot" for any other tile: it takes the two coordinates separated by a comma
+// or a space, with or without parentheses, e.g. "12, 40" or "(12 40)", as copied by the copy
+// buttons. Coordinates outside the world are refused with the reason next to the input, and so is
+// anything else which can't be parsed.
+
+pub struct GoToBox {
+    input: String,
+    error: Option<String>,
+}
+impl GoToBox {
+    const ERROR_COLOR: [f32; 4] = [1.0, 0.4, 0.4, 1.0];
+
+    pub fn new() -> Self {
+        Self { input: String::new(), error: None }
+    }
+
+    fn parse(input: &str, world_size: usize) -> Result<UVec2, String> {
+        let coordinates: Vec<&str> = input.trim().trim_start_matches('(').trim_end_matches(')')
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|coordinate| !coordinate.is_empty())
+            .collect();
+        let [x, y] = coordinates[..] else { return Err("expected two coordinates, e.g. 12, 40".into()) };
+        let parse = |coordinate: &str| coordinate.parse::<usize>().map_err(|_| format!("{coordinate:?} isn't a coordinate"));
+        let (x, y) = (parse(x)?, parse(y)?);
+        if x >= world_size || y >= world_size {
+            return Err(format!("({x}, {y}) is outside the {world_size}x{world_size} world"));
+        }
+        Ok(UVec2::new(x as u32, y as u32))
+    }
+
+    // draws the input and its "Go" button, returning the tile to fly to when either is confirmed
+    pub fn draw(&mut self, ui: &Ui, world_size: usize) -> Option<UVec2> {
+        ui.set_next_item_width(100.0);
+        let entered = ui.input_text("##go to tile", &mut self.input)
+            .hint("x, y")
+            .flags(InputTextFlags::ENTER_RETURNS_TRUE)
+            .build();
+        if ui.is_item_edited() {
+            self.error = None;
+        }
+        ui.same_line();
+        let clicked = ui.button("Go");
+        if ui.is_item_hovered() {
+            ui.tooltip_text("flies the camera to the tile");
+        }
+
+        if !(entered || clicked) {
+            if let Some(error) = &self.error {
+                ui.text_colored(Self::ERROR_COLOR, error);
+            }
+            return None;
+        }
+        match Self::parse(&self.input, world_size) {
+            Ok(tile) => {
+                self.error = None;
+                Some(tile)
+            }
+            Err(e) => {
+                ui.text_colored(Self::ERROR_COLOR, &e);
+                self.error = Some(e);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use imgui::{Condition, Ui, WindowFlags};
+use nalgebra_glm::UVec2;
+use robotics_lib::world::tile::Content;
+use crate::gui_runner::PartialWorld;
+
+// IdleDetector catches a robot which appears stuck (e.g. an AI caught in an infinite loop which
+// does nothing at every tick): when its position, energy and backpack haven't changed for
+// threshold ticks while the game is running, a banner at the top of the window says so and offers
+// to pause the game. The banner can be dismissed, in which case it stays hidden until the robot
+// changes again and then gets stuck again.
+
+pub struct IdleDetector {
+    pub threshold: usize, // in ticks, 0 disables the detection
+    state: Option<(UVec2, usize, HashMap<Content, usize>)>,
+    idle_since: usize, // tick of the last change of state
+    last_tick: usize,
+    dismissed: bool,
+}
+impl IdleDetector {
+    pub fn new(threshold: usize) -> Self {
+        Self { threshold, state: None, idle_since: 0, last_tick: 0, dismissed: false }
+    }
+
+    // must be called with every new world
+    pub fn record(&mut self, world: &PartialWorld) {
+        let changed = match &self.state {
+            Some((position, energy, backpack)) => {
+                *position != world.robot_position || *energy != world.energy || *backpack != world.backpack
+            }
+            None => true,
+        };
+        // going back in time (stepping back or seeking in a replay) restarts the count
+        if changed || world.tick < self.last_tick {
+            self.state = Some((world.robot_position, world.energy, world.backpack.clone()));
+            self.idle_since = world.tick;
+            self.dismissed = false;
+        }
+        self.last_tick = world.tick;
+    }
+
+    pub fn idle_ticks(&self) -> usize {
+        self.last_tick - self.idle_since
+    }
+
+    fn is_stuck(&self) -> bool {
+        self.threshold > 0 && self.idle_ticks() >= self.threshold
+    }
+
+    // draws the warning banner if the robot is stuck while the game is running, returning whether
+    // the user asked to pause the game
+    pub fn draw_banner(&mut self, ui: &Ui, running: bool) -> bool {
+        if !running || self.dismissed || !self.is_stuck() {
+            return false;
+        }
+        let mut pause = false;
+        let display_size = ui.io().display_size;
+        ui.window("##robot_stuck")
+            .position([display_size[0] / 2.0, 8.0], Condition::Always)
+            .position_pivot([0.5, 0.0])
+            .flags(WindowFlags::NO_TITLE_BAR | WindowFlags::NO_RESIZE | WindowFlags::NO_MOVE | WindowFlags::ALWAYS_AUTO_RESIZE | WindowFlags::NO_SAVED_SETTINGS)
+            .build(|| {
+                ui.text_colored([1.0, 0.75, 0.3, 1.0], format!(
+                    "The robot appears stuck: its position, energy and backpack haven't changed for {} ticks",
+                    self.idle_ticks()
+                ));
+                pause = ui.button("Pause");
+                ui.same_line();
+                if ui.button("Dismiss") {
+                    self.dismissed = true;
+                }
+            });
+        pause
+    }
+}
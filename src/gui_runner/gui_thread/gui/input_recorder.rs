@@ -0,0 +1,196 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use imgui::{Condition, Ui};
+use nalgebra_glm::Vec3;
+use serde::{Deserialize, Serialize};
+use crate::replay::{CameraKeyframe, CameraPath};
+use super::annotations::{from_pose, to_pose};
+use super::keyboard_event_handler::KeyAction;
+
+// InputRecorder is an imgui window which records what the user does with the camera and the
+// keyboard, saves it to a JSON file and plays it back, so that a demo flythrough can be reproduced.
+// The movement keys aren't recorded, since how far they move the camera depends on the frame rate:
+// the camera itself is sampled every RECORDING_INTERVAL (as a CameraPath, like the camera paths of
+// the annotations) and followed during the playback. The other keys are recorded as KeyActions
+// (running ticks, toggling views, chords, ...), and repeated at the same times during the playback.
+// The map view's pan and zoom aren't recorded. The ticks shown during the recording are recorded
+// along with the camera, so that when playing back on a replay the replay can be sought along.
+
+#[derive(Serialize, Deserialize)]
+struct InputRecording {
+    camera: CameraPath,
+    actions: Vec<(f32, KeyAction)>, // seconds since the start of the recording, action
+}
+
+// InputPlayback is what the recorder asks the GUI to do in the current frame
+#[derive(Default)]
+pub struct InputPlayback {
+    pub camera: Option<(Vec3, Vec3)>, // position, direction
+    pub actions: Vec<KeyAction>,
+    pub seek: Option<usize>,
+}
+
+pub struct InputRecorder {
+    pub open: bool,
+    path: String,
+    status: Option<String>,
+    recorded: Option<InputRecording>, // the last recording, or the one loaded
+    recording: Option<(Instant, InputRecording)>,
+    playing: Option<(Instant, usize)>, // start of the playback, index of the next action
+    seek_replay: bool,
+    last_seek: Option<usize>,
+}
+impl InputRecorder {
+    const RECORDING_INTERVAL: Duration = Duration::from_millis(33);
+
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            path: "input.json".into(),
+            status: None,
+            recorded: None,
+            recording: None,
+            playing: None,
+            seek_replay: true,
+            last_seek: None,
+        }
+    }
+
+    // must be called with the actions of every keyboard event
+    pub fn record_actions(&mut self, actions: &[KeyAction]) {
+        if let Some((start, recording)) = &mut self.recording {
+            let time = start.elapsed().as_secs_f32();
+            recording.actions.extend(actions.iter().map(|action| (time, *action)));
+        }
+    }
+
+    // records or plays back the input; must be called once per frame
+    pub fn update(&mut self, cam_pos: Vec3, cam_dir: Vec3, tick: usize) -> InputPlayback {
+        if let Some((start, recording)) = &mut self.recording {
+            let time = start.elapsed().as_secs_f32();
+            let keyframes = &mut recording.camera.keyframes;
+            let is_due = keyframes.last().map(|k| time - k.time >= Self::RECORDING_INTERVAL.as_secs_f32()).unwrap_or(true);
+            if is_due {
+                keyframes.push(CameraKeyframe { time, tick, pose: to_pose(cam_pos, cam_dir) });
+            }
+        }
+
+        let (Some((start, next_action)), Some(recorded)) = (&mut self.playing, &self.recorded) else { return InputPlayback::default() };
+        let time = start.elapsed().as_secs_f32();
+        let due_actions = recorded.actions[*next_action..].iter().take_while(|(t, _)| *t <= time).count();
+        let actions: Vec<KeyAction> = recorded.actions[*next_action..*next_action + due_actions].iter().map(|(_, action)| *action).collect();
+        *next_action += due_actions;
+
+        let Some((pose, tick)) = recorded.camera.sample(time) else {
+            // the last actions may come after the last sample of the camera
+            let remaining = recorded.actions[*next_action..].iter().map(|(_, action)| *action);
+            let actions = Vec::from_iter(actions.into_iter().chain(remaining));
+            self.playing = None;
+            return InputPlayback { actions, ..Default::default() };
+        };
+        let seek = (self.seek_replay && self.last_seek != Some(tick)).then_some(tick);
+        self.last_seek = Some(tick);
+        InputPlayback { camera: Some(from_pose(&pose)), actions, seek }
+    }
+
+    fn stop_recording(&mut self, cam_pos: Vec3, cam_dir: Vec3, tick: usize) {
+        if let Some((start, mut recording)) = self.recording.take() {
+            // close the path, so that it lasts as long as the recording
+            let time = start.elapsed().as_secs_f32();
+            recording.camera.keyframes.push(CameraKeyframe { time, tick, pose: to_pose(cam_pos, cam_dir) });
+            self.status = Some(format!("recorded {:.1}s and {} key presses", time, recording.actions.len()));
+            self.recorded = Some(recording);
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(recorded) = &self.recorded else { return Err("nothing was recorded".into()) };
+        let file = File::create(&self.path).map_err(|e| e.to_string())?;
+        serde_json::to_writer(BufWriter::new(file), recorded).map_err(|e| e.to_string())
+    }
+
+    fn load(&mut self) {
+        let loaded = File::open(&self.path).map_err(|e| e.to_string())
+            .and_then(|file| serde_json::from_reader::<_, InputRecording>(BufReader::new(file)).map_err(|e| e.to_string()));
+        self.status = Some(match loaded {
+            Ok(recording) => {
+                let status = format!("loaded {:.1}s and {} key presses", recording.camera.duration(), recording.actions.len());
+                self.recorded = Some(recording);
+                status
+            }
+            Err(e) => format!("could not load: {e}"),
+        });
+    }
+
+    // loads the recording at path (e.g. a file dropped onto the window), opening the window to
+    // report how it went
+    pub fn open_file(&mut self, path: &Path) {
+        self.open = true;
+        if self.recording.is_some() || self.playing.is_some() {
+            self.status = Some(format!("stop the {} to load {}", if self.recording.is_some() { "recording" } else { "playback" }, path.display()));
+            return;
+        }
+        self.path = path.display().to_string();
+        self.load();
+    }
+
+    pub fn draw(&mut self, ui: &Ui, cam_pos: Vec3, cam_dir: Vec3, tick: usize, is_replay: bool) {
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        ui.window("Input recording")
+            .opened(&mut open)
+            .size([320.0, 180.0], Condition::FirstUseEver)
+            .build(|| {
+                ui.input_text("file", &mut self.path).build();
+
+                if self.recording.is_none() {
+                    ui.disabled(self.playing.is_some(), || {
+                        if ui.button("Record") {
+                            let camera = CameraPath { name: "input recording".into(), keyframes: vec![] };
+                            self.recording = Some((Instant::now(), InputRecording { camera, actions: vec![] }));
+                            self.status = Some("recording...".into());
+                        }
+                    });
+                } else if ui.button("Stop recording") {
+                    self.stop_recording(cam_pos, cam_dir, tick);
+                }
+                ui.same_line();
+                ui.disabled(self.recording.is_some() || self.recorded.is_none(), || {
+                    if ui.button(if self.playing.is_some() { "Stop" } else { "Play" }) {
+                        self.playing = match self.playing {
+                            Some(_) => None,
+                            None => Some((Instant::now(), 0)),
+                        };
+                        self.last_seek = None;
+                    }
+                });
+                if is_replay {
+                    ui.checkbox("Seek the replay along", &mut self.seek_replay);
+                }
+
+                ui.separator();
+                ui.disabled(self.recorded.is_none(), || {
+                    if ui.button("Save") {
+                        self.status = Some(match self.save() {
+                            Ok(()) => format!("saved to {}", self.path),
+                            Err(e) => format!("could not save: {e}"),
+                        });
+                    }
+                });
+                ui.same_line();
+                ui.disabled(self.recording.is_some() || self.playing.is_some(), || {
+                    if ui.button("Load") {
+                        self.load();
+                    }
+                });
+                if let Some(status) = &self.status {
+                    ui.text_wrapped(status);
+                }
+            });
+        self.open = open;
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::{BTreeMap, HashMap};
+use imgui::{Condition, TreeNodeFlags, Ui};
+use nalgebra_glm::UVec2;
+use robotics_lib::world::tile::Content;
+use crate::gui_runner::{ContentChange, PartialWorld};
+use crate::gui_runner::event_journal::LoggedEvent;
+
+// InteractionTrace is an imgui window telling what the robot did at every tick, as a tree with a
+// node per tick: where it moved (or teleported, when it moved farther than a step), the contents it
+// destroyed, placed or deposited in a bank, market, bin or crate next to it, what went in and out
+// of its backpack, the energy it spent and the tiles it discovered. The robot's calls to the
+// interface aren't seen directly: the trace is worked out from the difference between the worlds
+// of consecutive ticks, the contents of the known tiles being kept to compare with those which
+// changed (see PartialWorld::changed_tiles). The events the robot received during the tick are
+// listed under the actions. Only the first robot is traced, and only the last MAX_TICKS ticks are
+// kept; a world of an earlier tick (e.g. a replay played backwards) starts the trace over.
+
+struct TraceItem {
+    text: String,
+    tile: Option<UVec2>, // selecting the item returns it
+}
+
+#[derive(Default)]
+struct TickTrace {
+    actions: Vec<TraceItem>,
+    events: Vec<TraceItem>,
+}
+
+pub struct InteractionTrace {
+    pub open: bool,
+    ticks: BTreeMap<usize, TickTrace>,
+    contents: Vec<Vec<Option<Content>>>, // of the tiles of the last world, None where unknown
+    last: Option<(usize, UVec2, usize, HashMap<Content, usize>)>, // tick, robot position, energy, backpack
+    hide_uneventful: bool,
+    show_events: bool,
+}
+impl InteractionTrace {
+    const MAX_TICKS: usize = 5000;
+    const MAX_SHOWN: usize = 300; // ticks listed, the latest first
+
+    pub fn new() -> Self {
+        Self { open: false, ticks: BTreeMap::new(), contents: vec![], last: None, hide_uneventful: true, show_events: true }
+    }
+
+    // the name of the variant, without its value
+    fn variant_name(content: &Content) -> String {
+        format!("{content:?}").split('(').next().unwrap_or_default().to_string()
+    }
+
+    fn is_container(content: &Content) -> bool {
+        matches!(content, Content::Bank(_) | Content::Market(_) | Content::Bin(_) | Content::Crate(_))
+    }
+
+    // must be called with every world received, after the worker thread filled its changed_tiles
+    pub fn record(&mut self, world: &PartialWorld) {
+        let went_back = self.last.as_ref().is_some_and(|(tick, ..)| world.tick <= *tick);
+        let last = self.last.take().filter(|_| !went_back && self.contents.len() == world.world.len());
+        self.last = Some((world.tick, world.robot_position, world.energy, world.backpack.clone()));
+        let Some((_, last_position, last_energy, last_backpack)) = last else {
+            if went_back {
+                self.ticks.clear();
+            }
+            self.contents = world.world.iter()
+                .map(|row| row.iter().map(|tile| tile.as_ref().map(|tile| tile.content.clone())).collect())
+                .collect();
+            return;
+        };
+
+        let mut actions = vec![];
+        let position = world.robot_position;
+        if position != last_position {
+            let steps = last_position.x.abs_diff(position.x) + last_position.y.abs_diff(position.y);
+            let verb = if steps > 1 { "teleported" } else { "moved" };
+            actions.push(TraceItem {
+                text: format!("{verb} from ({}, {}) to ({}, {})", last_position.x, last_position.y, position.x, position.y),
+                tile: Some(position),
+            });
+        }
+
+        let mut added = vec![];
+        let mut removed = vec![];
+        for content in last_backpack.keys().chain(world.backpack.keys().filter(|content| !last_backpack.contains_key(*content))) {
+            let (before, after) = (last_backpack.get(content).copied().unwrap_or(0), world.backpack.get(content).copied().unwrap_or(0));
+            let name = Self::variant_name(content);
+            if after > before {
+                added.push(format!("{} {name}", after - before));
+            } else if before > after {
+                removed.push(format!("{} {name}", before - after));
+            }
+        }
+
+        let causes: HashMap<UVec2, ContentChange> = world.content_changes.iter().copied().collect();
+        let mut discovered = 0;
+        for tile in world.changed_tiles.iter() {
+            let (x, y) = (tile.x as usize, tile.y as usize);
+            let new = world.world[x][y].as_ref().map(|tile| tile.content.clone());
+            let old = std::mem::replace(&mut self.contents[x][y], new.clone());
+            let (Some(old), Some(new)) = (old, new) else {
+                discovered += 1;
+                continue;
+            };
+            if old == new {
+                continue;
+            }
+            let at = format!("at ({x}, {y})");
+            let text = match causes.get(tile) {
+                Some(ContentChange::Respawned) => format!("{new:?} respawned {at}"),
+                Some(ContentChange::Decayed) => format!("{old:?} decayed {at}"),
+                Some(ContentChange::ByRobot) | None => match (&old, &new) {
+                    (_, Content::None) => format!("destroyed {old:?} {at}"),
+                    (Content::None, _) => format!("placed {new:?} {at}"),
+                    _ if Self::is_container(&new) && !removed.is_empty() => format!("deposited {} in {} {at}", removed.join(", "), Self::variant_name(&new)),
+                    _ => format!("{old:?} became {new:?} {at}"),
+                },
+            };
+            actions.push(TraceItem { text, tile: Some(*tile) });
+        }
+
+        if !added.is_empty() {
+            actions.push(TraceItem { text: format!("backpack gained {}", added.join(", ")), tile: None });
+        }
+        if !removed.is_empty() {
+            actions.push(TraceItem { text: format!("backpack lost {}", removed.join(", ")), tile: None });
+        }
+        if discovered > 0 {
+            actions.push(TraceItem { text: format!("discovered {discovered} tiles"), tile: None });
+        }
+        if world.energy != last_energy {
+            let change = if world.energy > last_energy { "recharged" } else { "spent" };
+            actions.push(TraceItem { text: format!("{change} {} energy ({} left)", world.energy.abs_diff(last_energy), world.energy), tile: None });
+        }
+
+        self.ticks.entry(world.tick).or_default().actions = actions;
+        self.drop_oldest();
+    }
+
+    // must be called with the events received by the event log every frame
+    pub fn receive(&mut self, events: &[LoggedEvent]) {
+        for event in events {
+            let entry = &event.entry;
+            let tile = entry.coordinate.map(|(x, y)| UVec2::new(x as u32, y as u32));
+            self.ticks.entry(entry.tick).or_default().events.push(TraceItem { text: entry.description.clone(), tile });
+        }
+        self.drop_oldest();
+    }
+
+    fn drop_oldest(&mut self) {
+        while self.ticks.len() > Self::MAX_TICKS {
+            self.ticks.pop_first();
+        }
+    }
+
+    // returns the tile of the item selected this frame, if any
+    pub fn draw(&mut self, ui: &Ui) -> Option<UVec2> {
+        if !self.open {
+            return None;
+        }
+
+        let mut selected = None;
+        let mut open = self.open;
+        ui.window("Interaction trace")
+            .opened(&mut open)
+            .size([420.0, 440.0], Condition::FirstUseEver)
+            .build(|| {
+                ui.checkbox("hide ticks without actions", &mut self.hide_uneventful);
+                ui.same_line();
+                ui.checkbox("events", &mut self.show_events);
+                ui.separator();
+                if self.ticks.is_empty() {
+                    return ui.text_disabled("no tick was traced yet");
+                }
+
+                ui.child_window("ticks").build(|| {
+                    let last_tick = self.ticks.keys().next_back().copied();
+                    let shown = self.ticks.iter().rev()
+                        .filter(|(_, trace)| !(self.hide_uneventful && trace.actions.is_empty()))
+                        .take(Self::MAX_SHOWN);
+                    for (tick, trace) in shown {
+                        let flags = if Some(*tick) == last_tick { TreeNodeFlags::DEFAULT_OPEN } else { TreeNodeFlags::empty() };
+                        let label = format!("tick {tick}: {} actions, {} events##{tick}", trace.actions.len(), trace.events.len());
+                        let Some(_node) = ui.tree_node_config(&label).flags(flags).push() else { continue };
+                        for (i, item) in trace.actions.iter().enumerate() {
+                            if ui.selectable(format!("{}##action {i}", item.text)) {
+                                selected = item.tile;
+                            }
+                        }
+                        if !self.show_events || trace.events.is_empty() {
+                            continue;
+                        }
+                        if let Some(_events) = ui.tree_node(format!("{} events##events", trace.events.len())) {
+                            for (i, item) in trace.events.iter().enumerate() {
+                                if ui.selectable(format!("{}##event {i}", item.text)) {
+                                    selected = item.tile;
+                                }
+                            }
+                        }
+                    }
+                });
+            });
+        self.open = open;
+        selected
+    }
+}
@@ -0,0 +1,302 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use imgui::{Condition, ListClipper, Ui};
+use nalgebra_glm::UVec2;
+use crate::gui_runner::event_journal::{rotated_path, JournalEntry};
+
+// JournalViewer is an imgui window which can open an event journal saved to disk (including its
+// rotated files) or follow the live journal being written by the game thread, and list its entries
+// filtered by event type, tick range and distance from a tile. Selecting an entry returns the
+// location it refers to, so that the GUI can move the camera there.
+// The journal is appended to by every run, so the entries are split into runs at the RunStarted
+// entries (see EventJournal) and only those of one run are listed, by default the latest.
+
+pub struct JournalViewer {
+    pub open: bool,
+    live_path: Option<PathBuf>,
+    path_input: String,
+    loaded_path: Option<PathBuf>,
+    read_offset: u64,
+    follow_live: bool,
+    last_refresh: Instant,
+    entries: Vec<JournalEntry>,
+    runs: Vec<usize>, // the index of the first entry of every run
+    run_filter: Option<usize>, // the run listed, None for all of them
+    error: Option<String>,
+
+    kind_filter: String,
+    tick_range: (i32, i32), // a negative upper bound means no upper bound
+    coord_filter_enabled: bool,
+    coord_filter: [i32; 3], // x, y, radius
+    filtered: Vec<usize>,
+    selected: Option<usize>,
+}
+impl JournalViewer {
+    const LIVE_REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+    pub fn new(live_path: Option<PathBuf>) -> Self {
+        Self {
+            open: false,
+            path_input: live_path.as_ref().map(|p| p.display().to_string()).unwrap_or_default(),
+            live_path,
+            loaded_path: None,
+            read_offset: 0,
+            follow_live: false,
+            last_refresh: Instant::now(),
+            entries: vec![],
+            runs: vec![],
+            run_filter: None,
+            error: None,
+
+            kind_filter: String::new(),
+            tick_range: (0, -1),
+            coord_filter_enabled: false,
+            coord_filter: [0, 0, 5],
+            filtered: vec![],
+            selected: None,
+        }
+    }
+
+    pub fn entry_location(entry: &JournalEntry) -> UVec2 {
+        match entry.coordinate {
+            Some((x, y)) => UVec2::new(x as u32, y as u32),
+            None => UVec2::new(entry.robot_position.0, entry.robot_position.1),
+        }
+    }
+
+    // opens the journal at path (e.g. a file dropped onto the window) in the window
+    pub fn open_file(&mut self, path: PathBuf) {
+        self.open = true;
+        self.path_input = path.display().to_string();
+        self.follow_live = self.live_path.as_ref() == Some(&path);
+        self.load(path);
+    }
+
+    fn load(&mut self, path: PathBuf) {
+        self.entries.clear();
+        self.runs.clear();
+        self.run_filter = None;
+        self.filtered.clear();
+        self.selected = None;
+        self.read_offset = 0;
+        self.error = None;
+
+        // rotated files contain older entries, the highest number being the oldest
+        let rotated_files = (1..).map(|i| rotated_path(&path, i)).take_while(|p| p.exists()).collect::<Vec<_>>();
+        let mut malformed_lines = 0;
+        for rotated_file in rotated_files.iter().rev() {
+            match Self::read_entries(rotated_file, 0) {
+                Ok((entries, _, malformed)) => {
+                    self.add_entries(entries);
+                    malformed_lines += malformed;
+                }
+                Err(e) => self.error = Some(format!("could not read {}: {e}", rotated_file.display())),
+            }
+        }
+        self.loaded_path = Some(path);
+        self.read_new_entries(malformed_lines);
+        self.apply_filters(); // the entries of the rotated files too
+    }
+
+    fn refresh(&mut self) {
+        self.last_refresh = Instant::now();
+        if self.loaded_path.is_some() {
+            self.read_new_entries(0);
+        }
+    }
+
+    fn read_new_entries(&mut self, malformed_lines: usize) {
+        let Some(path) = self.loaded_path.clone() else { return };
+        let first_new_entry = self.entries.len();
+        let run_filter = self.run_filter;
+        match Self::read_entries(&path, self.read_offset) {
+            Ok((entries, offset, malformed)) => {
+                self.add_entries(entries);
+                self.read_offset = offset;
+                if malformed_lines + malformed > 0 {
+                    self.error = Some(format!("skipped {} malformed lines", malformed_lines + malformed));
+                }
+            }
+            Err(e) => self.error = Some(format!("could not read {}: {e}", path.display())),
+        }
+        if self.run_filter != run_filter {
+            return self.apply_filters();
+        }
+        for i in first_new_entry..self.entries.len() {
+            if self.matches_filters(i) {
+                self.filtered.push(i);
+            }
+        }
+    }
+
+    // a run starting while the latest one is listed is listed instead, as it's the latest now
+    fn add_entries(&mut self, entries: Vec<JournalEntry>) {
+        for entry in entries {
+            // the entries before the first RunStarted (e.g. written by an older version) are a run of their own
+            if entry.kind == JournalEntry::RUN_STARTED || self.runs.is_empty() {
+                let listing_latest = self.runs.is_empty() || self.run_filter == Some(self.runs.len() - 1);
+                self.runs.push(self.entries.len());
+                if listing_latest {
+                    self.run_filter = Some(self.runs.len() - 1);
+                }
+            }
+            self.entries.push(entry);
+        }
+    }
+
+    fn run_label(&self, run: usize) -> String {
+        let first = &self.entries[self.runs[run]];
+        match first.unix_time_ms {
+            Some(unix_time_ms) if first.kind == JournalEntry::RUN_STARTED => format!("run {} (id {unix_time_ms})", run + 1),
+            _ => format!("run {}", run + 1),
+        }
+    }
+
+    // reads the complete lines of the file starting from offset, returning the entries read, the
+    // offset of the first unread byte and the number of lines which could not be parsed
+    fn read_entries(path: &Path, offset: u64) -> io::Result<(Vec<JournalEntry>, u64, usize)> {
+        let mut file = File::open(path)?;
+        // the live journal was rotated since the last read
+        let offset = if file.metadata()?.len() < offset { 0 } else { offset };
+        file.seek(SeekFrom::Start(offset))?;
+
+        let mut reader = BufReader::new(file);
+        let mut entries = vec![];
+        let mut offset = offset;
+        let mut malformed = 0;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 || !line.ends_with('\n') {
+                break; // the last line may still be being written
+            }
+            offset += read as u64;
+            match serde_json::from_str(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(_) => malformed += 1,
+            }
+        }
+        Ok((entries, offset, malformed))
+    }
+
+    fn matches_filters(&self, index: usize) -> bool {
+        let entry = &self.entries[index];
+        let run_matches = self.run_filter.map_or(true, |run| {
+            let end = self.runs.get(run + 1).copied().unwrap_or(usize::MAX);
+            (self.runs[run]..end).contains(&index)
+        });
+
+        let kind_matches = self.kind_filter.is_empty()
+            || entry.kind.to_lowercase().contains(&self.kind_filter.to_lowercase());
+
+        let tick = entry.tick as i64;
+        let tick_matches = tick >= self.tick_range.0 as i64 && (self.tick_range.1 < 0 || tick <= self.tick_range.1 as i64);
+
+        let coord_matches = !self.coord_filter_enabled || {
+            let location = Self::entry_location(entry);
+            let [x, y, radius] = self.coord_filter;
+            (location.x as i32 - x).abs() <= radius && (location.y as i32 - y).abs() <= radius
+        };
+
+        run_matches && kind_matches && tick_matches && coord_matches
+    }
+
+    fn apply_filters(&mut self) {
+        self.filtered = (0..self.entries.len()).filter(|i| self.matches_filters(*i)).collect();
+    }
+
+    // returns the location of the entry selected this frame, if any
+    pub fn draw(&mut self, ui: &Ui) -> Option<UVec2> {
+        if !self.open {
+            return None;
+        }
+        if self.follow_live && self.last_refresh.elapsed() > Self::LIVE_REFRESH_INTERVAL {
+            self.refresh();
+        }
+
+        let mut selected_location = None;
+        let mut open = self.open;
+        ui.window("Event journal")
+            .opened(&mut open)
+            .size([520.0, 450.0], Condition::FirstUseEver)
+            .build(|| {
+                ui.input_text("File", &mut self.path_input).build();
+                ui.same_line();
+                if ui.button("Open") {
+                    self.follow_live = false;
+                    self.load(PathBuf::from(&self.path_input));
+                }
+                if let Some(live_path) = self.live_path.clone() {
+                    if ui.button("Open live journal") {
+                        self.path_input = live_path.display().to_string();
+                        self.follow_live = true;
+                        self.load(live_path);
+                    }
+                    ui.same_line();
+                    ui.checkbox("Follow", &mut self.follow_live);
+                }
+                if let Some(error) = &self.error {
+                    ui.text_colored([1.0, 0.4, 0.4, 1.0], error);
+                }
+
+                ui.separator();
+
+                let mut filters_changed = false;
+                let run_label = self.run_filter.map_or("all runs".to_string(), |run| self.run_label(run));
+                if let Some(_combo) = ui.begin_combo("Run", run_label) {
+                    if ui.selectable_config("all runs").selected(self.run_filter.is_none()).build() {
+                        self.run_filter = None;
+                        filters_changed = true;
+                    }
+                    for run in (0..self.runs.len()).rev() {
+                        if ui.selectable_config(self.run_label(run)).selected(self.run_filter == Some(run)).build() {
+                            self.run_filter = Some(run);
+                            filters_changed = true;
+                        }
+                    }
+                }
+                filters_changed |= ui.input_text("Event type", &mut self.kind_filter).build();
+                filters_changed |= ui.input_int("From tick", &mut self.tick_range.0).build();
+                filters_changed |= ui.input_int("To tick (-1: last)", &mut self.tick_range.1).build();
+                filters_changed |= ui.checkbox("Near tile", &mut self.coord_filter_enabled);
+                if self.coord_filter_enabled {
+                    filters_changed |= ui.input_int("x", &mut self.coord_filter[0]).build();
+                    filters_changed |= ui.input_int("y", &mut self.coord_filter[1]).build();
+                    filters_changed |= ui.input_int("radius", &mut self.coord_filter[2]).build();
+                }
+                if filters_changed {
+                    self.apply_filters();
+                }
+
+                ui.separator();
+                ui.text(format!("{} of {} entries", self.filtered.len(), self.entries.len()));
+
+                ui.child_window("journal entries").size([0.0, -60.0]).build(|| {
+                    let mut clipper = ListClipper::new(self.filtered.len() as i32).begin(ui);
+                    while clipper.step() {
+                        for row in clipper.display_start()..clipper.display_end() {
+                            let index = self.filtered[row as usize];
+                            let entry = &self.entries[index];
+                            let location = Self::entry_location(entry);
+                            let label = format!("tick {:>6}  {:<20} ({}, {})##{index}", entry.tick, entry.kind, location.x, location.y);
+                            if ui.selectable_config(label).selected(self.selected == Some(index)).build() {
+                                self.selected = Some(index);
+                                selected_location = Some(location);
+                            }
+                        }
+                    }
+                });
+
+                if let Some(entry) = self.selected.and_then(|i| self.entries.get(i)) {
+                    ui.text_wrapped(&entry.description);
+                }
+            });
+        self.open = open;
+
+        selected_location
+    }
+}
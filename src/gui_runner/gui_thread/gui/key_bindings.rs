@@ -0,0 +1,322 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use imgui::{Condition, TableFlags, Ui};
+use serde::{Deserialize, Serialize};
+use winit::event::{ModifiersState, VirtualKeyCode};
+use super::keyboard_event_handler::{ChordAction, KeyboardEventHandler};
+
+// KeyBindings maps the keys to what they do in the KeyboardEventHandler: every KeyBinding is bound
+// to a single key (which is tracked while it is held, for the camera movement), and every
+// ChordAction to a chord, optionally after the chord prefix. The bindings are saved to and loaded
+// from a JSON file; the bindings missing from a file (e.g. one saved by an older version) keep
+// their default. There are QWERTY and AZERTY defaults, which differ in the movement keys.
+// KeyBindingsEditor is the imgui window where they are edited: clicking a binding makes the
+// KeyboardEventHandler capture the next key (or chord) pressed, which replaces it.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyBinding {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Sprint,
+    LookUp,
+    LookDown,
+    LookLeft,
+    LookRight,
+    ToggleContinuousMode,
+    SingleTick,
+    StepBack,
+    FindRobot,
+    ToggleFollowRobot,
+    CycleFollowTarget,
+    ToggleMapView,
+}
+impl KeyBinding {
+    fn description(&self) -> &'static str {
+        match self {
+            KeyBinding::MoveForward => "move the camera forward (pan the map view up)",
+            KeyBinding::MoveBackward => "move the camera backward (pan the map view down)",
+            KeyBinding::MoveLeft => "move the camera left (pan the map view left)",
+            KeyBinding::MoveRight => "move the camera right (pan the map view right)",
+            KeyBinding::MoveUp => "move the camera up (zoom the map view out)",
+            KeyBinding::MoveDown => "move the camera down (zoom the map view in)",
+            KeyBinding::Sprint => "move the camera faster",
+            KeyBinding::LookUp => "rotate the camera up",
+            KeyBinding::LookDown => "rotate the camera down",
+            KeyBinding::LookLeft => "rotate the camera left",
+            KeyBinding::LookRight => "rotate the camera right",
+            KeyBinding::ToggleContinuousMode => "toggle continuous execution of the game",
+            KeyBinding::SingleTick => "advance the game by a single tick",
+            KeyBinding::StepBack => "go back by a single tick (replays only)",
+            KeyBinding::FindRobot => "find the robot and move the camera to it",
+            KeyBinding::ToggleFollowRobot => "toggle following the robot with the camera",
+            KeyBinding::CycleFollowTarget => "cycle the robot followed by the camera (robot / ghost)",
+            KeyBinding::ToggleMapView => "toggle the top-down map view",
+        }
+    }
+}
+impl ChordAction {
+    fn description(&self) -> &'static str {
+        match self {
+            ChordAction::RunTicks => "advance the game by the number of ticks set in the Simulation settings",
+            ChordAction::Screenshot => "save a screenshot",
+            ChordAction::OverlookWorld => "move the camera to overlook the whole world",
+            ChordAction::ToggleMinimap => "toggle the minimap",
+            ChordAction::ToggleEventLog => "toggle the event log",
+            ChordAction::ToggleGodView => "toggle the god view",
+        }
+    }
+}
+
+// a key pressed while holding exactly the given modifiers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chord {
+    pub modifiers: ModifiersState,
+    pub key: VirtualKeyCode,
+}
+impl Chord {
+    const fn new(modifiers: ModifiersState, key: VirtualKeyCode) -> Self {
+        Self { modifiers, key }
+    }
+
+    fn name(&self) -> String {
+        let mut name = String::new();
+        for (modifier, modifier_name) in [(ModifiersState::CTRL, "Ctrl+"), (ModifiersState::ALT, "Alt+"), (ModifiersState::SHIFT, "Shift+"), (ModifiersState::LOGO, "Logo+")] {
+            if self.modifiers.contains(modifier) {
+                name.push_str(modifier_name);
+            }
+        }
+        name + &key_name(self.key)
+    }
+}
+
+pub fn key_name(key: VirtualKeyCode) -> String {
+    format!("{key:?}")
+}
+
+// what is being rebound
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BindingSlot {
+    Key(KeyBinding),
+    ChordPrefix,
+    Chord(ChordAction),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct KeyBindings {
+    pub keys: Vec<(KeyBinding, VirtualKeyCode)>,
+    pub chords: Vec<(bool, Chord, ChordAction)>, // whether the chord must follow the prefix, the chord, and what it does
+    pub chord_prefix: Chord,
+}
+impl KeyBindings {
+    pub fn qwerty() -> Self {
+        use VirtualKeyCode as K;
+        Self {
+            keys: vec![
+                (KeyBinding::MoveForward, K::W),
+                (KeyBinding::MoveBackward, K::S),
+                (KeyBinding::MoveLeft, K::A),
+                (KeyBinding::MoveRight, K::D),
+                (KeyBinding::MoveUp, K::Space),
+                (KeyBinding::MoveDown, K::LControl),
+                (KeyBinding::Sprint, K::LShift),
+                (KeyBinding::LookUp, K::Up),
+                (KeyBinding::LookDown, K::Down),
+                (KeyBinding::LookLeft, K::Left),
+                (KeyBinding::LookRight, K::Right),
+                (KeyBinding::ToggleContinuousMode, K::M),
+                (KeyBinding::SingleTick, K::N),
+                (KeyBinding::StepBack, K::B),
+                (KeyBinding::FindRobot, K::F),
+                (KeyBinding::ToggleFollowRobot, K::G),
+                (KeyBinding::CycleFollowTarget, K::C),
+                (KeyBinding::ToggleMapView, K::V),
+            ],
+            chord_prefix: Chord::new(ModifiersState::CTRL, K::K),
+            chords: vec![
+                (false, Chord::new(ModifiersState::SHIFT, K::N), ChordAction::RunTicks),
+                (false, Chord::new(ModifiersState::CTRL, K::S), ChordAction::Screenshot),
+                (false, Chord::new(ModifiersState::SHIFT, K::F), ChordAction::OverlookWorld),
+                (true, Chord::new(ModifiersState::empty(), K::M), ChordAction::ToggleMinimap),
+                (true, Chord::new(ModifiersState::empty(), K::E), ChordAction::ToggleEventLog),
+                (true, Chord::new(ModifiersState::empty(), K::G), ChordAction::ToggleGodView),
+            ],
+        }
+    }
+
+    // ZQSD rather than WASD
+    pub fn azerty() -> Self {
+        let mut bindings = Self::qwerty();
+        for (binding, key) in bindings.keys.iter_mut() {
+            match binding {
+                KeyBinding::MoveForward => *key = VirtualKeyCode::Z,
+                KeyBinding::MoveLeft => *key = VirtualKeyCode::Q,
+                _ => {}
+            }
+        }
+        bindings
+    }
+
+    pub fn key_of(&self, binding: KeyBinding) -> Option<VirtualKeyCode> {
+        self.keys.iter().find(|(b, _)| *b == binding).map(|(_, key)| *key)
+    }
+
+    pub fn binding_of(&self, key: VirtualKeyCode) -> Option<KeyBinding> {
+        self.keys.iter().find(|(_, k)| *k == key).map(|(binding, _)| *binding)
+    }
+
+    // the name of the key bound to the given binding, to be shown as a shortcut
+    pub fn key_shortcut(&self, binding: KeyBinding) -> Option<String> {
+        self.key_of(binding).map(key_name)
+    }
+
+    // the name of the chord bound to the given action, to be shown as a shortcut
+    pub fn chord_shortcut(&self, action: ChordAction) -> Option<String> {
+        self.chords.iter().find(|(.., a)| *a == action).map(|(prefixed, chord, _)| match prefixed {
+            true => format!("{} {}", self.chord_prefix.name(), chord.name()),
+            false => chord.name(),
+        })
+    }
+
+    pub fn rebind(&mut self, slot: BindingSlot, chord: Chord) {
+        match slot {
+            BindingSlot::Key(binding) => match self.keys.iter_mut().find(|(b, _)| *b == binding) {
+                Some((_, key)) => *key = chord.key,
+                None => self.keys.push((binding, chord.key)),
+            },
+            BindingSlot::ChordPrefix => self.chord_prefix = chord,
+            BindingSlot::Chord(action) => {
+                if let Some((_, binding, _)) = self.chords.iter_mut().find(|(.., a)| *a == action) {
+                    *binding = chord;
+                }
+            }
+        }
+    }
+
+    pub fn explanation(&self) -> String {
+        let mut lines: Vec<String> = self.keys.iter()
+            .map(|(binding, key)| format!("{}: {}", key_name(*key), binding.description()))
+            .collect();
+        lines.extend(self.chords.iter().map(|(prefixed, chord, action)| match prefixed {
+            true => format!("{} then {}: {}", self.chord_prefix.name(), chord.name(), action.description()),
+            false => format!("{}: {}", chord.name(), action.description()),
+        }));
+        lines.push("right click: pin an info panel to a tile".into());
+        lines.join("\n")
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let loaded: KeyBindings = serde_json::from_reader(BufReader::new(file)).map_err(|e| e.to_string())?;
+        // the bindings missing from the file keep their default
+        let mut bindings = Self::qwerty();
+        for (binding, key) in loaded.keys {
+            bindings.rebind(BindingSlot::Key(binding), Chord::new(ModifiersState::empty(), key));
+        }
+        bindings.chord_prefix = loaded.chord_prefix;
+        for (_, chord, action) in loaded.chords {
+            bindings.rebind(BindingSlot::Chord(action), chord);
+        }
+        Ok(bindings)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| e.to_string())?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self).map_err(|e| e.to_string())
+    }
+}
+
+pub struct KeyBindingsEditor {
+    pub open: bool,
+    path: String,
+    status: Option<String>,
+}
+impl KeyBindingsEditor {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let path = path.unwrap_or_else(|| PathBuf::from("keybindings.json"));
+        Self { open: false, path: path.to_string_lossy().into_owned(), status: None }
+    }
+
+    // loads the bindings from the file, if it exists
+    pub fn load_into(&mut self, kbd_event_handler: &mut KeyboardEventHandler) {
+        let path = Path::new(&self.path);
+        if path.exists() {
+            match KeyBindings::load(path) {
+                Ok(bindings) => kbd_event_handler.set_bindings(bindings),
+                Err(e) => self.status = Some(format!("could not load {}: {e}", self.path)),
+            }
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, kbd_event_handler: &mut KeyboardEventHandler) {
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        ui.window("Key bindings")
+            .opened(&mut open)
+            .size([460.0, 520.0], Condition::FirstUseEver)
+            .build(|| {
+                if ui.button("QWERTY defaults") {
+                    kbd_event_handler.set_bindings(KeyBindings::qwerty());
+                }
+                ui.same_line();
+                if ui.button("AZERTY defaults") {
+                    kbd_event_handler.set_bindings(KeyBindings::azerty());
+                }
+                ui.input_text("file", &mut self.path).build();
+                if ui.button("Save") {
+                    self.status = Some(match kbd_event_handler.bindings().save(Path::new(&self.path)) {
+                        Ok(()) => format!("saved to {}", self.path),
+                        Err(e) => format!("could not save: {e}"),
+                    });
+                }
+                ui.same_line();
+                if ui.button("Load") {
+                    self.status = Some(match KeyBindings::load(Path::new(&self.path)) {
+                        Ok(bindings) => {
+                            kbd_event_handler.set_bindings(bindings);
+                            format!("loaded {}", self.path)
+                        }
+                        Err(e) => format!("could not load: {e}"),
+                    });
+                }
+                if let Some(status) = &self.status {
+                    ui.text_wrapped(status);
+                }
+                ui.separator();
+                ui.text_disabled("click a binding, then press the new key or chord");
+
+                let capturing = kbd_event_handler.capturing();
+                let bindings = kbd_event_handler.bindings().clone();
+                let mut rows: Vec<(BindingSlot, &str, String)> = bindings.keys.iter()
+                    .map(|(binding, key)| (BindingSlot::Key(*binding), binding.description(), key_name(*key)))
+                    .collect();
+                rows.push((BindingSlot::ChordPrefix, "prefix of the chords below marked with \"then\"", bindings.chord_prefix.name()));
+                rows.extend(bindings.chords.iter().map(|(prefixed, chord, action)| {
+                    let name = if *prefixed { format!("then {}", chord.name()) } else { chord.name() };
+                    (BindingSlot::Chord(*action), action.description(), name)
+                }));
+
+                let flags = TableFlags::ROW_BG | TableFlags::SIZING_STRETCH_PROP;
+                if let Some(_table) = ui.begin_table_with_flags("key bindings", 2, flags) {
+                    for (i, (slot, description, name)) in rows.into_iter().enumerate() {
+                        let _id = ui.push_id_usize(i);
+                        ui.table_next_row();
+                        ui.table_next_column();
+                        let label = if capturing == Some(slot) { "press a key...".to_string() } else { name };
+                        if ui.button(label) {
+                            kbd_event_handler.capture(slot);
+                        }
+                        ui.table_next_column();
+                        ui.text_wrapped(description);
+                    }
+                }
+            });
+        self.open = open;
+    }
+}
@@ -1,52 +1,141 @@
 use nalgebra_glm::{vec2, Vec2, Vec3, vec3};
-use winit::event::{ElementState, KeyboardInput, VirtualKeyCode};
+use serde::{Deserialize, Serialize};
+use winit::event::{ElementState, KeyboardInput, ModifiersState, VirtualKeyCode};
 use nalgebra_glm as glm;
 use super::UP;
+use super::key_bindings::{BindingSlot, Chord, KeyBinding, KeyBindings};
 
 // KeyboardEventHandler is a struct which given winit keyboard events processes them into
 // ProcessedKeyboardInput.
 // ProcessedKeyboardInput in turn is able to move and rotate the camera according to input,
 // and exposes all other types of input as public fields
+// The keys are looked up in the KeyBindings, which can be edited at runtime: while a binding is
+// being captured, the next key pressed (or the next chord, if a chord is being rebound) replaces it
+// and does nothing else.
+// Since the single letters are nearly all taken, less common actions are bound to chords: a key
+// pressed while holding modifiers (e.g. Ctrl+S), optionally after the prefix chord (e.g. Ctrl+K
+// then M). A key pressed as part of a chord doesn't also trigger what it is bound to on its own,
+// but keeps being tracked for the movement when released.
+// Everything but the camera movement can also be listed as KeyActions, which the GUI handles in
+// the same way whether they come from the keyboard or from a recording being played back.
 
 pub struct KeyboardEventHandler {
     sprint_pressed: bool,
-    direction_pressed: [bool; 6], // w / s / a / d / ctrl / space
+    direction_pressed: [bool; 6], // forward / backward / left / right / up / down
     rotation_pressed: [bool; 4], // up / down / left / right
     toggle_continuous_mode: bool,
     single_tick: bool,
+    step_back: bool,
     find_robot: bool,
     toggle_follow_robot: bool,
+    cycle_follow_target: bool,
+    toggle_map_view: bool,
+    modifiers: ModifiersState,
+    prefix_pressed: bool, // the chord prefix was the last key pressed
+    chord: Option<ChordAction>,
 
-    movement_speed: f32,
-    look_speed: f32,
+    pub movement_speed: f32,
+    pub look_speed: f32,
 
+    bindings: KeyBindings,
+    capturing: Option<BindingSlot>,
     explanation: String,
 }
 impl KeyboardEventHandler {
     pub fn get_explanation(&self) -> &str { &self.explanation }
     pub fn new(movement_speed: f32, look_speed: f32) -> Self {
+        let bindings = KeyBindings::qwerty();
         Self {
             sprint_pressed: false,
             direction_pressed: [false; 6],
             rotation_pressed: [false; 4],
             toggle_continuous_mode: false,
             single_tick: false,
+            step_back: false,
             find_robot: false,
             toggle_follow_robot: false,
+            cycle_follow_target: false,
+            toggle_map_view: false,
+            modifiers: ModifiersState::empty(),
+            prefix_pressed: false,
+            chord: None,
 
             movement_speed,
             look_speed,
 
-            explanation:
-"WASD: control camera movement;
-arrows: control camera rotation;
-N: advance the game by a single tick;
-M: toggle continuous execution of the game.
-F: find the robot and move the camera to it
-G: toggle following the robot with the camera".into()
+            explanation: bindings.explanation(),
+            bindings,
+            capturing: None,
         }
     }
 
+    pub fn bindings(&self) -> &KeyBindings { &self.bindings }
+
+    pub fn set_bindings(&mut self, bindings: KeyBindings) {
+        self.explanation = bindings.explanation();
+        self.bindings = bindings;
+        // the keys held may not be bound to the same things anymore
+        self.direction_pressed = [false; 6];
+        self.rotation_pressed = [false; 4];
+        self.sprint_pressed = false;
+    }
+
+    // the next key pressed replaces the given binding
+    pub fn capture(&mut self, slot: BindingSlot) {
+        self.capturing = Some(slot);
+    }
+
+    pub fn capturing(&self) -> Option<BindingSlot> { self.capturing }
+
+    // must be called whenever the modifiers change, for the chords to be recognized
+    pub fn process_modifiers(&mut self, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
+    }
+
+    // returns whether the key press was (part of) a chord
+    fn handle_chord(&mut self, keycode: VirtualKeyCode) -> bool {
+        let chord = Chord { modifiers: self.modifiers, key: keycode };
+        let prefix = self.bindings.chord_prefix;
+        if std::mem::take(&mut self.prefix_pressed) {
+            // the modifiers of the prefix may still be held; whatever follows the prefix is
+            // consumed, so that a mistyped chord doesn't do something else
+            let without_prefix_modifiers = Chord { modifiers: chord.modifiers - prefix.modifiers, ..chord };
+            self.chord = self.bindings.chords.iter()
+                .find(|(prefixed, binding, _)| *prefixed && (*binding == chord || *binding == without_prefix_modifiers))
+                .map(|(.., action)| *action);
+            return true;
+        }
+        if chord == prefix {
+            self.prefix_pressed = true;
+            return true;
+        }
+        match self.bindings.chords.iter().find(|(prefixed, binding, _)| !*prefixed && *binding == chord) {
+            Some((.., action)) => {
+                self.chord = Some(*action);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // returns whether the key press was captured
+    fn handle_capture(&mut self, keycode: VirtualKeyCode) -> bool {
+        let Some(slot) = self.capturing else { return false };
+        // single keys may be modifiers (e.g. sprinting), chords wait for the key after them
+        if is_modifier(keycode) && !matches!(slot, BindingSlot::Key(_)) {
+            return false;
+        }
+        let modifiers = match slot {
+            BindingSlot::Key(_) => ModifiersState::empty(),
+            _ => self.modifiers,
+        };
+        self.capturing = None;
+        let mut bindings = self.bindings.clone();
+        bindings.rebind(slot, Chord { modifiers, key: keycode });
+        self.set_bindings(bindings);
+        true
+    }
+
     pub fn process_input(&mut self, input: KeyboardInput) -> ProcessedKeyboardInput {
         self.handle(input);
         self.get_processed_input()
@@ -57,43 +146,63 @@ G: toggle following the robot with the camera".into()
             ElementState::Released => false,
         };
         if let Some(keycode) = input.virtual_keycode {
-            match keycode {
-                VirtualKeyCode::W =>        self.direction_pressed[0] = pressed,
-                VirtualKeyCode::S =>        self.direction_pressed[1] = pressed,
-                VirtualKeyCode::A =>        self.direction_pressed[2] = pressed,
-                VirtualKeyCode::D =>        self.direction_pressed[3] = pressed,
-                VirtualKeyCode::Space =>    self.direction_pressed[4] = pressed,
-                VirtualKeyCode::LControl => self.direction_pressed[5] = pressed,
-
-                VirtualKeyCode::LShift => self.sprint_pressed = pressed,
-
-                VirtualKeyCode::Up =>    self.rotation_pressed[0] = pressed,
-                VirtualKeyCode::Down =>  self.rotation_pressed[1] = pressed,
-                VirtualKeyCode::Left =>  self.rotation_pressed[2] = pressed,
-                VirtualKeyCode::Right => self.rotation_pressed[3] = pressed,
-
-                VirtualKeyCode::M => {
+            if pressed && self.handle_capture(keycode) {
+                return;
+            }
+            if pressed && !is_modifier(keycode) && self.handle_chord(keycode) {
+                return;
+            }
+            let Some(binding) = self.bindings.binding_of(keycode) else { return };
+            match binding {
+                KeyBinding::MoveForward =>  self.direction_pressed[0] = pressed,
+                KeyBinding::MoveBackward => self.direction_pressed[1] = pressed,
+                KeyBinding::MoveLeft =>     self.direction_pressed[2] = pressed,
+                KeyBinding::MoveRight =>    self.direction_pressed[3] = pressed,
+                KeyBinding::MoveUp =>       self.direction_pressed[4] = pressed,
+                KeyBinding::MoveDown =>     self.direction_pressed[5] = pressed,
+
+                KeyBinding::Sprint => self.sprint_pressed = pressed,
+
+                KeyBinding::LookUp =>    self.rotation_pressed[0] = pressed,
+                KeyBinding::LookDown =>  self.rotation_pressed[1] = pressed,
+                KeyBinding::LookLeft =>  self.rotation_pressed[2] = pressed,
+                KeyBinding::LookRight => self.rotation_pressed[3] = pressed,
+
+                KeyBinding::ToggleContinuousMode => {
                     if pressed {
                         self.toggle_continuous_mode = true;
                     }
                 }
-                VirtualKeyCode::N => {
+                KeyBinding::SingleTick => {
                     if pressed {
                         self.single_tick = true;
                     }
                 }
-                VirtualKeyCode::F => {
+                KeyBinding::StepBack => {
+                    if pressed {
+                        self.step_back = true;
+                    }
+                }
+                KeyBinding::FindRobot => {
                     if pressed {
                         self.find_robot = true;
                     }
                 }
-                VirtualKeyCode::G => {
+                KeyBinding::ToggleFollowRobot => {
                     if pressed {
                         self.toggle_follow_robot = true;
                     }
                 }
-
-                _ => {}
+                KeyBinding::CycleFollowTarget => {
+                    if pressed {
+                        self.cycle_follow_target = true;
+                    }
+                }
+                KeyBinding::ToggleMapView => {
+                    if pressed {
+                        self.toggle_map_view = true;
+                    }
+                }
             }
         }
     }
@@ -118,17 +227,43 @@ G: toggle following the robot with the camera".into()
         let single_tick = self.single_tick;
         self.single_tick = false;
 
+        let step_back = self.step_back;
+        self.step_back = false;
+
         let find_robot = self.find_robot;
         self.find_robot = false;
 
         let toggle_follow_robot = self.toggle_follow_robot;
         self.toggle_follow_robot = false;
 
+        let cycle_follow_target = self.cycle_follow_target;
+        self.cycle_follow_target = false;
+
+        let toggle_map_view = self.toggle_map_view;
+        self.toggle_map_view = false;
 
-        ProcessedKeyboardInput { relative_cam_speed, cam_turn_speed, toggle_continuous_mode, single_tick, find_robot, toggle_follow_robot }
+        let chord = self.chord.take();
+
+        ProcessedKeyboardInput { relative_cam_speed, cam_turn_speed, toggle_continuous_mode, single_tick, step_back, find_robot, toggle_follow_robot, cycle_follow_target, toggle_map_view, chord }
     }
 }
 
+fn is_modifier(keycode: VirtualKeyCode) -> bool {
+    matches!(keycode,
+        VirtualKeyCode::LControl | VirtualKeyCode::RControl | VirtualKeyCode::LShift | VirtualKeyCode::RShift |
+        VirtualKeyCode::LAlt | VirtualKeyCode::RAlt | VirtualKeyCode::LWin | VirtualKeyCode::RWin)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChordAction {
+    RunTicks,
+    Screenshot,
+    OverlookWorld,
+    ToggleMinimap,
+    ToggleEventLog,
+    ToggleGodView,
+}
+
 #[derive(Default)]
 pub struct ProcessedKeyboardInput {
     relative_cam_speed : Vec3,
@@ -136,16 +271,57 @@ pub struct ProcessedKeyboardInput {
 
     pub toggle_continuous_mode: bool,
     pub single_tick: bool,
+    pub step_back: bool,
     pub find_robot: bool,
     pub toggle_follow_robot: bool,
+    pub cycle_follow_target: bool,
+    pub toggle_map_view: bool,
+    pub chord: Option<ChordAction>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyAction {
+    ToggleContinuousMode,
+    SingleTick,
+    StepBack,
+    FindRobot,
+    ToggleFollowRobot,
+    CycleFollowTarget,
+    ToggleMapView,
+    Chord(ChordAction),
 }
 
 impl ProcessedKeyboardInput {
+    pub fn actions(&self) -> Vec<KeyAction> {
+        let flags = [
+            (self.toggle_continuous_mode, KeyAction::ToggleContinuousMode),
+            (self.single_tick, KeyAction::SingleTick),
+            (self.step_back, KeyAction::StepBack),
+            (self.find_robot, KeyAction::FindRobot),
+            (self.toggle_follow_robot, KeyAction::ToggleFollowRobot),
+            (self.cycle_follow_target, KeyAction::CycleFollowTarget),
+            (self.toggle_map_view, KeyAction::ToggleMapView),
+        ];
+        flags.into_iter()
+            .filter_map(|(set, action)| set.then_some(action))
+            .chain(self.chord.map(KeyAction::Chord))
+            .collect()
+    }
+
     pub fn update_cam_dir_and_pos(&self, cam_dir: &mut Vec3, cam_pos: &mut Vec3, delta: f32) {
         *cam_dir = Self::rotate_camera(*cam_dir, self.cam_turn_speed, delta);
         *cam_pos += Self::camera_movement(*cam_dir, self.relative_cam_speed, delta);
     }
 
+    // pans the top-down map (forward/backward along the rows, left/right along the columns) and
+    // zooms it (up/down),
+    // at a speed proportional to the zoom so that it feels the same at any zoom level
+    pub fn update_map_view(&self, center: &mut Vec2, half_height: &mut f32, delta: f32) {
+        let speed = *half_height / 20.0;
+        *center += vec2(-self.relative_cam_speed.x, -self.relative_cam_speed.y) * speed * delta;
+        *half_height *= (self.relative_cam_speed.z * delta * 0.02).exp();
+    }
+
     fn rotate_camera(cam_dir: Vec3, cam_turn_speed: Vec2, delta: f32) -> Vec3 {
         let cam_dir_right = cam_dir.cross(&UP);
 
@@ -0,0 +1,206 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use imgui::{Context, Ui};
+
+// Layouts persists the imgui settings (the position, size and collapsed state of the windows and
+// the widths of the table columns) to INI_PATH, and keeps named presets of them in PRESETS_DIR.
+// imgui is never given a file to load by itself, since a corrupt one (e.g. truncated by a crash
+// while it was being written) could crash it: the file is checked first, and if it doesn't look
+// like imgui settings it is moved aside (with a .bad extension) and the default layout is used. The
+// settings are written to a temporary file which then replaces the old one, so that they can't be
+// truncated in the first place.
+// The settings can only be loaded and saved between frames, so the View menu queues the request,
+// which is carried out before the next frame.
+// The HUD is made of several windows (HudWindows), which can be docked around the 3D view or to
+// one another; imgui keeps how they are docked along with the rest of the settings, so the docking
+// is persisted and restored with the layout. Closed windows are reopened from the View menu.
+
+pub struct HudWindows {
+    pub simulation: bool,
+    pub robot: bool,
+    pub environment: bool,
+    pub tools: bool,
+    pub stats: bool,
+}
+impl HudWindows {
+    pub fn new() -> Self {
+        Self { simulation: true, robot: true, environment: true, tools: true, stats: true }
+    }
+
+    // draws the Windows entry of the View menu
+    pub fn draw_menu(&mut self, ui: &Ui, is_preview: bool) {
+        ui.menu("Windows", || {
+            if !is_preview {
+                ui.menu_item_config("Simulation").build_with_ref(&mut self.simulation);
+            }
+            ui.menu_item_config("Robot").build_with_ref(&mut self.robot);
+            ui.menu_item_config("Environment").build_with_ref(&mut self.environment);
+            ui.menu_item_config("Tools").build_with_ref(&mut self.tools);
+            ui.menu_item_config("Stats").build_with_ref(&mut self.stats);
+        });
+    }
+}
+
+enum LayoutRequest {
+    SavePreset(String),
+    LoadPreset(String),
+}
+
+pub struct Layouts {
+    requested: Option<LayoutRequest>,
+    new_preset_name: String,
+    status: Option<String>,
+}
+impl Layouts {
+    const INI_PATH: &'static str = "ragnarok-imgui.ini";
+    const PRESETS_DIR: &'static str = "ragnarok-layouts";
+    const MAX_FILE_SIZE: u64 = 1024 * 1024;
+    const MAX_COORDINATE: f32 = 100_000.0;
+
+    // loads the settings saved by the last run, if any
+    pub fn new(ctx: &mut Context) -> Self {
+        let mut layouts = Self { requested: None, new_preset_name: String::new(), status: None };
+        let path = Path::new(Self::INI_PATH);
+        if path.exists() {
+            if let Err(e) = Self::load(ctx, path) {
+                let bad_path = path.with_extension("ini.bad");
+                let _ = fs::rename(path, &bad_path);
+                eprintln!("ignoring the saved layout: {e} (moved to {bad_path:?})");
+                layouts.status = Some(format!("the saved layout was ignored: {e}"));
+            }
+        }
+        layouts
+    }
+
+    fn preset_path(name: &str) -> PathBuf {
+        Path::new(Self::PRESETS_DIR).join(format!("{name}.ini"))
+    }
+
+    fn presets() -> Vec<String> {
+        let Ok(entries) = fs::read_dir(Self::PRESETS_DIR) else { return vec![] };
+        let mut presets: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().map_or(false, |extension| extension == "ini"))
+            .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+            .collect();
+        presets.sort();
+        presets
+    }
+
+    fn load(ctx: &mut Context, path: &Path) -> Result<(), String> {
+        let size = fs::metadata(path).map_err(|e| e.to_string())?.len();
+        if size > Self::MAX_FILE_SIZE {
+            return Err(format!("the file is too large ({size} bytes)"));
+        }
+        let data = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::validate(&data)?;
+        ctx.load_ini_settings(&data);
+        Ok(())
+    }
+
+    // checks that the data is made of [Type][Name] sections of key=value lines, that it isn't
+    // truncated and that the positions and sizes are sensible
+    fn validate(data: &str) -> Result<(), String> {
+        if !data.is_empty() && !data.ends_with('\n') {
+            return Err("the file is truncated".into());
+        }
+        let mut in_section = false;
+        for (i, line) in data.lines().enumerate() {
+            let line = line.trim();
+            let invalid = || format!("line {} is invalid", i + 1);
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') {
+                if !line.ends_with(']') || !line.contains("][") {
+                    return Err(invalid());
+                }
+                in_section = true;
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else { return Err(invalid()) };
+            if !in_section || key.is_empty() {
+                return Err(invalid());
+            }
+            if key == "Pos" || key == "Size" {
+                let coordinates: Vec<Option<f32>> = value.split(',').map(|c| c.trim().parse().ok()).collect();
+                let valid = coordinates.len() == 2 && coordinates.iter().all(|c| c.map_or(false, |c: f32| c.abs() <= Self::MAX_COORDINATE));
+                if !valid {
+                    return Err(invalid());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn save(ctx: &mut Context, path: &Path) -> Result<(), String> {
+        let mut data = String::new();
+        ctx.save_ini_settings(&mut data);
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let tmp_path = path.with_extension("ini.tmp");
+        fs::write(&tmp_path, data).map_err(|e| e.to_string())?;
+        fs::rename(&tmp_path, path).map_err(|e| e.to_string())
+    }
+
+    // must be called before every frame (outside of it)
+    pub fn before_frame(&mut self, ctx: &mut Context) {
+        match self.requested.take() {
+            Some(LayoutRequest::SavePreset(name)) => {
+                self.status = Some(match Self::save(ctx, &Self::preset_path(&name)) {
+                    Ok(()) => format!("saved the layout as \"{name}\""),
+                    Err(e) => format!("could not save the layout: {e}"),
+                });
+            }
+            Some(LayoutRequest::LoadPreset(name)) => {
+                self.status = Some(match Self::load(ctx, &Self::preset_path(&name)) {
+                    Ok(()) => format!("loaded the layout \"{name}\""),
+                    Err(e) => format!("could not load the layout \"{name}\": {e}"),
+                });
+            }
+            None => {}
+        }
+        if ctx.io().want_save_ini_settings {
+            ctx.io_mut().want_save_ini_settings = false;
+            self.save_settings(ctx);
+        }
+    }
+
+    // saves the current layout, to be restored by the next run
+    pub fn save_settings(&mut self, ctx: &mut Context) {
+        if let Err(e) = Self::save(ctx, Path::new(Self::INI_PATH)) {
+            eprintln!("could not save the layout: {e}");
+        }
+    }
+
+    // draws the entries of the View menu
+    pub fn draw_menu(&mut self, ui: &Ui) {
+        ui.menu("Save layout as preset", || {
+            ui.input_text("##preset_name", &mut self.new_preset_name).hint("preset name").build();
+            let valid = !self.new_preset_name.trim().is_empty() && !self.new_preset_name.contains(['/', '\\', '.']);
+            ui.disabled(!valid, || {
+                if ui.button("Save") {
+                    self.requested = Some(LayoutRequest::SavePreset(self.new_preset_name.trim().to_string()));
+                    ui.close_current_popup();
+                }
+            });
+        });
+        ui.menu("Load preset", || {
+            let presets = Self::presets();
+            if presets.is_empty() {
+                ui.text_disabled("(no presets)");
+            }
+            for preset in presets {
+                if ui.menu_item(&preset) {
+                    self.requested = Some(LayoutRequest::LoadPreset(preset));
+                }
+            }
+        });
+        if let Some(status) = &self.status {
+            ui.separator();
+            ui.text_disabled(status);
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use nalgebra_glm::{Mat4, Vec2, Vec3, vec2, vec3};
+use super::{compute_mvp, world_mesh};
+
+// MapCamera is the alternative to the perspective fly camera: a top-down orthographic view of the
+// world, with rows going downwards and columns rightwards as in the minimap. It only has a center
+// (row, column) and a zoom (how many tiles fit in half the height of the window), which the
+// keyboard pans and zooms (see ProcessedKeyboardInput::update_map_view); the eye is placed high
+// above the center, looking straight down. Where a camera position is needed for something other
+// than the projection (level of detail, icons' distance, ...) the map camera stands in with the
+// position of a perspective camera above the center which would show as much of the world.
+// When it is toggled the fly camera and the map are kept looking at the same spot, so that the
+// user doesn't lose track of where they were.
+
+pub struct MapCamera {
+    pub enabled: bool,
+    pub center: Vec2,
+    pub half_height: f32,
+}
+impl MapCamera {
+    const EYE_HEIGHT: f32 = 500.0;
+    // distance of the point the fly camera looks at, as in GUI::cam_pos_looking_at
+    const FLY_CAMERA_DISTANCE: f32 = 30.0;
+    const MIN_HALF_HEIGHT: f32 = 4.0;
+
+    pub fn new(world_size: usize) -> Self {
+        let mut map_camera = Self { enabled: false, center: Vec2::zeros(), half_height: 0.0 };
+        map_camera.overlook(world_size);
+        map_camera
+    }
+
+    // centers the map on the world, zoomed out so that it's all visible
+    pub fn overlook(&mut self, world_size: usize) {
+        let half = world_size as f32 / 2.0;
+        self.center = vec2(half, half);
+        self.half_height = half.max(Self::MIN_HALF_HEIGHT);
+    }
+
+    // switches between the map and the fly camera, moving the one being switched to where the other was looking
+    pub fn toggle(&mut self, cam_pos: &mut Vec3, cam_dir: Vec3) {
+        if self.enabled {
+            *cam_pos = vec3(self.center.x, cam_pos.y, self.center.y) - vec3(cam_dir.x, 0.0, cam_dir.z) * Self::FLY_CAMERA_DISTANCE;
+        } else {
+            self.center_on(*cam_pos + cam_dir * Self::FLY_CAMERA_DISTANCE);
+        }
+        self.enabled = !self.enabled;
+    }
+
+    pub fn center_on(&mut self, point: Vec3) {
+        self.center = vec2(point.x, point.z);
+    }
+
+    pub fn zoom(&mut self, factor: f32) {
+        self.half_height *= factor;
+    }
+
+    // keeps the center over the world and the zoom within reasonable bounds
+    pub fn clamp(&mut self, world_size: usize) {
+        let world_size = world_size as f32;
+        self.center = self.center.map(|v| v.clamp(-10.0, world_size + 10.0));
+        self.half_height = self.half_height.clamp(Self::MIN_HALF_HEIGHT, world_size + 10.0);
+    }
+
+    pub fn mvp(&self, frame_size: (u32, u32), far: f32) -> Mat4 {
+        let eye = vec3(self.center.x, Self::EYE_HEIGHT, self.center.y);
+        compute_mvp::compute_ortho_mvp(frame_size, eye, self.half_height, Self::EYE_HEIGHT + far)
+    }
+
+    // position of the perspective camera looking down at the center which would show half_height tiles above and below it
+    pub fn equivalent_cam_pos(&self) -> Vec3 {
+        let distance = self.half_height / (compute_mvp::FOV / 2.0).tan();
+        vec3(self.center.x, world_mesh::elevation_to_mesh_space_y(0.0) + distance, self.center.y)
+    }
+
+    // returns the world space size which spans the given number of pixels (at any distance)
+    pub fn world_size_of_pixels(&self, pixels: f32, viewport_height: f32) -> f32 {
+        2.0 * self.half_height * pixels / viewport_height
+    }
+}
@@ -0,0 +1,50 @@
+use imgui::Ui;
+use nalgebra_glm::Mat4;
+use nalgebra_glm::Vec3;
+use crate::gui_runner::{MarkerIcon, MarkerStyle};
+use super::picking;
+
+// draw_robot_marker draws a screen-space marker (an icon with a label, see MarkerStyle) over a
+// robot, on top of everything else: it has the same size at any zoom level and stays visible even
+// when the robot is hidden behind terrain.
+// edit_marker_style draws the widgets to edit a MarkerStyle.
+
+pub fn draw_robot_marker(ui: &Ui, mvp: &Mat4, robot_anchor: Vec3, style: &MarkerStyle, alpha: f32) {
+    let Some(center) = picking::project_to_screen(mvp, robot_anchor, ui.io().display_size) else { return };
+    let [r, g, b] = style.color;
+    let color = [r, g, b, alpha];
+    let draw_list = ui.get_foreground_draw_list();
+    let [x, y] = center;
+    const R: f32 = 12.0;
+    match style.icon {
+        MarkerIcon::Ring => draw_list.add_circle(center, R, color).thickness(2.0).build(),
+        MarkerIcon::Diamond => {
+            let corners = [[x, y - R], [x + R, y], [x, y + R], [x - R, y]];
+            for i in 0..4 {
+                draw_list.add_line(corners[i], corners[(i + 1) % 4], color).thickness(2.0).build();
+            }
+        }
+        MarkerIcon::Square => {
+            let r = R * 0.8;
+            draw_list.add_rect([x - r, y - r], [x + r, y + r], color).thickness(2.0).build();
+        }
+        MarkerIcon::Cross => {
+            let r = R * 0.75;
+            draw_list.add_line([x - r, y - r], [x + r, y + r], color).thickness(2.0).build();
+            draw_list.add_line([x - r, y + r], [x + r, y - r], color).thickness(2.0).build();
+        }
+    }
+    draw_list.add_text([x + 14.0, y - 20.0], color, &style.label);
+}
+
+pub fn edit_marker_style(ui: &Ui, id: &str, style: &mut MarkerStyle) {
+    let _id = ui.push_id(id);
+    ui.color_edit3("color", &mut style.color);
+
+    let mut icon_index = MarkerIcon::ALL.iter().position(|icon| *icon == style.icon).unwrap_or(0);
+    if ui.combo("icon", &mut icon_index, &MarkerIcon::ALL, |icon| icon.name().into()) {
+        style.icon = MarkerIcon::ALL[icon_index];
+    }
+
+    ui.input_text("label", &mut style.label).build();
+}
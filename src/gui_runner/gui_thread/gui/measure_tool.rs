@@ -0,0 +1,149 @@
+use imgui::{Condition, ImColor32, MouseButton, Ui};
+use nalgebra_glm::{Mat4, UVec2, vec3};
+use robotics_lib::world::tile::Tile;
+use super::picking;
+use crate::grid;
+
+// MeasureTool measures between two tiles: while its window is open, left clicking in the world
+// picks the first tile and then the second (a third click starts over), and the window shows the
+// straight line, manhattan (the moves of the robot) and chebyshev distances between them, along
+// with the elevation profile of the tiles on the straight line from the first to the second,
+// which tells how much climbing (and so energy) the way between them may take.
+// The tiles on the line are those nearest to evenly spaced points, one per step of the longer
+// axis, so that consecutive tiles touch. Undiscovered tiles leave gaps in the profile.
+
+pub struct MeasureTool {
+    pub open: bool,
+    from: Option<UVec2>,
+    to: Option<UVec2>,
+}
+impl MeasureTool {
+    const PROFILE_HEIGHT: f32 = 120.0;
+    const LINE_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 1.0];
+    const PROFILE_COLOR: [f32; 4] = [0.4, 0.8, 1.0, 1.0];
+
+    pub fn new() -> Self {
+        Self { open: false, from: None, to: None }
+    }
+
+    // must be called every frame, with the tile under the mouse (None when over a window)
+    pub fn handle_click(&mut self, ui: &Ui, hovered_tile: Option<UVec2>) {
+        if !self.open || !ui.is_mouse_clicked(MouseButton::Left) {
+            return;
+        }
+        let Some(tile) = hovered_tile else { return };
+        match (self.from, self.to) {
+            (Some(_), None) => self.to = Some(tile),
+            _ => (self.from, self.to) = (Some(tile), None),
+        }
+    }
+
+    // the tiles on the straight line between the two, both included
+    fn line(from: UVec2, to: UVec2) -> Vec<UVec2> {
+        let steps = grid::chebyshev_distance((from.x as usize, from.y as usize), (to.x as usize, to.y as usize));
+        if steps == 0 {
+            return vec![from];
+        }
+        (0..=steps).map(|step| {
+            let t = step as f32 / steps as f32;
+            let lerp = |a: u32, b: u32| (a as f32 + (b as f32 - a as f32) * t).round() as u32;
+            UVec2::new(lerp(from.x, to.x), lerp(from.y, to.y))
+        }).collect()
+    }
+
+    // draws the line between the tiles picked, over the world
+    pub fn draw_line(&self, ui: &Ui, mvp: &Mat4, world: &Vec<Vec<Option<Tile>>>) {
+        if !self.open {
+            return;
+        }
+        let display_size = ui.io().display_size;
+        let to_screen = |tile: UVec2| picking::project_to_screen(mvp, picking::tile_anchor(tile, world) + vec3(0.0, 0.3, 0.0), display_size);
+        let draw_list = ui.get_background_draw_list();
+        let ends: Vec<[f32; 2]> = [self.from, self.to].into_iter().flatten().filter_map(to_screen).collect();
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            let points: Vec<[f32; 2]> = Self::line(from, to).into_iter().filter_map(to_screen).collect();
+            draw_list.add_polyline(points, Self::LINE_COLOR).thickness(2.0).build();
+        }
+        for end in ends {
+            draw_list.add_circle(end, 5.0, Self::LINE_COLOR).filled(true).build();
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, world: &Vec<Vec<Option<Tile>>>) {
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        ui.window("Measure")
+            .opened(&mut open)
+            .size([320.0, 300.0], Condition::FirstUseEver)
+            .build(|| {
+                let (from, to) = match (self.from, self.to) {
+                    (None, _) => return ui.text_wrapped("Left click a tile to measure from."),
+                    (Some(from), None) => {
+                        ui.text(format!("from ({}, {})", from.x, from.y));
+                        return ui.text_wrapped("Left click a tile to measure to.");
+                    }
+                    (Some(from), Some(to)) => (from, to),
+                };
+                ui.text(format!("from ({}, {}) to ({}, {})", from.x, from.y, to.x, to.y));
+                ui.same_line();
+                if ui.small_button("Clear") {
+                    (self.from, self.to) = (None, None);
+                }
+                let (a, b) = ((from.x as usize, from.y as usize), (to.x as usize, to.y as usize));
+                ui.text(format!("straight line: {:.2} tiles", grid::euclidean_distance(a, b)));
+                ui.text(format!("manhattan: {} moves", grid::manhattan_distance(a, b)));
+                ui.text(format!("chebyshev: {}", grid::chebyshev_distance(a, b)));
+
+                let elevation = |tile: &UVec2| world.get(tile.x as usize).and_then(|row| row.get(tile.y as usize)).and_then(Option::as_ref).map(|tile| tile.elevation);
+                let line = Self::line(from, to);
+                let profile: Vec<Option<usize>> = line.iter().map(elevation).collect();
+                ui.separator();
+                Self::draw_profile(ui, &line, &profile);
+            });
+        self.open = open;
+    }
+
+    fn draw_profile(ui: &Ui, line: &[UVec2], profile: &[Option<usize>]) {
+        let known: Vec<usize> = profile.iter().flatten().copied().collect();
+        let (Some(min), Some(max)) = (known.iter().min().copied(), known.iter().max().copied()) else {
+            return ui.text_disabled("none of the tiles on the line is discovered");
+        };
+        let (mut ascent, mut descent) = (0, 0);
+        for pair in profile.windows(2) {
+            if let [Some(a), Some(b)] = pair {
+                ascent += b.saturating_sub(*a);
+                descent += a.saturating_sub(*b);
+            }
+        }
+        ui.text(format!("elevation: {min}..{max}, climbing {ascent} and descending {descent}"));
+        let undiscovered = profile.len() - known.len();
+        if undiscovered > 0 {
+            ui.text_disabled(format!("{undiscovered} of the {} tiles on the line are undiscovered", profile.len()));
+        }
+
+        let width = ui.content_region_avail()[0];
+        let height = Self::PROFILE_HEIGHT;
+        let [left, top] = ui.cursor_screen_pos();
+        let x_of = |i: usize| left + width * i as f32 / (profile.len() - 1).max(1) as f32;
+        let y_of = |elevation: usize| top + height * (1.0 - (elevation - min) as f32 / (max - min).max(1) as f32);
+        let draw_list = ui.get_window_draw_list();
+        draw_list.add_rect([left, top], [left + width, top + height], ImColor32::from_rgba(40, 40, 40, 255)).filled(true).build();
+        // the known stretches, separated by the undiscovered tiles
+        for stretch in profile.iter().enumerate().collect::<Vec<_>>().split(|(_, elevation)| elevation.is_none()) {
+            let points: Vec<[f32; 2]> = stretch.iter().filter_map(|(i, elevation)| Some([x_of(*i), y_of((**elevation)?)])).collect();
+            match points.len() {
+                0 => {}
+                1 => { draw_list.add_circle(points[0], 2.0, Self::PROFILE_COLOR).filled(true).build(); }
+                _ => { draw_list.add_polyline(points, Self::PROFILE_COLOR).thickness(1.5).build(); }
+            }
+        }
+        ui.invisible_button("profile", [width, height]);
+        if ui.is_item_hovered() {
+            let i = (((ui.io().mouse_pos[0] - left) / width * (profile.len() - 1) as f32).round().max(0.0) as usize).min(profile.len() - 1);
+            let elevation = profile[i].map_or("undiscovered".to_string(), |elevation| format!("elevation {elevation}"));
+            ui.tooltip_text(format!("({}, {}): {elevation}", line[i].x, line[i].y));
+        }
+    }
+}
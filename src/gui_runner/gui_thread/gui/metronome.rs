@@ -0,0 +1,93 @@
+use std::time::{Duration, Instant};
+use imgui::Ui;
+
+// Metronome signals every game tick received by the GUI with a visual pulse (a dot in the top right
+// corner which fades out) and, if the crate is built with the "audio" feature, optionally with a
+// short click. It only does so when ticks are slow enough to follow (about 10 ticks per second or
+// less), since at higher rates the pulses would just blend together.
+
+pub struct Metronome {
+    pub visual: bool,
+    pub audible: bool,
+    last_tick: Option<usize>,
+    last_tick_time: Option<Instant>,
+    pulse_start: Option<Instant>,
+    #[cfg(feature = "audio")]
+    audio: Option<(rodio::OutputStream, rodio::OutputStreamHandle)>,
+}
+impl Metronome {
+    const MIN_TICK_INTERVAL: Duration = Duration::from_millis(100);
+    const PULSE_DURATION: Duration = Duration::from_millis(200);
+
+    pub fn new() -> Self {
+        Self {
+            visual: false,
+            audible: false,
+            last_tick: None,
+            last_tick_time: None,
+            pulse_start: None,
+            #[cfg(feature = "audio")]
+            audio: None,
+        }
+    }
+
+    pub fn audio_is_supported() -> bool { cfg!(feature = "audio") }
+
+    pub fn on_tick(&mut self, tick: usize) {
+        if self.last_tick == Some(tick) {
+            return;
+        }
+        self.last_tick = Some(tick);
+
+        let now = Instant::now();
+        let is_slow = self.last_tick_time.map(|t| now - t >= Self::MIN_TICK_INTERVAL).unwrap_or(true);
+        self.last_tick_time = Some(now);
+
+        if is_slow {
+            if self.visual {
+                self.pulse_start = Some(now);
+            }
+            if self.audible {
+                self.click();
+            }
+        }
+    }
+
+    #[cfg(feature = "audio")]
+    fn click(&mut self) {
+        use rodio::Source;
+
+        if self.audio.is_none() {
+            match rodio::OutputStream::try_default() {
+                Ok(audio) => self.audio = Some(audio),
+                Err(e) => {
+                    eprintln!("could not open an audio output stream, disabling the metronome sound: {e}");
+                    self.audible = false;
+                    return;
+                }
+            }
+        }
+        if let Some((_stream, handle)) = &self.audio {
+            let click = rodio::source::SineWave::new(1200.0)
+                .take_duration(Duration::from_millis(25))
+                .amplify(0.15);
+            let _ = handle.play_raw(click.convert_samples());
+        }
+    }
+    #[cfg(not(feature = "audio"))]
+    fn click(&mut self) {}
+
+    pub fn draw(&self, ui: &Ui) {
+        let Some(pulse_start) = self.pulse_start else { return };
+        let elapsed = pulse_start.elapsed();
+        if !self.visual || elapsed > Self::PULSE_DURATION {
+            return;
+        }
+        let alpha = 1.0 - elapsed.as_secs_f32() / Self::PULSE_DURATION.as_secs_f32();
+        let display_size = ui.io().display_size;
+        ui.get_foreground_draw_list()
+            .add_circle([display_size[0] - 24.0, 110.0], 8.0, [1.0, 1.0, 1.0, alpha])
+            .filled(true)
+            .build();
+    }
+}
@@ -0,0 +1,152 @@
+use std::rc::Rc;
+use glium::Display;
+use glium::texture::{RawImage2d, Texture2d};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerBehavior};
+use imgui::{Condition, MouseButton, TextureId, Ui};
+use imgui_glium_renderer::{Renderer, Texture};
+use nalgebra_glm::{Mat4, UVec2, vec4};
+use super::world_mesh;
+use crate::gui_runner::PartialWorld;
+
+// Minimap is a 2D map of the discovered world, one pixel per tile colored by tile type, kept in a
+// texture registered in the imgui renderer (like the charts): the pixels of the tiles which changed
+// are updated on the CPU, and only the rectangle containing them is re-uploaded. Over it the window
+// draws the robot and the footprint of the camera's view (where the corners of the view hit the
+// ground, or at most world_size tiles away from the near plane), and clicking it moves the camera
+// there. Rows go downwards and columns rightwards, as in the robot's map.
+
+pub struct Minimap {
+    pub open: bool,
+    world_size: u32,
+    pixels: Vec<u8>, // RGBA, row by row
+    dirty: Option<(UVec2, UVec2)>, // min and max corners of the tiles changed since the last upload
+    texture: Option<(TextureId, Rc<Texture2d>)>,
+}
+impl Minimap {
+    const UNDISCOVERED: [u8; 4] = [0, 0, 0, 160];
+
+    pub fn new(world_size: usize) -> Self {
+        let world_size = world_size as u32;
+        let pixels = Self::UNDISCOVERED.repeat((world_size * world_size) as usize);
+        Self { open: false, world_size, pixels, dirty: None, texture: None }
+    }
+
+    // must be called with every new world, before its tiles_to_refresh are cleared
+    pub fn update(&mut self, world: &PartialWorld) {
+        for tile_pos in world.tiles_to_refresh.iter() {
+            let rgba = match &world.world[tile_pos.x as usize][tile_pos.y as usize] {
+                Some(tile) => {
+                    let c = world_mesh::tile_to_color(tile);
+                    [c.x, c.y, c.z, 1.0].map(|v| (v.clamp(0.0, 1.0) * 255.0) as u8)
+                }
+                None => Self::UNDISCOVERED,
+            };
+            let i = ((tile_pos.x * self.world_size + tile_pos.y) * 4) as usize;
+            self.pixels[i..i + 4].copy_from_slice(&rgba);
+
+            self.dirty = Some(match self.dirty {
+                Some((min, max)) => (min.inf(tile_pos), max.sup(tile_pos)),
+                None => (*tile_pos, *tile_pos),
+            });
+        }
+    }
+
+    pub fn update_texture(&mut self, display: &Display, renderer: &mut Renderer) {
+        if !self.open || self.world_size == 0 {
+            return;
+        }
+        match &self.texture {
+            Some((_id, texture)) => {
+                let Some((min, max)) = self.dirty.take() else { return };
+                // rows are written top to bottom, which is how imgui samples the texture with default uvs
+                let (width, height) = (max.y - min.y + 1, max.x - min.x + 1);
+                let mut rect_pixels = Vec::with_capacity((width * height * 4) as usize);
+                for row in min.x..=max.x {
+                    let start = ((row * self.world_size + min.y) * 4) as usize;
+                    rect_pixels.extend_from_slice(&self.pixels[start..start + (width * 4) as usize]);
+                }
+                let image = RawImage2d::from_raw_rgba(rect_pixels, (width, height));
+                texture.write(glium::Rect { left: min.y, bottom: min.x, width, height }, image);
+            }
+            None => {
+                let image = RawImage2d::from_raw_rgba(self.pixels.clone(), (self.world_size, self.world_size));
+                let texture = match Texture2d::new(display, image) {
+                    Ok(t) => Rc::new(t),
+                    Err(_) => return,
+                };
+                let id = renderer.textures().insert(Texture {
+                    texture: texture.clone(),
+                    sampler: SamplerBehavior {
+                        magnify_filter: MagnifySamplerFilter::Nearest,
+                        minify_filter: MinifySamplerFilter::Linear,
+                        ..Default::default()
+                    },
+                });
+                self.texture = Some((id, texture));
+                self.dirty = None;
+            }
+        }
+    }
+
+    // where the ray through the given corner of the view (in normalized device coordinates) hits
+    // the ground, as (row, column) in tiles; the ray goes from the near to the far plane, so that
+    // this works for both the perspective and the orthographic (map view) projections
+    fn footprint_corner(inverse_mvp: &Mat4, ndc: [f32; 2], max_distance: f32) -> [f32; 2] {
+        let unproject = |ndc_z: f32| {
+            let point = inverse_mvp * vec4(ndc[0], ndc[1], ndc_z, 1.0);
+            point.xyz() / point.w
+        };
+        let origin = unproject(-1.0);
+        let ray = (unproject(1.0) - origin).normalize();
+        let ground = world_mesh::elevation_to_mesh_space_y(0.0);
+        let distance = if ray.y < 0.0 { ((ground - origin.y) / ray.y).min(max_distance) } else { max_distance };
+        let hit = origin + ray * distance.max(0.0);
+        [hit.x, hit.z]
+    }
+
+    // draws the minimap window, returning the tile the user clicked on, if any
+    pub fn draw(&mut self, ui: &Ui, mvp: &Mat4, robot_position: UVec2) -> Option<UVec2> {
+        if !self.open {
+            return None;
+        }
+        let mut clicked = None;
+        let mut open = self.open;
+        ui.window("Minimap")
+            .opened(&mut open)
+            .size([280.0, 300.0], Condition::FirstUseEver)
+            .build(|| {
+                let Some((id, _)) = &self.texture else { return };
+                let available = ui.content_region_avail();
+                let side = available[0].min(available[1]).max(32.0);
+                let origin = ui.cursor_screen_pos();
+                imgui::Image::new(*id, [side, side]).build(ui);
+                let scale = side / self.world_size as f32;
+                let to_screen = |[row, col]: [f32; 2]| [origin[0] + col * scale, origin[1] + row * scale];
+
+                let draw_list = ui.get_window_draw_list();
+                draw_list.with_clip_rect_intersect(origin, [origin[0] + side, origin[1] + side], || {
+                    let inverse_mvp = nalgebra_glm::inverse(mvp);
+                    let corners = [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]]
+                        .map(|ndc| to_screen(Self::footprint_corner(&inverse_mvp, ndc, self.world_size as f32)));
+                    for i in 0..4 {
+                        draw_list.add_line(corners[i], corners[(i + 1) % 4], [1.0, 1.0, 1.0, 0.8]).build();
+                    }
+
+                    let robot = to_screen([robot_position.x as f32 + 0.5, robot_position.y as f32 + 0.5]);
+                    draw_list.add_circle(robot, 4.0, [1.0, 0.9, 0.2, 1.0]).filled(true).build();
+                    draw_list.add_circle(robot, 4.0, [0.0, 0.0, 0.0, 1.0]).build();
+                });
+
+                if ui.is_item_hovered() && ui.is_mouse_clicked(MouseButton::Left) {
+                    let mouse_pos = ui.io().mouse_pos;
+                    let row = ((mouse_pos[1] - origin[1]) / scale) as u32;
+                    let col = ((mouse_pos[0] - origin[0]) / scale) as u32;
+                    if row < self.world_size && col < self.world_size {
+                        clicked = Some(UVec2::new(row, col));
+                    }
+                }
+            });
+        self.open = open;
+        clicked
+    }
+}
@@ -0,0 +1,132 @@
+use glium::{HeadlessRenderer, Surface};
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::glutin::{self, dpi::PhysicalSize};
+use glium::index::PrimitiveType;
+use glium::texture::{DepthFormat, DepthRenderBuffer, RawImage2d, Texture2d};
+use nalgebra_glm::{Vec3, vec3};
+use robotics_lib::world::tile::Tile;
+use crate::gui_runner::PartialWorld;
+use crate::gui_runner::builder::Config;
+use crate::gui_runner::worker_thread;
+use super::world_mesh::WorldMesh;
+use super::daylight::Daylight;
+use super::robot_model::RobotModel;
+use super::skybox::{SkyMode, Skybox};
+use super::{compute_mvp, picking, shaders};
+
+#[cfg(target_os = "linux")] use glutin::platform::unix::HeadlessContextExt;
+
+// OffscreenRenderer renders the world mesh to a texture without opening a window, so that frames
+// can be generated on a machine with no display (e.g. a server). on linux the OpenGL context is
+// created through OSMesa (a software renderer, which needs libOSMesa to be installed), elsewhere
+// through a hidden context of the windowing system.
+// It plays the roles of both the worker thread (diffing each world against the previous one) and
+// of the GUI (keeping the WorldMesh up to date and drawing it, along with the sky), with no imgui
+// overlay.
+
+pub struct OffscreenRenderer {
+    renderer: HeadlessRenderer,
+    shader_program: glium::Program,
+    liquid_shader_program: glium::Program,
+    world_mesh: WorldMesh,
+    skybox: Skybox,
+    color_texture: Texture2d,
+    depth_buffer: DepthRenderBuffer,
+    size: (u32, u32),
+    config: Config,
+
+    world: Option<PartialWorld>,
+    world_copy: Option<Vec<Vec<Option<Tile>>>>, // last world diffed, as in the worker thread
+}
+impl OffscreenRenderer {
+    pub fn new(size: (u32, u32)) -> Result<Self, String> {
+        let renderer = HeadlessRenderer::new(Self::make_context(size)?).map_err(|e| e.to_string())?;
+        let shader_program = shaders::make_program(&renderer).map_err(|e| e.to_string())?;
+        let liquid_shader_program = shaders::make_liquid_program(&renderer).map_err(|e| e.to_string())?;
+        let world_mesh = WorldMesh::new(&renderer);
+        let skybox = Skybox::new(&renderer)?;
+        let color_texture = Texture2d::empty(&renderer, size.0, size.1).map_err(|e| e.to_string())?;
+        let depth_buffer = DepthRenderBuffer::new(&renderer, DepthFormat::I24, size.0, size.1).map_err(|e| e.to_string())?;
+        Ok(Self {
+            renderer, shader_program, liquid_shader_program, world_mesh, skybox, color_texture, depth_buffer, size,
+            config: Config::default(),
+            world: None,
+            world_copy: None,
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    fn make_context(size: (u32, u32)) -> Result<glutin::Context<glutin::NotCurrent>, String> {
+        glutin::ContextBuilder::new()
+            .build_osmesa(PhysicalSize::new(size.0, size.1))
+            .map_err(|e| format!("could not create an OSMesa context (is libOSMesa installed?): {e}"))
+    }
+    #[cfg(not(target_os = "linux"))]
+    fn make_context(size: (u32, u32)) -> Result<glutin::Context<glutin::NotCurrent>, String> {
+        let event_loop = glutin::event_loop::EventLoop::new();
+        glutin::ContextBuilder::new()
+            .build_headless(&event_loop, PhysicalSize::new(size.0, size.1))
+            .map_err(|e| format!("could not create a headless OpenGL context: {e}"))
+    }
+
+    // replaces the world shown, only rebuilding the chunks which changed since the last one
+    pub fn set_world(&mut self, mut world: PartialWorld) {
+        world.tiles_to_refresh = worker_thread::tiles_to_refresh(&mut self.world_copy, &world, self.config.vicinity_refresh_radius, true);
+        self.world = Some(world);
+    }
+
+    // camera pose framing the robot from the default direction of the GUI
+    pub fn robot_camera(&self, distance: f32) -> Option<(Vec3, Vec3)> {
+        let world = self.world.as_ref()?;
+        let cam_dir = vec3(-1.0, -1.0, -1.0).normalize();
+        Some((picking::tile_anchor(world.robot_position, &world.world) - cam_dir * distance, cam_dir))
+    }
+
+    // renders the current world and returns the frame as RGBA8 pixels, rows from top to bottom.
+    // time (in seconds) drives the animation of water and lava
+    pub fn render(&mut self, cam_pos: Vec3, cam_dir: Vec3, time: f32) -> Vec<u8> {
+        if let Some(world) = &mut self.world {
+            let robot_position = RobotModel::position_of(world.robot_position, &world.world);
+            self.world_mesh.update(world, &self.renderer, Some((robot_position, 0.0)), 1.0);
+            world.tiles_to_refresh.clear();
+        }
+
+        let mut target = SimpleFrameBuffer::with_depth_buffer(&self.renderer, &self.color_texture, &self.depth_buffer).unwrap();
+        let daylight = self.world.as_ref().map(|world| Daylight::from_env_cond(&world.env_cond)).unwrap_or(Daylight::NEUTRAL);
+        target.clear_color_and_depth(daylight.sky_color, 1.0);
+
+        if let Some(world) = &self.world {
+            let mvp = compute_mvp::compute_mvp(self.size, cam_pos, cam_dir, self.config.near_plane, self.config.far_plane);
+            let log_depth_coef = compute_mvp::log_depth_coefficient(self.config.far_plane);
+            let uniforms = shaders::uniforms(&mvp, self.config.logarithmic_depth, log_depth_coef, &daylight, true);
+            let unlit_uniforms = shaders::uniforms(&mvp, self.config.logarithmic_depth, log_depth_coef, &daylight, false);
+            let liquid_uniforms = shaders::liquid_uniforms(&mvp, self.config.logarithmic_depth, log_depth_coef, &daylight, time);
+            let draw_params = glium::DrawParameters {
+                depth: glium::Depth {
+                    test: glium::draw_parameters::DepthTest::IfLess,
+                    write: true,
+                    .. Default::default()
+                },
+                .. Default::default()
+            };
+
+            target.draw(&self.world_mesh.misc_vbo, &glium::index::NoIndices(PrimitiveType::TrianglesList),
+                        &self.shader_program, &unlit_uniforms, &draw_params).unwrap();
+            for (_chunk_pos, chunk) in self.world_mesh.chunks() {
+                target.draw(&chunk.vbo, &glium::index::NoIndices(PrimitiveType::TrianglesList),
+                            &self.shader_program, &uniforms, &draw_params).unwrap();
+                if let Some(liquid_vbo) = &chunk.liquid_vbo {
+                    target.draw(liquid_vbo, &glium::index::NoIndices(PrimitiveType::TrianglesList),
+                                &self.liquid_shader_program, &liquid_uniforms, &draw_params).unwrap();
+                }
+            }
+            let sky_view_projection = compute_mvp::compute_mvp(self.size, vec3(0.0, 0.0, 0.0), cam_dir, self.config.near_plane, self.config.far_plane);
+            self.skybox.draw(&mut target, &self.renderer, &sky_view_projection, SkyMode::Automatic, &world.env_cond);
+        }
+
+        // OpenGL stores the rows from the bottom up
+        let image: RawImage2d<u8> = self.color_texture.read();
+        let row_len = image.width as usize * 4;
+        image.data.chunks_exact(row_len).rev().flatten().copied().collect()
+    }
+}
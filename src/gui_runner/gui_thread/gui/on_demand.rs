@@ -0,0 +1,56 @@
+use std::time::{Duration, Instant};
+use nalgebra_glm::Vec3;
+use winit::event_loop::ControlFlow;
+
+// OnDemand implements the low power mode of the GUI, in which frames are only drawn when something
+// changed: a window event (any input, a resize, ...), a new world or a move of the camera. Instead
+// of polling, the event loop waits for the next event, waking up every POLL_INTERVAL to receive the
+// worlds (which come through a channel, not as events), run the logic of the frame and tell
+// whether anything changed. After a change frames keep being drawn for SETTLE_TIME, so that what
+// lags behind it (the robot gliding to its new tile, imgui's hover highlights, ...) gets there, and
+// a frame is drawn every MAX_IDLE anyway, so that the clocks and the diagnostics stay current.
+// Animations which only depend on time (e.g. the liquids) stop while nothing changes.
+
+pub struct OnDemand {
+    last_change: Instant,
+    last_draw: Instant,
+    camera: (Vec3, Vec3), // position and direction at the last frame
+}
+impl OnDemand {
+    const POLL_INTERVAL: Duration = Duration::from_millis(50);
+    const SETTLE_TIME: Duration = Duration::from_millis(750);
+    const MAX_IDLE: Duration = Duration::from_secs(1);
+
+    pub fn new() -> Self {
+        Self { last_change: Instant::now(), last_draw: Instant::now(), camera: (Vec3::zeros(), Vec3::zeros()) }
+    }
+
+    // must be called with every window event and every new world
+    pub fn wake(&mut self) {
+        self.last_change = Instant::now();
+    }
+
+    // must be called at the start of every frame
+    pub fn set_control_flow(&self, control_flow: &mut ControlFlow, low_power: bool) {
+        if matches!(control_flow, ControlFlow::ExitWithCode(_)) {
+            return;
+        }
+        match low_power {
+            true => control_flow.set_wait_until(Instant::now() + Self::POLL_INTERVAL),
+            false => control_flow.set_poll(),
+        }
+    }
+
+    // whether the frame must be drawn, given the camera it would be drawn from
+    pub fn must_draw(&mut self, cam_pos: Vec3, cam_dir: Vec3) -> bool {
+        if (cam_pos, cam_dir) != self.camera {
+            self.camera = (cam_pos, cam_dir);
+            self.wake();
+        }
+        let must_draw = self.last_change.elapsed() < Self::SETTLE_TIME || self.last_draw.elapsed() >= Self::MAX_IDLE;
+        if must_draw {
+            self.last_draw = Instant::now();
+        }
+        must_draw
+    }
+}
@@ -0,0 +1,177 @@
+use imgui::{TableFlags, Ui};
+use crate::gui_runner::{PartialWorld, Telemetry};
+
+// PhaseTimeline keeps the named phases of the run, begun either by the robot (through the
+// Telemetry handle, if any) or by the user at the tick being shown, and draws them as a colored
+// strip spanning from the first recorded tick to the last one, like the WeatherTimeline. Every
+// world received is attributed to the phase it falls in, breaking down what the robot did (the
+// ticks it ran, the tiles it moved, the energy it spent and recharged and the contents it put in
+// its backpack) by phase. Ticks before the first phase count towards an unnamed one.
+// Since the GUI may not receive every tick, the statistics compare the worlds it did receive, so
+// the few ticks around a phase change may be attributed to the wrong phase.
+
+#[derive(Default, Clone)]
+struct PhaseStats {
+    ticks: usize,
+    tiles_moved: usize,
+    energy_spent: usize,
+    energy_recharged: usize,
+    contents_collected: usize,
+}
+
+struct Phase {
+    first_tick: usize,
+    name: String,
+}
+
+pub struct PhaseTimeline {
+    telemetry: Option<Telemetry>,
+    telemetry_cursor: usize,
+    phases: Vec<Phase>, // sorted by first tick
+    names: Vec<String>, // in order of appearance, which picks their color
+    stats: Vec<PhaseStats>, // by name, the last one for the ticks before the first phase
+    last_world: Option<(usize, [u32; 2], usize, usize)>, // tick, robot position, energy, contents in the backpack
+    last_tick: usize,
+    new_phase_name: String,
+}
+impl PhaseTimeline {
+    const STRIP_HEIGHT: f32 = 10.0;
+    const COLORS: [[f32; 4]; 8] = [
+        [0.9, 0.35, 0.3, 1.0],
+        [0.3, 0.7, 0.35, 1.0],
+        [0.3, 0.5, 0.9, 1.0],
+        [0.95, 0.75, 0.25, 1.0],
+        [0.65, 0.4, 0.85, 1.0],
+        [0.3, 0.8, 0.8, 1.0],
+        [0.9, 0.5, 0.75, 1.0],
+        [0.6, 0.6, 0.6, 1.0],
+    ];
+    const NO_PHASE_COLOR: [f32; 4] = [0.25, 0.25, 0.25, 1.0];
+
+    pub fn new(telemetry: Option<Telemetry>) -> Self {
+        Self {
+            telemetry,
+            telemetry_cursor: 0,
+            phases: vec![],
+            names: vec![],
+            stats: vec![PhaseStats::default()],
+            last_world: None,
+            last_tick: 0,
+            new_phase_name: String::new(),
+        }
+    }
+
+    fn begin_phase(&mut self, first_tick: usize, name: String) {
+        if !self.names.contains(&name) {
+            self.names.push(name.clone());
+            self.stats.insert(self.names.len() - 1, PhaseStats::default());
+        }
+        let i = self.phases.partition_point(|phase| phase.first_tick <= first_tick);
+        self.phases.insert(i, Phase { first_tick, name });
+    }
+
+    // index of the stats of the phase at the given tick
+    fn phase_at(&self, tick: usize) -> usize {
+        let i = self.phases.partition_point(|phase| phase.first_tick <= tick);
+        match i {
+            0 => self.names.len(),
+            _ => self.names.iter().position(|name| *name == self.phases[i - 1].name).unwrap_or(self.names.len()),
+        }
+    }
+
+    // must be called with every new world
+    pub fn record(&mut self, world: &PartialWorld) {
+        let new_phases = self.telemetry.as_ref().map(|telemetry| telemetry.read_new_phases(&mut self.telemetry_cursor)).unwrap_or_default();
+        for (first_tick, name) in new_phases {
+            self.begin_phase(first_tick, name);
+        }
+
+        let position = [world.robot_position.x, world.robot_position.y];
+        let contents: usize = world.backpack.values().sum();
+        // stepping back or seeking in a replay doesn't count
+        if let Some((tick, last_position, energy, last_contents)) = self.last_world.filter(|(tick, ..)| *tick < world.tick) {
+            let phase = self.phase_at(world.tick);
+            let stats = &mut self.stats[phase];
+            stats.ticks += world.tick - tick;
+            stats.tiles_moved += last_position[0].abs_diff(position[0]).max(last_position[1].abs_diff(position[1])) as usize;
+            stats.energy_spent += energy.saturating_sub(world.energy);
+            stats.energy_recharged += world.energy.saturating_sub(energy);
+            stats.contents_collected += contents.saturating_sub(last_contents);
+        }
+        self.last_world = Some((world.tick, position, world.energy, contents));
+        self.last_tick = self.last_tick.max(world.tick);
+    }
+
+    fn color(&self, name: &str) -> [f32; 4] {
+        match self.names.iter().position(|n| n == name) {
+            Some(i) => Self::COLORS[i % Self::COLORS.len()],
+            None => Self::NO_PHASE_COLOR,
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, tick: usize) {
+        let Some(_node) = ui.tree_node("Phases") else { return };
+
+        ui.set_next_item_width(140.0);
+        ui.input_text("##new_phase_name", &mut self.new_phase_name).hint("phase name").build();
+        ui.same_line();
+        ui.disabled(self.new_phase_name.is_empty(), || {
+            if ui.button(format!("Begin at tick {tick}")) {
+                let name = std::mem::take(&mut self.new_phase_name);
+                self.begin_phase(tick, name);
+            }
+        });
+
+        if self.phases.is_empty() {
+            ui.text_disabled("(no phases)");
+            return;
+        }
+
+        // strip
+        let first_tick = self.phases[0].first_tick.min(self.last_tick);
+        let span = (self.last_tick - first_tick + 1) as f32;
+        let width = ui.content_region_avail()[0].max(1.0);
+        let origin = ui.cursor_screen_pos();
+        let tick_to_x = |tick: usize| origin[0] + (tick.max(first_tick) - first_tick) as f32 / span * width;
+        {
+            let draw_list = ui.get_window_draw_list();
+            for (i, phase) in self.phases.iter().enumerate() {
+                let end = self.phases.get(i + 1).map_or(self.last_tick + 1, |next| next.first_tick);
+                let x0 = tick_to_x(phase.first_tick);
+                let x1 = tick_to_x(end).max(x0 + 1.0);
+                draw_list.add_rect([x0, origin[1]], [x1, origin[1] + Self::STRIP_HEIGHT], self.color(&phase.name)).filled(true).build();
+            }
+        }
+        ui.invisible_button("phase timeline", [width, Self::STRIP_HEIGHT]);
+        if ui.is_item_hovered() {
+            let mouse_x = ui.io().mouse_pos[0];
+            let hovered_tick = first_tick + ((mouse_x - origin[0]) / width * span).max(0.0) as usize;
+            let i = self.phases.partition_point(|phase| phase.first_tick <= hovered_tick);
+            if i > 0 {
+                ui.tooltip_text(format!("tick {hovered_tick}: {}", self.phases[i - 1].name));
+            }
+        }
+
+        // statistics
+        let flags = TableFlags::BORDERS | TableFlags::ROW_BG | TableFlags::SIZING_FIXED_FIT;
+        if let Some(_table) = ui.begin_table_with_flags("phase stats", 6, flags) {
+            for header in ["phase", "ticks", "moved", "spent", "recharged", "collected"] {
+                ui.table_setup_column(header);
+            }
+            ui.table_headers_row();
+            let rows = self.names.iter().map(String::as_str).chain(["(none)"]);
+            for (name, stats) in rows.zip(self.stats.iter()) {
+                if stats.ticks == 0 {
+                    continue;
+                }
+                ui.table_next_row();
+                ui.table_next_column();
+                ui.text_colored(self.color(name), name);
+                for value in [stats.ticks, stats.tiles_moved, stats.energy_spent, stats.energy_recharged, stats.contents_collected] {
+                    ui.table_next_column();
+                    ui.text(value.to_string());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use nalgebra_glm as glm;
+use glm::{Mat4, UVec2, Vec3, vec3, vec4};
+use robotics_lib::world::tile::Tile;
+use super::{compute_mvp, world_mesh};
+
+// picking contains the conversions between world space and screen space: project_to_screen maps a
+// point in world (mesh) space to imgui screen coordinates using the mvp matrix, and pick_tile does
+// the opposite for tiles, casting a ray from the camera through a point of the screen and marching
+// along it until it goes below the elevation of a discovered tile. world_size_of_pixels is used
+// to keep objects at a constant size on screen regardless of their distance from the camera.
+
+pub fn project_to_screen(mvp: &Mat4, point: Vec3, display_size: [f32; 2]) -> Option<[f32; 2]> {
+    let clip = mvp * vec4(point.x, point.y, point.z, 1.0);
+    if clip.w <= 0.0 {
+        return None; // behind the camera
+    }
+    let ndc = clip.xyz() / clip.w;
+    if ndc.x.abs() > 1.0 || ndc.y.abs() > 1.0 {
+        return None;
+    }
+    Some([(ndc.x + 1.0) / 2.0 * display_size[0], (1.0 - ndc.y) / 2.0 * display_size[1]])
+}
+
+// returns the world space size which spans the given number of pixels at distance from the camera
+pub fn world_size_of_pixels(distance: f32, pixels: f32, viewport_height: f32) -> f32 {
+    2.0 * distance * (compute_mvp::FOV / 2.0).tan() * pixels / viewport_height
+}
+
+// returns the origin and normalized direction of the ray which goes through screen_pos
+pub fn screen_ray(mvp: &Mat4, screen_pos: [f32; 2], display_size: [f32; 2]) -> Option<(Vec3, Vec3)> {
+    let inverse = mvp.try_inverse()?;
+    let ndc_x = screen_pos[0] / display_size[0] * 2.0 - 1.0;
+    let ndc_y = 1.0 - screen_pos[1] / display_size[1] * 2.0;
+
+    let unproject = |ndc_z: f32| {
+        let p = inverse * vec4(ndc_x, ndc_y, ndc_z, 1.0);
+        p.xyz() / p.w
+    };
+    let near = unproject(-1.0);
+    let far = unproject(1.0);
+    Some((near, (far - near).normalize()))
+}
+
+// returns the tile position under screen_pos, if it is a discovered tile
+pub fn pick_tile(mvp: &Mat4, screen_pos: [f32; 2], display_size: [f32; 2], world: &Vec<Vec<Option<Tile>>>) -> Option<UVec2> {
+    const STEP: f32 = 0.25;
+
+    let (origin, direction) = screen_ray(mvp, screen_pos, display_size)?;
+    let world_size = world.len() as f32;
+    let max_distance = world_size * 3.0 + 100.0;
+
+    let mut distance = 0.0;
+    while distance < max_distance {
+        let p = origin + direction * distance;
+        distance += STEP;
+
+        if p.x < 0.0 || p.z < 0.0 || p.x >= world_size || p.z >= world_size {
+            continue;
+        }
+        let tile_pos = UVec2::new(p.x as u32, p.z as u32);
+        if let Some(tile) = &world[tile_pos.x as usize][tile_pos.y as usize] {
+            if p.y <= world_mesh::elevation_to_mesh_space_y(tile.elevation as f32) {
+                return Some(tile_pos);
+            }
+        }
+    }
+    None
+}
+
+// the point at the center of the top face of a tile, in world space
+pub fn tile_anchor(tile_pos: UVec2, world: &Vec<Vec<Option<Tile>>>) -> Vec3 {
+    let elevation = world[tile_pos.x as usize][tile_pos.y as usize].as_ref().map(|t| t.elevation).unwrap_or(0);
+    vec3(tile_pos.x as f32 + 0.5, world_mesh::elevation_to_mesh_space_y(elevation as f32), tile_pos.y as f32 + 0.5)
+}
@@ -0,0 +1,85 @@
+use imgui::{Condition, MouseButton, Ui, WindowFlags};
+use nalgebra_glm::{Mat4, UVec2};
+use robotics_lib::world::tile::Tile;
+use super::{clipboard, picking};
+
+// PinnedPanels manages small floating panels attached to tiles: each panel shows live information
+// about its tile plus an editable note, and follows the tile as the camera moves (a line connects
+// it to the tile). Panels can be dragged away from their tile, in which case they keep following it
+// at the new offset. Panels whose tile is off screen are hidden.
+
+struct PinnedPanel {
+    id: usize,
+    tile: UVec2,
+    note: String,
+    offset: [f32; 2], // from the projected tile position to the top left corner of the panel
+    is_being_dragged: bool,
+}
+
+pub struct PinnedPanels {
+    panels: Vec<PinnedPanel>,
+    next_id: usize,
+}
+impl PinnedPanels {
+    pub fn new() -> Self {
+        Self { panels: vec![], next_id: 0 }
+    }
+
+    pub fn pin(&mut self, tile: UVec2) {
+        self.panels.push(PinnedPanel { id: self.next_id, tile, note: String::new(), offset: [30.0, -60.0], is_being_dragged: false });
+        self.next_id += 1;
+    }
+
+    pub fn draw(&mut self, ui: &Ui, mvp: &Mat4, world: &Vec<Vec<Option<Tile>>>) {
+        let display_size = ui.io().display_size;
+        self.panels.retain_mut(|panel| {
+            let anchor = match picking::project_to_screen(mvp, picking::tile_anchor(panel.tile, world), display_size) {
+                Some(anchor) => anchor,
+                None => return true,
+            };
+            let position = [anchor[0] + panel.offset[0], anchor[1] + panel.offset[1]];
+            let condition = if panel.is_being_dragged { Condition::Never } else { Condition::Always };
+
+            let mut open = true;
+            let mut window_pos = position;
+            ui.window(format!("Tile ({}, {})##pinned panel {}", panel.tile.x, panel.tile.y, panel.id))
+                .opened(&mut open)
+                .position(position, condition)
+                .flags(WindowFlags::ALWAYS_AUTO_RESIZE | WindowFlags::NO_COLLAPSE | WindowFlags::NO_SAVED_SETTINGS)
+                .build(|| {
+                    let tile = world[panel.tile.x as usize][panel.tile.y as usize].as_ref();
+                    match tile {
+                        Some(tile) => {
+                            ui.text(format!("{:?}", tile.tile_type));
+                            ui.same_line();
+                            clipboard::copy_button(ui, "tile", || clipboard::describe_tile(panel.tile, Some(tile)));
+                            ui.text(format!("Content: {:?}", tile.content));
+                            ui.text(format!("Elevation: {}", tile.elevation));
+                        }
+                        None => ui.text_disabled("(undiscovered)"),
+                    }
+                    ui.set_next_item_width(160.0);
+                    ui.input_text("##note", &mut panel.note).hint("note").build();
+
+                    window_pos = ui.window_pos();
+                    if ui.is_window_hovered() && ui.is_mouse_dragging(MouseButton::Left) {
+                        panel.is_being_dragged = true;
+                    }
+                });
+
+            if panel.is_being_dragged {
+                panel.offset = [window_pos[0] - anchor[0], window_pos[1] - anchor[1]];
+                if !ui.is_mouse_down(MouseButton::Left) {
+                    panel.is_being_dragged = false;
+                }
+            }
+
+            ui.get_background_draw_list()
+                .add_line(anchor, window_pos, [1.0, 1.0, 1.0, 0.6])
+                .thickness(1.5)
+                .build();
+
+            open
+        });
+    }
+}
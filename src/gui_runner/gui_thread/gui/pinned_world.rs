@@ -0,0 +1,164 @@
+use std::rc::Rc;
+use glium::{Display, Program, Surface};
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::index::PrimitiveType;
+use glium::texture::{DepthFormat, DepthRenderBuffer, Texture2d};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerBehavior};
+use imgui::{Condition, TextureId, Ui};
+use imgui_glium_renderer::{Renderer, Texture};
+use nalgebra_glm::Mat4;
+use crate::gui_runner::PartialWorld;
+use crate::gui_runner::worker_thread::tiles_to_refresh;
+use super::daylight::Daylight;
+use super::robot_model::RobotModel;
+use super::shaders;
+use super::skybox::{SkyMode, Skybox};
+use super::world_mesh::WorldMesh;
+
+// PinnedWorld keeps a copy of the world as it was when the user pinned it, with a WorldMesh of its
+// own, and shows it in a window next to the live world for before/after comparisons (e.g. around a
+// change of strategy in the middle of a run). The pinned world is rendered to a texture registered
+// in the imgui renderer (like the minimap) with the mvp of the main view, so that the two views
+// share the camera: moving it moves both. The texture has half the resolution (and so the aspect
+// ratio) of the window, and is recreated when the window is resized.
+// Since the pinned mesh starts empty, all the discovered tiles are refreshed when pinning.
+
+pub struct PinnedWorld {
+    pub open: bool,
+    pinned: Option<(PartialWorld, WorldMesh)>,
+    target: Option<(TextureId, Rc<Texture2d>, DepthRenderBuffer)>,
+}
+impl PinnedWorld {
+    pub fn new() -> Self {
+        Self { open: false, pinned: None, target: None }
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        self.pinned.is_some()
+    }
+
+    pub fn pin(&mut self, world: &PartialWorld, display: &Display) {
+        let mut world = world.clone();
+        let undiscovered = vec![vec![None; world.world.len()]; world.world.len()];
+        world.touched_tiles = None;
+        world.tiles_to_refresh = tiles_to_refresh(&mut Some(undiscovered), &world, 0, true);
+        let mesh = WorldMesh::new(display);
+        self.pinned = Some((world, mesh));
+        self.open = true;
+    }
+
+    fn unpin(&mut self) {
+        self.pinned = None;
+        self.open = false;
+    }
+
+    // renders the pinned world with the mvp of the main view (whose size is target_size), if the
+    // window is open, along with the sky if given its mode and view projection (see Skybox::draw).
+    // returns the number of triangles drawn
+    pub fn render(&mut self, display: &Display, renderer: &mut Renderer, target_size: (u32, u32), mvp: &Mat4,
+                  programs: (&Program, &Program), logarithmic_depth: bool, log_depth_coef: f32,
+                  day_night_lighting: bool, sky: Option<(&mut Skybox, SkyMode, &Mat4)>, liquids_time: f32) -> usize {
+        if !self.open {
+            return 0;
+        }
+        let Some((world, mesh)) = &mut self.pinned else { return 0 };
+        let size = ((target_size.0 / 2).max(1), (target_size.1 / 2).max(1));
+        if !matches!(&self.target, Some((_, texture, _)) if texture.dimensions() == size) {
+            let (Ok(texture), Ok(depth_buffer)) = (
+                Texture2d::empty(display, size.0, size.1),
+                DepthRenderBuffer::new(display, DepthFormat::I24, size.0, size.1),
+            ) else { return 0 };
+            let texture = Rc::new(texture);
+            let imgui_texture = Texture {
+                texture: texture.clone(),
+                sampler: SamplerBehavior {
+                    magnify_filter: MagnifySamplerFilter::Linear,
+                    minify_filter: MinifySamplerFilter::Linear,
+                    ..Default::default()
+                },
+            };
+            let id = match &self.target {
+                Some((id, _, _)) => {
+                    renderer.textures().replace(*id, imgui_texture);
+                    *id
+                }
+                None => renderer.textures().insert(imgui_texture),
+            };
+            self.target = Some((id, texture, depth_buffer));
+        }
+        let Some((_, texture, depth_buffer)) = &self.target else { return 0 };
+
+        let robot_position = RobotModel::position_of(world.robot_position, &world.world);
+        mesh.update(world, display, Some((robot_position, 0.0)), 1.0);
+        world.tiles_to_refresh.clear();
+
+        let Ok(mut frame) = SimpleFrameBuffer::with_depth_buffer(display, texture.as_ref(), depth_buffer) else { return 0 };
+        let daylight = if day_night_lighting { Daylight::from_env_cond(&world.env_cond) } else { Daylight::NEUTRAL };
+        frame.clear_color_and_depth(daylight.sky_color, 1.0);
+
+        let (shader_program, liquid_shader_program) = programs;
+        let uniforms = shaders::uniforms(mvp, logarithmic_depth, log_depth_coef, &daylight, true);
+        let unlit_uniforms = shaders::uniforms(mvp, logarithmic_depth, log_depth_coef, &daylight, false);
+        let liquid_uniforms = shaders::liquid_uniforms(mvp, logarithmic_depth, log_depth_coef, &daylight, liquids_time);
+        let draw_params = glium::DrawParameters {
+            depth: glium::Depth {
+                test: glium::draw_parameters::DepthTest::IfLess,
+                write: true,
+                .. Default::default()
+            },
+            .. Default::default()
+        };
+
+        let mut triangles = mesh.misc_vbo.len() / 3;
+        frame.draw(&mesh.misc_vbo, &glium::index::NoIndices(PrimitiveType::TrianglesList),
+                   shader_program, &unlit_uniforms, &draw_params).unwrap();
+        for (_chunk_pos, chunk) in mesh.chunks() {
+            frame.draw(&chunk.vbo, &glium::index::NoIndices(PrimitiveType::TrianglesList),
+                       shader_program, &uniforms, &draw_params).unwrap();
+            triangles += chunk.vbo.len() / 3;
+            if let Some(liquid_vbo) = &chunk.liquid_vbo {
+                frame.draw(liquid_vbo, &glium::index::NoIndices(PrimitiveType::TrianglesList),
+                           liquid_shader_program, &liquid_uniforms, &draw_params).unwrap();
+                triangles += liquid_vbo.len() / 3;
+            }
+        }
+        if let Some((skybox, mode, view_projection)) = sky {
+            triangles += skybox.draw(&mut frame, display, view_projection, mode, &world.env_cond);
+        }
+        triangles
+    }
+
+    pub fn draw(&mut self, ui: &Ui, live_tick: usize) {
+        if !self.open {
+            return;
+        }
+        let Some((world, _)) = &self.pinned else { return };
+        let pinned_tick = world.tick;
+        let mut open = self.open;
+        let mut unpin = false;
+        ui.window(format!("Pinned tick {pinned_tick}###pinned world"))
+            .opened(&mut open)
+            .size([480.0, 320.0], Condition::FirstUseEver)
+            .build(|| {
+                ui.text(format!("{} ticks before the live world", live_tick.saturating_sub(pinned_tick)));
+                ui.same_line();
+                if ui.small_button("Unpin") {
+                    unpin = true;
+                }
+                let Some((id, texture, _)) = &self.target else { return };
+                // keep the aspect ratio of the main view, which shares the camera
+                let (width, height) = texture.dimensions();
+                let available = ui.content_region_avail();
+                let scale = (available[0] / width as f32).min(available[1] / height as f32).max(0.01);
+                // the framebuffer's rows go from the bottom up
+                imgui::Image::new(*id, [width as f32 * scale, height as f32 * scale])
+                    .uv0([0.0, 1.0])
+                    .uv1([1.0, 0.0])
+                    .build(ui);
+            });
+        self.open = open;
+        if unpin {
+            self.unpin();
+        }
+    }
+}
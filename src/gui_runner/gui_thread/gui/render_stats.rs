@@ -0,0 +1,96 @@
+use glium::Display;
+use imgui::{DrawCmd, DrawData, Ui};
+use imgui_glium_renderer::Renderer;
+use super::charts::{Chart, DownsampledHistory};
+
+// RenderStats counts the draw calls, triangles, bytes uploaded to the gpu and world mesh chunks
+// rebuilt, simplified and culled during each frame (including the draw calls issued by imgui), and keeps a short history
+// of the uploaded bytes to chart. Since it is drawn while the frame is being built, the numbers
+// shown are those of the previous frame.
+
+#[derive(Clone, Copy, Default)]
+struct FrameStats {
+    draw_calls: usize,
+    triangles: usize,
+    bytes_uploaded: usize,
+    chunks_rebuilt: usize,
+    chunks_drawn: usize,
+    chunks_simplified: usize,
+    chunks_total: usize,
+}
+
+pub struct RenderStats {
+    current: FrameStats,
+    last: FrameStats,
+    uploads_history: DownsampledHistory,
+    uploads_chart: Chart,
+}
+impl RenderStats {
+    pub fn new() -> Self {
+        Self {
+            current: FrameStats::default(),
+            last: FrameStats::default(),
+            uploads_history: DownsampledHistory::with_window(256, 600),
+            uploads_chart: Chart::new(48, [220, 160, 80]),
+        }
+    }
+
+    pub fn record_draw(&mut self, triangles: usize) {
+        self.current.draw_calls += 1;
+        self.current.triangles += triangles;
+    }
+
+    pub fn record_upload(&mut self, bytes: usize) {
+        self.current.bytes_uploaded += bytes;
+    }
+
+    pub fn record_chunks_rebuilt(&mut self, chunks: usize) {
+        self.current.chunks_rebuilt += chunks;
+    }
+
+    pub fn record_chunks_drawn(&mut self, drawn: usize, simplified: usize, total: usize) {
+        self.current.chunks_drawn += drawn;
+        self.current.chunks_simplified += simplified;
+        self.current.chunks_total += total;
+    }
+
+    pub fn record_imgui(&mut self, draw_data: &DrawData) {
+        for draw_list in draw_data.draw_lists() {
+            for command in draw_list.commands() {
+                if let DrawCmd::Elements { count, .. } = command {
+                    self.current.draw_calls += 1;
+                    self.current.triangles += count / 3;
+                }
+            }
+        }
+    }
+
+    pub fn end_frame(&mut self) {
+        self.last = self.current;
+        self.current = FrameStats::default();
+        self.uploads_history.push(self.last.bytes_uploaded as f32 / 1024.0);
+    }
+
+    pub fn update_chart(&mut self, display: &Display, renderer: &mut Renderer) {
+        self.uploads_chart.update(&self.uploads_history, display, renderer);
+    }
+
+    // the statistics of the last frame, one per line
+    pub fn summary(&self) -> String {
+        [
+            format!("Draw calls: {}", self.last.draw_calls),
+            format!("Triangles: {}", self.last.triangles),
+            format!("Uploaded: {:.1} KiB", self.last.bytes_uploaded as f32 / 1024.0),
+            format!("Chunks rebuilt: {}", self.last.chunks_rebuilt),
+            format!("Chunks drawn: {} / {} ({} simplified)", self.last.chunks_drawn, self.last.chunks_total, self.last.chunks_simplified),
+        ].join("\n")
+    }
+
+    pub fn draw(&self, ui: &Ui) {
+        ui.text(self.summary());
+        if let Some((_, max)) = self.uploads_history.range() {
+            ui.text_disabled(format!("uploads over the last 600 frames (peak {max:.1} KiB)"));
+        }
+        self.uploads_chart.draw(ui, [ui.content_region_avail()[0], 48.0]);
+    }
+}
@@ -0,0 +1,48 @@
+use imgui::Ui;
+use crate::gui_runner::snapshot_history::SnapshotHistory;
+use super::detached_view::ViewRequest;
+
+// Rewind draws a slider over the ticks of a live run kept by the worker thread in a
+// SnapshotHistory, which shows the world (map, robot, energy and backpack) as it was at an earlier
+// tick, while the run goes on in the background. It is view-only: the run isn't affected, and
+// returning to it shows its latest world. The oldest ticks are dropped when the history exceeds
+// its memory budget, and the ticks still being compressed can't be rewound to yet.
+
+pub struct Rewind {
+    history: SnapshotHistory,
+    tick: Option<usize>, // Some while rewound
+}
+impl Rewind {
+    pub fn new(history: SnapshotHistory) -> Self {
+        Self { history, tick: None }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, live_tick: usize, is_detached: bool) -> Option<ViewRequest> {
+        if !is_detached {
+            self.tick = None; // something else returned to the run
+        }
+        let Some((first_tick, last_tick)) = self.history.compressed_ticks() else {
+            ui.text_disabled("(no history to rewind yet)");
+            return None;
+        };
+        let mut request = None;
+        let mut tick = self.tick.unwrap_or(live_tick).clamp(first_tick, last_tick);
+        if ui.slider("rewind", first_tick, last_tick, &mut tick) {
+            if let Some(snapshot) = self.history.get(tick) {
+                self.tick = Some(tick);
+                request = Some(ViewRequest::Show(snapshot.to_partial_world()));
+            }
+        }
+        if self.tick.is_some() {
+            if ui.button("Back to the live run") {
+                self.tick = None;
+                request = Some(ViewRequest::ReturnToRun);
+            }
+            ui.same_line();
+            ui.text_disabled(format!("(the run is at tick {live_tick})"));
+        }
+        let (bytes, budget) = self.history.memory_usage();
+        ui.text_disabled(format!("history: {:.1} of {:.0} MiB", bytes as f32 / (1024.0 * 1024.0), budget as f32 / (1024.0 * 1024.0)));
+        request
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::mem::take;
+use glium::Display;
+use imgui::{SliderFlags, Ui};
+use imgui_glium_renderer::Renderer;
+use robotics_lib::world::tile::Content;
+use crate::gui_runner::PartialWorld;
+use super::charts::{Chart, DownsampledHistory};
+
+// RobotHistory keeps the robot's energy and the quantity of every content in its backpack over the
+// last `ticks` ticks, each in a DownsampledHistory of one sample per tick (when the game is too fast
+// for every tick to reach the GUI, the ticks in between get the values of the world which follows
+// them), and charts them in the Robot panel. Changing the span restarts the history. Contents
+// which were never in the backpack during the window aren't charted.
+
+pub struct RobotHistory {
+    pub ticks: usize,
+    last_tick: Option<usize>,
+    energy: Series,
+    contents: HashMap<Content, Series>,
+    shown: bool, // whether the charts were drawn since the last update
+}
+// the history of a value, with the chart drawing it
+struct Series {
+    history: DownsampledHistory,
+    chart: Chart,
+    current: usize,
+}
+impl Series {
+    fn new(ticks: usize, color: [u8; 3]) -> Self {
+        Self { history: RobotHistory::history(ticks), chart: Chart::new(RobotHistory::PLOT_HEIGHT as u32, color), current: 0 }
+    }
+
+    fn restart(&mut self, ticks: usize) {
+        self.history = RobotHistory::history(ticks);
+        self.chart.invalidate();
+    }
+}
+impl RobotHistory {
+    const MAX_ENERGY: f32 = 1000.0;
+    const PLOT_HEIGHT: f32 = 40.0;
+    const BUCKETS: usize = 256;
+    const ENERGY_COLOR: [u8; 3] = [120, 220, 120];
+    const CONTENT_COLOR: [u8; 3] = [120, 200, 255];
+
+    pub fn new(ticks: usize) -> Self {
+        Self { ticks, last_tick: None, energy: Series::new(ticks, Self::ENERGY_COLOR), contents: HashMap::new(), shown: false }
+    }
+
+    fn history(ticks: usize) -> DownsampledHistory {
+        DownsampledHistory::with_window(Self::BUCKETS, ticks)
+    }
+
+    // must be called with every new world
+    pub fn record(&mut self, world: &PartialWorld) {
+        // going back in time (stepping back or seeking in a replay) restarts the history
+        if self.last_tick.is_some_and(|last_tick| world.tick < last_tick) {
+            self.restart();
+        }
+        let ticks = self.last_tick.map_or(1, |last_tick| (world.tick - last_tick).min(self.ticks));
+        self.last_tick = Some(world.tick);
+
+        for content in world.backpack.iter().filter(|(_, n)| **n != 0).map(|(content, _)| content) {
+            if !self.contents.contains_key(content) {
+                // starting with zeros, so that it lines up with the other charts
+                let mut series = Series::new(self.ticks, Self::CONTENT_COLOR);
+                for _ in 0..self.energy.history.total_samples().min(self.ticks as u64) {
+                    series.history.push(0.0);
+                }
+                self.contents.insert(content.clone(), series);
+            }
+        }
+        self.energy.current = world.energy;
+        for (content, series) in self.contents.iter_mut() {
+            series.current = world.backpack.get(content).copied().unwrap_or(0);
+        }
+        for _ in 0..ticks {
+            self.energy.history.push(self.energy.current as f32);
+            for series in self.contents.values_mut() {
+                series.history.push(series.current as f32);
+            }
+        }
+    }
+
+    // the contents are kept (with their charts, whose textures are registered in the renderer)
+    fn restart(&mut self) {
+        self.last_tick = None;
+        self.energy.restart(self.ticks);
+        for series in self.contents.values_mut() {
+            series.restart(self.ticks);
+        }
+    }
+
+    // must be called once per frame, before draw
+    pub fn update_charts(&mut self, display: &Display, renderer: &mut Renderer) {
+        if !take(&mut self.shown) {
+            return;
+        }
+        self.energy.chart.set_range(Some((0.0, Self::MAX_ENERGY)));
+        self.energy.chart.update(&self.energy.history, display, renderer);
+        for series in self.contents.values_mut() {
+            let max = series.history.range().map_or(0.0, |(_, max)| max);
+            series.chart.set_range(Some((0.0, max.max(1.0))));
+            series.chart.update(&series.history, display, renderer);
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui) {
+        let Some(_node) = ui.tree_node("History") else { return };
+        self.shown = true;
+        if ui.slider_config("span (ticks)", 10, 100_000)
+            .flags(SliderFlags::LOGARITHMIC)
+            .build(&mut self.ticks) {
+            self.restart();
+        }
+        let width = ui.content_region_avail()[0];
+
+        ui.text_disabled(format!("energy: {}", self.energy.current));
+        self.energy.chart.draw(ui, [width, Self::PLOT_HEIGHT]);
+
+        let mut contents: Vec<(&Content, &Series)> = self.contents.iter()
+            .filter(|(_, series)| series.history.range().is_some_and(|(_, max)| max > 0.0))
+            .collect();
+        contents.sort_by_key(|(content, _)| content.to_string());
+        for (content, series) in contents {
+            ui.text_disabled(format!("{content}: {}", series.current));
+            series.chart.draw(ui, [width, Self::PLOT_HEIGHT]);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::time::{Duration, Instant};
+use nalgebra_glm::{UVec2, Vec3, vec3};
+use robotics_lib::world::tile::Tile;
+use super::picking;
+use crate::gui_runner::PartialWorld;
+
+// RobotModel keeps track of where the robot model should be drawn: instead of jumping from tile to
+// tile, the model glides from its previous position to the new one, over either a fixed duration
+// or the time which passed between the last two ticks (so that at any speed it reaches a tile just
+// as the robot moves on), and turns to face the direction it's moving in. The robot marker and the
+// camera following the robot move along with the model. Jumps longer than a tile (teleports) and worlds
+// going back in time (replay seeks) are not interpolated. The pose is only computed here: the mesh
+// itself is part of the WorldMesh.
+
+pub struct RobotModel {
+    pub show: bool,
+    pub smooth: bool,
+    pub match_tick_rate: bool,
+    pub duration: f32, // in seconds, used unless match_tick_rate
+    from: Vec3,
+    to: Vec3,
+    yaw: f32, // rotation around the y axis, 0 when facing +x
+    moved_at: Instant,
+    last_tick: (usize, Instant), // last tick received and when
+    tick_interval: Duration,
+}
+impl RobotModel {
+    const MIN_INTERPOLATION: Duration = Duration::from_millis(30);
+    const MAX_INTERPOLATION: Duration = Duration::from_millis(400);
+
+    pub fn new(world: &PartialWorld) -> Self {
+        let position = Self::position_of(world.robot_position, &world.world);
+        Self {
+            show: true, smooth: true, match_tick_rate: true, duration: 0.2,
+            from: position, to: position, yaw: 0.0,
+            moved_at: Instant::now(),
+            last_tick: (world.tick, Instant::now()),
+            tick_interval: Self::MAX_INTERPOLATION,
+        }
+    }
+
+    // position of the model standing on the given tile, in mesh space
+    pub fn position_of(tile: UVec2, world: &Vec<Vec<Option<Tile>>>) -> Vec3 {
+        picking::tile_anchor(tile, world) + vec3(0.0, 0.5, 0.0)
+    }
+
+    // must be called with every world received
+    pub fn record(&mut self, world: &PartialWorld) {
+        let now = Instant::now();
+        let (last_tick, last_tick_at) = self.last_tick;
+        if last_tick < world.tick {
+            self.tick_interval = now.duration_since(last_tick_at).clamp(Self::MIN_INTERPOLATION, Self::MAX_INTERPOLATION);
+        }
+        let went_back = last_tick > world.tick;
+        self.last_tick = (world.tick, now);
+
+        let to = Self::position_of(world.robot_position, &world.world);
+        if to == self.to {
+            return;
+        }
+        let step = to - self.to;
+        if step.x != 0.0 || step.z != 0.0 {
+            self.yaw = f32::atan2(-step.z, step.x);
+        }
+        let is_jump = step.x.abs() > 1.0 || step.z.abs() > 1.0;
+        self.from = if went_back || is_jump { to } else { self.position() };
+        self.to = to;
+        self.moved_at = now;
+    }
+
+    pub fn position(&self) -> Vec3 {
+        if !self.smooth {
+            return self.to;
+        }
+        let duration = if self.match_tick_rate { self.tick_interval.as_secs_f32() } else { self.duration };
+        let t = (self.moved_at.elapsed().as_secs_f32() / duration.max(f32::EPSILON)).min(1.0);
+        let t = t * t * (3.0 - 2.0 * t); // ease in and out
+        self.from + (self.to - self.from) * t
+    }
+
+    // position and yaw of the model, or None if it's hidden
+    pub fn pose(&self) -> Option<(Vec3, f32)> {
+        self.show.then(|| (self.position(), self.yaw))
+    }
+}
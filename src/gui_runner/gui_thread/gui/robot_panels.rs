@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use imgui::{TableFlags, TreeNodeFlags, Ui};
+use robotics_lib::world::tile::Content;
+use crate::gui_runner::PartialWorld;
+use super::{clipboard, content_icons};
+
+// Every robot shown in the GUI (the live one and, when a replay is loaded, its ghost) gets its own
+// collapsible panel in the main window; draw_robot_status draws the part they have in common (the
+// position, the energy and the backpack), under an id so that the panels don't share their state.
+// The backpack lists every content with its icon (see ContentIcons) and amount, how many items of
+// it were gained or lost in the last tick (which BackpackDeltas keeps for every robot, when the
+// world shown is the one following the previous tick) and how full the backpack is, when its
+// capacity is known.
+// FollowTarget is the robot the follow camera is following, which the user can cycle through.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FollowTarget {
+    Robot,
+    Ghost,
+}
+impl FollowTarget {
+    // the next robot to follow; the ghost is skipped when it isn't shown
+    pub fn next(self, ghost_available: bool) -> Self {
+        match self {
+            FollowTarget::Robot if ghost_available => FollowTarget::Ghost,
+            _ => FollowTarget::Robot,
+        }
+    }
+}
+
+// BackpackDeltas keeps how the backpack of every robot changed in the last tick
+pub struct BackpackDeltas {
+    tick: usize,
+    backpacks: Vec<HashMap<Content, usize>>, // of the first robot and then of the others, at tick
+    deltas: Vec<HashMap<Content, isize>>, // as above, empty when tick doesn't follow the previous world
+}
+impl BackpackDeltas {
+    const GAIN_COLOR: [f32; 4] = [0.4, 0.9, 0.4, 1.0];
+    const LOSS_COLOR: [f32; 4] = [1.0, 0.4, 0.4, 1.0];
+
+    pub fn new() -> Self {
+        Self { tick: 0, backpacks: vec![], deltas: vec![] }
+    }
+
+    // must be called with every new world
+    pub fn record(&mut self, world: &PartialWorld) {
+        let backpacks: Vec<HashMap<Content, usize>> = std::iter::once(&world.backpack)
+            .chain(world.other_robots.iter().map(|robot| &robot.backpack))
+            .cloned()
+            .collect();
+        let follows = world.tick == self.tick + 1 && backpacks.len() == self.backpacks.len();
+        self.deltas = match follows {
+            true => backpacks.iter().zip(&self.backpacks).map(|(backpack, previous)| backpack_delta(previous, backpack)).collect(),
+            false => vec![],
+        };
+        self.tick = world.tick;
+        self.backpacks = backpacks;
+    }
+
+    // the changes of the backpack of the robot (0 for the first one) in the tick of the world shown
+    pub fn of_robot(&self, robot: usize, tick: usize) -> Option<&HashMap<Content, isize>> {
+        self.deltas.get(robot).filter(|_| tick == self.tick)
+    }
+}
+
+// how many items of every content the backpack gained (or lost, when negative) since previous
+pub fn backpack_delta(previous: &HashMap<Content, usize>, backpack: &HashMap<Content, usize>) -> HashMap<Content, isize> {
+    let amount = |backpack: &HashMap<Content, usize>, content: &Content| backpack.get(content).copied().unwrap_or(0) as isize;
+    previous.keys().chain(backpack.keys())
+        .map(|content| (content.clone(), amount(backpack, content) - amount(previous, content)))
+        .filter(|(_, delta)| *delta != 0)
+        .collect()
+}
+
+pub fn draw_robot_status(ui: &Ui, id: &str, position: [u32; 2], energy: usize, backpack: &HashMap<Content, usize>, backpack_size: Option<usize>, deltas: Option<&HashMap<Content, isize>>) {
+    let _id = ui.push_id(id);
+    ui.text(format!("Position: {position:?}"));
+    ui.same_line();
+    clipboard::copy_button(ui, "position", || format!("{}, {}", position[0], position[1]));
+
+    ui.text_wrapped("Energy:");
+    ui.same_line();
+    imgui::ProgressBar::new(energy as f32 / 1000.0)
+        .overlay_text(format!("{energy}"))
+        .build(ui);
+
+    if ui.collapsing_header("Backpack:", TreeNodeFlags::DEFAULT_OPEN) {
+        ui.indent();
+
+        let total: usize = backpack.values().sum();
+        match backpack_size {
+            Some(size) => {
+                imgui::ProgressBar::new(if size == 0 { 1.0 } else { total as f32 / size as f32 })
+                    .overlay_text(format!("{total} / {size}"))
+                    .build(ui);
+            }
+            None => ui.text_disabled(format!("{total} items (capacity unknown)")),
+        }
+
+        let delta = |content: &Content| deltas.and_then(|deltas| deltas.get(content)).copied().unwrap_or(0);
+        // the contents just emptied are listed too, with their loss
+        let mut contents: Vec<(&Content, usize)> = backpack.iter().map(|(content, amount)| (content, *amount))
+            .chain(deltas.into_iter().flatten().filter(|(content, _)| !backpack.contains_key(*content)).map(|(content, _)| (content, 0)))
+            .filter(|(content, amount)| *amount != 0 || delta(content) != 0)
+            .collect();
+        contents.sort_by_key(|(content, _)| content.to_string());
+
+        if contents.is_empty() {
+            ui.text_wrapped("(empty)");
+        } else if let Some(_table) = ui.begin_table_with_flags("backpack", 3, TableFlags::SIZING_FIXED_FIT) {
+            for (content, amount) in contents {
+                ui.table_next_row();
+                ui.table_next_column();
+                content_icons::draw_inline_icon(ui, content);
+                ui.same_line();
+                ui.text(content.to_string());
+                ui.table_next_column();
+                ui.text(format!("x{amount}"));
+                ui.table_next_column();
+                match delta(content) {
+                    0 => {}
+                    gain if gain > 0 => ui.text_colored(BackpackDeltas::GAIN_COLOR, format!("+{gain}")),
+                    loss => ui.text_colored(BackpackDeltas::LOSS_COLOR, loss.to_string()),
+                }
+            }
+        }
+
+        ui.unindent();
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use imgui::{Condition, ImColor32, TableFlags, Ui};
+use robotics_lib::world::tile::Content;
+use crate::replay;
+
+// RunComparison is an imgui window comparing several runs side by side, e.g. those of a sweep of
+// the parameters of an AI. Every run is loaded on a background thread from either:
+// - a replay, from which the series below are computed at every tick;
+// - a CSV file with a "tick" column and a column per series (the time series exported by the
+//   Telemetry panel have this shape, and so can the files written by other tools).
+// The series known by name are "explored" (the percentage of the world discovered), "coins" (the
+// coins put in the backpack so far) and "energy_spent" (the energy consumed so far); the table
+// compares the ticks each run took to explore a chosen percentage of the world, the coins it
+// collected and how efficiently it spent its energy, while the chart overlays any series of the
+// runs, each run in its own color. Series with other names can only be charted.
+
+struct ComparedRun {
+    path: PathBuf,
+    series: BTreeMap<String, Vec<(usize, f64)>>, // name, (tick, value) in tick order
+    show: bool,
+}
+impl ComparedRun {
+    fn name(&self) -> String {
+        self.path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+    }
+
+    fn last(&self, series: &str) -> Option<f64> {
+        self.series.get(series)?.last().map(|(_, value)| *value)
+    }
+
+    fn last_tick(&self) -> usize {
+        self.series.values().filter_map(|samples| samples.last()).map(|(tick, _)| *tick).max().unwrap_or(0)
+    }
+
+    // the first tick at which the given percentage of the world was explored
+    fn ticks_to_explore(&self, percentage: f32) -> Option<usize> {
+        self.series.get("explored")?.iter().find(|(_, explored)| *explored >= percentage as f64).map(|(tick, _)| *tick)
+    }
+
+    fn from_replay(path: PathBuf) -> Result<Self, String> {
+        let mut explored = vec![];
+        let mut coins = vec![];
+        let mut energy_spent = vec![];
+        let mut last: Option<(usize, usize)> = None; // energy, coins in the backpack
+        let (mut spent, mut collected) = (0, 0);
+        replay::load_streaming(&path, |snapshot| {
+            let tiles = snapshot.world.len() * snapshot.world.len();
+            let discovered = snapshot.world.iter().flatten().filter(|tile| tile.is_some()).count();
+            explored.push((snapshot.tick, 100.0 * discovered as f64 / tiles.max(1) as f64));
+
+            let coins_in_backpack: usize = snapshot.backpack.iter()
+                .filter(|(content, _)| matches!(content, Content::Coin(_)))
+                .map(|(_, quantity)| *quantity)
+                .sum();
+            if let Some((energy, last_coins)) = last {
+                spent += energy.saturating_sub(snapshot.energy);
+                collected += coins_in_backpack.saturating_sub(last_coins);
+            }
+            last = Some((snapshot.energy, coins_in_backpack));
+            coins.push((snapshot.tick, collected as f64));
+            energy_spent.push((snapshot.tick, spent as f64));
+        }).map_err(|e| e.to_string())?;
+
+        let series = BTreeMap::from([
+            ("explored".to_string(), explored),
+            ("coins".to_string(), coins),
+            ("energy_spent".to_string(), energy_spent),
+        ]);
+        Ok(Self { path, series, show: true })
+    }
+
+    fn from_csv(path: PathBuf) -> Result<Self, String> {
+        let data = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let mut lines = data.lines().filter(|line| !line.trim().is_empty());
+        let header: Vec<&str> = lines.next().ok_or("the file is empty")?.split(',').map(str::trim).collect();
+        let tick_column = header.iter().position(|column| *column == "tick").ok_or("there is no tick column")?;
+
+        let mut series: BTreeMap<String, Vec<(usize, f64)>> = BTreeMap::new();
+        for (i, line) in lines.enumerate() {
+            let values: Vec<&str> = line.split(',').map(str::trim).collect();
+            let tick = values.get(tick_column).and_then(|tick| tick.parse().ok())
+                .ok_or_else(|| format!("line {}: the tick is missing or invalid", i + 2))?;
+            for (column, value) in header.iter().zip(values.iter()).filter(|(column, _)| **column != "tick") {
+                // empty cells are series which weren't sampled at that tick
+                if let Ok(value) = value.parse::<f64>() {
+                    series.entry(column.to_string()).or_default().push((tick, value));
+                }
+            }
+        }
+        for samples in series.values_mut() {
+            samples.sort_by_key(|(tick, _)| *tick);
+        }
+        Ok(Self { path, series, show: true })
+    }
+
+    fn load(path: PathBuf) -> Result<Self, String> {
+        match replay::is_replay(&path) {
+            true => Self::from_replay(path),
+            false => Self::from_csv(path),
+        }
+    }
+}
+
+pub struct RunComparison {
+    pub open: bool,
+    path_input: String,
+    loading: Vec<(PathBuf, Receiver<Result<ComparedRun, String>>)>,
+    runs: Vec<ComparedRun>,
+    errors: Vec<String>,
+    explored_percentage: f32,
+    charted_series: String,
+}
+impl RunComparison {
+    const CHART_HEIGHT: f32 = 160.0;
+    const COLORS: [[f32; 4]; 8] = [
+        [0.9, 0.35, 0.3, 1.0],
+        [0.3, 0.7, 0.35, 1.0],
+        [0.3, 0.5, 0.9, 1.0],
+        [0.95, 0.75, 0.25, 1.0],
+        [0.65, 0.4, 0.85, 1.0],
+        [0.3, 0.8, 0.8, 1.0],
+        [0.9, 0.5, 0.75, 1.0],
+        [0.6, 0.6, 0.6, 1.0],
+    ];
+
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            path_input: String::new(),
+            loading: vec![],
+            runs: vec![],
+            errors: vec![],
+            explored_percentage: 50.0,
+            charted_series: "explored".into(),
+        }
+    }
+
+    fn add(&mut self, path: PathBuf) {
+        let (tx, rx) = mpsc::channel();
+        let thread_path = path.clone();
+        thread::spawn(move || {
+            let _ = tx.send(ComparedRun::load(thread_path)); // the GUI may have been closed in the meantime
+        });
+        self.loading.push((path, rx));
+    }
+
+    fn poll_loading(&mut self) {
+        let mut still_loading = vec![];
+        for (path, loading) in std::mem::take(&mut self.loading) {
+            match loading.try_recv() {
+                Ok(Ok(run)) => self.runs.push(run),
+                Ok(Err(e)) => self.errors.push(format!("{}: {e}", path.display())),
+                Err(TryRecvError::Empty) => still_loading.push((path, loading)),
+                Err(TryRecvError::Disconnected) => self.errors.push(format!("{}: the loading thread panicked", path.display())),
+            }
+        }
+        self.loading = still_loading;
+    }
+
+    fn color(i: usize) -> [f32; 4] {
+        Self::COLORS[i % Self::COLORS.len()]
+    }
+
+    pub fn draw(&mut self, ui: &Ui) {
+        self.poll_loading();
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        ui.window("Run comparison")
+            .opened(&mut open)
+            .size([520.0, 480.0], Condition::FirstUseEver)
+            .build(|| {
+                ui.input_text("##path", &mut self.path_input).hint("path of a replay or a CSV file").build();
+                ui.same_line();
+                if ui.button("Add") && !self.path_input.is_empty() {
+                    self.add(PathBuf::from(self.path_input.trim()));
+                    self.path_input.clear();
+                }
+                for (path, _) in self.loading.iter() {
+                    ui.text_disabled(format!("loading {}...", path.display()));
+                }
+                let mut dismiss_errors = false;
+                for error in self.errors.iter() {
+                    ui.text_colored([1.0, 0.4, 0.4, 1.0], format!("could not load {error}"));
+                }
+                if !self.errors.is_empty() {
+                    dismiss_errors = ui.small_button("Dismiss");
+                }
+                if dismiss_errors {
+                    self.errors.clear();
+                }
+                if self.runs.is_empty() {
+                    ui.text_wrapped("Add the replays or CSV exports of the runs to compare.");
+                    return;
+                }
+
+                ui.separator();
+                self.draw_table(ui);
+                ui.separator();
+                self.draw_chart(ui);
+            });
+        self.open = open;
+    }
+
+    fn draw_table(&mut self, ui: &Ui) {
+        ui.set_next_item_width(120.0);
+        ui.slider_config("explored", 1.0, 100.0).display_format("%.0f%%").build(&mut self.explored_percentage);
+        let flags = TableFlags::BORDERS | TableFlags::ROW_BG | TableFlags::SIZING_FIXED_FIT;
+        let mut removed = None;
+        if let Some(_table) = ui.begin_table_with_flags("runs", 8, flags) {
+            let ticks_to_explore = format!("ticks to {:.0}%", self.explored_percentage);
+            for header in ["run", "ticks", ticks_to_explore.as_str(), "coins", "energy spent", "coins/100 energy", "explored/1000 energy", ""] {
+                ui.table_setup_column(header);
+            }
+            ui.table_headers_row();
+            let optional = |value: Option<f64>, precision: usize| value.map(|value| format!("{value:.precision$}")).unwrap_or_else(|| "-".into());
+            for (i, run) in self.runs.iter_mut().enumerate() {
+                let _id = ui.push_id_usize(i);
+                let energy_spent = run.last("energy_spent").filter(|spent| *spent > 0.0);
+                ui.table_next_row();
+                ui.table_next_column();
+                ui.checkbox("##show", &mut run.show);
+                ui.same_line();
+                ui.text_colored(Self::color(i), run.name());
+                if ui.is_item_hovered() {
+                    ui.tooltip_text(run.path.display().to_string());
+                }
+                let columns = [
+                    run.last_tick().to_string(),
+                    run.ticks_to_explore(self.explored_percentage).map(|tick| tick.to_string()).unwrap_or_else(|| "-".into()),
+                    optional(run.last("coins"), 0),
+                    optional(run.last("energy_spent"), 0),
+                    optional(run.last("coins").zip(energy_spent).map(|(coins, spent)| 100.0 * coins / spent), 2),
+                    optional(run.last("explored").zip(energy_spent).map(|(explored, spent)| 1000.0 * explored / spent), 2),
+                ];
+                for column in columns {
+                    ui.table_next_column();
+                    ui.text(column);
+                }
+                ui.table_next_column();
+                if ui.small_button("Remove") {
+                    removed = Some(i);
+                }
+            }
+        }
+        if let Some(i) = removed {
+            self.runs.remove(i);
+        }
+    }
+
+    fn draw_chart(&mut self, ui: &Ui) {
+        let mut names: Vec<&String> = self.runs.iter().flat_map(|run| run.series.keys()).collect();
+        names.sort();
+        names.dedup();
+        ui.set_next_item_width(160.0);
+        if let Some(_combo) = ui.begin_combo("series", self.charted_series.as_str()) {
+            for name in names {
+                if ui.selectable_config(name).selected(*name == self.charted_series).build() {
+                    self.charted_series = name.clone();
+                }
+            }
+        }
+
+        let shown = || self.runs.iter().enumerate().filter(|(_, run)| run.show)
+            .filter_map(|(i, run)| Some((i, run.series.get(&self.charted_series)?)));
+        let max_tick = shown().filter_map(|(_, samples)| samples.last()).map(|(tick, _)| *tick).max().unwrap_or(0).max(1);
+        let (min_value, max_value) = shown().flat_map(|(_, samples)| samples.iter().map(|(_, value)| *value))
+            .fold((0.0f64, f64::MIN), |(min, max), value| (min.min(value), max.max(value)));
+        if max_value <= min_value {
+            ui.text_disabled("none of the runs shown has this series");
+            return;
+        }
+
+        let width = ui.content_region_avail()[0];
+        let height = Self::CHART_HEIGHT;
+        let [left, top] = ui.cursor_screen_pos();
+        let draw_list = ui.get_window_draw_list();
+        draw_list.add_rect([left, top], [left + width, top + height], ImColor32::from_rgba(40, 40, 40, 255)).filled(true).build();
+        for (i, samples) in shown() {
+            // one point per pixel column is plenty
+            let step = (samples.len() / width.max(1.0) as usize).max(1);
+            let points: Vec<[f32; 2]> = samples.iter().step_by(step).chain(samples.last())
+                .map(|(tick, value)| [
+                    left + width * *tick as f32 / max_tick as f32,
+                    top + height * (1.0 - ((value - min_value) / (max_value - min_value)) as f32),
+                ])
+                .collect();
+            draw_list.add_polyline(points, Self::color(i)).thickness(1.5).build();
+        }
+        ui.dummy([width, height]);
+        ui.text_disabled(format!("{}: {min_value:.1}..{max_value:.1} over ticks 0..{max_tick}", self.charted_series));
+    }
+}
@@ -0,0 +1,58 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+use imgui::Ui;
+use nalgebra_glm::UVec2;
+use crate::gui_runner::{PartialWorld, RunMode, RunModeChange, RunModeSource};
+
+// RunModeLog is the GUI's end of the gui->game channel: every change of RunMode goes through
+// request, which sends it to the game thread along with what requested it (a key, a widget, ...)
+// and the tick and robot position shown at the time, and keeps the last MAX_RECENT changes to be
+// listed in the Simulation settings. The game thread is the one writing the changes to the event
+// journal, if enabled.
+
+pub struct RunModeLog {
+    tx_to_game: Sender<RunModeChange>,
+    start: Instant,
+    tick: usize,
+    robot_position: UVec2,
+    recent: VecDeque<(Duration, RunMode, RunModeChange)>, // time since the start, previous mode, change
+}
+impl RunModeLog {
+    const MAX_RECENT: usize = 32;
+
+    pub fn new(tx_to_game: Sender<RunModeChange>, world: &PartialWorld) -> Self {
+        Self { tx_to_game, start: Instant::now(), tick: world.tick, robot_position: world.robot_position, recent: VecDeque::new() }
+    }
+
+    // must be called with every new world
+    pub fn update(&mut self, world: &PartialWorld) {
+        self.tick = world.tick;
+        self.robot_position = world.robot_position;
+    }
+
+    pub fn request(&mut self, run_mode: &mut RunMode, new_run_mode: RunMode, source: RunModeSource) {
+        let change = RunModeChange { run_mode: new_run_mode, source, tick: self.tick, robot_position: self.robot_position };
+        let _ = self.tx_to_game.send(change);
+
+        if self.recent.len() == Self::MAX_RECENT {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((self.start.elapsed(), *run_mode, change));
+        *run_mode = new_run_mode;
+    }
+
+    pub fn draw(&self, ui: &Ui) {
+        let Some(_node) = ui.tree_node("Recent run mode changes") else { return };
+        if self.recent.is_empty() {
+            ui.text_disabled("(none)");
+        }
+        for (elapsed, previous, change) in self.recent.iter().rev() {
+            let seconds = elapsed.as_secs_f32();
+            ui.text(format!(
+                "{:02}:{:04.1}  tick {}  {previous:?} -> {:?} ({})",
+                (seconds / 60.0) as u32, seconds % 60.0, change.tick, change.run_mode, change.source.name()
+            ));
+        }
+    }
+}
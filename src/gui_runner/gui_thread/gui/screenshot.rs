@@ -0,0 +1,30 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use glium::Display;
+use glium::texture::RawImage2d;
+
+// save_screenshot reads back the frame which was just presented (the front buffer, so it must be
+// called after the frame is finished) and writes it as a PNG in the working directory, named
+// after the time it was taken. The GUI is included, as it was on screen.
+
+pub fn save_screenshot(display: &Display) -> Result<PathBuf, String> {
+    let image: RawImage2d<u8> = display.read_front_buffer().map_err(|e| format!("could not read the frame: {e:?}"))?;
+    let (width, height) = (image.width, image.height);
+
+    // OpenGL returns the rows bottom to top
+    let row_len = (width * 4) as usize;
+    let pixels: Vec<u8> = image.data.chunks(row_len).rev().flatten().copied().collect();
+
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+    let path = PathBuf::from(format!("ragnarok-{millis}.png"));
+    let file = File::create(&path).map_err(|e| format!("could not create {path:?}: {e}"))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels))
+        .map_err(|e| format!("could not write {path:?}: {e}"))?;
+    Ok(path)
+}
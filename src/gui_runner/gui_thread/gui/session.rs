@@ -0,0 +1,62 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+use nalgebra_glm::Vec3;
+use serde::{Deserialize, Serialize};
+use crate::gui_runner::{MarkerStyle, PartialWorld};
+use crate::replay::CameraPose;
+use crate::snapshot::WorldSnapshot;
+use super::annotations::{from_pose, to_pose};
+use super::settings;
+
+// A session is what the File menu saves to and opens from session.json in the configuration
+// directory: the world being shown (as a WorldSnapshot, like SnapshotFile saves it), where the
+// camera was looking from and the styles of the markers, so that the user can get back to the view
+// they left. An opened session is shown through the DetachedView, like a snapshot, and only if its
+// world is of the same size. The layout of the windows isn't part of it, since Layouts already
+// persists it.
+
+#[derive(Serialize, Deserialize)]
+struct Session {
+    world: WorldSnapshot,
+    camera: CameraPose,
+    #[serde(default)]
+    markers: Option<MarkerStyles>, // None in the sessions saved before the markers were
+}
+
+// the styles of the markers, as edited in the GUI
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MarkerStyles {
+    pub robot: MarkerStyle,
+    #[serde(default)]
+    pub other_robots: Vec<MarkerStyle>, // those edited or set by the host code, of the robots after the first
+    pub ghost: MarkerStyle,
+}
+
+fn session_path() -> Option<PathBuf> {
+    settings::config_dir().map(|dir| dir.join("session.json"))
+}
+
+pub fn save_session(world: &PartialWorld, cam_pos: Vec3, cam_dir: Vec3, markers: MarkerStyles) -> Result<PathBuf, String> {
+    let path = session_path().ok_or("the configuration directory is unknown")?;
+    let session = Session { world: WorldSnapshot::from_partial_world(world), camera: to_pose(cam_pos, cam_dir), markers: Some(markers) };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let file = File::create(&path).map_err(|e| e.to_string())?;
+    serde_json::to_writer(BufWriter::new(file), &session).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+// returns the world of the session, the camera position and direction, and the styles of the
+// markers (if the session has them)
+pub fn load_session(world_size: usize) -> Result<(PartialWorld, Vec3, Vec3, Option<MarkerStyles>), String> {
+    let path = session_path().ok_or("the configuration directory is unknown")?;
+    let file = File::open(&path).map_err(|e| format!("could not open {}: {e}", path.display()))?;
+    let session: Session = serde_json::from_reader(BufReader::new(file)).map_err(|e| e.to_string())?;
+    if session.world.world.len() != world_size {
+        return Err(format!("the session is of a {0}x{0} world, this one is {1}x{1}", session.world.world.len(), world_size));
+    }
+    let (cam_pos, cam_dir) = from_pose(&session.camera);
+    Ok((session.world.to_partial_world(), cam_pos, cam_dir, session.markers))
+}
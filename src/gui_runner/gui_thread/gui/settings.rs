@@ -0,0 +1,203 @@
+use std::fs;
+use std::path::PathBuf;
+use imgui::{Condition, Ui};
+use serde::{Deserialize, Serialize};
+use super::key_bindings::KeyBindings;
+use super::skybox::SkyMode;
+use super::themes::{self, Theme};
+
+// Settings are the preferences of the user which outlive a run: they are loaded from a TOML file
+// in the platform's configuration directory when the GUI starts, and saved back when it exits.
+// A missing or unreadable file gives the defaults (reporting why it couldn't be read), and so does
+// a missing entry, so that a file written by an older version still loads.
+// The look of the GUI is chosen in the Settings window: the theme and its accent color (see Theme)
+// and the background of the 3D view (the color it is cleared with, which is otherwise the color of
+// the sky, and a vignette darkening its edges), so that screenshots can be made to match e.g. the
+// background of slides. The UI scale enlarges the text and the windows on top of the scale factor of
+// the display (see Fonts).
+// The number of samples of the multisample anti-aliasing and v-sync are settings too. They can only
+// be chosen when the OpenGL context is created, so changing them creates the context again, right
+// away. The cap of the frame rate applies right away too: without it or v-sync the GUI redraws as
+// fast as it can, keeping a core and the GPU busy even when nothing moves.
+// The low power mode goes further, only drawing the frames in which something changed (see OnDemand).
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub size: [u32; 2],
+    pub position: Option<[i32; 2]>, // None on platforms where it can't be known (e.g. Wayland)
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub movement_speed: f32,
+    pub look_speed: f32,
+    pub ticks_per_second_cap: f32,
+    pub uncapped: bool,
+    pub sky: SkyMode,
+    pub theme: Theme,
+    pub accent_color: [f32; 3],
+    pub ui_scale: f32,
+    pub clear_color: Option<[f32; 3]>, // None to clear with the color of the sky
+    pub vignette: f32, // strength, 0 for none
+    pub msaa_samples: u16, // 0 for none
+    pub vsync: bool,
+    pub frame_rate_cap: Option<f32>, // in frames per second, None for uncapped
+    pub low_power: bool,
+    pub window: Option<WindowGeometry>,
+    pub key_bindings: Option<KeyBindings>,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            movement_speed: 50.0,
+            look_speed: 1.0,
+            ticks_per_second_cap: 5.0,
+            uncapped: false,
+            sky: SkyMode::Automatic,
+            theme: Theme::Dark,
+            accent_color: Theme::DEFAULT_ACCENT,
+            ui_scale: 1.0,
+            clear_color: None,
+            vignette: 0.0,
+            msaa_samples: 4,
+            vsync: true,
+            frame_rate_cap: None,
+            low_power: false,
+            window: None,
+            key_bindings: None,
+        }
+    }
+}
+
+// draws the Settings window, with the settings of the look of the GUI. Returns whether the UI scale
+// is being dragged, in which case it mustn't be applied yet: the slider would move under the mouse
+pub fn draw_settings_window(ui: &Ui, settings: &mut Settings, open: &mut bool) -> bool {
+    if !*open {
+        return false;
+    }
+    let mut scaling = false;
+    ui.window("Settings")
+        .opened(open)
+        .size([320.0, 220.0], Condition::FirstUseEver)
+        .build(|| {
+            ui.text("Appearance");
+            themes::draw_theme_settings(ui, &mut settings.theme, &mut settings.accent_color);
+            ui.set_next_item_width(120.0);
+            ui.slider_config("UI scale", 0.5, 3.0).display_format("%.2fx").build(&mut settings.ui_scale);
+            scaling = ui.is_item_active();
+            if ui.is_item_hovered() {
+                ui.tooltip_text("enlarges the text and the windows, on top of the scale factor of the display");
+            }
+            ui.separator();
+            ui.text("Background");
+            draw_background_settings(ui, settings);
+        });
+    scaling
+}
+
+// draws the background settings, in the Settings window
+fn draw_background_settings(ui: &Ui, settings: &mut Settings) {
+    let mut custom = settings.clear_color.is_some();
+    if ui.checkbox("Custom background color", &mut custom) {
+        settings.clear_color = custom.then_some([0.2, 0.2, 0.2]);
+    }
+    if ui.is_item_hovered() {
+        ui.tooltip_text("clears the view with this color instead of the color of the sky (the skybox still covers it)");
+    }
+    if let Some(clear_color) = &mut settings.clear_color {
+        ui.color_edit3("background", clear_color);
+    }
+    ui.set_next_item_width(120.0);
+    ui.slider_config("vignette", 0.0, 1.0).display_format("%.2f").build(&mut settings.vignette);
+}
+
+// draws the anti-aliasing settings, in the Graphics section. the GUI rebuilds its context when they change
+pub fn draw_antialiasing_settings(ui: &Ui, settings: &mut Settings) {
+    let name = |samples: u16| if samples == 0 { "Off".to_string() } else { format!("{samples}x MSAA") };
+    ui.set_next_item_width(120.0);
+    if let Some(_combo) = ui.begin_combo("Anti-aliasing", name(settings.msaa_samples)) {
+        for samples in [0, 2, 4, 8] {
+            if ui.selectable_config(name(samples)).selected(samples == settings.msaa_samples).build() {
+                settings.msaa_samples = samples;
+            }
+        }
+    }
+}
+
+// draws the v-sync, frame rate and low power settings, in the Graphics section. the GUI rebuilds its
+// context when v-sync changes
+pub fn draw_frame_rate_settings(ui: &Ui, settings: &mut Settings) {
+    ui.checkbox("V-sync", &mut settings.vsync);
+    if ui.is_item_hovered() {
+        ui.tooltip_text("waits for the display to refresh before showing a frame, so that the frame rate doesn't exceed its refresh rate");
+    }
+    let mut capped = settings.frame_rate_cap.is_some();
+    if ui.checkbox("Cap the frame rate", &mut capped) {
+        settings.frame_rate_cap = capped.then_some(60.0);
+    }
+    if let Some(cap) = &mut settings.frame_rate_cap {
+        ui.set_next_item_width(120.0);
+        ui.slider_config("max FPS", 10.0, 240.0).display_format("%.0f").build(cap);
+    }
+    ui.checkbox("Low power mode", &mut settings.low_power);
+    if ui.is_item_hovered() {
+        ui.tooltip_text("only redraws when a new tick arrives, on input or when the camera moves");
+    }
+}
+
+// darkens the edges of the view, beneath the imgui windows
+pub fn draw_vignette(ui: &Ui, strength: f32) {
+    if strength <= 0.0 {
+        return;
+    }
+    let [width, height] = ui.io().display_size;
+    let band = 0.5 * strength * width.min(height);
+    let dark = [0.0, 0.0, 0.0, strength.min(1.0) * 0.8];
+    let clear = [0.0, 0.0, 0.0, 0.0];
+    let draw_list = ui.get_background_draw_list();
+    // the corners get the sum of two bands, as the sides of a real vignette would
+    draw_list.add_rect_filled_multicolor([0.0, 0.0], [width, band], dark, dark, clear, clear);
+    draw_list.add_rect_filled_multicolor([0.0, height - band], [width, height], clear, clear, dark, dark);
+    draw_list.add_rect_filled_multicolor([0.0, 0.0], [band, height], dark, clear, clear, dark);
+    draw_list.add_rect_filled_multicolor([width - band, 0.0], [width, height], clear, dark, dark, clear);
+}
+
+// the directory of the files which outlive a run, e.g. ~/.config/ragnarok on Linux
+pub fn config_dir() -> Option<PathBuf> {
+    let config_dir = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    config_dir.map(|dir| dir.join("ragnarok"))
+}
+
+impl Settings {
+    fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("settings.toml"))
+    }
+
+    pub fn load() -> Self {
+        let Some(path) = Self::path().filter(|path| path.exists()) else { return Self::default() };
+        let settings = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| toml::from_str(&data).map_err(|e| e.to_string()));
+        settings.unwrap_or_else(|e| {
+            eprintln!("ignoring the settings in {path:?}: {e}");
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("the configuration directory is unknown")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let data = toml::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(&path, data).map_err(|e| format!("could not write {path:?}: {e}"))
+    }
+}
@@ -1,5 +1,21 @@
+// the fragment shader can write a logarithmic depth (log2(1 + w) / log2(1 + far)) instead of the
+// default one, which distributes the depth buffer's precision much more evenly over distance and
+// thus avoids z-fighting on distant terrain.
+// when lighting is enabled the fragments are shaded by the sun (or moon) described by Daylight:
+// since the vertices have no normals, the normal of each (flat) triangle is computed from the screen
+// space derivatives of the position, and flipped to face upwards so that the winding doesn't matter.
+// uniforms builds the uniforms of the program; lighting should be disabled for the skybox and for
+// lines, which have no meaningful normal.
+// both programs can also darken the fragments in the shadow of the light, given the uniforms of
+// a ShadowMap (see Shadowed); without them the shadows are skipped.
+// liquid tiles (water and lava) are drawn by a second program, which animates them with the time
+// uniform: the vertices are displaced by a few overlapping waves (slow and shallow ones for lava)
+// and the color pulsates as if flowing. emissive vertices (lava) glow, ignoring the lighting.
 
-pub fn make_program(display: &glium::Display) -> Result<glium::Program, glium::ProgramCreationError> {
+use nalgebra_glm::Mat4;
+use super::daylight::Daylight;
+
+pub fn make_program(facade: &impl glium::backend::Facade) -> Result<glium::Program, glium::ProgramCreationError> {
     let vtx_shader_src = {r#"
             #version 150
 
@@ -7,12 +23,19 @@ pub fn make_program(display: &glium::Display) -> Result<glium::Program, glium::P
             in vec3 color;
 
             smooth out vec3 v_color;
+            smooth out vec3 v_position;
+            smooth out float v_log_z;
+            smooth out vec4 v_light_space;
 
             uniform mat4 mvp;
+            uniform mat4 light_mvp;
 
             void main() {
                 v_color = color;
+                v_position = position;
+                v_light_space = light_mvp * vec4(position, 1.0);
                 gl_Position = mvp * vec4(position, 1.0);
+                v_log_z = 1.0 + gl_Position.w;
             }
         "#};
 
@@ -20,13 +43,142 @@ pub fn make_program(display: &glium::Display) -> Result<glium::Program, glium::P
             #version 150
 
             smooth in vec3 v_color;
+            smooth in vec3 v_position;
+            smooth in float v_log_z;
+            smooth in vec4 v_light_space;
             out vec4 color;
-            uniform vec3 u_light;
+            uniform bool lighting;
+            uniform vec3 u_light; // direction towards the light
+            uniform vec3 u_light_color;
+            uniform vec3 u_ambient;
+            uniform bool log_depth;
+            uniform float log_depth_coef;
+            uniform bool shadows;
+            uniform sampler2DShadow shadow_map;
+
+            // 1 where the light reaches the fragment, 0 in the shadow
+            float light_reaching() {
+                if (!shadows) return 1.0;
+                vec3 p = v_light_space.xyz / v_light_space.w * 0.5 + 0.5;
+                if (any(lessThan(p, vec3(0.0))) || any(greaterThan(p, vec3(1.0)))) return 1.0;
+                return texture(shadow_map, vec3(p.xy, p.z - 0.0005));
+            }
 
             void main() {
-                color = vec4(v_color, 1.0);
+                if (lighting) {
+                    vec3 normal = normalize(cross(dFdx(v_position), dFdy(v_position)));
+                    if (normal.y < 0.0) normal = -normal;
+                    vec3 light = u_ambient + u_light_color * max(dot(normal, u_light), 0.0) * light_reaching();
+                    color = vec4(v_color * light, 1.0);
+                } else {
+                    color = vec4(v_color, 1.0);
+                }
+                gl_FragDepth = log_depth ? log2(v_log_z) * log_depth_coef : gl_FragCoord.z;
             }
         "#};
 
-    glium::Program::from_source(display, vtx_shader_src, frag_shader_src, None)
-}
\ No newline at end of file
+    glium::Program::from_source(facade, vtx_shader_src, frag_shader_src, None)
+}
+
+pub fn make_liquid_program(facade: &impl glium::backend::Facade) -> Result<glium::Program, glium::ProgramCreationError> {
+    let vtx_shader_src = {r#"
+            #version 150
+
+            in vec3 position;
+            in vec3 color;
+            in float emissive;
+
+            smooth out vec3 v_color;
+            smooth out vec3 v_position;
+            smooth out float v_emissive;
+            smooth out float v_log_z;
+
+            smooth out vec4 v_light_space;
+
+            uniform mat4 mvp;
+            uniform mat4 light_mvp;
+            uniform float time;
+
+            void main() {
+                vec3 p = position;
+                if (emissive > 0.5) {
+                    p.y += 0.03 * sin(time * 0.8 + p.x * 0.7 + p.z * 0.5);
+                    v_color = color * (1.0 + 0.35 * sin(time * 1.2 + p.x * 1.5 - p.z * 0.8));
+                } else {
+                    p.y += 0.05 * sin(time * 2.0 + p.x * 1.7 + p.z * 1.1) + 0.025 * sin(time * 3.1 - p.x * 0.9 + p.z * 2.3);
+                    v_color = color * (1.0 + 0.15 * sin(time * 1.5 + p.x * 2.0 - p.z * 1.3));
+                }
+                v_position = p;
+                v_emissive = emissive;
+                v_light_space = light_mvp * vec4(p, 1.0);
+                gl_Position = mvp * vec4(p, 1.0);
+                v_log_z = 1.0 + gl_Position.w;
+            }
+        "#};
+
+    let frag_shader_src = {r#"
+            #version 150
+
+            smooth in vec3 v_color;
+            smooth in vec3 v_position;
+            smooth in float v_emissive;
+            smooth in float v_log_z;
+            smooth in vec4 v_light_space;
+            out vec4 color;
+            uniform bool lighting;
+            uniform vec3 u_light; // direction towards the light
+            uniform vec3 u_light_color;
+            uniform vec3 u_ambient;
+            uniform bool log_depth;
+            uniform float log_depth_coef;
+            uniform bool shadows;
+            uniform sampler2DShadow shadow_map;
+
+            // 1 where the light reaches the fragment, 0 in the shadow
+            float light_reaching() {
+                if (!shadows) return 1.0;
+                vec3 p = v_light_space.xyz / v_light_space.w * 0.5 + 0.5;
+                if (any(lessThan(p, vec3(0.0))) || any(greaterThan(p, vec3(1.0)))) return 1.0;
+                return texture(shadow_map, vec3(p.xy, p.z - 0.0005));
+            }
+
+            void main() {
+                vec3 light = vec3(1.0);
+                if (lighting) {
+                    vec3 normal = normalize(cross(dFdx(v_position), dFdy(v_position)));
+                    if (normal.y < 0.0) normal = -normal;
+                    light = u_ambient + u_light_color * max(dot(normal, u_light), 0.0) * light_reaching();
+                }
+                light = mix(light, vec3(1.3), v_emissive);
+                color = vec4(v_color * light, 1.0);
+                gl_FragDepth = log_depth ? log2(v_log_z) * log_depth_coef : gl_FragCoord.z;
+            }
+        "#};
+
+    glium::Program::from_source(facade, vtx_shader_src, frag_shader_src, None)
+}
+
+pub fn uniforms(mvp: &Mat4, log_depth: bool, log_depth_coef: f32, daylight: &Daylight, lighting: bool) -> impl glium::uniforms::Uniforms {
+    uniform! {
+        mvp: *mvp.as_ref(),
+        log_depth: log_depth,
+        log_depth_coef: log_depth_coef,
+        lighting: lighting,
+        u_light: *daylight.light_direction.as_ref(),
+        u_light_color: *daylight.light_color.as_ref(),
+        u_ambient: *daylight.ambient.as_ref(),
+    }
+}
+
+pub fn liquid_uniforms(mvp: &Mat4, log_depth: bool, log_depth_coef: f32, daylight: &Daylight, time: f32) -> impl glium::uniforms::Uniforms {
+    uniform! {
+        mvp: *mvp.as_ref(),
+        log_depth: log_depth,
+        log_depth_coef: log_depth_coef,
+        lighting: true,
+        u_light: *daylight.light_direction.as_ref(),
+        u_light_color: *daylight.light_color.as_ref(),
+        u_ambient: *daylight.ambient.as_ref(),
+        time: time,
+    }
+}
@@ -0,0 +1,136 @@
+use glium::{DrawParameters, Program, Surface};
+use glium::backend::Facade;
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::index::PrimitiveType;
+use glium::texture::DepthTexture2d;
+use glium::uniforms::{DepthTextureComparison, MagnifySamplerFilter, MinifySamplerFilter, SamplerBehavior, SamplerWrapFunction, UniformValue, Uniforms};
+use nalgebra_glm::{look_at, Mat4, ortho, Vec3, vec3};
+use super::daylight::Daylight;
+use super::world_mesh::Chunk;
+
+// ShadowMap makes the terrain and the contents cast shadows from the sun (or the moon) described by
+// Daylight: before the world is drawn its chunks are rendered from the light, with an orthographic
+// projection, into a depth texture (the shadow map), and the programs of shaders.rs darken the
+// fragments which are farther from the light than what the map recorded. Since the map has a fixed
+// resolution it only covers a square of RADIUS tiles around the focus (the area the camera looks
+// at); the fragments outside it are lit. The center of the square is snapped to the texels of the
+// map, so that the edges of the shadows don't shimmer as the camera moves. The full meshes of the
+// chunks are drawn into the map (the simplified ones would cast blocky shadows), and liquids don't
+// cast shadows, only receive them.
+// Shadowed adds the uniforms of the shadow map to those of a program; when the shadows are
+// disabled (or the light is below the horizon, or the map could not be created) the programs skip
+// sampling the map.
+
+pub struct ShadowMap {
+    pub enabled: bool,
+    program: Program,
+    texture: DepthTexture2d,
+    light_mvp: Mat4,
+    rendered: bool, // whether the map holds the current frame
+}
+impl ShadowMap {
+    const SIZE: u32 = 2048;
+    const RADIUS: f32 = 96.0;
+    // distance of the light from the focus, more than the terrain can rise above it
+    const LIGHT_DISTANCE: f32 = 256.0;
+
+    pub fn new(facade: &impl Facade) -> Result<Self, String> {
+        let program = Self::make_program(facade).map_err(|e| e.to_string())?;
+        let texture = DepthTexture2d::empty(facade, Self::SIZE, Self::SIZE).map_err(|e| e.to_string())?;
+        Ok(Self { enabled: true, program, texture, light_mvp: Mat4::identity(), rendered: false })
+    }
+
+    fn make_program(facade: &impl Facade) -> Result<Program, glium::ProgramCreationError> {
+        let vtx_shader_src = {r#"
+            #version 150
+
+            in vec3 position;
+            uniform mat4 light_mvp;
+
+            void main() {
+                gl_Position = light_mvp * vec4(position, 1.0);
+            }
+        "#};
+
+        let frag_shader_src = {r#"
+            #version 150
+
+            void main() {}
+        "#};
+
+        Program::from_source(facade, vtx_shader_src, frag_shader_src, None)
+    }
+
+    // the view projection of the light, looking at focus from light_direction
+    fn light_mvp(light_direction: Vec3, focus: Vec3) -> Mat4 {
+        let forward = -light_direction.normalize();
+        let right = forward.cross(&vec3(0.0, 1.0, 0.0)).normalize();
+        let up = right.cross(&forward);
+        let texel = 2.0 * Self::RADIUS / Self::SIZE as f32;
+        let snap = |v: f32| (v / texel).floor() * texel;
+        let focus = right * snap(focus.dot(&right)) + up * snap(focus.dot(&up)) + forward * focus.dot(&forward);
+
+        let view = look_at(&(focus - forward * Self::LIGHT_DISTANCE), &focus, &up);
+        let projection = ortho(-Self::RADIUS, Self::RADIUS, -Self::RADIUS, Self::RADIUS, 0.0, 2.0 * Self::LIGHT_DISTANCE);
+        projection * view
+    }
+
+    // renders the chunks around focus into the shadow map; must be called once per frame, before the
+    // world is drawn. returns the number of triangles drawn
+    pub fn render<'c>(&mut self, facade: &impl Facade, chunks: impl Iterator<Item = &'c Chunk>, daylight: &Daylight, focus: Vec3) -> usize {
+        // a light grazing the horizon would stretch the shadows over the whole map, and without
+        // lighting (Daylight::NEUTRAL) there is nothing to shadow
+        self.rendered = self.enabled && daylight.light_direction.y > 0.05 && daylight.light_color != Vec3::zeros();
+        if !self.rendered {
+            return 0;
+        }
+        self.light_mvp = Self::light_mvp(daylight.light_direction, focus);
+
+        let mut target = SimpleFrameBuffer::depth_only(facade, &self.texture).unwrap();
+        target.clear_depth(1.0);
+        let uniforms = uniform! { light_mvp: *self.light_mvp.as_ref() };
+        let draw_params = DrawParameters {
+            depth: glium::Depth {
+                test: glium::draw_parameters::DepthTest::IfLess,
+                write: true,
+                .. Default::default()
+            },
+            // pushes the depths away from the light, so that surfaces don't shadow themselves
+            polygon_offset: glium::draw_parameters::PolygonOffset { factor: 2.0, units: 4.0, fill: true, .. Default::default() },
+            .. Default::default()
+        };
+        let mut triangles = 0;
+        for chunk in chunks {
+            let is_near = chunk.min.x < focus.x + Self::RADIUS && chunk.max.x > focus.x - Self::RADIUS
+                && chunk.min.z < focus.z + Self::RADIUS && chunk.max.z > focus.z - Self::RADIUS;
+            if is_near {
+                target.draw(&chunk.vbo, &glium::index::NoIndices(PrimitiveType::TrianglesList), &self.program, &uniforms, &draw_params).unwrap();
+                triangles += chunk.vbo.len() / 3;
+            }
+        }
+        triangles
+    }
+}
+
+pub struct Shadowed<'s, U> {
+    pub uniforms: U,
+    pub shadow_map: Option<&'s ShadowMap>,
+}
+impl<U: Uniforms> Uniforms for Shadowed<'_, U> {
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut visit: F) {
+        self.uniforms.visit_values(&mut visit);
+        let Some(shadow_map) = self.shadow_map else {
+            return visit("shadows", UniformValue::Bool(false));
+        };
+        visit("shadows", UniformValue::Bool(shadow_map.rendered));
+        visit("light_mvp", UniformValue::Mat4(*shadow_map.light_mvp.as_ref()));
+        let sampler = SamplerBehavior {
+            wrap_function: (SamplerWrapFunction::Clamp, SamplerWrapFunction::Clamp, SamplerWrapFunction::Clamp),
+            minify_filter: MinifySamplerFilter::Linear,
+            magnify_filter: MagnifySamplerFilter::Linear,
+            depth_texture_comparison: Some(DepthTextureComparison::LessOrEqual),
+            .. Default::default()
+        };
+        visit("shadow_map", UniformValue::DepthTexture2d(&shadow_map.texture, Some(sampler)));
+    }
+}
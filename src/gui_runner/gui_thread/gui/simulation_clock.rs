@@ -0,0 +1,54 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use imgui::Ui;
+use crate::gui_runner::PartialWorld;
+
+// SimulationClock shows how fast the game is actually going, which can be far from the speed cap
+// when the robot's AI is slow: the ticks per second are measured over the worlds received in the
+// last WINDOW (not every tick reaches the GUI, but the tick numbers do), and averaged over the
+// whole run from the wall time the game thread puts in the worlds.
+
+pub struct SimulationClock {
+    samples: VecDeque<(Instant, usize)>, // when each world was received, and its tick
+}
+impl SimulationClock {
+    const WINDOW: Duration = Duration::from_secs(2);
+
+    pub fn new() -> Self {
+        Self { samples: VecDeque::new() }
+    }
+
+    // must be called with every new world
+    pub fn record(&mut self, world: &PartialWorld) {
+        let now = Instant::now();
+        // going back in time (stepping back or seeking in a replay) isn't progress
+        if self.samples.back().map_or(false, |(_, tick)| world.tick < *tick) {
+            self.samples.clear();
+        }
+        self.samples.push_back((now, world.tick));
+        while self.samples.front().map_or(false, |(received, _)| now - *received > Self::WINDOW) {
+            self.samples.pop_front();
+        }
+    }
+
+    // ticks per second over the last WINDOW, or None if no world was received in a while
+    fn recent_ticks_per_second(&self) -> Option<f32> {
+        let ((first_time, first_tick), (_, last_tick)) = (self.samples.front()?, self.samples.back()?);
+        let seconds = first_time.elapsed().as_secs_f32();
+        (seconds > 0.0 && first_time.elapsed() <= Self::WINDOW).then(|| (last_tick - first_tick) as f32 / seconds)
+    }
+
+    pub fn draw(&self, ui: &Ui, world: &PartialWorld) {
+        ui.text(format!("Tick: {}", world.tick));
+        if !world.elapsed.is_zero() {
+            let seconds = world.elapsed.as_secs();
+            ui.text(format!("Elapsed: {:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60));
+            ui.same_line();
+            ui.text_disabled(format!("(avg {:.1} ticks/s)", world.tick as f32 / world.elapsed.as_secs_f32()));
+        }
+        match self.recent_ticks_per_second() {
+            Some(ticks_per_second) => ui.text(format!("Actual speed: {ticks_per_second:.1} ticks/s")),
+            None => ui.text("Actual speed: -"),
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use glium::{BlitTarget, Program, Surface, VertexBuffer};
+use glium::backend::Facade;
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::index::PrimitiveType;
+use glium::texture::{CubeLayer, Cubemap, RawImage2d, Texture2d};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter};
+use imgui::Ui;
+use nalgebra_glm::{lerp, Mat4, Vec3, vec3};
+use robotics_lib::world::environmental_conditions::{DayTime, EnvironmentalConditions, WeatherType};
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+// Skybox draws the sky as a cubemap around the camera, with a program of its own: the cube is
+// drawn after the opaque geometry with the depth of the far plane (the vertex shader outputs xyww),
+// so that only the fragments no geometry covered are shaded, and without the camera's translation,
+// so that the sky never gets closer. The cubemaps are generated procedurally, one for each weather
+// and time of day (a vertical gradient tinted by the time of day, with the sun or the moon and, in
+// clear nights, the stars), the first time they are needed; their faces are written by blitting a
+// texture into each layer of the cubemap.
+// SkyMode is what the sky shows, chosen from a dropdown: nothing (the view is cleared with the
+// color of the sky, see Daylight), the current weather and time of day, or a fixed weather at the
+// current time of day.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SkyMode {
+    Off,
+    Automatic,
+    Fixed { weather: usize }, // WeatherType as usize
+}
+
+#[derive(Clone, Copy, Debug)]
+struct SkyboxVertex {
+    position: [f32; 3],
+}
+implement_vertex!(SkyboxVertex, position);
+
+pub struct Skybox {
+    program: Program,
+    cube: VertexBuffer<SkyboxVertex>,
+    cubemaps: HashMap<(usize, usize), Cubemap>, // by weather and time of day
+}
+impl Skybox {
+    const FACE_SIZE: u32 = 128;
+
+    pub fn new(facade: &impl Facade) -> Result<Self, String> {
+        let program = Self::make_program(facade).map_err(|e| e.to_string())?;
+        // two triangles per face of the cube around the origin
+        let corners = |face: [[f32; 3]; 4]| [face[0], face[1], face[2], face[0], face[2], face[3]];
+        let faces = [
+            [[1.0, -1.0, -1.0], [1.0, 1.0, -1.0], [1.0, 1.0, 1.0], [1.0, -1.0, 1.0]],
+            [[-1.0, -1.0, -1.0], [-1.0, 1.0, -1.0], [-1.0, 1.0, 1.0], [-1.0, -1.0, 1.0]],
+            [[-1.0, 1.0, -1.0], [1.0, 1.0, -1.0], [1.0, 1.0, 1.0], [-1.0, 1.0, 1.0]],
+            [[-1.0, -1.0, -1.0], [1.0, -1.0, -1.0], [1.0, -1.0, 1.0], [-1.0, -1.0, 1.0]],
+            [[-1.0, -1.0, 1.0], [1.0, -1.0, 1.0], [1.0, 1.0, 1.0], [-1.0, 1.0, 1.0]],
+            [[-1.0, -1.0, -1.0], [1.0, -1.0, -1.0], [1.0, 1.0, -1.0], [-1.0, 1.0, -1.0]],
+        ];
+        let vertices: Vec<SkyboxVertex> = faces.into_iter().flat_map(corners).map(|position| SkyboxVertex { position }).collect();
+        let cube = VertexBuffer::new(facade, &vertices).map_err(|e| e.to_string())?;
+        Ok(Self { program, cube, cubemaps: HashMap::new() })
+    }
+
+    fn make_program(facade: &impl Facade) -> Result<Program, glium::ProgramCreationError> {
+        let vtx_shader_src = {r#"
+            #version 150
+
+            in vec3 position;
+            smooth out vec3 v_direction;
+
+            uniform mat4 view_projection;
+
+            void main() {
+                v_direction = position;
+                gl_Position = (view_projection * vec4(position, 1.0)).xyww;
+            }
+        "#};
+
+        let frag_shader_src = {r#"
+            #version 150
+
+            smooth in vec3 v_direction;
+            out vec4 color;
+            uniform samplerCube sky;
+
+            void main() {
+                color = texture(sky, v_direction);
+            }
+        "#};
+
+        Program::from_source(facade, vtx_shader_src, frag_shader_src, None)
+    }
+
+    // draws the sky behind what was already drawn on the surface. view_projection is the mvp of a
+    // camera at the origin, looking in the direction of the actual camera. returns the number of
+    // triangles drawn
+    pub fn draw(&mut self, surface: &mut impl Surface, facade: &impl Facade, view_projection: &Mat4, mode: SkyMode, env_cond: &EnvironmentalConditions) -> usize {
+        let weather = match mode {
+            SkyMode::Off => return 0,
+            SkyMode::Automatic => env_cond.get_weather_condition() as usize,
+            SkyMode::Fixed { weather } => weather,
+        };
+        let key = (weather, env_cond.get_time_of_day() as usize);
+        if !self.cubemaps.contains_key(&key) {
+            match Self::generate_cubemap(facade, key.0, key.1) {
+                Ok(cubemap) => { self.cubemaps.insert(key, cubemap); }
+                Err(e) => {
+                    eprintln!("could not generate the skybox: {e}");
+                    return 0;
+                }
+            }
+        }
+        let uniforms = uniform! {
+            view_projection: *view_projection.as_ref(),
+            sky: self.cubemaps[&key].sampled()
+                .magnify_filter(MagnifySamplerFilter::Linear)
+                .minify_filter(MinifySamplerFilter::Linear),
+        };
+        let draw_params = glium::DrawParameters {
+            depth: glium::Depth {
+                test: glium::draw_parameters::DepthTest::IfLessOrEqual,
+                write: false,
+                .. Default::default()
+            },
+            .. Default::default()
+        };
+        surface.draw(&self.cube, &glium::index::NoIndices(PrimitiveType::TrianglesList), &self.program, &uniforms, &draw_params).unwrap();
+        self.cube.len() / 3
+    }
+
+    fn generate_cubemap(facade: &impl Facade, weather: usize, time_of_day: usize) -> Result<Cubemap, String> {
+        let size = Self::FACE_SIZE;
+        let cubemap = Cubemap::empty(facade, size).map_err(|e| e.to_string())?;
+        // the direction of the texel (s, t) of each face, each in -1..1, as OpenGL samples cubemaps
+        let layers: [(CubeLayer, fn(f32, f32) -> Vec3); 6] = [
+            (CubeLayer::PositiveX, |s, t| vec3(1.0, -t, -s)),
+            (CubeLayer::NegativeX, |s, t| vec3(-1.0, -t, s)),
+            (CubeLayer::PositiveY, |s, t| vec3(s, 1.0, t)),
+            (CubeLayer::NegativeY, |s, t| vec3(s, -1.0, -t)),
+            (CubeLayer::PositiveZ, |s, t| vec3(s, -t, 1.0)),
+            (CubeLayer::NegativeZ, |s, t| vec3(-s, -t, -1.0)),
+        ];
+        for (layer, direction_of) in layers {
+            let mut pixels = Vec::with_capacity((size * size * 3) as usize);
+            for row in 0..size {
+                for column in 0..size {
+                    let to_unit = |i: u32| 2.0 * (i as f32 + 0.5) / size as f32 - 1.0;
+                    let color = Self::sky_color(direction_of(to_unit(column), to_unit(row)).normalize(), weather, time_of_day);
+                    pixels.extend(color.iter().map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8));
+                }
+            }
+            let texture = Texture2d::new(facade, RawImage2d::from_raw_rgb(pixels, (size, size))).map_err(|e| e.to_string())?;
+            let face = SimpleFrameBuffer::new(facade, cubemap.main_level().image(layer)).map_err(|e| e.to_string())?;
+            let target = BlitTarget { left: 0, bottom: 0, width: size as i32, height: size as i32 };
+            texture.as_surface().blit_whole_color_to(&face, &target, MagnifySamplerFilter::Linear);
+        }
+        Ok(cubemap)
+    }
+
+    fn sky_color(direction: Vec3, weather: usize, time_of_day: usize) -> Vec3 {
+        // zenith, horizon and nadir
+        let weather_gradients = [
+            [vec3(0.35, 0.55, 0.95), vec3(0.75, 0.8, 0.9), vec3(0.3, 0.28, 0.25)], // sunny
+            [vec3(0.45, 0.48, 0.58), vec3(0.6, 0.62, 0.68), vec3(0.2, 0.2, 0.22)], // rainy
+            [vec3(0.62, 0.62, 0.62), vec3(0.72, 0.72, 0.72), vec3(0.45, 0.45, 0.45)], // foggy
+            [vec3(0.12, 0.14, 0.16), vec3(0.25, 0.27, 0.28), vec3(0.05, 0.05, 0.05)], // tropical monsoon
+            [vec3(0.7, 0.72, 0.78), vec3(0.85, 0.86, 0.9), vec3(0.6, 0.6, 0.62)], // trentino snow
+        ];
+        let time_of_day_tints = [vec3(1.0, 0.92, 0.85), vec3(1.0, 1.0, 1.0), vec3(0.12, 0.13, 0.22)];
+        let [zenith, horizon, nadir] = weather_gradients[weather.min(weather_gradients.len() - 1)];
+        let tint = time_of_day_tints[time_of_day.min(time_of_day_tints.len() - 1)];
+
+        let mut color = if direction.y >= 0.0 {
+            lerp(&horizon, &zenith, direction.y.powf(0.6))
+        } else {
+            lerp(&horizon, &nadir, (-direction.y).powf(0.5))
+        }.component_mul(&tint);
+
+        let is_clear = weather == WeatherType::Sunny as usize;
+        let is_night = time_of_day == DayTime::Night as usize;
+        // the sun rises on the -x side and sets on the +x side, as in Daylight
+        let (body_direction, body_color, body_size) = match DayTime::iter().nth(time_of_day) {
+            Some(DayTime::Morning) => (vec3(-0.8, 0.35, 0.35), vec3(1.0, 0.85, 0.6), 0.9995),
+            Some(DayTime::Afternoon) => (vec3(0.6, 0.65, 0.35), vec3(1.0, 0.97, 0.9), 0.9995),
+            _ => (vec3(-0.5, 0.6, 0.35), vec3(0.85, 0.88, 0.95), 0.9997),
+        };
+        let alignment = direction.dot(&body_direction.normalize());
+        if alignment > body_size {
+            // behind the clouds the sun (or moon) is only a brighter patch
+            let visibility = if is_clear { 1.0 } else { 0.25 };
+            color = lerp(&color, &body_color, visibility);
+        } else if is_clear && !is_night {
+            color += body_color * 0.35 * ((alignment - 0.9) / 0.1).max(0.0).powi(3);
+        }
+
+        if is_clear && is_night && direction.y > 0.0 {
+            // sparse stars, from a hash of the direction quantized to a fine grid
+            let cell = (direction * 300.0).map(|c| c.floor());
+            let hash = ((cell.x * 12.9898 + cell.y * 78.233 + cell.z * 37.719).sin() * 43758.547).fract().abs();
+            if hash > 0.997 {
+                color = Vec3::repeat(0.6 + 0.4 * (hash - 0.997) / 0.003);
+            }
+        }
+        color
+    }
+
+    // the dropdown choosing the sky
+    pub fn draw_mode_combo(ui: &Ui, mode: &mut SkyMode) {
+        let name = |mode: SkyMode| match mode {
+            SkyMode::Off => "Off".to_string(),
+            SkyMode::Automatic => "Current weather".to_string(),
+            SkyMode::Fixed { weather } => WeatherType::iter().nth(weather).map(|weather| format!("{weather:?}")).unwrap_or_default(),
+        };
+        let modes: Vec<SkyMode> = [SkyMode::Off, SkyMode::Automatic].into_iter()
+            .chain((0..WeatherType::iter().count()).map(|weather| SkyMode::Fixed { weather }))
+            .collect();
+        ui.set_next_item_width(150.0);
+        if let Some(_combo) = ui.begin_combo("Skybox", name(*mode)) {
+            for option in modes {
+                if ui.selectable_config(name(option)).selected(option == *mode).build() {
+                    *mode = option;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use imgui::{Condition, Ui};
+use crate::gui_runner::PartialWorld;
+use crate::snapshot::WorldSnapshot;
+use super::detached_view::ViewRequest;
+
+// SnapshotFile is an imgui window which saves the world being shown to a JSON file (as a
+// WorldSnapshot, so it can also be read by other programs) and loads it back, so that a
+// discovered map can be inspected later without running the robot again. A loaded snapshot is
+// shown through the DetachedView, until the user returns to the run. Since the mesh is sized once,
+// only snapshots of a world of the same size can be loaded.
+
+pub struct SnapshotFile {
+    pub open: bool,
+    path: String,
+    status: Option<String>,
+}
+impl SnapshotFile {
+    pub fn new() -> Self {
+        Self { open: false, path: "snapshot.json".into(), status: None }
+    }
+
+    fn save(&self, world: &PartialWorld) -> Result<(), String> {
+        let file = File::create(&self.path).map_err(|e| e.to_string())?;
+        serde_json::to_writer(BufWriter::new(file), &WorldSnapshot::from_partial_world(world)).map_err(|e| e.to_string())
+    }
+
+    fn load(&self, world_size: usize) -> Result<PartialWorld, String> {
+        let file = File::open(&self.path).map_err(|e| e.to_string())?;
+        let snapshot: WorldSnapshot = serde_json::from_reader(BufReader::new(file)).map_err(|e| e.to_string())?;
+        if snapshot.world.len() != world_size {
+            return Err(format!("the snapshot is of a {0}x{0} world, this one is {1}x{1}", snapshot.world.len(), world_size));
+        }
+        Ok(snapshot.to_partial_world())
+    }
+
+    fn load_and_show(&mut self, world_size: usize) -> Option<ViewRequest> {
+        match self.load(world_size) {
+            Ok(snapshot) => {
+                self.status = Some(format!("showing tick {} from {}", snapshot.tick, self.path));
+                Some(ViewRequest::Show(snapshot))
+            }
+            Err(e) => {
+                self.status = Some(format!("could not load: {e}"));
+                None
+            }
+        }
+    }
+
+    // loads the snapshot at path (e.g. a file dropped onto the window), opening the window to
+    // report how it went
+    pub fn open_file(&mut self, path: &Path, world_size: usize) -> Option<ViewRequest> {
+        self.open = true;
+        self.path = path.display().to_string();
+        self.load_and_show(world_size)
+    }
+
+    pub fn draw(&mut self, ui: &Ui, world: &PartialWorld, is_detached: bool) -> Option<ViewRequest> {
+        if !self.open {
+            return None;
+        }
+        let mut request = None;
+        let mut open = self.open;
+        ui.window("Snapshot")
+            .opened(&mut open)
+            .size([320.0, 140.0], Condition::FirstUseEver)
+            .build(|| {
+                ui.input_text("file", &mut self.path).build();
+                if ui.button("Save snapshot") {
+                    self.status = Some(match self.save(world) {
+                        Ok(()) => format!("saved tick {} to {}", world.tick, self.path),
+                        Err(e) => format!("could not save: {e}"),
+                    });
+                }
+                ui.same_line();
+                if ui.button("Load snapshot") {
+                    request = self.load_and_show(world.world.len());
+                }
+                if is_detached && ui.button("Return to the run") {
+                    request = Some(ViewRequest::ReturnToRun);
+                    self.status = None;
+                }
+                if let Some(status) = &self.status {
+                    ui.text_wrapped(status);
+                }
+            });
+        self.open = open;
+        request
+    }
+}
@@ -0,0 +1,34 @@
+use imgui::{Drag, SliderFlags, Ui};
+
+// speed_dial edits a tick rate: the main control is a logarithmic drag showing the value with its
+// unit, which can also be typed in (with a double or ctrl click) and goes well beyond what the
+// slider below it covers, for robots whose ticks take next to no time. The slider is kept for
+// picking a common rate at a glance. Returns whether the rate changed.
+
+const MIN: f32 = 1.0;
+const MAX: f32 = 100_000.0;
+const SLIDER_MAX: f32 = 200.0;
+
+pub fn speed_dial(ui: &Ui, label: &str, ticks_per_second: &mut f32) -> bool {
+    let _id = ui.push_id(label);
+    let mut changed = Drag::new(label)
+        .range(MIN, MAX)
+        .speed(0.01 * ticks_per_second.max(MIN))
+        .display_format("%.1f ticks/s")
+        .flags(SliderFlags::LOGARITHMIC | SliderFlags::ALWAYS_CLAMP)
+        .build(ui, ticks_per_second);
+    if ui.is_item_hovered() {
+        ui.tooltip_text("drag to change, double click to type a value");
+    }
+
+    // the slider can't show the rates beyond its range, so it is left at its end for them
+    let mut slider_value = ticks_per_second.min(SLIDER_MAX);
+    if ui.slider_config("##slider", MIN, SLIDER_MAX)
+        .flags(SliderFlags::LOGARITHMIC)
+        .display_format("%.0f")
+        .build(&mut slider_value) {
+        *ticks_per_second = slider_value;
+        changed = true;
+    }
+    changed
+}
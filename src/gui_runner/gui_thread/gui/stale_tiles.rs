@@ -0,0 +1,63 @@
+use glium::{Display, VertexBuffer};
+use nalgebra_glm::{UVec2, vec3};
+use super::picking;
+use super::world_mesh::Vertex;
+use crate::gui_runner::PartialWorld;
+
+// StaleTilesOverlay highlights the tiles where the robot's map is out of date, i.e. whose content
+// changed after the robot last saw them (see PartialWorld::stale_tiles, only known in runs with the
+// god view enabled): every stale tile is crossed out, slightly above the terrain, by a LinesList
+// vertex buffer which is only rebuilt when the set of stale tiles changes. An AI which caches what
+// it saw could be acting on exactly these tiles.
+
+pub struct StaleTilesOverlay {
+    pub show: bool,
+    stale_tiles: Vec<UVec2>, // sorted
+    lines: Vec<Vertex>,
+    pub vbo: Option<VertexBuffer<Vertex>>,
+    vbo_is_outdated: bool,
+}
+impl StaleTilesOverlay {
+    const COLOR: [f32; 3] = [1.0, 0.15, 0.15];
+    const HEIGHT_ABOVE_TERRAIN: f32 = 0.2;
+
+    pub fn new() -> Self {
+        Self { show: true, stale_tiles: vec![], lines: vec![], vbo: None, vbo_is_outdated: false }
+    }
+
+    pub fn count(&self) -> usize {
+        self.stale_tiles.len()
+    }
+
+    // must be called with every new world
+    pub fn update(&mut self, world: &PartialWorld) {
+        let mut stale_tiles = world.stale_tiles.clone();
+        stale_tiles.sort_by_key(|tile_pos| (tile_pos.x, tile_pos.y));
+        if stale_tiles == self.stale_tiles {
+            return;
+        }
+
+        self.lines.clear();
+        for tile_pos in stale_tiles.iter() {
+            let y = picking::tile_anchor(*tile_pos, &world.world).y + Self::HEIGHT_ABOVE_TERRAIN;
+            let corner = |dx: f32, dz: f32| vec3(tile_pos.x as f32 + dx, y, tile_pos.y as f32 + dz);
+            let [a, b, c, d] = [corner(0.1, 0.1), corner(0.9, 0.1), corner(0.9, 0.9), corner(0.1, 0.9)];
+            for (from, to) in [(a, b), (b, c), (c, d), (d, a), (a, c), (b, d)] {
+                self.lines.push(Vertex { position: *from.as_ref(), color: Self::COLOR });
+                self.lines.push(Vertex { position: *to.as_ref(), color: Self::COLOR });
+            }
+        }
+        self.stale_tiles = stale_tiles;
+        self.vbo_is_outdated = true;
+    }
+
+    // returns the number of bytes uploaded to the gpu
+    pub fn update_vbo(&mut self, display: &Display) -> usize {
+        if !self.vbo_is_outdated {
+            return 0;
+        }
+        self.vbo_is_outdated = false;
+        self.vbo = if self.lines.is_empty() { None } else { VertexBuffer::new(display, &self.lines).ok() };
+        self.lines.len() * std::mem::size_of::<Vertex>()
+    }
+}
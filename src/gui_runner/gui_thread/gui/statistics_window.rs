@@ -0,0 +1,142 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use glium::Display;
+use imgui::{Condition, TableFlags, Ui};
+use imgui_glium_renderer::Renderer;
+use crate::gui_runner::run_statistics::{RunStatistics, StatisticsHistory, StatisticsRow};
+use super::charts::Chart;
+
+// StatisticsWindow shows the statistics the worker thread accumulates (see RunStatistics): the
+// totals of the run so far and a chart of every statistic over the ticks, which can be exported to
+// a CSV file with a row per bucket of their histories (readable by the Run comparison window, among
+// others; TickExport writes a row per tick instead). Its copy of the statistics is only updated
+// while the window is open, and only when they changed.
+
+pub struct StatisticsWindow {
+    pub open: bool,
+    statistics: RunStatistics,
+    seen: Option<(u64, u64)>, // see RunStatistics::read_if_changed
+    history: Option<StatisticsHistory>,
+    charts: Vec<Chart>, // one per plot
+    csv_path: String,
+    status: Option<String>,
+}
+impl StatisticsWindow {
+    const PLOT_HEIGHT: f32 = 50.0;
+    const PLOT_COLOR: [u8; 3] = [120, 200, 255];
+    // the name of every plot and the index of its column (see StatisticsRow::CSV_HEADER)
+    const PLOTS: [(&'static str, usize); 6] = [
+        ("tiles discovered per tick", 1),
+        ("tiles discovered", 2),
+        ("distance traveled", 3),
+        ("energy consumed", 4),
+        ("energy recharged", 5),
+        ("items collected", 6),
+    ];
+
+    pub fn new(statistics: RunStatistics) -> Self {
+        let charts = Self::PLOTS.iter().map(|_| Chart::new(Self::PLOT_HEIGHT as u32, Self::PLOT_COLOR)).collect();
+        Self { open: false, statistics, seen: None, history: None, charts, csv_path: "statistics.csv".into(), status: None }
+    }
+
+    // must be called once per frame
+    pub fn update(&mut self) {
+        if !self.open {
+            return;
+        }
+        if let Some(history) = self.statistics.read_if_changed(&mut self.seen) {
+            // the statistics may have started over with as many rows
+            for chart in self.charts.iter_mut() {
+                chart.invalidate();
+            }
+            self.history = Some(history);
+        }
+    }
+
+    // must be called once per frame, after update
+    pub fn update_charts(&mut self, display: &Display, renderer: &mut Renderer) {
+        let Some(history) = self.history.as_ref().filter(|_| self.open) else { return };
+        for (chart, (_, column)) in self.charts.iter_mut().zip(Self::PLOTS) {
+            let max = history.columns[column].range().map_or(0.0, |(_, max)| max);
+            chart.set_range(Some((0.0, max.max(1.0))));
+            chart.update(&history.columns[column], display, renderer);
+        }
+    }
+
+    fn export_csv(&self, history: &StatisticsHistory) -> std::io::Result<usize> {
+        let mut writer = BufWriter::new(File::create(&self.csv_path)?);
+        writeln!(writer, "{}", StatisticsRow::CSV_HEADER)?;
+        let lines = history.csv_lines();
+        for line in lines.iter() {
+            writeln!(writer, "{line}")?;
+        }
+        writer.flush()?;
+        Ok(lines.len())
+    }
+
+    pub fn draw(&mut self, ui: &Ui, world_size: usize) {
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        ui.window("Statistics")
+            .opened(&mut open)
+            .size([360.0, 520.0], Condition::FirstUseEver)
+            .build(|| {
+                let Some((history, last)) = self.history.as_ref().and_then(|history| Some((history, history.last?))) else {
+                    return ui.text_disabled("no tick was run yet");
+                };
+                let first_tick = history.first_tick().unwrap_or(last.tick);
+                ui.text(format!("ticks {first_tick}..{}", last.tick));
+
+                let flags = TableFlags::BORDERS | TableFlags::ROW_BG | TableFlags::SIZING_FIXED_FIT;
+                if let Some(_table) = ui.begin_table_with_flags("totals", 2, flags) {
+                    let per_energy = |value: usize| match last.energy_consumed {
+                        0 => "-".to_string(),
+                        consumed => format!("{:.1}", value as f32 * 100.0 / consumed as f32),
+                    };
+                    let world_tiles = (world_size * world_size).max(1);
+                    let totals = [
+                        ("tiles discovered", format!("{} ({:.1}%)", last.discovered, last.discovered as f32 * 100.0 / world_tiles as f32)),
+                        ("distance traveled", last.distance.to_string()),
+                        ("energy consumed", last.energy_consumed.to_string()),
+                        ("energy recharged", last.energy_recharged.to_string()),
+                        ("items collected", last.items_collected.to_string()),
+                        ("items removed", last.items_removed.to_string()),
+                        ("tiles discovered / 100 energy", per_energy(last.discovered)),
+                        ("items collected / 100 energy", per_energy(last.items_collected)),
+                    ];
+                    for (name, value) in totals {
+                        ui.table_next_row();
+                        ui.table_next_column();
+                        ui.text(name);
+                        ui.table_next_column();
+                        ui.text(value);
+                    }
+                }
+
+                ui.separator();
+                let width = ui.content_region_avail()[0];
+                let values = last.values();
+                for (chart, (name, column)) in self.charts.iter().zip(Self::PLOTS) {
+                    ui.text_disabled(format!("{name}: {}", values[column]));
+                    chart.draw(ui, [width, Self::PLOT_HEIGHT]);
+                }
+
+                ui.separator();
+                ui.set_next_item_width(200.0);
+                ui.input_text("##csv_path", &mut self.csv_path).build();
+                ui.same_line();
+                if ui.button("Export CSV") {
+                    self.status = Some(match self.export_csv(history) {
+                        Ok(rows) => format!("exported {rows} rows to {}", self.csv_path),
+                        Err(e) => format!("could not export: {e}"),
+                    });
+                }
+                if let Some(status) = &self.status {
+                    ui.text_disabled(status);
+                }
+            });
+        self.open = open;
+    }
+}
@@ -0,0 +1,71 @@
+use imgui::{Condition, StyleVar, Ui, WindowFlags};
+use nalgebra_glm::{UVec2, Vec3};
+use crate::gui_runner::RunMode;
+
+// draw_status_bar shows a thin undecorated window along the bottom of the screen with the tick, the
+// run state, the FPS, the camera position, the tile under the mouse (if any) and the outcome of the
+// last action which has no window to tell it in (e.g. saving a screenshot). Like the menu bar
+// at the top, it can't be moved, collapsed or closed, so that the basic state of the run is always
+// in sight regardless of which panels are open.
+
+pub struct StatusInfo {
+    pub tick: usize,
+    pub run_mode: RunMode,
+    pub is_preview: bool,
+    pub is_detached: bool, // showing a world other than the latest one of the run
+    pub fps: f32,
+    pub cam_pos: Vec3,
+    pub hovered_tile: Option<UVec2>,
+    pub message: Option<String>,
+}
+
+const HEIGHT: f32 = 24.0;
+
+fn run_state(info: &StatusInfo) -> String {
+    if info.is_preview {
+        return "preview".into();
+    }
+    let state = match info.run_mode {
+        RunMode::Continuous(Some(cap)) => format!("running ({cap:.0} ticks/s)"),
+        RunMode::Continuous(None) => "running (uncapped)".into(),
+        RunMode::RunTicks(ticks) => format!("running {ticks} ticks"),
+        RunMode::SingleTick | RunMode::StepBack => "stepping".into(),
+        RunMode::Seek(tick) => format!("seeking tick {tick}"),
+        RunMode::Paused => "paused".into(),
+        RunMode::Terminate => "terminated".into(),
+    };
+    match info.is_detached {
+        true => format!("{state}, detached"),
+        false => state,
+    }
+}
+
+pub fn draw_status_bar(ui: &Ui, info: &StatusInfo) {
+    let [width, height] = ui.io().display_size;
+    let _padding = ui.push_style_var(StyleVar::WindowPadding([8.0, 4.0]));
+    let _rounding = ui.push_style_var(StyleVar::WindowRounding(0.0));
+    ui.window("##status_bar")
+        .position([0.0, height - HEIGHT], Condition::Always)
+        .size([width, HEIGHT], Condition::Always)
+        .flags(WindowFlags::NO_DECORATION | WindowFlags::NO_MOVE | WindowFlags::NO_SAVED_SETTINGS
+            | WindowFlags::NO_FOCUS_ON_APPEARING | WindowFlags::NO_BRING_TO_FRONT_ON_FOCUS | WindowFlags::NO_NAV)
+        .build(|| {
+            ui.text(format!("tick {}", info.tick));
+            for text in [
+                run_state(info),
+                format!("{:.0} FPS", info.fps),
+                format!("camera {:.1}, {:.1}, {:.1}", info.cam_pos.x, info.cam_pos.y, info.cam_pos.z),
+            ] {
+                ui.same_line_with_spacing(0.0, 24.0);
+                ui.text(text);
+            }
+            if let Some(tile) = info.hovered_tile {
+                ui.same_line_with_spacing(0.0, 24.0);
+                ui.text(format!("tile {}, {}", tile.x, tile.y));
+            }
+            if let Some(message) = &info.message {
+                ui.same_line_with_spacing(0.0, 24.0);
+                ui.text_disabled(message);
+            }
+        });
+}
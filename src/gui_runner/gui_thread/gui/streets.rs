@@ -0,0 +1,158 @@
+use std::collections::HashSet;
+use glium::{Display, VertexBuffer};
+use imgui::{Condition, Ui};
+use nalgebra_glm::{Mat4, UVec2, vec3};
+use robotics_lib::world::tile::TileType;
+use super::picking;
+use super::world_mesh::Vertex;
+use crate::gui_runner::PartialWorld;
+
+// StreetNetwork extracts the road network from the discovered Street tiles: every street tile is a
+// node, connected to the street tiles next to it (not diagonally). The network is drawn in the world
+// as a highlighted line along every road (a LinesList vertex buffer slightly above the terrain,
+// rebuilt only when street tiles are discovered or removed), with markers over its junctions (street
+// tiles connected to three or more others), and summarized in a window: total length, number of
+// junctions and dead ends, and number of separate networks (connected components).
+// Street tiles are only searched for among the tiles which changed, like the world mesh does.
+
+#[derive(Default)]
+struct NetworkStats {
+    length: usize, // in tiles, i.e. the number of connections between adjacent street tiles
+    junctions: usize,
+    dead_ends: usize,
+    components: usize,
+}
+
+pub struct StreetNetwork {
+    pub open: bool,
+    pub show_overlay: bool,
+    streets: HashSet<UVec2>,
+    junctions: Vec<UVec2>,
+    stats: NetworkStats,
+    lines: Vec<Vertex>,
+    pub vbo: Option<VertexBuffer<Vertex>>,
+    vbo_is_outdated: bool,
+}
+impl StreetNetwork {
+    const COLOR: [f32; 3] = [1.0, 0.55, 0.1];
+    const HEIGHT_ABOVE_TERRAIN: f32 = 0.15;
+
+    pub fn new() -> Self {
+        Self {
+            open: false, show_overlay: false,
+            streets: HashSet::new(), junctions: vec![], stats: NetworkStats::default(),
+            lines: vec![], vbo: None, vbo_is_outdated: false,
+        }
+    }
+
+    // must be called with every new world, before its tiles_to_refresh are cleared
+    pub fn update(&mut self, world: &PartialWorld) {
+        let mut changed = false;
+        for tile_pos in world.tiles_to_refresh.iter() {
+            let is_street = world.world[tile_pos.x as usize][tile_pos.y as usize].as_ref()
+                .map(|tile| matches!(tile.tile_type, TileType::Street))
+                .unwrap_or(false);
+            changed |= if is_street { self.streets.insert(*tile_pos) } else { self.streets.remove(tile_pos) };
+        }
+        if changed {
+            self.rebuild(world);
+        }
+    }
+
+    // street tiles next to tile_pos, only looking right and down when forward_only is set (so that
+    // every connection is only found from one of its ends)
+    fn neighbours(&self, tile_pos: UVec2, forward_only: bool) -> impl Iterator<Item = UVec2> + '_ {
+        let offsets: &[(i64, i64)] = if forward_only { &[(1, 0), (0, 1)] } else { &[(1, 0), (0, 1), (-1, 0), (0, -1)] };
+        offsets.iter().filter_map(move |(dx, dy)| {
+            let x = u32::try_from(tile_pos.x as i64 + dx).ok()?;
+            let y = u32::try_from(tile_pos.y as i64 + dy).ok()?;
+            Some(UVec2::new(x, y)).filter(|neighbour| self.streets.contains(neighbour))
+        })
+    }
+
+    fn rebuild(&mut self, world: &PartialWorld) {
+        let anchor = |tile_pos: UVec2| picking::tile_anchor(tile_pos, &world.world) + vec3(0.0, Self::HEIGHT_ABOVE_TERRAIN, 0.0);
+
+        let mut lines = vec![];
+        let mut junctions = vec![];
+        let mut stats = NetworkStats::default();
+        for street in self.streets.iter() {
+            for neighbour in self.neighbours(*street, true) {
+                lines.push(Vertex { position: *anchor(*street).as_ref(), color: Self::COLOR });
+                lines.push(Vertex { position: *anchor(neighbour).as_ref(), color: Self::COLOR });
+                stats.length += 1;
+            }
+            match self.neighbours(*street, false).count() {
+                0 | 2 => {}
+                1 => stats.dead_ends += 1,
+                _ => junctions.push(*street),
+            }
+        }
+        stats.junctions = junctions.len();
+
+        // count the connected components with a flood fill
+        let mut visited = HashSet::new();
+        for street in self.streets.iter() {
+            if !visited.insert(*street) {
+                continue;
+            }
+            stats.components += 1;
+            let mut stack = vec![*street];
+            while let Some(tile_pos) = stack.pop() {
+                stack.extend(self.neighbours(tile_pos, false).filter(|neighbour| visited.insert(*neighbour)));
+            }
+        }
+
+        self.lines = lines;
+        self.junctions = junctions;
+        self.stats = stats;
+        self.vbo_is_outdated = true;
+    }
+
+    // returns the number of bytes uploaded to the gpu
+    pub fn update_vbo(&mut self, display: &Display) -> usize {
+        if !self.vbo_is_outdated {
+            return 0;
+        }
+        self.vbo_is_outdated = false;
+        self.vbo = if self.lines.is_empty() { None } else { VertexBuffer::new(display, &self.lines).ok() };
+        self.lines.len() * std::mem::size_of::<Vertex>()
+    }
+
+    // draws a marker over every junction in view
+    pub fn draw_junctions(&self, ui: &Ui, mvp: &Mat4, world: &PartialWorld) {
+        if !self.show_overlay {
+            return;
+        }
+        let display_size = ui.io().display_size;
+        let draw_list = ui.get_background_draw_list();
+        for junction in self.junctions.iter() {
+            let anchor = picking::tile_anchor(*junction, &world.world) + vec3(0.0, Self::HEIGHT_ABOVE_TERRAIN, 0.0);
+            if let Some(position) = picking::project_to_screen(mvp, anchor, display_size) {
+                draw_list.add_circle(position, 5.0, [1.0, 0.55, 0.1, 1.0]).filled(true).build();
+                draw_list.add_circle(position, 5.0, [0.0, 0.0, 0.0, 1.0]).build();
+            }
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        ui.window("Street network")
+            .opened(&mut open)
+            .size([260.0, 150.0], Condition::FirstUseEver)
+            .build(|| {
+                ui.checkbox("Show overlay", &mut self.show_overlay);
+                ui.separator();
+                ui.text(format!("Street tiles discovered: {}", self.streets.len()));
+                ui.text(format!("Total road length: {} tiles", self.stats.length));
+                ui.text(format!("Junctions: {}", self.stats.junctions));
+                ui.text(format!("Dead ends: {}", self.stats.dead_ends));
+                ui.text(format!("Separate networks: {}", self.stats.components));
+            });
+        self.open = open;
+    }
+}
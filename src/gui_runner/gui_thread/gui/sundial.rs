@@ -0,0 +1,64 @@
+use std::f32::consts::PI;
+use imgui::{Condition, Ui, WindowFlags};
+use robotics_lib::world::environmental_conditions::{DayTime, EnvironmentalConditions};
+
+// draw_sundial draws a small undecorated HUD window in the top right corner showing the time of
+// day as a sun (or moon) travelling along an arc over the horizon, with the time written below it.
+// The sun rises at 6:00 on the left and sets at 18:00 on the right; the moon does the same from
+// 18:00 to 6:00.
+
+const SIZE: [f32; 2] = [120.0, 80.0];
+
+// parses the "hh:mm" string of EnvironmentalConditions into hours since midnight, falling back to
+// a representative hour for the time of day if the format is not the expected one
+pub fn hours_since_midnight(env_cond: &EnvironmentalConditions) -> f32 {
+    let time_string = env_cond.get_time_of_day_string();
+    let mut split = time_string.split(':').map(|s| s.trim().parse::<f32>());
+    match (split.next(), split.next()) {
+        (Some(Ok(h)), Some(Ok(m))) => (h + m / 60.0) % 24.0,
+        _ => match env_cond.get_time_of_day() {
+            DayTime::Morning => 9.0,
+            DayTime::Afternoon => 15.0,
+            DayTime::Night => 0.0,
+        },
+    }
+}
+
+pub fn draw_sundial(ui: &Ui, env_cond: &EnvironmentalConditions) {
+    let display_size = ui.io().display_size;
+    ui.window("Clock")
+        .position([display_size[0] - SIZE[0] - 10.0, 10.0], Condition::Always)
+        .size(SIZE, Condition::Always)
+        .flags(WindowFlags::NO_DECORATION | WindowFlags::NO_MOVE | WindowFlags::NO_SAVED_SETTINGS | WindowFlags::NO_FOCUS_ON_APPEARING | WindowFlags::NO_NAV)
+        .bg_alpha(0.5)
+        .build(|| {
+            let hours = hours_since_midnight(env_cond);
+            let is_day = (6.0..18.0).contains(&hours);
+            // 0 at rise, 1 at set
+            let progress = if is_day { (hours - 6.0) / 12.0 } else { ((hours + 6.0) % 24.0) / 12.0 };
+
+            let [x, y] = ui.cursor_screen_pos();
+            let center = [x + SIZE[0] / 2.0 - 8.0, y + 42.0];
+            let radius = 40.0;
+
+            let draw_list = ui.get_window_draw_list();
+            let arc_color = [0.7, 0.7, 0.7, 0.6];
+            let segments = 24;
+            for i in 0..segments {
+                let a0 = PI * i as f32 / segments as f32;
+                let a1 = PI * (i + 1) as f32 / segments as f32;
+                let p0 = [center[0] - radius * a0.cos(), center[1] - radius * a0.sin()];
+                let p1 = [center[0] - radius * a1.cos(), center[1] - radius * a1.sin()];
+                draw_list.add_line(p0, p1, arc_color).build();
+            }
+            draw_list.add_line([center[0] - radius - 4.0, center[1]], [center[0] + radius + 4.0, center[1]], [0.5, 0.4, 0.3, 1.0]).thickness(2.0).build();
+
+            let angle = PI * progress;
+            let body_position = [center[0] - radius * angle.cos(), center[1] - radius * angle.sin()];
+            let body_color = if is_day { [1.0, 0.85, 0.2, 1.0] } else { [0.85, 0.85, 1.0, 1.0] };
+            draw_list.add_circle(body_position, 6.0, body_color).filled(true).build();
+
+            let time_string = env_cond.get_time_of_day_string();
+            draw_list.add_text([center[0] - 16.0, center[1] + 4.0], [1.0, 1.0, 1.0, 1.0], &time_string);
+        });
+}
@@ -0,0 +1,340 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use glium::Display;
+use imgui::{Condition, ImColor32, Ui};
+use imgui_glium_renderer::Renderer;
+use crate::gui_runner::Telemetry;
+use super::charts::{self, DownsampledHistory};
+
+// TelemetryPanel is an imgui window charting the series published by the host code through the
+// Telemetry handle. It keeps its own copy of the last MAX_SAMPLES values of every series (read
+// once per frame), along with a DownsampledHistory of the last TIME_SERIES_SAMPLES of each, and
+// any number of charts, each of which is either:
+// - a time series, drawing the DownsampledHistory of a series as a texture (see charts::Chart);
+// - a histogram, counting the values of a series in a number of bins spanning the range of its
+//   values (or a range set by hand), counted again only when the series or the bins change;
+// - a scatter plot of a series against another, pairing their values by tick (the last value
+//   published at each tick).
+// Every chart can be exported to a CSV file with the same data it plots.
+
+#[derive(Clone, Copy, PartialEq)]
+enum ChartKind {
+    TimeSeries,
+    Histogram,
+    Scatter,
+}
+impl ChartKind {
+    const ALL: [ChartKind; 3] = [ChartKind::TimeSeries, ChartKind::Histogram, ChartKind::Scatter];
+
+    fn name(&self) -> &'static str {
+        match self {
+            ChartKind::TimeSeries => "time series",
+            ChartKind::Histogram => "histogram",
+            ChartKind::Scatter => "scatter plot",
+        }
+    }
+}
+
+// a range of values, either fitted to the data or set by hand
+struct Axis {
+    auto: bool,
+    range: [f32; 2],
+}
+impl Axis {
+    fn new() -> Self {
+        Self { auto: true, range: [0.0, 1.0] }
+    }
+
+    fn fit(&mut self, values: impl Iterator<Item = f64>) {
+        if !self.auto {
+            return;
+        }
+        let range = values.filter(|value| value.is_finite()).fold(None, |range, value| match range {
+            None => Some([value, value]),
+            Some([min, max]) => Some([min.min(value), max.max(value)]),
+        });
+        if let Some([min, max]) = range {
+            self.range = [min as f32, max as f32];
+        }
+    }
+
+    fn draw(&mut self, ui: &Ui, label: &str) {
+        ui.checkbox(format!("fit {label}"), &mut self.auto);
+        ui.same_line();
+        ui.disabled(self.auto, || {
+            ui.set_next_item_width(140.0);
+            ui.input_float2(format!("{label} range"), &mut self.range).build();
+        });
+    }
+
+    // maps the value to 0..=1, if it is in range
+    fn normalize(&self, value: f64) -> Option<f32> {
+        let [min, max] = self.range;
+        let t = if max > min { (value as f32 - min) / (max - min) } else { 0.5 };
+        (0.0..=1.0).contains(&t).then_some(t)
+    }
+}
+
+struct Chart {
+    kind: ChartKind,
+    series: String,
+    other_series: String, // y axis of the scatter plots
+    bins: i32,
+    x_axis: Axis,
+    y_axis: Axis,
+    csv_path: String,
+    status: Option<String>,
+    texture: charts::Chart, // of the time series
+    counts: Vec<f32>, // of the histogram
+    counted: Option<(u64, i32, [f32; 2], bool)>, // samples of the series, bins, range and whether it was fitted
+}
+
+pub struct TelemetryPanel {
+    pub open: bool,
+    telemetry: Telemetry,
+    cursors: HashMap<String, u64>,
+    series: BTreeMap<String, VecDeque<(usize, f64)>>, // tick, value
+    histories: HashMap<String, DownsampledHistory>, // of the time series
+    charts: Vec<Chart>,
+    charts_created: usize,
+}
+impl TelemetryPanel {
+    const TIME_SERIES_SAMPLES: usize = 2000; // shown at most
+    const TIME_SERIES_BUCKETS: usize = 256;
+    const CHART_HEIGHT: f32 = 120.0;
+
+    pub fn new(telemetry: Telemetry) -> Self {
+        Self { open: false, telemetry, cursors: HashMap::new(), series: BTreeMap::new(), histories: HashMap::new(), charts: vec![], charts_created: 0 }
+    }
+
+    // must be called once per frame
+    pub fn receive(&mut self) {
+        for (name, samples) in self.telemetry.read_new(&mut self.cursors) {
+            let history = self.histories.entry(name.clone())
+                .or_insert_with(|| DownsampledHistory::with_window(Self::TIME_SERIES_BUCKETS, Self::TIME_SERIES_SAMPLES));
+            for (_, value) in samples.iter() {
+                history.push(*value as f32);
+            }
+            let series = self.series.entry(name).or_default();
+            series.extend(samples);
+            let excess = series.len().saturating_sub(Telemetry::MAX_SAMPLES);
+            series.drain(..excess);
+        }
+    }
+
+    fn add_chart(&mut self, kind: ChartKind) {
+        let series = self.series.keys().next().cloned().unwrap_or_default();
+        let other_series = self.series.keys().nth(1).cloned().unwrap_or_else(|| series.clone());
+        self.charts_created += 1;
+        let csv_path = format!("chart-{}.csv", self.charts_created);
+        let texture = charts::Chart::new(Self::CHART_HEIGHT as u32, [120, 200, 255]);
+        self.charts.push(Chart { kind, series, other_series, bins: 20, x_axis: Axis::new(), y_axis: Axis::new(), csv_path, status: None, texture, counts: vec![], counted: None });
+    }
+
+    // must be called once per frame, before draw, while the panel is open
+    pub fn update_charts(&mut self, display: &Display, renderer: &mut Renderer) {
+        if !self.open {
+            return;
+        }
+        for chart in self.charts.iter_mut().filter(|chart| chart.kind == ChartKind::TimeSeries) {
+            if let Some(history) = self.histories.get(&chart.series) {
+                if let Some((min, max)) = history.range() {
+                    chart.y_axis.fit([min as f64, max as f64].into_iter());
+                }
+                let [min, max] = chart.y_axis.range;
+                chart.texture.set_range((!chart.y_axis.auto).then_some((min, max)));
+                chart.texture.update(history, display, renderer);
+            }
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui) {
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        ui.window("Telemetry")
+            .opened(&mut open)
+            .size([420.0, 480.0], Condition::FirstUseEver)
+            .build(|| {
+                if self.series.is_empty() {
+                    ui.text_wrapped("Nothing was published yet: values are published through the Telemetry passed to GuiRunnerBuilder::telemetry.");
+                    return;
+                }
+                for kind in ChartKind::ALL {
+                    if ui.button(format!("Add {}", kind.name())) {
+                        self.add_chart(kind);
+                    }
+                    ui.same_line();
+                }
+                ui.new_line();
+
+                let mut to_remove = None;
+                for (i, chart) in self.charts.iter_mut().enumerate() {
+                    let _id = ui.push_id_usize(i);
+                    ui.separator();
+                    chart.draw(ui, &self.series, &self.histories);
+                    if ui.small_button("Remove") {
+                        to_remove = Some(i);
+                    }
+                }
+                if let Some(i) = to_remove {
+                    self.charts.remove(i);
+                }
+            });
+        self.open = open;
+    }
+}
+
+impl Chart {
+    fn draw(&mut self, ui: &Ui, all_series: &BTreeMap<String, VecDeque<(usize, f64)>>, histories: &HashMap<String, DownsampledHistory>) {
+        ui.text(self.kind.name());
+        ui.same_line();
+        if Self::series_combo(ui, "##series", &mut self.series, all_series) {
+            self.texture.invalidate();
+        }
+        if self.kind == ChartKind::Scatter {
+            ui.same_line();
+            ui.text("vs");
+            ui.same_line();
+            Self::series_combo(ui, "##other_series", &mut self.other_series, all_series);
+        }
+
+        let empty = VecDeque::new();
+        let samples = all_series.get(&self.series).unwrap_or(&empty);
+        let width = ui.content_region_avail()[0];
+        match self.kind {
+            ChartKind::TimeSeries => {
+                self.y_axis.draw(ui, "y");
+                ui.text_disabled(format!("{}: {}", self.series, samples.back().map_or(String::new(), |(_, value)| value.to_string())));
+                if histories.contains_key(&self.series) {
+                    self.texture.draw(ui, [width, TelemetryPanel::CHART_HEIGHT]);
+                }
+            }
+            ChartKind::Histogram => {
+                ui.set_next_item_width(100.0);
+                ui.input_int("bins", &mut self.bins).build();
+                self.bins = self.bins.clamp(1, 1000);
+                self.x_axis.draw(ui, "x");
+                // the range is fitted and the values counted again only when they may have changed
+                let published = histories.get(&self.series).map_or(0, DownsampledHistory::total_samples);
+                let fitted = self.counted.is_some_and(|(counted, _, _, fitted)| counted == published && fitted);
+                if self.x_axis.auto && !fitted {
+                    self.x_axis.fit(samples.iter().map(|(_, value)| *value));
+                }
+                let counted = (published, self.bins, self.x_axis.range, self.x_axis.auto);
+                if self.counted != Some(counted) {
+                    self.counts = self.histogram(samples);
+                    self.counted = Some(counted);
+                }
+                let [min, max] = self.x_axis.range;
+                ui.plot_histogram("##histogram", &self.counts)
+                    .overlay_text(format!("{} in {min}..{max}", self.series))
+                    .scale_min(0.0)
+                    .graph_size([width, TelemetryPanel::CHART_HEIGHT])
+                    .build();
+            }
+            ChartKind::Scatter => {
+                self.x_axis.draw(ui, "x");
+                self.y_axis.draw(ui, "y");
+                let points = self.scatter_points(all_series);
+                self.x_axis.fit(points.iter().map(|(_, x, _)| *x));
+                self.y_axis.fit(points.iter().map(|(_, _, y)| *y));
+                self.draw_scatter(ui, &points, [width, TelemetryPanel::CHART_HEIGHT]);
+            }
+        }
+
+        ui.set_next_item_width(200.0);
+        ui.input_text("##csv_path", &mut self.csv_path).build();
+        ui.same_line();
+        if ui.button("Export CSV") {
+            self.status = Some(match self.export_csv(all_series) {
+                Ok(()) => format!("exported to {}", self.csv_path),
+                Err(e) => format!("could not export: {e}"),
+            });
+        }
+        if let Some(status) = &self.status {
+            ui.text_disabled(status);
+        }
+    }
+
+    // returns whether another series was selected
+    fn series_combo(ui: &Ui, label: &str, selected: &mut String, all_series: &BTreeMap<String, VecDeque<(usize, f64)>>) -> bool {
+        let mut changed = false;
+        ui.set_next_item_width(140.0);
+        if let Some(_combo) = ui.begin_combo(label, selected.as_str()) {
+            for name in all_series.keys() {
+                if ui.selectable_config(name).selected(name == selected).build() && name != selected {
+                    *selected = name.clone();
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+
+    fn histogram(&self, samples: &VecDeque<(usize, f64)>) -> Vec<f32> {
+        let mut counts = vec![0.0; self.bins as usize];
+        for (_, value) in samples {
+            if let Some(t) = self.x_axis.normalize(*value) {
+                let bin = ((t * self.bins as f32) as usize).min(counts.len() - 1);
+                counts[bin] += 1.0;
+            }
+        }
+        counts
+    }
+
+    // the last values of the two series at every tick at which both were published
+    fn scatter_points(&self, all_series: &BTreeMap<String, VecDeque<(usize, f64)>>) -> Vec<(usize, f64, f64)> {
+        let (Some(xs), Some(ys)) = (all_series.get(&self.series), all_series.get(&self.other_series)) else { return vec![] };
+        let ys: HashMap<usize, f64> = ys.iter().copied().collect();
+        let xs: BTreeMap<usize, f64> = xs.iter().copied().collect();
+        xs.into_iter().filter_map(|(tick, x)| ys.get(&tick).map(|y| (tick, x, *y))).collect()
+    }
+
+    fn draw_scatter(&self, ui: &Ui, points: &[(usize, f64, f64)], [width, height]: [f32; 2]) {
+        const POINT_RADIUS: f32 = 2.0;
+        let [left, top] = ui.cursor_screen_pos();
+        let draw_list = ui.get_window_draw_list();
+        draw_list.add_rect([left, top], [left + width, top + height], ImColor32::from_rgba(40, 40, 40, 255)).filled(true).build();
+        for (_, x, y) in points {
+            if let (Some(tx), Some(ty)) = (self.x_axis.normalize(*x), self.y_axis.normalize(*y)) {
+                let center = [left + tx * width, top + (1.0 - ty) * height];
+                draw_list.add_circle(center, POINT_RADIUS, ImColor32::from_rgb(120, 200, 255)).filled(true).build();
+            }
+        }
+        ui.dummy([width, height]);
+        let ([x_min, x_max], [y_min, y_max]) = (self.x_axis.range, self.y_axis.range);
+        ui.text_disabled(format!("{} points; x: {x_min}..{x_max}, y: {y_min}..{y_max}", points.len()));
+    }
+
+    fn export_csv(&self, all_series: &BTreeMap<String, VecDeque<(usize, f64)>>) -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(&self.csv_path)?);
+        let empty = VecDeque::new();
+        let samples = all_series.get(&self.series).unwrap_or(&empty);
+        match self.kind {
+            ChartKind::TimeSeries => {
+                writeln!(writer, "tick,{}", self.series)?;
+                for (tick, value) in samples {
+                    writeln!(writer, "{tick},{value}")?;
+                }
+            }
+            ChartKind::Histogram => {
+                writeln!(writer, "bin_start,bin_end,count")?;
+                let [min, max] = self.x_axis.range;
+                let bin_width = (max - min) / self.bins as f32;
+                for (i, count) in self.histogram(samples).iter().enumerate() {
+                    writeln!(writer, "{},{},{count}", min + bin_width * i as f32, min + bin_width * (i + 1) as f32)?;
+                }
+            }
+            ChartKind::Scatter => {
+                writeln!(writer, "tick,{},{}", self.series, self.other_series)?;
+                for (tick, x, y) in self.scatter_points(all_series) {
+                    writeln!(writer, "{tick},{x},{y}")?;
+                }
+            }
+        }
+        writer.flush()
+    }
+}
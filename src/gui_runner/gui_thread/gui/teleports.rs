@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use glium::{Display, VertexBuffer};
+use imgui::{Condition, MouseButton, Ui};
+use nalgebra_glm::{UVec2, Vec3, vec3};
+use robotics_lib::world::tile::TileType;
+use super::picking;
+use super::world_mesh::Vertex;
+use crate::gui_runner::PartialWorld;
+
+// TeleportNetwork collects the discovered Teleport tiles and shows the network they form: since the
+// robot can teleport from any teleport to any activated one, every pair of activated teleports is
+// connected. The network is drawn both in the world, as arcs between the teleports (kept in a
+// LinesList vertex buffer, rebuilt only when a teleport is discovered, activated or removed), and
+// as a small 2D graph in its own window, where clicking a teleport moves the camera to it.
+// Teleports are only searched for among the tiles which changed, like the world mesh does.
+
+pub struct TeleportNetwork {
+    pub open: bool,
+    pub show_arcs: bool,
+    teleports: BTreeMap<(u32, u32), bool>, // position -> activated
+    arcs: Vec<Vertex>,
+    pub vbo: Option<VertexBuffer<Vertex>>,
+    vbo_is_outdated: bool,
+}
+impl TeleportNetwork {
+    const ARC_SEGMENTS: usize = 16;
+    const ARC_COLOR: [f32; 3] = [0.95, 0.3, 1.0];
+    const GRAPH_SIZE: f32 = 220.0;
+
+    pub fn new() -> Self {
+        Self { open: false, show_arcs: true, teleports: BTreeMap::new(), arcs: vec![], vbo: None, vbo_is_outdated: false }
+    }
+
+    // must be called with every new world, before its tiles_to_refresh are cleared
+    pub fn update(&mut self, world: &PartialWorld) {
+        let mut changed = false;
+        for tile_pos in world.tiles_to_refresh.iter() {
+            let key = (tile_pos.x, tile_pos.y);
+            let teleport = world.world[tile_pos.x as usize][tile_pos.y as usize].as_ref().and_then(|tile| match tile.tile_type {
+                TileType::Teleport(activated) => Some(activated),
+                _ => None,
+            });
+            changed |= match teleport {
+                Some(activated) => self.teleports.insert(key, activated) != Some(activated),
+                None => self.teleports.remove(&key).is_some(),
+            };
+        }
+        if changed {
+            self.rebuild_arcs(world);
+        }
+    }
+
+    fn rebuild_arcs(&mut self, world: &PartialWorld) {
+        let activated: Vec<Vec3> = self.teleports.iter()
+            .filter(|(_, activated)| **activated)
+            .map(|((x, y), _)| picking::tile_anchor(UVec2::new(*x, *y), &world.world))
+            .collect();
+
+        self.arcs.clear();
+        for (i, from) in activated.iter().enumerate() {
+            for to in activated[i + 1..].iter() {
+                // a parabola whose height grows with the distance it spans
+                let height = nalgebra_glm::distance(from, to) * 0.25 + 2.0;
+                let point_at = |t: f32| from + (to - from) * t + vec3(0.0, height * 4.0 * t * (1.0 - t), 0.0);
+                for segment in 0..Self::ARC_SEGMENTS {
+                    for t in [segment, segment + 1].map(|n| n as f32 / Self::ARC_SEGMENTS as f32) {
+                        self.arcs.push(Vertex { position: *point_at(t).as_ref(), color: Self::ARC_COLOR });
+                    }
+                }
+            }
+        }
+        self.vbo_is_outdated = true;
+    }
+
+    // returns the number of bytes uploaded to the gpu
+    pub fn update_vbo(&mut self, display: &Display) -> usize {
+        if !self.vbo_is_outdated {
+            return 0;
+        }
+        self.vbo_is_outdated = false;
+        self.vbo = if self.arcs.is_empty() { None } else { VertexBuffer::new(display, &self.arcs).ok() };
+        self.arcs.len() * std::mem::size_of::<Vertex>()
+    }
+
+    // draws the graph window, returning the teleport the user clicked on, if any
+    pub fn draw(&mut self, ui: &Ui, world_size: usize) -> Option<UVec2> {
+        if !self.open {
+            return None;
+        }
+
+        let mut clicked = None;
+        let mut open = self.open;
+        ui.window("Teleport network")
+            .opened(&mut open)
+            .size([Self::GRAPH_SIZE + 20.0, Self::GRAPH_SIZE + 100.0], Condition::FirstUseEver)
+            .build(|| {
+                let activated = self.teleports.values().filter(|activated| **activated).count();
+                ui.text(format!("{} teleports discovered, {} activated", self.teleports.len(), activated));
+                ui.text(format!("{} connections", activated * activated.saturating_sub(1) / 2));
+                ui.checkbox("Show arcs in the world", &mut self.show_arcs);
+
+                let origin = ui.cursor_screen_pos();
+                ui.invisible_button("graph", [Self::GRAPH_SIZE, Self::GRAPH_SIZE]);
+                let draw_list = ui.get_window_draw_list();
+                draw_list.add_rect(origin, [origin[0] + Self::GRAPH_SIZE, origin[1] + Self::GRAPH_SIZE], [0.5, 0.5, 0.5, 0.6]).build();
+
+                // rows go downwards and columns rightwards, as in the robot's map
+                let to_screen = |(x, y): (u32, u32)| [
+                    origin[0] + (y as f32 + 0.5) / world_size.max(1) as f32 * Self::GRAPH_SIZE,
+                    origin[1] + (x as f32 + 0.5) / world_size.max(1) as f32 * Self::GRAPH_SIZE,
+                ];
+                let activated: Vec<[f32; 2]> = self.teleports.iter().filter(|(_, a)| **a).map(|(p, _)| to_screen(*p)).collect();
+                for (i, from) in activated.iter().enumerate() {
+                    for to in activated[i + 1..].iter() {
+                        draw_list.add_line(*from, *to, [0.95, 0.3, 1.0, 0.4]).build();
+                    }
+                }
+
+                let mouse_pos = ui.io().mouse_pos;
+                for (position, activated) in self.teleports.iter() {
+                    let center = to_screen(*position);
+                    let color = if *activated { [0.95, 0.3, 1.0, 1.0] } else { [0.6, 0.6, 0.6, 1.0] };
+                    draw_list.add_circle(center, 4.0, color).filled(true).build();
+
+                    let is_hovered = (mouse_pos[0] - center[0]).abs() <= 6.0 && (mouse_pos[1] - center[1]).abs() <= 6.0;
+                    if is_hovered && ui.is_window_hovered() {
+                        let state = if *activated { "activated" } else { "not activated" };
+                        ui.tooltip_text(format!("teleport at ({}, {}), {state}\nclick to go there", position.0, position.1));
+                        if ui.is_mouse_clicked(MouseButton::Left) {
+                            clicked = Some(UVec2::new(position.0, position.1));
+                        }
+                    }
+                }
+            });
+        self.open = open;
+        clicked
+    }
+}
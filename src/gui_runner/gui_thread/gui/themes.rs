@@ -0,0 +1,123 @@
+use imgui::{Style, StyleColor, Ui};
+use serde::{Deserialize, Serialize};
+
+// Theme is the style of the imgui windows. Dark and Light are ragnarok's own styles: imgui's
+// colors with quieter backgrounds, rounder corners and roomier frames, and every highlight (buttons,
+// headers, sliders, check marks, tabs, docking previews, ...) tinted with the accent color chosen
+// by the user. Classic is imgui's classic style as it is, which ignores the accent color.
+// Themes are applied to imgui's default style, so that switching themes leaves nothing of the
+// previous one behind, and all the sizes are then multiplied by the UI scale of the settings.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    Dark,
+    Light,
+    Classic,
+}
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Dark, Theme::Light, Theme::Classic];
+    pub const DEFAULT_ACCENT: [f32; 3] = [0.26, 0.59, 0.98];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+            Theme::Classic => "Classic",
+        }
+    }
+
+    pub fn apply(&self, accent: [f32; 3], ui_scale: f32, default: &Style, style: &mut Style) {
+        *style = *default;
+        match self {
+            Theme::Dark => {
+                style.use_dark_colors();
+                style[StyleColor::WindowBg] = [0.08, 0.08, 0.09, 0.94];
+                style[StyleColor::ChildBg] = [0.0, 0.0, 0.0, 0.0];
+                style[StyleColor::PopupBg] = [0.09, 0.09, 0.10, 0.96];
+                style[StyleColor::FrameBg] = [0.18, 0.19, 0.21, 0.8];
+                style[StyleColor::TitleBg] = [0.06, 0.06, 0.07, 1.0];
+                style[StyleColor::MenuBarBg] = [0.11, 0.11, 0.12, 1.0];
+                Self::apply_accent(accent, 0.45, style);
+            }
+            Theme::Light => {
+                style.use_light_colors();
+                style[StyleColor::WindowBg] = [0.95, 0.95, 0.96, 0.96];
+                style[StyleColor::PopupBg] = [0.98, 0.98, 0.99, 0.98];
+                style[StyleColor::FrameBg] = [0.88, 0.89, 0.91, 1.0];
+                style[StyleColor::TitleBg] = [0.86, 0.86, 0.88, 1.0];
+                style[StyleColor::MenuBarBg] = [0.89, 0.89, 0.91, 1.0];
+                Self::apply_accent(accent, 0.85, style);
+            }
+            Theme::Classic => {
+                style.use_classic_colors();
+            }
+        }
+        if *self != Theme::Classic {
+            style.window_rounding = 5.0;
+            style.child_rounding = 4.0;
+            style.popup_rounding = 4.0;
+            style.frame_rounding = 3.0;
+            style.grab_rounding = 3.0;
+            style.scrollbar_rounding = 6.0;
+            style.tab_rounding = 3.0;
+            style.frame_padding = [6.0, 4.0];
+            style.item_spacing = [8.0, 5.0];
+            style.window_border_size = 0.0;
+        }
+        style.scale_all_sizes(ui_scale);
+    }
+
+    // tints the highlights with the accent color. title_shade darkens (or lightens) the accent for
+    // the title bar of the focused window, which sits behind text of the theme's color
+    fn apply_accent([r, g, b]: [f32; 3], title_shade: f32, style: &mut Style) {
+        let shade = |k: f32, alpha: f32| [(r * k).min(1.0), (g * k).min(1.0), (b * k).min(1.0), alpha];
+        let colors = [
+            (StyleColor::CheckMark, shade(1.0, 1.0)),
+            (StyleColor::SliderGrab, shade(0.9, 1.0)),
+            (StyleColor::SliderGrabActive, shade(1.1, 1.0)),
+            (StyleColor::Button, shade(1.0, 0.4)),
+            (StyleColor::ButtonHovered, shade(1.0, 1.0)),
+            (StyleColor::ButtonActive, shade(0.85, 1.0)),
+            (StyleColor::Header, shade(1.0, 0.31)),
+            (StyleColor::HeaderHovered, shade(1.0, 0.8)),
+            (StyleColor::HeaderActive, shade(1.0, 1.0)),
+            (StyleColor::FrameBgHovered, shade(1.0, 0.4)),
+            (StyleColor::FrameBgActive, shade(1.0, 0.67)),
+            (StyleColor::TitleBgActive, shade(title_shade, 1.0)),
+            (StyleColor::SeparatorHovered, shade(0.8, 0.78)),
+            (StyleColor::SeparatorActive, shade(0.8, 1.0)),
+            (StyleColor::ResizeGrip, shade(1.0, 0.2)),
+            (StyleColor::ResizeGripHovered, shade(1.0, 0.67)),
+            (StyleColor::ResizeGripActive, shade(1.0, 0.95)),
+            (StyleColor::Tab, shade(0.7, 0.86)),
+            (StyleColor::TabHovered, shade(1.0, 0.8)),
+            (StyleColor::TabActive, shade(0.8, 1.0)),
+            (StyleColor::TabUnfocusedActive, shade(0.55, 1.0)),
+            (StyleColor::DockingPreview, shade(1.0, 0.7)),
+            (StyleColor::TextSelectedBg, shade(1.0, 0.35)),
+            (StyleColor::NavHighlight, shade(1.0, 1.0)),
+        ];
+        for (color, value) in colors {
+            style[color] = value;
+        }
+    }
+}
+
+// draws the choice of the theme and of the accent color, in the Settings window
+pub fn draw_theme_settings(ui: &Ui, theme: &mut Theme, accent: &mut [f32; 3]) {
+    ui.set_next_item_width(120.0);
+    if let Some(_combo) = ui.begin_combo("Theme", theme.name()) {
+        for option in Theme::ALL {
+            if ui.selectable_config(option.name()).selected(*theme == option).build() {
+                *theme = option;
+            }
+        }
+    }
+    ui.disabled(*theme == Theme::Classic, || {
+        ui.color_edit3("Accent color", accent);
+        ui.same_line();
+        if ui.small_button("Reset") {
+            *accent = Theme::DEFAULT_ACCENT;
+        }
+    });
+}
@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use imgui::{Condition, Ui};
+use serde::Serialize;
+use crate::gui_runner::PartialWorld;
+
+// TickExport streams a row per tick to a file while it's started, for analysing robot runs offline:
+// the tick, the position and energy of the robot, the items in its backpack, the weather and the
+// time of day. The file is either CSV, with the total of the backpack, or JSON lines (an object per
+// line), which also holds the amount of every content in the backpack. Every world received is
+// written as it comes, so a replay played backwards writes its ticks backwards. The file is flushed
+// every frame, so that it can be read while the export is running.
+
+#[derive(Clone, Copy, PartialEq)]
+enum ExportFormat {
+    Csv,
+    JsonLines,
+}
+impl ExportFormat {
+    const ALL: [ExportFormat; 2] = [ExportFormat::Csv, ExportFormat::JsonLines];
+
+    fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::JsonLines => "JSON lines",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::JsonLines => "jsonl",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TickRow {
+    tick: usize,
+    x: u32,
+    y: u32,
+    energy: usize,
+    backpack_items: usize,
+    backpack: BTreeMap<String, usize>,
+    weather: String,
+    time_of_day: String,
+}
+impl TickRow {
+    const CSV_HEADER: &'static str = "tick,x,y,energy,backpack_items,weather,time_of_day";
+
+    fn new(world: &PartialWorld) -> Self {
+        let backpack: BTreeMap<String, usize> = world.backpack.iter()
+            .filter(|(_, amount)| **amount > 0)
+            .map(|(content, amount)| (format!("{content:?}").split('(').next().unwrap_or_default().to_string(), *amount))
+            .collect();
+        Self {
+            tick: world.tick,
+            x: world.robot_position.x,
+            y: world.robot_position.y,
+            energy: world.energy,
+            backpack_items: backpack.values().sum(),
+            backpack,
+            weather: format!("{:?}", world.env_cond.get_weather_condition()),
+            time_of_day: world.env_cond.get_time_of_day_string(),
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        format!("{},{},{},{},{},{},{}", self.tick, self.x, self.y, self.energy, self.backpack_items, self.weather, self.time_of_day)
+    }
+}
+
+pub struct TickExport {
+    pub open: bool,
+    path: String,
+    format: ExportFormat,
+    writer: Option<(BufWriter<File>, usize)>, // the file being written, rows written
+    status: Option<String>,
+}
+impl TickExport {
+    pub fn new() -> Self {
+        Self { open: false, path: "ticks.csv".into(), format: ExportFormat::Csv, writer: None, status: None }
+    }
+
+    fn start(&mut self) -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(&self.path)?);
+        if self.format == ExportFormat::Csv {
+            writeln!(writer, "{}", TickRow::CSV_HEADER)?;
+        }
+        self.writer = Some((writer, 0));
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        if let Some((mut writer, rows)) = self.writer.take() {
+            self.status = Some(match writer.flush() {
+                Ok(()) => format!("exported {rows} ticks to {}", self.path),
+                Err(e) => format!("could not export: {e}"),
+            });
+        }
+    }
+
+    // must be called with every world received
+    pub fn record(&mut self, world: &PartialWorld) {
+        let Some((writer, rows)) = &mut self.writer else { return };
+        let row = TickRow::new(world);
+        let result = match self.format {
+            ExportFormat::Csv => writeln!(writer, "{}", row.to_csv()),
+            ExportFormat::JsonLines => serde_json::to_writer(&mut *writer, &row).map_err(std::io::Error::from)
+                .and_then(|()| writeln!(writer)),
+        };
+        match result {
+            Ok(()) => *rows += 1,
+            Err(e) => {
+                eprintln!("could not export the tick {}: {e}", world.tick);
+                self.writer = None;
+                self.status = Some(format!("could not export: {e}"));
+            }
+        }
+    }
+
+    // must be called once per frame
+    pub fn flush(&mut self) {
+        if let Some((writer, _)) = &mut self.writer {
+            if let Err(e) = writer.flush() {
+                eprintln!("could not flush the tick export: {e}");
+                self.writer = None;
+                self.status = Some(format!("could not export: {e}"));
+            }
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui) {
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        ui.window("Tick export")
+            .opened(&mut open)
+            .size([340.0, 150.0], Condition::FirstUseEver)
+            .build(|| {
+                let running = self.writer.is_some();
+                ui.disabled(running, || {
+                    ui.set_next_item_width(120.0);
+                    if let Some(_combo) = ui.begin_combo("format", self.format.name()) {
+                        for format in ExportFormat::ALL {
+                            if ui.selectable_config(format.name()).selected(format == self.format).build() && format != self.format {
+                                // follows the format in the extension, unless the user chose another one
+                                if let Some(stem) = self.path.strip_suffix(self.format.extension()) {
+                                    self.path = format!("{stem}{}", format.extension());
+                                }
+                                self.format = format;
+                            }
+                        }
+                    }
+                    ui.set_next_item_width(200.0);
+                    ui.input_text("file", &mut self.path).build();
+                });
+
+                if !running && ui.button("Start") {
+                    self.status = match self.start() {
+                        Ok(()) => None,
+                        Err(e) => Some(format!("could not create {}: {e}", self.path)),
+                    };
+                }
+                if running && ui.button("Stop") {
+                    self.stop();
+                }
+                if let Some((_, rows)) = &self.writer {
+                    ui.same_line();
+                    ui.text(format!("{rows} ticks written to {}", self.path));
+                } else if let Some(status) = &self.status {
+                    ui.text_disabled(status);
+                }
+            });
+        self.open = open;
+    }
+}
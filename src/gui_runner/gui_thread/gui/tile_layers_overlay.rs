@@ -0,0 +1,223 @@
+use glium::{Display, VertexBuffer};
+use imgui::{Condition, ImColor32, Ui};
+use nalgebra_glm::vec3;
+use super::picking;
+use super::world_mesh::Vertex;
+use crate::gui_runner::PartialWorld;
+use crate::gui_runner::tile_layers::TileLayers;
+
+// TileLayersOverlay renders the layer selected among the TileLayers of the host code as a heatmap:
+// every tile with a value is covered, slightly above the terrain, by a quad colored according to
+// where the value falls in the color scale (automatically fitted to the values of the layer, or set
+// by hand), with a window to pick the layer, the palette and the scale, which shows the legend.
+// The quads are only rebuilt when the layers change (according to their version) or when the
+// scale or the tiles discovered do; tiles which weren't discovered yet aren't covered, since
+// there is no terrain to lay them on.
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Palette {
+    Heat,
+    Viridis,
+    Grayscale,
+}
+impl Palette {
+    const ALL: [Palette; 3] = [Palette::Heat, Palette::Viridis, Palette::Grayscale];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Palette::Heat => "heat",
+            Palette::Viridis => "viridis",
+            Palette::Grayscale => "grayscale",
+        }
+    }
+
+    // t is in 0..=1
+    pub fn color(&self, t: f32) -> [f32; 3] {
+        let stops: &[[f32; 3]] = match self {
+            Palette::Heat => &[[0.0, 0.0, 0.5], [0.0, 0.6, 1.0], [0.2, 0.9, 0.2], [1.0, 0.9, 0.0], [1.0, 0.1, 0.0]],
+            Palette::Viridis => &[[0.27, 0.0, 0.33], [0.23, 0.32, 0.55], [0.13, 0.57, 0.55], [0.37, 0.79, 0.38], [0.99, 0.91, 0.14]],
+            Palette::Grayscale => &[[0.0, 0.0, 0.0], [1.0, 1.0, 1.0]],
+        };
+        let x = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let i = (x as usize).min(stops.len() - 2);
+        let f = x - i as f32;
+        let (a, b) = (stops[i], stops[i + 1]);
+        [a[0] + (b[0] - a[0]) * f, a[1] + (b[1] - a[1]) * f, a[2] + (b[2] - a[2]) * f]
+    }
+}
+
+pub struct TileLayersOverlay {
+    pub open: bool,
+    pub show: bool,
+    pub opacity: f32,
+    layers: TileLayers,
+    selected: Option<String>,
+    palette: Palette,
+    auto_scale: bool,
+    scale: [f32; 2], // min, max
+    value_range: Option<[f32; 2]>, // of the values of the selected layer
+    tile_count: usize,
+
+    built_version: Option<u64>, // None when the quads must be rebuilt
+    built_tick: usize,
+    triangles: Vec<Vertex>,
+    pub vbo: Option<VertexBuffer<Vertex>>,
+    vbo_is_outdated: bool,
+}
+impl TileLayersOverlay {
+    const HEIGHT_ABOVE_TERRAIN: f32 = 0.1;
+    const LEGEND_SIZE: [f32; 2] = [240.0, 14.0];
+
+    pub fn new(layers: TileLayers) -> Self {
+        Self {
+            open: false,
+            show: true,
+            opacity: 0.6,
+            layers,
+            selected: None,
+            palette: Palette::Heat,
+            auto_scale: true,
+            scale: [0.0, 1.0],
+            value_range: None,
+            tile_count: 0,
+            built_version: None,
+            built_tick: 0,
+            triangles: vec![],
+            vbo: None,
+            vbo_is_outdated: false,
+        }
+    }
+
+    // must be called once per frame
+    pub fn update(&mut self, world: &PartialWorld) {
+        if !self.show {
+            return;
+        }
+        if self.selected.is_none() {
+            self.selected = self.layers.names().into_iter().next();
+        }
+        let Some(selected) = &self.selected else { return };
+        // a newly discovered tile may have a value
+        let is_up_to_date = self.built_version == Some(self.layers.version()) && self.built_tick == world.tick;
+        if is_up_to_date {
+            return;
+        }
+
+        let (values, version) = self.layers.layer(selected);
+        self.value_range = values.iter().map(|(_, value)| *value).filter(|value| value.is_finite())
+            .fold(None, |range, value| match range {
+                None => Some([value, value]),
+                Some([min, max]) => Some([min.min(value), max.max(value)]),
+            });
+        if self.auto_scale {
+            if let Some(range) = self.value_range {
+                self.scale = range;
+            }
+        }
+
+        let [min, max] = self.scale;
+        self.triangles.clear();
+        self.tile_count = 0;
+        for (tile_pos, value) in values {
+            let is_discovered = world.world.get(tile_pos.x as usize)
+                .and_then(|row| row.get(tile_pos.y as usize))
+                .map_or(false, |tile| tile.is_some());
+            if !is_discovered || !value.is_finite() {
+                continue;
+            }
+            let t = if max > min { (value - min) / (max - min) } else { 0.5 };
+            let color = self.palette.color(t);
+            let y = picking::tile_anchor(tile_pos, &world.world).y + Self::HEIGHT_ABOVE_TERRAIN;
+            let corner = |dx: f32, dz: f32| vec3(tile_pos.x as f32 + dx, y, tile_pos.y as f32 + dz);
+            let [a, b, c, d] = [corner(0.0, 0.0), corner(1.0, 0.0), corner(1.0, 1.0), corner(0.0, 1.0)];
+            for p in [a, b, c, a, c, d] {
+                self.triangles.push(Vertex { position: *p.as_ref(), color });
+            }
+            self.tile_count += 1;
+        }
+        self.built_version = Some(version);
+        self.built_tick = world.tick;
+        self.vbo_is_outdated = true;
+    }
+
+    // returns the number of bytes uploaded to the gpu
+    pub fn update_vbo(&mut self, display: &Display) -> usize {
+        if !self.vbo_is_outdated {
+            return 0;
+        }
+        self.vbo_is_outdated = false;
+        self.vbo = if self.triangles.is_empty() { None } else { VertexBuffer::new(display, &self.triangles).ok() };
+        self.triangles.len() * std::mem::size_of::<Vertex>()
+    }
+
+    pub fn draw(&mut self, ui: &Ui) {
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        let mut changed = false;
+        ui.window("Tile layers")
+            .opened(&mut open)
+            .size([300.0, 220.0], Condition::FirstUseEver)
+            .build(|| {
+                let names = self.layers.names();
+                if names.is_empty() {
+                    ui.text_wrapped("No layers: they are set through the TileLayers passed to GuiRunnerBuilder::tile_layers.");
+                    return;
+                }
+                changed |= ui.checkbox("Show", &mut self.show);
+                ui.same_line();
+                ui.set_next_item_width(120.0);
+                ui.slider("opacity", 0.1, 1.0, &mut self.opacity);
+
+                let preview = self.selected.clone().unwrap_or_default();
+                if let Some(_combo) = ui.begin_combo("layer", preview) {
+                    for name in names {
+                        let is_selected = self.selected.as_ref() == Some(&name);
+                        if ui.selectable_config(&name).selected(is_selected).build() {
+                            self.selected = Some(name);
+                            changed = true;
+                        }
+                    }
+                }
+                if let Some(_combo) = ui.begin_combo("palette", self.palette.name()) {
+                    for palette in Palette::ALL {
+                        if ui.selectable_config(palette.name()).selected(palette == self.palette).build() {
+                            self.palette = palette;
+                            changed = true;
+                        }
+                    }
+                }
+                changed |= ui.checkbox("Fit the scale to the values", &mut self.auto_scale);
+                ui.disabled(self.auto_scale, || {
+                    changed |= ui.input_float2("min, max", &mut self.scale).build();
+                });
+
+                // legend
+                let [x, y] = ui.cursor_screen_pos();
+                let [width, height] = Self::LEGEND_SIZE;
+                let draw_list = ui.get_window_draw_list();
+                const STEPS: usize = 32;
+                for i in 0..STEPS {
+                    let [r, g, b] = self.palette.color(i as f32 / (STEPS - 1) as f32);
+                    let x0 = x + width * i as f32 / STEPS as f32;
+                    let x1 = x + width * (i + 1) as f32 / STEPS as f32;
+                    draw_list.add_rect([x0, y], [x1, y + height], ImColor32::from_rgb_f32s(r, g, b)).filled(true).build();
+                }
+                ui.dummy([width, height]);
+                let [min, max] = self.scale;
+                ui.text(format!("{min:.3}"));
+                ui.same_line_with_pos(width - ui.calc_text_size(format!("{max:.3}"))[0]);
+                ui.text(format!("{max:.3}"));
+
+                match self.value_range {
+                    Some([min, max]) => ui.text_disabled(format!("{} tiles shown, values in {min:.3}..{max:.3}", self.tile_count)),
+                    None => ui.text_disabled("the layer is empty"),
+                }
+            });
+        self.open = open;
+        if changed {
+            self.built_version = None;
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::BTreeSet;
+use std::mem::{discriminant, Discriminant};
+use glium::{Display, VertexBuffer};
+use imgui::{Condition, TableFlags, Ui};
+use nalgebra_glm::{UVec2, vec3};
+use robotics_lib::world::tile::{Content, Tile, TileType};
+use super::picking;
+use super::world_mesh::Vertex;
+use crate::gui_runner::PartialWorld;
+
+// TileSearch finds the discovered tiles with a kind of content or of tile type, e.g. all the coins
+// or all the teleports: they are listed with their coordinates in its window, where clicking one
+// moves the camera to it, and outlined in the world (by a LinesList vertex buffer, like the stale
+// tiles) while the window is open. Kinds are told apart by their variant alone, so that searching
+// for coins finds them whatever their amount, and teleports whether activated or not.
+// The matches are only searched for among the tiles which changed, like the world mesh does, and
+// among all the discovered tiles when the query changes.
+
+#[derive(Clone, Copy, PartialEq)]
+enum Query {
+    Content(Discriminant<Content>),
+    TileType(Discriminant<TileType>),
+}
+impl Query {
+    fn matches(&self, tile: &Tile) -> bool {
+        match self {
+            Query::Content(content) => discriminant(&tile.content) == *content,
+            Query::TileType(tile_type) => discriminant(&tile.tile_type) == *tile_type,
+        }
+    }
+}
+
+pub struct TileSearch {
+    pub open: bool,
+    query: Option<(Query, String)>, // the query and its name
+    matches: BTreeSet<(u32, u32)>,
+    lines: Vec<Vertex>,
+    pub vbo: Option<VertexBuffer<Vertex>>,
+    vbo_is_outdated: bool,
+}
+impl TileSearch {
+    const COLOR: [f32; 3] = [1.0, 0.9, 0.1];
+    const HEIGHT_ABOVE_TERRAIN: f32 = 0.25;
+    const MAX_LISTED: usize = 1000;
+
+    pub fn new() -> Self {
+        Self { open: false, query: None, matches: BTreeSet::new(), lines: vec![], vbo: None, vbo_is_outdated: false }
+    }
+
+    // whether the matches must be outlined in the world
+    pub fn show(&self) -> bool {
+        self.open && self.query.is_some()
+    }
+
+    fn contents() -> [Content; 15] {
+        [
+            Content::Rock(0), Content::Tree(0), Content::Garbage(0), Content::Fire, Content::Coin(0),
+            Content::Bin(0..0), Content::Crate(0..0), Content::Bank(0..0), Content::Water(0), Content::Market(0),
+            Content::Fish(0), Content::Building, Content::Bush(0), Content::JollyBlock(0), Content::Scarecrow,
+        ]
+    }
+
+    fn tile_types() -> [TileType; 11] {
+        [
+            TileType::DeepWater, TileType::ShallowWater, TileType::Sand, TileType::Grass, TileType::Street, TileType::Hill,
+            TileType::Mountain, TileType::Snow, TileType::Lava, TileType::Teleport(false), TileType::Wall,
+        ]
+    }
+
+    // the name of the variant, without its value
+    fn variant_name(debug: String) -> String {
+        debug.split(['(', ' ', '{']).next().unwrap_or_default().to_string()
+    }
+
+    // must be called with every new world, before its tiles_to_refresh are cleared
+    pub fn update(&mut self, world: &PartialWorld) {
+        let Some((query, _)) = &self.query else { return };
+        let mut changed = false;
+        for tile_pos in world.tiles_to_refresh.iter() {
+            let key = (tile_pos.x, tile_pos.y);
+            let matches = world.world[tile_pos.x as usize][tile_pos.y as usize].as_ref().is_some_and(|tile| query.matches(tile));
+            changed |= match matches {
+                true => self.matches.insert(key),
+                false => self.matches.remove(&key),
+            };
+        }
+        if changed {
+            self.rebuild_lines(world);
+        }
+    }
+
+    fn search(&mut self, world: &PartialWorld) {
+        self.matches.clear();
+        if let Some((query, _)) = &self.query {
+            for (x, row) in world.world.iter().enumerate() {
+                for (y, tile) in row.iter().enumerate() {
+                    if tile.as_ref().is_some_and(|tile| query.matches(tile)) {
+                        self.matches.insert((x as u32, y as u32));
+                    }
+                }
+            }
+        }
+        self.rebuild_lines(world);
+    }
+
+    fn rebuild_lines(&mut self, world: &PartialWorld) {
+        self.lines.clear();
+        for (x, y) in self.matches.iter() {
+            let tile_pos = UVec2::new(*x, *y);
+            let y = picking::tile_anchor(tile_pos, &world.world).y + Self::HEIGHT_ABOVE_TERRAIN;
+            let corner = |dx: f32, dz: f32| vec3(tile_pos.x as f32 + dx, y, tile_pos.y as f32 + dz);
+            let [a, b, c, d] = [corner(0.05, 0.05), corner(0.95, 0.05), corner(0.95, 0.95), corner(0.05, 0.95)];
+            for (from, to) in [(a, b), (b, c), (c, d), (d, a)] {
+                self.lines.push(Vertex { position: *from.as_ref(), color: Self::COLOR });
+                self.lines.push(Vertex { position: *to.as_ref(), color: Self::COLOR });
+            }
+        }
+        self.vbo_is_outdated = true;
+    }
+
+    // returns the number of bytes uploaded to the gpu
+    pub fn update_vbo(&mut self, display: &Display) -> usize {
+        if !self.vbo_is_outdated {
+            return 0;
+        }
+        self.vbo_is_outdated = false;
+        self.vbo = if self.lines.is_empty() { None } else { VertexBuffer::new(display, &self.lines).ok() };
+        self.lines.len() * std::mem::size_of::<Vertex>()
+    }
+
+    // draws the search window, returning the tile the user clicked on, if any
+    pub fn draw(&mut self, ui: &Ui, world: &PartialWorld) -> Option<UVec2> {
+        if !self.open {
+            return None;
+        }
+
+        let mut clicked = None;
+        let mut new_query = None;
+        let mut open = self.open;
+        ui.window("Tile search")
+            .opened(&mut open)
+            .size([260.0, 360.0], Condition::FirstUseEver)
+            .build(|| {
+                let selected = self.query.as_ref().map_or("(none)", |(_, name)| name.as_str());
+                ui.set_next_item_width(160.0);
+                if let Some(_combo) = ui.begin_combo("search for", selected) {
+                    ui.text_disabled("Contents");
+                    for content in Self::contents() {
+                        let name = Self::variant_name(format!("{content:?}"));
+                        if ui.selectable_config(&name).selected(name == selected).build() {
+                            new_query = Some((Query::Content(discriminant(&content)), name));
+                        }
+                    }
+                    ui.separator();
+                    ui.text_disabled("Tile types");
+                    for tile_type in Self::tile_types() {
+                        let name = Self::variant_name(format!("{tile_type:?}"));
+                        if ui.selectable_config(format!("{name}##tile type")).selected(name == selected).build() {
+                            new_query = Some((Query::TileType(discriminant(&tile_type)), name));
+                        }
+                    }
+                }
+                if self.query.is_none() {
+                    ui.text_disabled("choose a content or a tile type");
+                    return;
+                }
+
+                ui.text(format!("{} discovered tiles", self.matches.len()));
+                let flags = TableFlags::BORDERS | TableFlags::ROW_BG | TableFlags::SIZING_FIXED_FIT | TableFlags::SCROLL_Y;
+                if let Some(_table) = ui.begin_table_with_flags("matches", 2, flags) {
+                    ui.table_setup_column("tile");
+                    ui.table_setup_column("distance from the robot");
+                    ui.table_headers_row();
+                    let robot = world.robot_position;
+                    for (x, y) in self.matches.iter().take(Self::MAX_LISTED) {
+                        ui.table_next_row();
+                        ui.table_next_column();
+                        if ui.selectable(format!("({x}, {y})")) {
+                            clicked = Some(UVec2::new(*x, *y));
+                        }
+                        ui.table_next_column();
+                        ui.text((x.abs_diff(robot.x) + y.abs_diff(robot.y)).to_string());
+                    }
+                }
+                if self.matches.len() > Self::MAX_LISTED {
+                    ui.text_disabled(format!("only the first {} are listed", Self::MAX_LISTED));
+                }
+            });
+        self.open = open;
+
+        if let Some(query) = new_query {
+            self.query = Some(query);
+            self.search(world);
+        }
+        clicked
+    }
+}
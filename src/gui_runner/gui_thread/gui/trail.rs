@@ -0,0 +1,85 @@
+use glium::{Display, VertexBuffer};
+use nalgebra_glm::{UVec2, Vec3, vec3};
+use super::world_mesh::{self, Vertex};
+use crate::gui_runner::PartialWorld;
+
+// Trail records the path walked by the robot (one point every time its position changes, along with
+// the energy it had there) and keeps a line strip of it in a vertex buffer, rebuilt only when new
+// points are recorded or the coloring changes. The trail can either be drawn with a uniform color
+// (that of the robot's marker) or colored by energy level, from red (no energy) to green (full energy). When the world goes back in
+// time (while playing back a replay) the points recorded after the new tick are dropped.
+
+struct TrailPoint {
+    tick: usize,
+    position: Vec3,
+    energy: usize,
+}
+
+pub struct Trail {
+    points: Vec<TrailPoint>,
+    last_tile: Option<UVec2>,
+    pub vbo: Option<VertexBuffer<Vertex>>,
+    vbo_is_outdated: bool,
+    color_by_energy: bool,
+    color: [f32; 3],
+}
+impl Trail {
+    const MAX_ENERGY: f32 = 1000.0;
+    pub fn new(color: [f32; 3]) -> Self {
+        Self { points: vec![], last_tile: None, vbo: None, vbo_is_outdated: false, color_by_energy: true, color }
+    }
+
+    pub fn record(&mut self, world: &PartialWorld) {
+        if self.points.last().is_some_and(|p| p.tick > world.tick) {
+            self.points.retain(|p| p.tick <= world.tick);
+            self.last_tile = None;
+            self.vbo_is_outdated = true;
+        }
+
+        let tile = world.robot_position;
+        if self.last_tile == Some(tile) {
+            return;
+        }
+        self.last_tile = Some(tile);
+
+        let elevation = world.world[tile.x as usize][tile.y as usize].as_ref().map(|t| t.elevation).unwrap_or(0);
+        let position = vec3(tile.x as f32 + 0.5, world_mesh::elevation_to_mesh_space_y(elevation as f32) + 0.3, tile.y as f32 + 0.5);
+        self.points.push(TrailPoint { tick: world.tick, position, energy: world.energy });
+        self.vbo_is_outdated = true;
+    }
+
+    pub fn set_color_by_energy(&mut self, color_by_energy: bool) {
+        if self.color_by_energy != color_by_energy {
+            self.color_by_energy = color_by_energy;
+            self.vbo_is_outdated = true;
+        }
+    }
+
+    pub fn set_color(&mut self, color: [f32; 3]) {
+        if self.color != color {
+            self.color = color;
+            self.vbo_is_outdated |= !self.color_by_energy;
+        }
+    }
+
+    fn energy_to_color(energy: usize) -> [f32; 3] {
+        let t = (energy as f32 / Self::MAX_ENERGY).clamp(0.0, 1.0);
+        [1.0 - t, t, 0.1]
+    }
+
+    // returns the number of bytes uploaded to the gpu
+    pub fn update_vbo(&mut self, display: &Display) -> usize {
+        if !self.vbo_is_outdated {
+            return 0;
+        }
+        self.vbo_is_outdated = false;
+
+        let verts = self.points.iter().map(|p| {
+            let color = if self.color_by_energy { Self::energy_to_color(p.energy) } else { self.color };
+            Vertex { position: *p.position.as_ref(), color }
+        }).collect::<Vec<_>>();
+
+        self.vbo = if verts.len() < 2 { None } else { VertexBuffer::dynamic(display, &verts).ok() };
+        verts.len() * std::mem::size_of::<Vertex>()
+    }
+}
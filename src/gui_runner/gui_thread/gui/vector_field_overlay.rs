@@ -0,0 +1,153 @@
+use glium::{Display, VertexBuffer};
+use imgui::{Condition, Ui};
+use nalgebra_glm::{Vec3, vec3};
+use super::picking;
+use super::world_mesh::Vertex;
+use crate::gui_runner::PartialWorld;
+use crate::gui_runner::tile_layers::TileLayers;
+
+// VectorFieldOverlay renders the vector field selected among the TileLayers of the host code as
+// arrows lying slightly above the terrain, one per discovered tile with a direction, from the
+// center of the tile. The longest vector of the field spans most of a tile and the others are
+// scaled in proportion, unless they are all drawn the same length. Like the heatmap of
+// TileLayersOverlay, the LinesList vertex buffer is only rebuilt when the layers, the settings or
+// the tiles discovered change.
+
+pub struct VectorFieldOverlay {
+    pub open: bool,
+    pub show: bool,
+    layers: TileLayers,
+    selected: Option<String>,
+    color: [f32; 3],
+    same_length: bool,
+    arrow_count: usize,
+
+    built_version: Option<u64>, // None when the arrows must be rebuilt
+    built_tick: usize,
+    lines: Vec<Vertex>,
+    pub vbo: Option<VertexBuffer<Vertex>>,
+    vbo_is_outdated: bool,
+}
+impl VectorFieldOverlay {
+    const HEIGHT_ABOVE_TERRAIN: f32 = 0.15;
+    const MAX_LENGTH: f32 = 0.8; // in tiles
+    const HEAD_LENGTH: f32 = 0.3; // relative to the length of the arrow
+
+    pub fn new(layers: TileLayers) -> Self {
+        Self {
+            open: false,
+            show: true,
+            layers,
+            selected: None,
+            color: [1.0, 1.0, 1.0],
+            same_length: false,
+            arrow_count: 0,
+            built_version: None,
+            built_tick: 0,
+            lines: vec![],
+            vbo: None,
+            vbo_is_outdated: false,
+        }
+    }
+
+    // must be called once per frame
+    pub fn update(&mut self, world: &PartialWorld) {
+        if !self.show {
+            return;
+        }
+        if self.selected.is_none() {
+            self.selected = self.layers.vector_field_names().into_iter().next();
+        }
+        let Some(selected) = &self.selected else { return };
+        // a newly discovered tile may have an arrow
+        let is_up_to_date = self.built_version == Some(self.layers.version()) && self.built_tick == world.tick;
+        if is_up_to_date {
+            return;
+        }
+
+        let (vectors, version) = self.layers.vector_field(selected);
+        let max_length = vectors.iter().map(|(_, [x, z])| (x * x + z * z).sqrt()).filter(|length| length.is_finite()).fold(0.0, f32::max);
+
+        self.lines.clear();
+        self.arrow_count = 0;
+        for (tile_pos, [x, z]) in vectors {
+            let is_discovered = world.world.get(tile_pos.x as usize)
+                .and_then(|row| row.get(tile_pos.y as usize))
+                .map_or(false, |tile| tile.is_some());
+            let length = (x * x + z * z).sqrt();
+            if !is_discovered || !length.is_finite() || length == 0.0 {
+                continue;
+            }
+            let scale = if self.same_length { Self::MAX_LENGTH } else { Self::MAX_LENGTH * length / max_length };
+            let direction = vec3(x, 0.0, z) / length;
+            let mut center = picking::tile_anchor(tile_pos, &world.world);
+            center.y += Self::HEIGHT_ABOVE_TERRAIN;
+
+            let tail = center - direction * scale / 2.0;
+            let head = center + direction * scale / 2.0;
+            let side = vec3(-direction.z, 0.0, direction.x);
+            let head_length = scale * Self::HEAD_LENGTH;
+            let barbs = [head - direction * head_length + side * head_length / 2.0, head - direction * head_length - side * head_length / 2.0];
+            self.push_line(tail, head);
+            for barb in barbs {
+                self.push_line(head, barb);
+            }
+            self.arrow_count += 1;
+        }
+        self.built_version = Some(version);
+        self.built_tick = world.tick;
+        self.vbo_is_outdated = true;
+    }
+
+    fn push_line(&mut self, from: Vec3, to: Vec3) {
+        self.lines.push(Vertex { position: *from.as_ref(), color: self.color });
+        self.lines.push(Vertex { position: *to.as_ref(), color: self.color });
+    }
+
+    // returns the number of bytes uploaded to the gpu
+    pub fn update_vbo(&mut self, display: &Display) -> usize {
+        if !self.vbo_is_outdated {
+            return 0;
+        }
+        self.vbo_is_outdated = false;
+        self.vbo = if self.lines.is_empty() { None } else { VertexBuffer::new(display, &self.lines).ok() };
+        self.lines.len() * std::mem::size_of::<Vertex>()
+    }
+
+    pub fn draw(&mut self, ui: &Ui) {
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        let mut changed = false;
+        ui.window("Vector fields")
+            .opened(&mut open)
+            .size([280.0, 160.0], Condition::FirstUseEver)
+            .build(|| {
+                let names = self.layers.vector_field_names();
+                if names.is_empty() {
+                    ui.text_wrapped("No vector fields: they are set through the TileLayers passed to GuiRunnerBuilder::tile_layers.");
+                    return;
+                }
+                changed |= ui.checkbox("Show", &mut self.show);
+
+                let preview = self.selected.clone().unwrap_or_default();
+                if let Some(_combo) = ui.begin_combo("field", preview) {
+                    for name in names {
+                        let is_selected = self.selected.as_ref() == Some(&name);
+                        if ui.selectable_config(&name).selected(is_selected).build() {
+                            self.selected = Some(name);
+                            changed = true;
+                        }
+                    }
+                }
+                changed |= ui.color_edit3("color", &mut self.color);
+                changed |= ui.checkbox("Same length for every arrow", &mut self.same_length);
+                ui.text_disabled(format!("{} arrows shown", self.arrow_count));
+            });
+        self.open = open;
+        if changed {
+            self.built_version = None;
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::{HashMap, HashSet};
+use glium::{Display, VertexBuffer};
+use imgui::Ui;
+use nalgebra_glm::UVec2;
+use robotics_lib::world::tile::TileType;
+use super::picking;
+use super::world_mesh::Vertex;
+use crate::gui_runner::PartialWorld;
+
+// WaterBodies groups the discovered water tiles (deep and shallow) into distinct bodies of water,
+// i.e. the connected components of water tiles (not connecting diagonally), and lists them by size
+// in the statistics panel, classifying each as a river or a lake from its shape. The selected body
+// is highlighted in the world by an outline along its shore (a LinesList vertex buffer).
+// Water tiles are only searched for among the tiles which changed, like the world mesh does, but
+// the bodies are only recomputed when the list is shown, since a flood fill of the water of a big
+// world is not cheap.
+
+struct WaterBody {
+    tiles: Vec<UVec2>,
+    deep_tiles: usize,
+    is_river: bool,
+}
+impl WaterBody {
+    // a body which only fills a small part of its (big enough) bounding box is long and thin
+    fn looks_like_a_river(tiles: &[UVec2]) -> bool {
+        let min = tiles.iter().fold(UVec2::repeat(u32::MAX), |min, t| min.inf(t));
+        let max = tiles.iter().fold(UVec2::zeros(), |max, t| max.sup(t));
+        let extent = max - min + UVec2::repeat(1);
+        let fill = tiles.len() as f32 / (extent.x as f32 * extent.y as f32);
+        extent.max() >= 8 && fill < 0.3
+    }
+
+    fn kind(&self) -> &'static str {
+        let depth = match self.deep_tiles {
+            0 => "shallow",
+            n if n == self.tiles.len() => "deep",
+            _ => "mixed",
+        };
+        match (self.is_river, depth) {
+            (true, "shallow") => "shallow river",
+            (true, "deep") => "deep river",
+            (true, _) => "river",
+            (false, "shallow") => "shallow lake",
+            (false, "deep") => "deep lake",
+            (false, _) => "lake",
+        }
+    }
+}
+
+pub struct WaterBodies {
+    water: HashMap<UVec2, bool>, // position -> is deep water
+    bodies: Vec<WaterBody>, // biggest first
+    bodies_are_outdated: bool,
+    selected: Option<UVec2>, // a tile of the selected body, which survives the bodies being recomputed
+    selected_index: Option<usize>,
+    outline: Vec<Vertex>,
+    pub vbo: Option<VertexBuffer<Vertex>>,
+    vbo_is_outdated: bool,
+}
+impl WaterBodies {
+    const MAX_LISTED: usize = 50;
+    const OUTLINE_COLOR: [f32; 3] = [0.3, 1.0, 1.0];
+
+    pub fn new() -> Self {
+        Self {
+            water: HashMap::new(), bodies: vec![], bodies_are_outdated: false, selected: None, selected_index: None,
+            outline: vec![], vbo: None, vbo_is_outdated: false,
+        }
+    }
+
+    // must be called with every new world, before its tiles_to_refresh are cleared
+    pub fn update(&mut self, world: &PartialWorld) {
+        for tile_pos in world.tiles_to_refresh.iter() {
+            let water = world.world[tile_pos.x as usize][tile_pos.y as usize].as_ref().and_then(|tile| match tile.tile_type {
+                TileType::DeepWater => Some(true),
+                TileType::ShallowWater => Some(false),
+                _ => None,
+            });
+            let changed = match water {
+                Some(is_deep) => self.water.insert(*tile_pos, is_deep) != Some(is_deep),
+                None => self.water.remove(tile_pos).is_some(),
+            };
+            self.bodies_are_outdated |= changed;
+        }
+    }
+
+    fn recompute_bodies(&mut self, world: &PartialWorld) {
+        self.bodies_are_outdated = false;
+        self.bodies.clear();
+        let mut visited = HashSet::new();
+        for start in self.water.keys() {
+            if !visited.insert(*start) {
+                continue;
+            }
+            let mut tiles = vec![];
+            let mut stack = vec![*start];
+            while let Some(tile_pos) = stack.pop() {
+                tiles.push(tile_pos);
+                for neighbour in neighbours(tile_pos) {
+                    if self.water.contains_key(&neighbour) && visited.insert(neighbour) {
+                        stack.push(neighbour);
+                    }
+                }
+            }
+            let deep_tiles = tiles.iter().filter(|t| self.water[t]).count();
+            let is_river = WaterBody::looks_like_a_river(&tiles);
+            self.bodies.push(WaterBody { tiles, deep_tiles, is_river });
+        }
+        self.bodies.sort_by_key(|body| std::cmp::Reverse(body.tiles.len()));
+        self.rebuild_outline(world);
+    }
+
+    // finds the selected body and outlines it with a line along every side of its tiles which isn't
+    // shared with another tile of the body
+    fn rebuild_outline(&mut self, world: &PartialWorld) {
+        self.selected_index = self.selected.and_then(|selected| self.bodies.iter().position(|body| body.tiles.contains(&selected)));
+        let mut outline = vec![];
+        if let Some(body) = self.selected_index.map(|i| &self.bodies[i]) {
+            let tiles: HashSet<UVec2> = body.tiles.iter().copied().collect();
+            for tile_pos in body.tiles.iter() {
+                let y = picking::tile_anchor(*tile_pos, &world.world).y + 0.15;
+                let (x0, z0) = (tile_pos.x as f32, tile_pos.y as f32);
+                let (x1, z1) = (x0 + 1.0, z0 + 1.0);
+                let sides = [
+                    (tile_pos.x.checked_sub(1).map(|x| UVec2::new(x, tile_pos.y)), [x0, z0], [x0, z1]),
+                    (Some(UVec2::new(tile_pos.x + 1, tile_pos.y)), [x1, z0], [x1, z1]),
+                    (tile_pos.y.checked_sub(1).map(|y| UVec2::new(tile_pos.x, y)), [x0, z0], [x1, z0]),
+                    (Some(UVec2::new(tile_pos.x, tile_pos.y + 1)), [x0, z1], [x1, z1]),
+                ];
+                for (neighbour, [ax, az], [bx, bz]) in sides {
+                    if neighbour.map(|n| !tiles.contains(&n)).unwrap_or(true) {
+                        outline.push(Vertex { position: [ax, y, az], color: Self::OUTLINE_COLOR });
+                        outline.push(Vertex { position: [bx, y, bz], color: Self::OUTLINE_COLOR });
+                    }
+                }
+            }
+        }
+        self.outline = outline;
+        self.vbo_is_outdated = true;
+    }
+
+    // returns the number of bytes uploaded to the gpu
+    pub fn update_vbo(&mut self, display: &Display) -> usize {
+        if !self.vbo_is_outdated {
+            return 0;
+        }
+        self.vbo_is_outdated = false;
+        self.vbo = if self.outline.is_empty() { None } else { VertexBuffer::new(display, &self.outline).ok() };
+        self.outline.len() * std::mem::size_of::<Vertex>()
+    }
+
+    // draws the list of bodies, returning the tile to move the camera to if the user asked to
+    pub fn draw(&mut self, ui: &Ui, world: &PartialWorld) -> Option<UVec2> {
+        if self.bodies_are_outdated {
+            self.recompute_bodies(world);
+        }
+
+        ui.text(format!("{} water tiles in {} bodies", self.water.len(), self.bodies.len()));
+        let mut go_to = None;
+        let mut new_selection = None;
+        for (i, body) in self.bodies.iter().take(Self::MAX_LISTED).enumerate() {
+            let _id = ui.push_id_usize(i);
+            let is_selected = self.selected_index == Some(i);
+            if ui.small_button("go") {
+                go_to = Some(body.tiles[body.tiles.len() / 2]);
+            }
+            ui.same_line();
+            let label = format!("#{}: {}, {} tiles", i + 1, body.kind(), body.tiles.len());
+            if ui.selectable_config(label).selected(is_selected).build() {
+                new_selection = Some(if is_selected { None } else { Some(body.tiles[0]) });
+            }
+        }
+        if self.bodies.len() > Self::MAX_LISTED {
+            ui.text_disabled(format!("and {} smaller ones", self.bodies.len() - Self::MAX_LISTED));
+        }
+
+        if let Some(selection) = new_selection {
+            self.selected = selection;
+            self.rebuild_outline(world);
+        }
+        go_to
+    }
+}
+
+fn neighbours(tile_pos: UVec2) -> impl Iterator<Item = UVec2> {
+    let (x, y) = (tile_pos.x, tile_pos.y);
+    [x.checked_sub(1).map(|x| (x, y)), Some((x + 1, y)), y.checked_sub(1).map(|y| (x, y)), Some((x, y + 1))]
+        .into_iter()
+        .flatten()
+        .map(|(x, y)| UVec2::new(x, y))
+}
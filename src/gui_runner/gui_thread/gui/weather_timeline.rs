@@ -0,0 +1,97 @@
+use imgui::Ui;
+use robotics_lib::world::environmental_conditions::{DayTime, EnvironmentalConditions, WeatherType};
+use strum::IntoEnumIterator;
+
+// WeatherTimeline records the weather and time of day of every tick received by the GUI as
+// run-length encoded segments (so memory only grows when the conditions change) and draws them as
+// a two-row colored strip spanning from the first to the last recorded tick: the top row shows
+// the weather, the bottom one the time of day. Hovering the strip shows the conditions at that tick.
+
+struct Segment {
+    first_tick: usize,
+    last_tick: usize,
+    weather: usize,     // WeatherType as usize
+    time_of_day: usize, // DayTime as usize
+}
+
+pub struct WeatherTimeline {
+    segments: Vec<Segment>,
+}
+impl WeatherTimeline {
+    const STRIP_ROW_HEIGHT: f32 = 10.0;
+
+    pub fn new() -> Self {
+        Self { segments: vec![] }
+    }
+
+    pub fn record(&mut self, tick: usize, env_cond: &EnvironmentalConditions) {
+        let weather = env_cond.get_weather_condition() as usize;
+        let time_of_day = env_cond.get_time_of_day() as usize;
+        if self.segments.last().is_some_and(|last| tick < last.last_tick) {
+            return; // already recorded (a replay went back in time)
+        }
+        match self.segments.last_mut() {
+            Some(last) if last.weather == weather && last.time_of_day == time_of_day => {
+                last.last_tick = last.last_tick.max(tick);
+            }
+            _ => self.segments.push(Segment { first_tick: tick, last_tick: tick, weather, time_of_day }),
+        }
+    }
+
+    fn weather_color(weather: usize) -> [f32; 4] {
+        [
+            [0.95, 0.85, 0.3, 1.0], // sunny
+            [0.3, 0.45, 0.85, 1.0], // rainy
+            [0.6, 0.6, 0.6, 1.0],   // foggy
+            [0.15, 0.2, 0.5, 1.0],  // tropical monsoon
+            [0.95, 0.95, 1.0, 1.0], // trentino snow
+        ][weather.min(4)]
+    }
+    fn time_of_day_color(time_of_day: usize) -> [f32; 4] {
+        [
+            [1.0, 0.7, 0.45, 1.0], // morning
+            [1.0, 0.95, 0.6, 1.0], // afternoon
+            [0.1, 0.1, 0.3, 1.0],  // night
+        ][time_of_day.min(2)]
+    }
+
+    pub fn draw(&self, ui: &Ui) {
+        let (first_tick, last_tick) = match (self.segments.first(), self.segments.last()) {
+            (Some(first), Some(last)) => (first.first_tick, last.last_tick),
+            _ => {
+                ui.text_disabled("(no data yet)");
+                return;
+            }
+        };
+        let span = (last_tick - first_tick + 1) as f32;
+
+        let width = ui.content_region_avail()[0].max(1.0);
+        let origin = ui.cursor_screen_pos();
+        let tick_to_x = |tick: usize| origin[0] + (tick - first_tick) as f32 / span * width;
+
+        {
+            let draw_list = ui.get_window_draw_list();
+            for segment in self.segments.iter() {
+                let x0 = tick_to_x(segment.first_tick);
+                let x1 = tick_to_x(segment.last_tick + 1).max(x0 + 1.0);
+                let y0 = origin[1];
+                let y1 = y0 + Self::STRIP_ROW_HEIGHT;
+                let y2 = y1 + Self::STRIP_ROW_HEIGHT;
+                draw_list.add_rect([x0, y0], [x1, y1], Self::weather_color(segment.weather)).filled(true).build();
+                draw_list.add_rect([x0, y1], [x1, y2], Self::time_of_day_color(segment.time_of_day)).filled(true).build();
+            }
+        }
+
+        ui.invisible_button("weather timeline", [width, Self::STRIP_ROW_HEIGHT * 2.0]);
+        if ui.is_item_hovered() {
+            let mouse_x = ui.io().mouse_pos[0];
+            let tick = first_tick + (((mouse_x - origin[0]) / width * span).max(0.0) as usize).min(last_tick - first_tick);
+            if let Some(segment) = self.segments.iter().find(|s| s.first_tick <= tick && tick <= s.last_tick) {
+                if let (Some(weather), Some(time_of_day)) = (WeatherType::iter().nth(segment.weather), DayTime::iter().nth(segment.time_of_day)) {
+                    ui.tooltip_text(format!("tick {tick}: {weather:?}, {time_of_day:?}"));
+                }
+            }
+        }
+        ui.text_disabled(format!("ticks {first_tick}..={last_tick}"));
+    }
+}
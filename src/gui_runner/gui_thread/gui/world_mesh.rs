@@ -1,15 +1,11 @@
-use std::cmp::max;
 use std::collections::{HashMap, HashSet};
 use std::f32::consts::PI;
-use std::ops::{Range};
-use glium::{Display, VertexBuffer};
+use glium::VertexBuffer;
+use glium::backend::Facade;
 use nalgebra_glm::{rotate_vec3, UVec2, Vec3, vec3};
 use rand::prelude::SmallRng;
 use rand::{Rng, SeedableRng};
 use robotics_lib::world::tile::{Content, Tile, TileType};
-use range_set::range_set;
-use robotics_lib::world::environmental_conditions::{DayTime, EnvironmentalConditions, WeatherType};
-use strum::IntoEnumIterator;
 use crate::gui_runner::PartialWorld;
 
 // Vertex is the vertex type of our mesh. it needs to be public because glium needs to be able to
@@ -25,244 +21,196 @@ impl Vertex {
 }
 implement_vertex!(Vertex, position, color);
 
-
-// WorldMesh manages the world mesh and its vertex buffer in the gpu.
-// the following optimizations were implemented:
-// - minimize allocated memory by not allocating memory for undiscovered tiles and instead filling
-//   the vbo (Vertex Buffer Object) with new vertices as new tiles are discovered.
-// - avoid frequent reallocation with a policy similar to what is often used in dynamic arrays
-//   (grow() increases capacity by 1/2, shrink() decreases it to 2/3 when fill factor < 1/2).
-// - avoid moving vertices around when a bunch of vertices in between the array is removed by
-//   keeping track (through Self::empty_meshes) of the position of vertices that are allocated but
-//   not currently in use.
-// - minimize writes to the gpu, rewriting to gpu only the vertices which actually changed, and
-//   bunching together contiguous writes into a single write.
-
-pub struct WorldMesh {
+// LiquidVertex is the vertex type of the meshes of liquid tiles (water and lava), which are drawn
+// by a separate program that animates them. emissive is 1 for tiles which glow (lava), 0 otherwise
+#[derive(Clone, Copy, Debug)]
+pub struct LiquidVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    pub emissive: f32,
+}
+implement_vertex!(LiquidVertex, position, color, emissive);
+
+
+// WorldMesh manages the world mesh and its vertex buffers in the gpu.
+// the world is split into chunks of CHUNK_SIZE x CHUNK_SIZE tiles, each with its own vbo (Vertex
+// Buffer Object) containing the meshes of the discovered tiles of the chunk and of their content:
+// - no memory is allocated for undiscovered tiles, and chunks with no discovered tiles have no vbo.
+// - when tiles change only the chunks containing them are rebuilt, so the cost of an update
+//   depends on how many chunks changed rather than on the size of the world, and a burst of
+//   changes doesn't cause frame hitches on big worlds.
+// - the robot, which changes every frame, is kept in a separate tiny vbo (the skybox is drawn by
+//   the Skybox, with a program of its own).
+// every chunk also has a simplified (level of detail) mesh, made of a single quad per
+// LOD_BLOCK_SIZE x LOD_BLOCK_SIZE block of tiles, which the GUI draws instead of the full mesh when
+// the chunk is far from the camera: this keeps the number of triangles drawn (and thus the frame
+// rate) reasonable even when the whole of a big world has been discovered.
+// the meshes of liquid tiles are kept in a third vbo per chunk, since they are drawn by a different
+// program (which animates them).
+// the vertices of the tiles are darkened by an ambient occlusion baked into their colors: the more
+// the tiles around a vertex rise above it, the darker it gets, so that the foot of a cliff stands
+// out from the slope above it. Since it only depends on the neighbouring tiles, which are refreshed
+// along with any changed tile, it never goes stale; changing its strength rebuilds every chunk.
+
+// Chunk is the mesh of a chunk of the world, along with its simplified mesh and its bounding box
+pub struct Chunk {
     pub vbo: VertexBuffer<Vertex>,
-    pub verts: Vec<Vertex>,
+    pub lod_vbo: VertexBuffer<Vertex>,
+    pub liquid_vbo: Option<VertexBuffer<LiquidVertex>>, // None if the chunk has no liquid tiles
+    pub min: Vec3,
+    pub max: Vec3,
+}
 
-    empty_meshes: Vec<usize>,
-    min_number_of_meshes: usize,
-    tiles_positions_map: HashMap<UVec2, (Tile, [u32;3])>, // keeps track of the association between world position and (stored meshes, [tile index, content_idx1, content_idx2]); 0 == null (since the 0 mesh is the robot mesh)
-    skybox_mesh_array: [[[Vertex; WorldMesh::MESH_LEN]; 3]; 5], // all skyboxes cached, generated by generate_skybox_meshes
+pub struct WorldMesh {
+    pub misc_vbo: VertexBuffer<Vertex>, // robot
+    chunks: HashMap<UVec2, Chunk>,
+    pub bytes_uploaded: usize, // bytes written to the gpu during the last call to update
+    pub rebuilt_chunks: Vec<UVec2>, // chunks rebuilt during the last call to update
+    ambient_occlusion: f32, // strength, 0 for none
+    rebuild_all: bool,
 }
 impl WorldMesh {
     const MESH_LEN: usize = 24;
-
-    const ROBOT_MESH_INDEX: usize = 0;
-    const SKYBOX_MESH_INDEX: usize = Self::MESH_LEN;
+    pub const CHUNK_SIZE: u32 = 32;
+    pub const LOD_BLOCK_SIZE: u32 = 4;
+    const OCCLUSION_RADIUS: f32 = 1.5; // in tiles
 
     const NULL_MESH: [Vertex; Self::MESH_LEN] = [Vertex::NULL; Self::MESH_LEN];
-    pub fn new(world_size: usize, min_number_of_meshes: usize, display: &Display) -> Self {
+    pub fn new(facade: &impl Facade) -> Self {
         Self {
-            vbo: VertexBuffer::empty_dynamic(display, min_number_of_meshes * Self::MESH_LEN).unwrap(),
-            verts: vec![Vertex::NULL; min_number_of_meshes * Self::MESH_LEN],
-            empty_meshes: Vec::from_iter((2..min_number_of_meshes).map(|i| i * Self::MESH_LEN)),
-            tiles_positions_map: HashMap::new(),
-            min_number_of_meshes,
-            skybox_mesh_array: Self::generate_skybox_meshes(world_size)
+            misc_vbo: VertexBuffer::empty_dynamic(facade, Self::MESH_LEN).unwrap(),
+            chunks: HashMap::new(),
+            bytes_uploaded: 0,
+            rebuilt_chunks: vec![],
+            ambient_occlusion: 0.6,
+            rebuild_all: false,
         }
     }
 
-    pub fn update(&mut self, world: &mut PartialWorld, display: &Display, enable_skybox: bool) {
-        //update robot mesh
-        let robot_elevation = world.world[world.robot_position.x as usize][world.robot_position.y as usize].as_ref().unwrap().elevation;
-        let robot_mesh = Self::get_robot_mesh(world.robot_position, robot_elevation);
-
-        self.get_mut_mesh_at_index(0).copy_from_slice(&robot_mesh);
-
-
-        //update skybox mesh
-        let skybox_mesh = Self::get_skybox_mesh(&self.skybox_mesh_array, &world.env_cond, enable_skybox);
-        self.verts[Self::SKYBOX_MESH_INDEX..Self::SKYBOX_MESH_INDEX + Self::MESH_LEN].copy_from_slice(skybox_mesh);
-
-        //tile and content meshes
-        for tile_pos in world.tiles_to_refresh.iter().cloned() {
-            let tile = world.world[tile_pos.x as usize][tile_pos.y as usize].clone();
-            match tile {
-                None => {},
-                Some(tile) => self.insert_mesh(tile_pos, tile, &world.world),
-            };
+    pub fn ambient_occlusion(&self) -> f32 { self.ambient_occlusion }
+    // takes effect with the next call to update, which rebuilds every chunk
+    pub fn set_ambient_occlusion(&mut self, strength: f32) {
+        if strength != self.ambient_occlusion {
+            self.ambient_occlusion = strength;
+            self.rebuild_all = true;
         }
-
-        self.update_vbo(&world.tiles_to_refresh, display);
     }
 
-    fn get_mesh_at_index(&self, i: usize) -> &[Vertex] { &self.verts[i..i + Self::MESH_LEN] }
-    fn get_mut_mesh_at_index(&mut self, i: usize) -> &mut [Vertex] { &mut self.verts[i..i + Self::MESH_LEN] }
-    fn grow(&mut self) {
-        //number of null meshes to add to the storage
-        let reallocation_size_difference = max(1, self.verts.len() / Self::MESH_LEN / 2);
-
-        self.empty_meshes.reserve(reallocation_size_difference);
-        self.verts.reserve_exact(reallocation_size_difference * Self::MESH_LEN);
-
-        let _prev_number_of_verts = self.verts.len();
+    // robot is the position and yaw of the robot model (see RobotModel), None to hide it
+    pub fn update(&mut self, world: &mut PartialWorld, facade: &impl Facade, robot: Option<(Vec3, f32)>, robot_scale: f32) {
+        self.bytes_uploaded = 0;
+        self.rebuilt_chunks.clear();
 
-        for _ in 0..reallocation_size_difference {
-            self.empty_meshes.push(self.verts.len());
-            self.verts.extend_from_slice(&Self::NULL_MESH);
+        //update robot mesh
+        let misc_verts = match robot {
+            Some((robot_position, robot_yaw)) => Self::get_robot_mesh(robot_position, robot_yaw, robot_scale),
+            None => Self::NULL_MESH,
+        };
+        self.misc_vbo.write(&misc_verts);
+        self.bytes_uploaded += std::mem::size_of_val(&misc_verts);
+
+        //rebuild the chunks containing tiles which changed
+        let mut dirty_chunks: HashSet<UVec2> = world.tiles_to_refresh.iter().map(|tile_pos| tile_pos / Self::CHUNK_SIZE).collect();
+        if self.rebuild_all {
+            self.rebuild_all = false;
+            dirty_chunks.extend(self.chunks.keys());
+        }
+        for chunk in dirty_chunks {
+            self.rebuild_chunk(chunk, &world.world, facade);
         }
-
-        assert_eq!(self.verts.len(), _prev_number_of_verts + reallocation_size_difference * Self::MESH_LEN);
     }
 
-    fn get_null_mesh_or_grow(&mut self) -> usize {
-        let ret = match self.empty_meshes.pop() {
-            Some(empty_content_mesh) => empty_content_mesh,
-            None => {
-                self.grow();
-                self.empty_meshes.pop().unwrap()
-            }
-        };
-
-        assert!(self.get_mesh_at_index(ret).iter().all(Vertex::is_null));
-
-        ret
+    // all the chunks which contain at least a discovered tile, along with their position (in
+    // chunks, so the chunk (x, y) contains the tiles from (x, y) * CHUNK_SIZE to (x+1, y+1) * CHUNK_SIZE)
+    pub fn chunks(&self) -> impl Iterator<Item = (UVec2, &Chunk)> {
+        self.chunks.iter().map(|(pos, chunk)| (*pos, chunk))
     }
 
-    fn insert_mesh(&mut self, tile_pos: UVec2, tile: Tile, world: &Vec<Vec<Option<Tile>>>) {
-        let (prev_tile, mut meshes_indices) = match self.tiles_positions_map.get(&tile_pos).cloned() {
-            Some((prev_tile, meshes_indices)) => (Some(prev_tile), meshes_indices),
-            None => (None, [0, 0, 0]),
-        };
-
-        let should_insert_content_mesh = prev_tile.map(|prev| prev.content.index() != tile.content.index()).unwrap_or(true);
-
-        //insert tile mesh
-        {
-            if meshes_indices[0] == 0 {
-                meshes_indices[0] = self.get_null_mesh_or_grow() as u32;
-            }
-            let tile_mesh_index = meshes_indices[0] as usize;
-            let tile_mesh = Self::get_tile_mesh(&tile, tile_pos, world);
-            self.get_mut_mesh_at_index(tile_mesh_index).copy_from_slice(&tile_mesh);
-        }
-
-        if should_insert_content_mesh {
-            let content_mesh = Self::get_content_mesh(&tile.content, tile_pos, tile.elevation);
-            if let Some(content_mesh) = content_mesh {
-                for i in 0..2 {
-                    if meshes_indices[i + 1] == 0 {
-                        meshes_indices[i + 1] = self.get_null_mesh_or_grow() as u32;
-                    }
-                    let mesh_idx = meshes_indices[i + 1] as usize;
-                    self.get_mut_mesh_at_index(mesh_idx).copy_from_slice(&content_mesh[i * Self::MESH_LEN..(i + 1) * Self::MESH_LEN]);
-                }
-            } else {
-                for i in 1..=2 {
-                    if meshes_indices[i] != 0 {
-                        let mesh_idx = meshes_indices[i] as usize;
-                        self.get_mut_mesh_at_index(mesh_idx).copy_from_slice(&Self::NULL_MESH);
-                        self.empty_meshes.push(mesh_idx);
-
-                        meshes_indices[i] = 0;
-
-                        if self.fill_factor_is_low() {
-                            self.shrink();
+    fn rebuild_chunk(&mut self, chunk: UVec2, world: &Vec<Vec<Option<Tile>>>, facade: &impl Facade) {
+        let world_size = world.len() as u32;
+        let first_tile = chunk * Self::CHUNK_SIZE;
+        let mut verts = vec![];
+        let mut liquid_verts = vec![];
+        for x in first_tile.x..(first_tile.x + Self::CHUNK_SIZE).min(world_size) {
+            for y in first_tile.y..(first_tile.y + Self::CHUNK_SIZE).min(world_size) {
+                if let Some(tile) = &world[x as usize][y as usize] {
+                    let tile_pos = UVec2::new(x, y);
+                    let tile_mesh = Self::get_tile_mesh(tile, tile_pos, world, self.ambient_occlusion);
+                    match tile.tile_type {
+                        TileType::DeepWater | TileType::ShallowWater | TileType::Lava => {
+                            let emissive = if tile.tile_type == TileType::Lava { 1.0 } else { 0.0 };
+                            liquid_verts.extend(tile_mesh.iter().filter(|v| !v.is_null()).map(|v| LiquidVertex { position: v.position, color: v.color, emissive }));
                         }
+                        _ => verts.extend_from_slice(&tile_mesh),
+                    }
+                    if let Some(content_mesh) = Self::get_content_mesh(&tile.content, tile_pos, tile.elevation) {
+                        // content meshes are padded with null vertices at the end
+                        verts.extend(content_mesh.iter().filter(|v| !v.is_null()));
                     }
                 }
             }
         }
 
-        self.tiles_positions_map.insert(tile_pos, (tile.clone(), meshes_indices));
-    }
-
-    //resizes the memory to 2/3 of what it previously was. assumes fill_factor_is_low()==true
-    fn shrink(&mut self) {
-        assert!(self.fill_factor_is_low());
-        let mut new_tiles_positions_map = HashMap::new();
-        let mut new_verts = Vec::new();
-        let mut new_empty_meshes = Vec::new();
-
-        new_verts.extend_from_slice(self.get_mesh_at_index(Self::ROBOT_MESH_INDEX));
-        new_verts.extend_from_slice(self.get_mesh_at_index(Self::SKYBOX_MESH_INDEX));
-
-        for (tile_pos, (tile, old_indices)) in self.tiles_positions_map.iter() {
-            let mut new_indices = [0; 3];
-            for (i, old_index) in old_indices.iter().cloned().enumerate() {
-                if old_index != 0 {
-                    new_indices[i] = new_verts.len() as u32;
-                    new_verts.extend_from_slice(self.get_mesh_at_index(old_index as usize));
-                }
-            }
-            new_tiles_positions_map.insert(tile_pos.clone(), (tile.clone(), new_indices));
-        }
-
-        let number_of_meshes = self.verts.len() / Self::MESH_LEN;
-        let new_number_of_meshes = number_of_meshes * 2 / 3;
-
-        for i in number_of_meshes..new_number_of_meshes {
-            new_empty_meshes.push(i * Self::MESH_LEN);
-        }
-
-        self.tiles_positions_map = new_tiles_positions_map;
-        self.empty_meshes = new_empty_meshes;
-        self.verts = new_verts;
-    }
-
-    #[allow(dead_code)]
-    fn remove_mesh(&mut self, tile_pos: UVec2) {
-        let prev_content = self.tiles_positions_map.remove(&tile_pos);
-        if let Some((_prev_tile, indices)) = prev_content {
-            let indices = indices.map(|n| n as usize);
-            //hashmap reports content where there is none
-            self.empty_meshes.extend_from_slice(&indices);
-            for index in indices {
-                if index != 0 {
-                    self.get_mut_mesh_at_index(index).copy_from_slice(&Self::NULL_MESH);
-                }
-            }
-
-            if self.fill_factor_is_low() {
-                self.shrink();
-            }
+        self.rebuilt_chunks.push(chunk);
+        if verts.is_empty() && liquid_verts.is_empty() {
+            // only happens when going back in time in a replay
+            self.chunks.remove(&chunk);
+        } else {
+            self.bytes_uploaded += verts.len() * std::mem::size_of::<Vertex>() + liquid_verts.len() * std::mem::size_of::<LiquidVertex>();
+            // waves displace liquids by less than a tenth of a unit
+            let positions = verts.iter().map(|v| v.position)
+                .chain(liquid_verts.iter().flat_map(|v| [0.1, -0.1].map(|dy| [v.position[0], v.position[1] + dy, v.position[2]])));
+            let (min, max) = positions.fold((Vec3::repeat(f32::MAX), Vec3::repeat(f32::MIN)), |(min, max), position| {
+                let position = Vec3::from(position);
+                (min.inf(&position), max.sup(&position))
+            });
+            let lod_verts = Self::get_lod_mesh(first_tile, world);
+            self.bytes_uploaded += lod_verts.len() * std::mem::size_of::<Vertex>();
+            self.chunks.insert(chunk, Chunk {
+                vbo: VertexBuffer::new(facade, &verts).unwrap(),
+                lod_vbo: VertexBuffer::new(facade, &lod_verts).unwrap(),
+                liquid_vbo: if liquid_verts.is_empty() { None } else { Some(VertexBuffer::new(facade, &liquid_verts).unwrap()) },
+                min, max,
+            });
         }
-        //do nothing if the hashmap agrees there is no content
-    }
-    fn fill_factor_is_low(&self) -> bool {
-        let number_of_meshes = self.verts.len() / Self::MESH_LEN;
-
-        number_of_meshes > self.min_number_of_meshes
-            && self.empty_meshes.len() > number_of_meshes / 2
-    }
-    fn update_vbo_slice(&mut self, range: Range<usize>) {
-        let vbo_slice = self.vbo.slice(range.clone()).unwrap();
-        vbo_slice.write(&self.verts[range]);
     }
-    fn update_vbo(&mut self, tiles_to_refresh: &HashSet<UVec2>, display: &Display) {
-        if self.vbo.len() == self.verts.len() {
-            //update robot mesh and skybox
-            let mut update_set = range_set![0..=(Self::MESH_LEN * 2 - 1);1];
-
-            for tile_pos in tiles_to_refresh {
-                //tiles to refresh are not necessarily known, since any tile close to a tile that has changed is pushed inside and that includes undiscovered tiles
-                if let Some((_tile, indices)) = self.tiles_positions_map.get(tile_pos).cloned() {
-
-                    //update tile
-                    let tile_mesh_idx = indices[0] as usize;
-                    update_set.insert_range(tile_mesh_idx..=(tile_mesh_idx + Self::MESH_LEN - 1));
-
-                    //update content
-                    for i in 1..=2 {
-                        let content_mesh_idx = indices[i] as usize;
-                        if content_mesh_idx != 0 {
-                            update_set.insert_range(content_mesh_idx..=(content_mesh_idx + Self::MESH_LEN - 1));
+    // returns the simplified mesh of the chunk starting at first_tile: a quad for each block of
+    // LOD_BLOCK_SIZE x LOD_BLOCK_SIZE tiles, covering the discovered tiles of the block, with their
+    // average elevation and color. contents are left out
+    fn get_lod_mesh(first_tile: UVec2, world: &Vec<Vec<Option<Tile>>>) -> Vec<Vertex> {
+        let world_size = world.len() as u32;
+        let end = (first_tile + UVec2::repeat(Self::CHUNK_SIZE)).inf(&UVec2::repeat(world_size));
+        let mut verts = vec![];
+        for block_x in (first_tile.x..end.x).step_by(Self::LOD_BLOCK_SIZE as usize) {
+            for block_y in (first_tile.y..end.y).step_by(Self::LOD_BLOCK_SIZE as usize) {
+                let (mut min, mut max) = (UVec2::repeat(u32::MAX), UVec2::zeros());
+                let (mut elevation, mut color, mut count) = (0.0, Vec3::zeros(), 0);
+                for x in block_x..(block_x + Self::LOD_BLOCK_SIZE).min(end.x) {
+                    for y in block_y..(block_y + Self::LOD_BLOCK_SIZE).min(end.y) {
+                        if let Some(tile) = &world[x as usize][y as usize] {
+                            min = min.inf(&UVec2::new(x, y));
+                            max = max.sup(&UVec2::new(x, y));
+                            elevation += tile.elevation as f32;
+                            color += tile_to_color(tile);
+                            count += 1;
                         }
                     }
                 }
+                if count == 0 {
+                    continue;
+                }
+
+                let y = elevation_to_mesh_space_y(elevation / count as f32);
+                let color: [f32; 3] = (color / count as f32).into();
+                let (x0, z0, x1, z1) = (min.x as f32, min.y as f32, (max.x + 1) as f32, (max.y + 1) as f32);
+                for [x, z] in [[x0, z0], [x1, z0], [x0, z1], [x0, z1], [x1, z0], [x1, z1]] {
+                    verts.push(Vertex { position: [x, y, z], color });
+                }
             }
-            for range in update_set.as_ref() {
-                let range = *range.start()..(*range.end() + 1);
-                self.update_vbo_slice(range);
-            }
-        } else {
-            self.vbo = VertexBuffer::dynamic(display, &self.verts).unwrap();
         }
+        verts
     }
-    fn get_tile_mesh(t: &Tile, tile_pos: UVec2, world: &Vec<Vec<Option<Tile>>>) -> [Vertex; Self::MESH_LEN] {
+    fn get_tile_mesh(t: &Tile, tile_pos: UVec2, world: &Vec<Vec<Option<Tile>>>, ambient_occlusion: f32) -> [Vertex; Self::MESH_LEN] {
         let color_displace_amount = 0.1;
         let position_displace_amount = 0.1;
 
@@ -291,6 +239,11 @@ impl WorldMesh {
                 let color = rand_displace_vec(color, color_displace_amount, &mut rng).as_ref().clone();
                 for [x, z] in tri {
                     let mut position = [*x as f32 / 2.0, get_elevation((*x as usize, *z as usize), &world).unwrap(), *z as f32 / 2.0];
+                    let color = if ambient_occlusion > 0.0 {
+                        (Vec3::from(color) * (1.0 - ambient_occlusion * Self::occlusion(position, world))).into()
+                    } else {
+                        color
+                    };
                     if *x % 2 != 1 || *z % 2 != 1 {
                         let mut vtx_pos_rng = SmallRng::seed_from_u64(*x as u64 + ((*z as u64) << 32));
                         position = rand_displace_vec(Vec3::from(position), position_displace_amount, &mut vtx_pos_rng).as_ref().clone();
@@ -306,6 +259,27 @@ impl WorldMesh {
         tile_vertices
     }
 
+    // how much the tiles within OCCLUSION_RADIUS of the position rise above it, from 0 (none do)
+    // to 1, weighting each by the angle it covers as seen from the position
+    fn occlusion(position: [f32; 3], world: &Vec<Vec<Option<Tile>>>) -> f32 {
+        let [x, y, z] = position;
+        let range = |c: f32| (c - Self::OCCLUSION_RADIUS).floor().max(0.0) as usize..((c + Self::OCCLUSION_RADIUS).ceil() as usize).min(world.len());
+        let (mut occlusion, mut samples) = (0.0, 0);
+        for tile_x in range(x) {
+            for tile_z in range(z) {
+                let Some(tile) = &world[tile_x][tile_z] else { continue };
+                let distance = ((tile_x as f32 + 0.5 - x).powi(2) + (tile_z as f32 + 0.5 - z).powi(2)).sqrt();
+                if distance > Self::OCCLUSION_RADIUS {
+                    continue;
+                }
+                let rise = elevation_to_mesh_space_y(tile.elevation as f32) - y;
+                occlusion += rise.max(0.0).atan2(distance.max(0.5)) / (PI / 2.0);
+                samples += 1;
+            }
+        }
+        if samples == 0 { 0.0 } else { (2.0 * occlusion / samples as f32).min(1.0) }
+    }
+
     fn get_content_mesh(c: &Content, tile_pos: UVec2, elevation: usize) -> Option<[Vertex; Self::MESH_LEN * 2]> {
         /*
         to get the vertices from blender use the following code; this will create a file ~/file.txt with our meshes inside.
@@ -400,108 +374,15 @@ with open(save_to_file, 'w') as file:
         }
     }
 
-    fn get_skybox_mesh<'a>(skybox_mesh_array: &'a [[[Vertex; WorldMesh::MESH_LEN]; 3]; 5], env_cond: &EnvironmentalConditions, enable_skybox: bool) -> &'a [Vertex; Self::MESH_LEN] {
-        if !enable_skybox {
-            &Self::NULL_MESH
-        } else {
-            &skybox_mesh_array[env_cond.get_weather_condition() as usize][env_cond.get_time_of_day() as usize]
-        }
-    }
-
-    fn generate_skybox_meshes(world_size: usize) -> [[[Vertex; Self::MESH_LEN]; 3]; 5] {
-        assert_eq!(WeatherType::Sunny as usize, 0);
-        assert_eq!(WeatherType::Rainy as usize, 1);
-        assert_eq!(WeatherType::Foggy as usize, 2);
-        assert_eq!(WeatherType::TropicalMonsoon as usize, 3);
-        assert_eq!(WeatherType::TrentinoSnow as usize, 4);
-        assert_eq!(WeatherType::iter().count(), 5);
-
-        let weather_gradients = [
-            [vec3(0.7, 0.6, 1.0), vec3(0.5, 0.4, 0.4), vec3(0.1, 0.1, 0.1)], // sunny
-            [vec3(0.6, 0.6, 0.8), vec3(0.5, 0.5, 0.7), vec3(0.0, 0.0, 0.0)], // rainy
-            [vec3(0.6, 0.6, 0.6), vec3(0.5, 0.5, 0.5), vec3(0.0, 0.0, 0.0)], // foggy
-            [vec3(0.1, 0.1, 0.1), vec3(0.2, 0.2, 0.2), vec3(0.0, 0.0, 0.0)], // tropical monsoon
-            [vec3(0.6, 0.6, 0.6), vec3(0.4, 0.4, 0.4), vec3(0.3, 0.3, 0.3)], // trentino snow
-        ];
-
-        assert_eq!(DayTime::Morning as usize, 0);
-        assert_eq!(DayTime::Afternoon as usize, 1);
-        assert_eq!(DayTime::Night as usize, 2);
-        assert_eq!(DayTime::iter().count(), 3);
-        let colors_for_time_of_day = [
-            vec3(1.0, 1.0, 1.0),
-            vec3(1.0, 0.9, 0.9),
-            vec3(0.3, 0.3, 0.3),
-        ];
-
-        let world_size = world_size as f32;
-
-        type Color = [f32; 3];
-        type Gradient = [Color; 3];
-        let mut gradients: [[Gradient; 3]; 5] = [[[[0.0; 3]; 3]; 3]; 5];
-
-        for weather in 0..weather_gradients.len() {
-            for time_of_day in 0..colors_for_time_of_day.len() {
-                let weather_gradient = weather_gradients[weather];
-                let c_t = colors_for_time_of_day[time_of_day];
-                let gradient = weather_gradient.map(|c_w| {
-                    [c_w.x * c_t.x, c_w.y * c_t.y, c_w.z * c_t.z]
-                });
-                gradients[weather][time_of_day] = gradient;
-            }
-        }
-
-
-        let octahedron = [
-            [0.0, 1.0, 0.0],
-            [-1.0, 0.0, -1.0],
-            [1.0, 0.0, -1.0],
-            [1.0, 0.0, 1.0],
-            [-1.0, 0.0, 1.0],
-            [0.0, -1.0, 0.0],
-        ];
-        //scale
-        let octahedron_correct_size = octahedron.map(|p| p.map(|n| n * (world_size+10.0) * 2.1));
-        //translate
-        let positions_repetitionless = octahedron_correct_size.map(|[x, y, z]| [x + world_size / 2.0, y, z + world_size / 2.0]);
-
-        let mut vertices_repetitionless = [[[Vertex::NULL; 6]; 3]; 5];
-        for w in 0..5 {
-            for tod in 0..3 {
-                for i in 0..positions_repetitionless.len() {
-                    let position = positions_repetitionless[i];
-                    let gradient = gradients[w][tod];
-                    let color = if i == 0 { gradient[0] } else if i == 5 { gradient[2] } else { gradient[1] };
-                    vertices_repetitionless[w][tod][i] = Vertex { position, color };
-                }
-            }
-        }
-
-        let indices = [
-            0, 1, 2,
-            0, 2, 3,
-            0, 3, 4,
-            0, 4, 1,
-            5, 1, 2,
-            5, 2, 3,
-            5, 3, 4,
-            5, 4, 1,
-        ];
-
-        vertices_repetitionless.map(|weather_based_vertices|
-            weather_based_vertices.map(|repetitionless_mesh| {
-                indices.map(|i| repetitionless_mesh[i])
-            })
-        )
-    }
-
-    fn get_robot_mesh(robot_pos: UVec2, elevation: usize) -> [Vertex; Self::MESH_LEN] {
+    // position is the center of the tile the robot stands on, and yaw the direction it faces (0
+    // being +x). scale enlarges the mesh around its base (1.0 being its natural size)
+    fn get_robot_mesh(position: Vec3, yaw: f32, scale: f32) -> [Vertex; Self::MESH_LEN] {
         let robot_vertices_repetitionless = [
             vec3(0.0, 0.0, 0.0),
-            vec3(0.5, 1.0, 0.0),
-            vec3(0.0, 1.0, 0.5),
-            vec3(-0.5, 1.0, 0.0),
-            vec3(0.0, 1.0, -0.5),
+            vec3(0.7, 1.0, 0.0), // the nose, longer than the other corners
+            vec3(0.0, 1.0, 0.4),
+            vec3(-0.4, 1.0, 0.0),
+            vec3(0.0, 1.0, -0.4),
             vec3(0.0, 2.0, 0.0),
         ];
         let robot_tris = [
@@ -513,17 +394,17 @@ with open(save_to_file, 'w') as file:
             [5, 2, 3],
             [5, 3, 4],
             [5, 4, 1],
-        ].map(|tri| tri.map(|i| robot_vertices_repetitionless[i]));
+        ].map(|tri| tri.map(|i| rotate_vec3(&robot_vertices_repetitionless[i], yaw, &vec3(0.0, 1.0, 0.0))));
 
+        // a dark body with a bright top, so that it stands out against the terrain
         let mut robot_color_rng = SmallRng::seed_from_u64(1);
-        let robot_mesh_position = vec3(robot_pos.x as f32, elevation_to_mesh_space_y(elevation as f32), robot_pos.y as f32) + vec3(0.5, 0.5, 0.5);
-
         let mut robot_vertices = [Vertex::NULL; Self::MESH_LEN];
         for tri in 0..robot_tris.len() {
-            let color = rand_displace_vec(vec3(0.2, 0.2, 0.2), 0.07, &mut robot_color_rng).as_ref().clone();
+            let base_color = if tri < 4 { vec3(0.2, 0.2, 0.2) } else { vec3(0.95, 0.75, 0.15) };
+            let color = rand_displace_vec(base_color, 0.07, &mut robot_color_rng).as_ref().clone();
             for tri_vert in 0..3 {
-                let position = (robot_tris[tri][tri_vert] + robot_mesh_position).as_ref().clone();
-                robot_vertices[tri*3 + tri_vert] = Vertex { position, color };
+                let vertex_position = (robot_tris[tri][tri_vert] * scale + position).as_ref().clone();
+                robot_vertices[tri*3 + tri_vert] = Vertex { position: vertex_position, color };
             }
         }
 
@@ -532,7 +413,7 @@ with open(save_to_file, 'w') as file:
         robot_vertices
     }
 }
-fn tile_to_color(t: &Tile) -> Vec3 {
+pub fn tile_to_color(t: &Tile) -> Vec3 {
     Vec3::from_row_slice(&match t.tile_type {
         TileType::DeepWater => [0.0, 0.0, 0.15],
         TileType::ShallowWater => [0.05, 0.05, 0.4],
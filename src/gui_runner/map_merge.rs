@@ -0,0 +1,160 @@
+use std::collections::{HashMap, HashSet};
+use std::mem::take;
+use std::sync::{Arc, Mutex};
+use nalgebra_glm::UVec2;
+use robotics_lib::world::tile::Tile;
+use super::PartialWorld;
+use super::worker_thread::{insert_vicinity, TOUCHED_TILES_RADIUS};
+
+// When several robots run, the map shown in the GUI is made by merging their maps, which often
+// disagree: a robot keeps seeing a tile as it was when it last looked at it, however the others
+// changed it since. The game thread sends the map of the first robot as it is, which is what the
+// replay, the breakpoints and the observers see, along with those of the others (see RobotMap).
+// The worker thread then merges them with a MapMerger, only for the GUI to show, according to the
+// MergePolicy chosen in the GUI: MapMerge is shared (cloned, through the Config) between the two.
+// Who last looked at every tile is tracked from the tiles the events of the robots touched and from
+// where the robots stand, whatever the policy, so that switching to LatestWriter shows the whole
+// history of the run. A policy chosen while the game is paused is applied to the next tick.
+
+/// How the maps of the robots of a run with several robots (see `GuiRunner::new_multi`) are merged
+/// into the one shown in the GUI, where it can also be changed. The replay, the breakpoints and the
+/// observers always see the map of the first robot alone.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// The tiles known by the first robot, and those it doesn't know as the others see them.
+    #[default]
+    Union,
+    /// Every tile as seen by the robot which last looked at it, or as in `Union` if no robot
+    /// looked at it since it was discovered.
+    LatestWriter,
+    /// The map of a single robot (0 is the first), as if it was the only one.
+    Robot(usize),
+}
+impl MergePolicy {
+    pub(crate) fn name(&self) -> String {
+        match self {
+            MergePolicy::Union => "union".to_string(),
+            MergePolicy::LatestWriter => "latest writer".to_string(),
+            MergePolicy::Robot(robot) => format!("robot {}", robot + 1),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct MapMerge {
+    policy: Arc<Mutex<MergePolicy>>,
+}
+impl MapMerge {
+    pub fn policy(&self) -> MergePolicy {
+        *self.policy.lock().unwrap()
+    }
+
+    pub fn set_policy(&self, policy: MergePolicy) {
+        *self.policy.lock().unwrap() = policy;
+    }
+}
+
+// the map of one of the robots after the first, with the tiles its events touched during its tick
+#[derive(Clone)]
+pub(crate) struct RobotMap {
+    pub map: Vec<Vec<Option<Tile>>>,
+    pub touched_tiles: Vec<UVec2>,
+}
+
+pub(crate) struct MapMerger {
+    shared: MapMerge,
+    last_policy: MergePolicy,
+    // the tiles last looked at by one of the other robots (1 is the first of them); those last
+    // looked at by the first robot, or by none, aren't kept
+    writers: HashMap<UVec2, usize>,
+}
+impl MapMerger {
+    pub fn new(shared: MapMerge) -> Self {
+        Self { shared, last_policy: MergePolicy::default(), writers: HashMap::new() }
+    }
+
+    // merges world.other_maps into world.world, emptying them, and adds to world.touched_tiles the
+    // tiles touched by the other robots and where they stand, around which the merged map may have
+    // changed (or clears it if the policy changed, since the whole map may have). maps of a
+    // different size (the Generator didn't create the same world for every robot) aren't merged
+    pub fn merge(&mut self, world: &mut PartialWorld) {
+        if world.other_maps.is_empty() {
+            return;
+        }
+        let robot_maps = take(&mut world.other_maps);
+        self.track_writers(world, &robot_maps);
+
+        let policy = self.shared.policy();
+        if policy != self.last_policy {
+            self.last_policy = policy;
+            world.touched_tiles = None;
+        }
+        if let Some(touched_tiles) = &mut world.touched_tiles {
+            for (robot_map, other_robot) in robot_maps.iter().zip(&world.other_robots) {
+                touched_tiles.extend(robot_map.touched_tiles.iter().copied());
+                touched_tiles.push(other_robot.position);
+            }
+        }
+
+        let size = world.world.len();
+        let mut other_maps: Vec<Option<Vec<Vec<Option<Tile>>>>> = robot_maps.into_iter()
+            .map(|robot_map| Some(robot_map.map).filter(|map| map.len() == size))
+            .collect();
+        match policy {
+            MergePolicy::Union => fill_unknown(&mut world.world, &other_maps),
+            MergePolicy::LatestWriter => {
+                fill_unknown(&mut world.world, &other_maps);
+                for (position, writer) in self.writers.iter() {
+                    let (x, y) = (position.x as usize, position.y as usize);
+                    let other_tile = other_maps.get(writer - 1).and_then(|other_map| other_map.as_ref()?[x][y].as_ref());
+                    if let Some(other_tile) = other_tile {
+                        world.world[x][y] = Some(other_tile.clone());
+                    }
+                }
+            }
+            MergePolicy::Robot(0) => {}
+            MergePolicy::Robot(robot) => {
+                if let Some(other_map) = other_maps.get_mut(robot - 1).and_then(Option::take) {
+                    world.world = other_map;
+                }
+            }
+        }
+    }
+
+    // the others ticked before the first robot, so the tiles it looks at were looked at last by it
+    fn track_writers(&mut self, world: &PartialWorld, robot_maps: &[RobotMap]) {
+        let size = world.world.len();
+        for (i, (robot_map, other_robot)) in robot_maps.iter().zip(&world.other_robots).enumerate() {
+            for position in looked_at(&robot_map.touched_tiles, other_robot.position, size) {
+                self.writers.insert(position, i + 1);
+            }
+        }
+        let touched_tiles = world.touched_tiles.as_deref().unwrap_or_default();
+        for position in looked_at(touched_tiles, world.robot_position, size) {
+            self.writers.remove(&position);
+        }
+    }
+}
+
+// fills the tiles of map which aren't known with those of the first of other_maps knowing them
+fn fill_unknown(map: &mut [Vec<Option<Tile>>], other_maps: &[Option<Vec<Vec<Option<Tile>>>>]) {
+    for other_map in other_maps.iter().flatten() {
+        for (row, other_row) in map.iter_mut().zip(other_map) {
+            for (tile, other_tile) in row.iter_mut().zip(other_row) {
+                if tile.is_none() {
+                    *tile = other_tile.clone();
+                }
+            }
+        }
+    }
+}
+
+// the tiles a robot standing at position looked at, given the tiles its events touched: it sees
+// (and discovers) the tiles next to those it moves to and interacts with
+fn looked_at(touched_tiles: &[UVec2], position: UVec2, world_size: usize) -> HashSet<UVec2> {
+    let mut tiles = HashSet::new();
+    for center in touched_tiles.iter().chain(std::iter::once(&position)) {
+        insert_vicinity(&mut tiles, *center, TOUCHED_TILES_RADIUS, world_size);
+    }
+    tiles
+}
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+// MarkerStyle describes how a robot (one of the live ones or a ghost replay) is marked in the GUI:
+// the color of its screen-space marker and of its trail, the shape of the marker and its label. The
+// styles given to the builder are only the initial ones: they can be edited from the GUI, and the
+// edits are saved along with the session (File > Save session), to be restored when it is opened.
+
+/// Shape of the screen-space marker drawn over a robot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MarkerIcon {
+    Ring,
+    Diamond,
+    Square,
+    Cross,
+}
+impl MarkerIcon {
+    pub(crate) const ALL: [MarkerIcon; 4] = [MarkerIcon::Ring, MarkerIcon::Diamond, MarkerIcon::Square, MarkerIcon::Cross];
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            MarkerIcon::Ring => "ring",
+            MarkerIcon::Diamond => "diamond",
+            MarkerIcon::Square => "square",
+            MarkerIcon::Cross => "cross",
+        }
+    }
+}
+
+/// Color, icon and label with which a robot is marked in the GUI.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MarkerStyle {
+    /// RGB color of the marker and of the trail (when the trail isn't colored by energy).
+    pub color: [f32; 3],
+    /// Shape of the marker.
+    pub icon: MarkerIcon,
+    /// Text drawn next to the marker.
+    pub label: String,
+}
+impl MarkerStyle {
+    pub fn new(color: [f32; 3], icon: MarkerIcon, label: impl Into<String>) -> Self {
+        Self { color, icon, label: label.into() }
+    }
+
+    /// Default style of the live robot: a yellow ring labelled "robot".
+    pub fn robot() -> Self {
+        Self::new([0.9, 0.9, 0.2], MarkerIcon::Ring, "robot")
+    }
+
+    /// Default style of the ghost replay: a light blue ring labelled "ghost".
+    pub fn ghost() -> Self {
+        Self::new([0.6, 0.8, 1.0], MarkerIcon::Ring, "ghost")
+    }
+}
@@ -0,0 +1,32 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use robotics_lib::event::events::Event;
+use crate::snapshot::WorldSnapshot;
+
+// GuiRunnerObserver lets the application embedding the GuiRunner see what the GUI sees: the
+// observers registered on the builder are called on the game thread, by the robot wrapper, with
+// every tick's world (as a WorldSnapshot, built only when there are observers) and every event
+// received by the robot, and by the GameRunner when the game stops. They are shared between the
+// two through an ObserversHandle, like the event journal.
+
+/// Receives the same data as the GUI while a robot is running, so that the application embedding
+/// the GuiRunner can log statistics or drive other systems from it. Registered with
+/// `GuiRunnerBuilder::observer`; every method does nothing by default.
+///
+/// Observers are called on the thread calling `GuiRunner::run` (or `GuiRunner::run_headless`),
+/// between the ticks of the game: the game waits for them, so they should return quickly.
+/// They are not called when playing back a replay or previewing a world.
+pub trait GuiRunnerObserver {
+    /// Called at the end of every tick (starting from the initialization tick, tick 0) with the
+    /// world as known to the robot.
+    fn on_tick(&mut self, _snapshot: &WorldSnapshot) {}
+
+    /// Called with every event received by the robot, along with the tick it was received at.
+    fn on_event(&mut self, _tick: usize, _event: &Event) {}
+
+    /// Called once when the game stops, either because the window was closed or because
+    /// `GuiRunner::run_headless` ran all of its ticks.
+    fn on_terminate(&mut self) {}
+}
+
+pub(crate) type ObserversHandle = Rc<RefCell<Vec<Box<dyn GuiRunnerObserver>>>>;
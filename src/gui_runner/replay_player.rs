@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, SyncSender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::replay::ReplayAnnotations;
+use super::{PartialWorld, RunMode, RunModeChange};
+use super::run_summary::{ExitReason, RunSummary};
+use super::snapshot_history::SnapshotHistory;
+use super::thread_health::{HealthMonitor, MonitoredThread};
+
+// ReplayPlayer takes the place of GameRunner when a replay is being viewed: rather than running
+// the game it sends the recorded snapshots through the game->worker channel, moving forwards or
+// backwards through the replay according to the RunMode received from the GUI. Continuous mode
+// plays the replay back at the chosen number of ticks per second and pauses at its end.
+// The snapshots are kept in a SnapshotHistory, so a long replay takes a fraction of the memory it
+// would take uncompressed (and its oldest ticks are dropped if it doesn't fit the memory budget).
+// When it stops, the RunSummary it returns is that of the last tick it sent.
+
+// ReplayInfo is what the GUI needs to know about the replay being played back
+pub struct ReplayInfo {
+    pub path: PathBuf,
+    pub ticks: RangeInclusive<usize>, // the ticks of the first and last snapshot
+    pub annotations: ReplayAnnotations,
+    pub history: SnapshotHistory, // to show and change its memory budget
+}
+
+pub struct ReplayPlayer {
+    path: PathBuf,
+    history: SnapshotHistory,
+    annotations: ReplayAnnotations,
+    game_to_worker_tx: SyncSender<PartialWorld>,
+    gui_to_game_rx: Receiver<RunModeChange>,
+    health: HealthMonitor,
+}
+impl ReplayPlayer {
+    pub fn new(path: PathBuf, history: SnapshotHistory, annotations: ReplayAnnotations, game_to_worker_tx: SyncSender<PartialWorld>, gui_to_game_rx: Receiver<RunModeChange>, health: HealthMonitor) -> Self {
+        Self { path, history, annotations, game_to_worker_tx, gui_to_game_rx, health }
+    }
+
+    pub fn info(&self) -> ReplayInfo {
+        let first = self.history.first_tick().unwrap_or(0);
+        let last = self.history.last_tick().unwrap_or(0);
+        ReplayInfo { path: self.path.clone(), ticks: first..=last, annotations: self.annotations.clone(), history: self.history.clone() }
+    }
+
+    pub fn run(mut self) -> RunSummary {
+        let _heartbeat_guard = self.health.guard(MonitoredThread::Game);
+        let Some(mut tick) = self.history.first_tick() else { return RunSummary::new(0, 0, HashMap::new(), ExitReason::WindowClosed) };
+
+        let mut sent = RunSummary::new(tick, 0, HashMap::new(), ExitReason::WindowClosed);
+        let mut sent_tick = None;
+        let mut last_frame_begin = Instant::now();
+        let mut run_mode = RunMode::Paused;
+        loop {
+            self.health.beat(MonitoredThread::Game);
+            run_mode = self.receive_run_mode(run_mode, &mut sent.exit_reason);
+
+            match run_mode {
+                RunMode::Terminate => return sent,
+                RunMode::Paused => {}
+                RunMode::SingleTick => {
+                    tick = self.history.tick_after(tick).unwrap_or(tick);
+                    run_mode = RunMode::Paused;
+                }
+                RunMode::RunTicks(ticks) => {
+                    for _ in 0..ticks {
+                        tick = self.history.tick_after(tick).unwrap_or(tick);
+                    }
+                    run_mode = RunMode::Paused;
+                }
+                RunMode::StepBack => {
+                    tick = self.history.tick_before(tick).unwrap_or(tick);
+                    run_mode = RunMode::Paused;
+                }
+                RunMode::Seek(target) => {
+                    tick = target;
+                    run_mode = RunMode::Paused;
+                }
+                RunMode::Continuous(cap) => {
+                    let interval = cap.map(|cap| Duration::from_secs_f32(1.0 / cap)).unwrap_or_default();
+                    if last_frame_begin.elapsed() >= interval {
+                        last_frame_begin = Instant::now();
+                        match self.history.tick_after(tick) {
+                            Some(next) => tick = next,
+                            None => run_mode = RunMode::Paused,
+                        }
+                    }
+                }
+            }
+
+            // the ticks before the first one in the history may have been dropped to fit the budget
+            let snapshot = self.history.get(tick);
+            if let Some(snapshot) = snapshot.filter(|snapshot| sent_tick != Some(snapshot.tick)) {
+                tick = snapshot.tick;
+                let world = snapshot.to_partial_world();
+                (sent.ticks, sent.energy, sent.backpack) = (world.tick, world.energy, world.backpack.clone());
+                if self.game_to_worker_tx.send(world).is_err() {
+                    // the GUI was closed, tell whether it terminated the game first
+                    self.receive_run_mode(RunMode::Paused, &mut sent.exit_reason);
+                    return sent;
+                }
+                sent_tick = Some(tick);
+            } else {
+                thread::sleep(Duration::from_millis(5));
+            }
+        }
+    }
+
+    // returns the last RunMode received from the GUI, or Terminate if the GUI is gone (telling why
+    // in exit_reason)
+    fn receive_run_mode(&self, mut run_mode: RunMode, exit_reason: &mut ExitReason) -> RunMode {
+        loop {
+            match self.gui_to_game_rx.try_recv() {
+                Ok(change) => {
+                    if change.run_mode == RunMode::Terminate {
+                        *exit_reason = ExitReason::from_source(change.source);
+                    }
+                    run_mode = change.run_mode;
+                }
+                Err(TryRecvError::Empty) => return run_mode,
+                Err(TryRecvError::Disconnected) => {
+                    if run_mode != RunMode::Terminate {
+                        *exit_reason = ExitReason::GuiExited;
+                    }
+                    return RunMode::Terminate;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::sync::{Arc, Mutex};
+use super::PartialWorld;
+use super::gui_thread::gui::charts::DownsampledHistory;
+
+// RunStatistics is shared (cloned) between the worker thread, which accumulates the statistics of
+// the run from every world it relays, and the GUI thread, which copies them when they changed to
+// chart and export them. Every row holds the running totals at the end of a tick: the tiles
+// discovered (counted by the worker thread's diff as the tiles which went from unknown to known),
+// the distance the robot moved (the moves between the positions of consecutive worlds), the energy
+// consumed and recharged (the net change of every tick, since the worlds don't tell the two apart
+// within a tick) and the items added to and removed from the backpack (likewise). Only the first
+// robot is accounted for. A world of an earlier tick (e.g. a replay being played backwards) starts
+// the statistics over, which the readers notice through the generation.
+// The rows aren't kept: every column goes into a DownsampledHistory of its own, so that a run of
+// any length takes the same memory (and the same time to copy), and the min and max of the ticks
+// merged into a bucket keep the peaks a chart of the averages would lose.
+
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct StatisticsRow {
+    pub tick: usize,
+    pub discovered_this_tick: usize,
+    pub discovered: usize,
+    pub distance: usize,
+    pub energy_consumed: usize,
+    pub energy_recharged: usize,
+    pub items_collected: usize,
+    pub items_removed: usize,
+}
+impl StatisticsRow {
+    pub const CSV_HEADER: &'static str = "tick,discovered_this_tick,discovered,distance,energy_consumed,energy_recharged,items_collected,items_removed";
+    pub const COLUMNS: usize = 8;
+    const PER_TICK_COLUMN: usize = 1; // discovered_this_tick, the others are the tick and totals
+
+    // in the order of CSV_HEADER
+    pub fn values(&self) -> [usize; Self::COLUMNS] {
+        [self.tick, self.discovered_this_tick, self.discovered, self.distance,
+         self.energy_consumed, self.energy_recharged, self.items_collected, self.items_removed]
+    }
+}
+
+// the statistics of the run so far: the last row, and the history of every column
+#[derive(Clone)]
+pub(crate) struct StatisticsHistory {
+    pub last: Option<StatisticsRow>,
+    pub columns: Vec<DownsampledHistory>, // in the order of StatisticsRow::CSV_HEADER
+}
+impl StatisticsHistory {
+    const BUCKETS: usize = 512;
+
+    fn new() -> Self {
+        Self { last: None, columns: (0..StatisticsRow::COLUMNS).map(|_| DownsampledHistory::new(Self::BUCKETS)).collect() }
+    }
+
+    fn push(&mut self, row: StatisticsRow) {
+        for (column, value) in self.columns.iter_mut().zip(row.values()) {
+            column.push(value as f32);
+        }
+        self.last = Some(row);
+    }
+
+    pub fn first_tick(&self) -> Option<usize> {
+        self.columns[0].range().map(|(min, _)| min as usize)
+    }
+
+    // one line per bucket: the tick and the totals at its end, since they only grow, and the tiles
+    // discovered per tick averaged over its ticks
+    pub fn csv_lines(&self) -> Vec<String> {
+        let mut columns: Vec<_> = self.columns.iter().map(|column| column.buckets()).collect();
+        let mut lines = vec![];
+        'lines: loop {
+            let mut values = vec![];
+            for (i, column) in columns.iter_mut().enumerate() {
+                let Some(bucket) = column.next() else { break 'lines };
+                values.push(match i {
+                    StatisticsRow::PER_TICK_COLUMN => format!("{:.2}", bucket.avg()),
+                    _ => (bucket.max as usize).to_string(),
+                });
+            }
+            lines.push(values.join(","));
+        }
+        lines
+    }
+}
+
+struct Shared {
+    history: StatisticsHistory,
+    generation: u64, // incremented whenever the statistics start over
+    last_world: Option<(usize, (u32, u32), usize, usize)>, // tick, robot position, energy, items in the backpack
+}
+impl Default for Shared {
+    fn default() -> Self {
+        Self { history: StatisticsHistory::new(), generation: 0, last_world: None }
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct RunStatistics {
+    shared: Arc<Mutex<Shared>>,
+}
+impl RunStatistics {
+    pub fn new() -> Self { Self::default() }
+
+    // must be called with every world relayed, along with the number of tiles it discovered
+    pub fn record(&self, world: &PartialWorld, discovered: usize) {
+        let mut shared = self.shared.lock().unwrap();
+        let position = (world.robot_position.x, world.robot_position.y);
+        let items: usize = world.backpack.values().sum();
+        if shared.last_world.is_some_and(|(tick, ..)| world.tick <= tick) {
+            shared.history = StatisticsHistory::new();
+            shared.last_world = None;
+            shared.generation += 1;
+        }
+
+        let mut row = shared.history.last.unwrap_or_default();
+        row.tick = world.tick;
+        row.discovered_this_tick = discovered;
+        row.discovered += discovered;
+        if let Some((_, last_position, last_energy, last_items)) = shared.last_world {
+            row.distance += last_position.0.abs_diff(position.0) as usize + last_position.1.abs_diff(position.1) as usize;
+            row.energy_consumed += last_energy.saturating_sub(world.energy);
+            row.energy_recharged += world.energy.saturating_sub(last_energy);
+            row.items_collected += items.saturating_sub(last_items);
+            row.items_removed += last_items.saturating_sub(items);
+        }
+        shared.history.push(row);
+        shared.last_world = Some((world.tick, position, world.energy, items));
+    }
+
+    // returns a copy of the statistics if they changed since the reader last read them. seen is the
+    // reader's: the generation and the number of rows of its copy
+    pub fn read_if_changed(&self, seen: &mut Option<(u64, u64)>) -> Option<StatisticsHistory> {
+        let shared = self.shared.lock().unwrap();
+        let current = Some((shared.generation, shared.history.columns[0].total_samples()));
+        if *seen == current {
+            return None;
+        }
+        *seen = current;
+        Some(shared.history.clone())
+    }
+}
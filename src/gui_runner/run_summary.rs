@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use robotics_lib::world::tile::Content;
+use super::RunModeSource;
+
+// RunSummary is what GuiRunner::run and GuiRunner::run_headless return: the game (GameRunner,
+// ReplayPlayer or WorldPreview) fills it with the tick, energy and backpack of the last world it
+// sent and why it stopped, and the GuiRunner with the wall time of the whole run. The reason a run
+// was terminated is told by the RunModeSource of the Terminate the game received.
+
+/// How a run ended, as returned by `GuiRunner::run` and `GuiRunner::run_headless`, so that
+/// automated runs can check what the robot achieved.
+#[derive(Clone, Debug)]
+pub struct RunSummary {
+    /// The last tick run (the initialization tick is tick 0). When playing back a replay, the last
+    /// tick shown; when previewing a world, 0.
+    pub ticks: usize,
+    /// Energy level of the robot at the end of the run.
+    pub energy: usize,
+    /// Contents of the backpack of the robot at the end of the run.
+    pub backpack: HashMap<Content, usize>,
+    /// Time from the start of the run until it returned.
+    pub wall_time: Duration,
+    /// Why the run ended.
+    pub exit_reason: ExitReason,
+}
+impl RunSummary {
+    pub(crate) fn new(ticks: usize, energy: usize, backpack: HashMap<Content, usize>, exit_reason: ExitReason) -> Self {
+        Self { ticks, energy, backpack, wall_time: Duration::ZERO, exit_reason } // the wall time is set by the GuiRunner
+    }
+}
+
+/// Why a run ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitReason {
+    /// The window was closed, or the run was terminated from the GUI.
+    WindowClosed,
+    /// The run was terminated through `ControlHandle::request_terminate`.
+    ControlHandle,
+    /// The GUI stopped without terminating the run (e.g. it panicked).
+    GuiExited,
+    /// `GuiRunner::run_headless` ran all of its ticks.
+    TicksCompleted,
+}
+impl ExitReason {
+    pub(crate) fn from_source(source: RunModeSource) -> Self {
+        match source {
+            RunModeSource::ControlHandle => ExitReason::ControlHandle,
+            RunModeSource::WindowClosed | RunModeSource::Gui | RunModeSource::Key | RunModeSource::AutoPause
+            | RunModeSource::InputPlayback | RunModeSource::Breakpoint | RunModeSource::RobotCrash => ExitReason::WindowClosed,
+        }
+    }
+}
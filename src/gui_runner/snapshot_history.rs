@@ -0,0 +1,222 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread;
+use robotics_lib::world::environmental_conditions::EnvironmentalConditions;
+use robotics_lib::world::tile::{Content, Tile};
+use serde::{Deserialize, Serialize};
+use crate::WorldSnapshot;
+
+// SnapshotHistory keeps a long sequence of WorldSnapshots in memory in compressed form, so that
+// features which move through the history of a run (such as replay playback) don't need several
+// copies of the whole world per tick. Snapshots are pushed uncompressed and compressed on a
+// background thread: every KEYFRAME_INTERVAL-th snapshot is stored whole (a keyframe), the others
+// as the difference from the previous one (the tiles which changed along with the robot state),
+// and both are then compressed with zstd. A snapshot is rebuilt by decoding the keyframe preceding
+// it and applying the deltas which follow; the last snapshot rebuilt is cached, so that moving
+// forwards one tick at a time only decodes a single delta.
+// When the compressed snapshots exceed the memory budget the oldest ones are dropped, a keyframe
+// interval at a time.
+// SnapshotHistory is a handle: its clones share the same history (but not the cache), and the
+// background thread terminates when the last one is dropped.
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotDelta {
+    changed_tiles: Vec<((u32, u32), Option<Tile>)>,
+    robot_position: (u32, u32),
+    energy: usize,
+    backpack: Vec<(Content, usize)>,
+    env_cond: EnvironmentalConditions,
+}
+
+struct Entry {
+    tick: usize,
+    is_keyframe: bool,
+    data: Vec<u8>, // zstd compressed JSON of a WorldSnapshot (keyframes) or of a SnapshotDelta
+}
+
+struct Shared {
+    entries: VecDeque<Entry>,
+    bytes: usize,
+    budget: usize,
+    pending: usize, // snapshots pushed but not yet compressed
+}
+impl Shared {
+    // drops the oldest keyframe intervals until the history fits the budget, always keeping the last one
+    fn enforce_budget(&mut self) {
+        while self.bytes > self.budget {
+            let Some(next_keyframe) = self.entries.iter().skip(1).position(|e| e.is_keyframe) else { return };
+            for entry in self.entries.drain(..=next_keyframe) {
+                self.bytes -= entry.data.len();
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SnapshotHistory {
+    shared: Arc<(Mutex<Shared>, Condvar)>,
+    tx: SyncSender<WorldSnapshot>,
+    cache: Option<WorldSnapshot>,
+}
+impl SnapshotHistory {
+    const KEYFRAME_INTERVAL: usize = 64;
+    const COMPRESSION_LEVEL: i32 = 3;
+    // uncompressed snapshots waiting for the background thread; push blocks when it is full, so
+    // that a fast producer can't fill the memory
+    const QUEUE_LEN: usize = 8;
+
+    pub fn new(budget: usize) -> Self {
+        let shared = Arc::new((Mutex::new(Shared { entries: VecDeque::new(), bytes: 0, budget, pending: 0 }), Condvar::new()));
+        let (tx, rx) = mpsc::sync_channel(Self::QUEUE_LEN);
+        let thread_shared = shared.clone();
+        thread::spawn(move || Self::compress_loop(rx, thread_shared));
+        Self { shared, tx, cache: None }
+    }
+
+    // queues a snapshot for compression. snapshots must be pushed in tick order
+    pub fn push(&self, snapshot: WorldSnapshot) {
+        self.lock().pending += 1;
+        if self.tx.send(snapshot).is_err() {
+            self.lock().pending -= 1; // only happens if the background thread panicked
+        }
+    }
+
+    pub fn first_tick(&self) -> Option<usize> {
+        self.wait_for(0).entries.front().map(|e| e.tick)
+    }
+    pub fn last_tick(&self) -> Option<usize> {
+        self.wait_for(usize::MAX).entries.back().map(|e| e.tick)
+    }
+    pub fn tick_after(&self, tick: usize) -> Option<usize> {
+        let shared = self.wait_for(tick.saturating_add(1));
+        let index = shared.entries.partition_point(|e| e.tick <= tick);
+        shared.entries.get(index).map(|e| e.tick)
+    }
+    pub fn tick_before(&self, tick: usize) -> Option<usize> {
+        let shared = self.wait_for(tick);
+        let index = shared.entries.partition_point(|e| e.tick < tick);
+        index.checked_sub(1).map(|i| shared.entries[i].tick)
+    }
+
+    // the first and last ticks compressed so far, without waiting for the pending ones
+    pub fn compressed_ticks(&self) -> Option<(usize, usize)> {
+        let shared = self.lock();
+        Some((shared.entries.front()?.tick, shared.entries.back()?.tick))
+    }
+
+    // bytes used by the compressed snapshots, and the budget
+    pub fn memory_usage(&self) -> (usize, usize) {
+        let shared = self.lock();
+        (shared.bytes, shared.budget)
+    }
+    pub fn set_budget(&self, budget: usize) {
+        let mut shared = self.lock();
+        shared.budget = budget;
+        shared.enforce_budget();
+    }
+
+    // returns the last snapshot taken at or before tick (or the first one, if tick precedes it)
+    pub fn get(&mut self, tick: usize) -> Option<WorldSnapshot> {
+        // copy out the compressed entries needed, so that the background thread isn't blocked
+        // while they are decoded
+        let (target_tick, to_decode) = {
+            let shared = self.wait_for(tick);
+            let index = shared.entries.partition_point(|e| e.tick <= tick).saturating_sub(1);
+            let target_tick = shared.entries.get(index)?.tick;
+            let keyframe = shared.entries.range(..=index).rposition(|e| e.is_keyframe).unwrap_or(0);
+            // start from the cached snapshot if it lies between the keyframe and the target
+            let start = match &self.cache {
+                Some(cached) if cached.tick <= target_tick => {
+                    let cached_index = shared.entries.partition_point(|e| e.tick < cached.tick);
+                    let cache_is_valid = shared.entries.get(cached_index).map(|e| e.tick == cached.tick).unwrap_or(false);
+                    if cache_is_valid && cached_index >= keyframe { cached_index + 1 } else { keyframe }
+                }
+                _ => keyframe,
+            };
+            if start == keyframe {
+                self.cache = None;
+            }
+            let to_decode: Vec<(usize, bool, Vec<u8>)> = shared.entries.range(start..=index)
+                .map(|e| (e.tick, e.is_keyframe, e.data.clone()))
+                .collect();
+            (target_tick, to_decode)
+        };
+
+        for (tick, is_keyframe, data) in to_decode {
+            let json = zstd::decode_all(data.as_slice()).expect("corrupted snapshot history");
+            if is_keyframe {
+                self.cache = Some(serde_json::from_slice(&json).expect("corrupted snapshot history"));
+            } else {
+                let delta: SnapshotDelta = serde_json::from_slice(&json).expect("corrupted snapshot history");
+                let snapshot = self.cache.as_mut().expect("delta without a keyframe in the snapshot history");
+                for ((x, y), tile) in delta.changed_tiles {
+                    snapshot.world[x as usize][y as usize] = tile;
+                }
+                snapshot.tick = tick;
+                snapshot.robot_position = delta.robot_position;
+                snapshot.energy = delta.energy;
+                snapshot.backpack = delta.backpack;
+                snapshot.env_cond = delta.env_cond;
+            }
+        }
+        self.cache.clone().filter(|snapshot| snapshot.tick == target_tick)
+    }
+
+    fn compress_loop(rx: Receiver<WorldSnapshot>, shared: Arc<(Mutex<Shared>, Condvar)>) {
+        let mut previous = Option::<WorldSnapshot>::None;
+        let mut since_keyframe = 0;
+        for snapshot in rx {
+            let delta = previous.as_ref()
+                .filter(|previous| since_keyframe < Self::KEYFRAME_INTERVAL && previous.world.len() == snapshot.world.len())
+                .map(|previous| Self::delta(previous, &snapshot));
+            let is_keyframe = delta.is_none();
+            let json = match &delta {
+                Some(delta) => serde_json::to_vec(delta),
+                None => serde_json::to_vec(&snapshot),
+            }.expect("snapshots are always serializable");
+            let data = zstd::encode_all(json.as_slice(), Self::COMPRESSION_LEVEL).expect("compressing to memory can't fail");
+            since_keyframe = if is_keyframe { 1 } else { since_keyframe + 1 };
+
+            let (lock, condvar) = &*shared;
+            let mut shared = lock.lock().unwrap();
+            shared.bytes += data.len();
+            shared.entries.push_back(Entry { tick: snapshot.tick, is_keyframe, data });
+            shared.pending -= 1;
+            shared.enforce_budget();
+            condvar.notify_all();
+            drop(shared);
+
+            previous = Some(snapshot);
+        }
+    }
+
+    fn delta(previous: &WorldSnapshot, snapshot: &WorldSnapshot) -> SnapshotDelta {
+        let mut changed_tiles = vec![];
+        for (x, (previous_row, row)) in previous.world.iter().zip(snapshot.world.iter()).enumerate() {
+            for (y, (previous_tile, tile)) in previous_row.iter().zip(row.iter()).enumerate() {
+                if previous_tile != tile {
+                    changed_tiles.push(((x as u32, y as u32), tile.clone()));
+                }
+            }
+        }
+        SnapshotDelta {
+            changed_tiles,
+            robot_position: snapshot.robot_position,
+            energy: snapshot.energy,
+            backpack: snapshot.backpack.clone(),
+            env_cond: snapshot.env_cond.clone(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Shared> {
+        self.shared.0.lock().unwrap()
+    }
+    // waits until the snapshots up to tick have been compressed (or nothing is left to compress)
+    fn wait_for(&self, tick: usize) -> std::sync::MutexGuard<'_, Shared> {
+        let (lock, condvar) = &*self.shared;
+        condvar.wait_while(lock.lock().unwrap(), |shared| {
+            shared.pending > 0 && shared.entries.back().map(|e| e.tick < tick).unwrap_or(true)
+        }).unwrap()
+    }
+}
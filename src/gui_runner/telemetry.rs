@@ -0,0 +1,124 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+// Telemetry is shared (cloned) between the host code, which publishes named series of values from
+// whatever thread it likes, the robot wrapper, which tells it the tick being run so that every
+// value is stamped with the tick it was published at, and the GUI thread, which reads the values
+// published since it last did (tracking how many values of each series it has seen) to chart
+// them. Only the last MAX_SAMPLES values of a series are kept, so that a long run (or one without
+// the GUI) doesn't grow without bound. The phases the robot begins are kept whole (they are few),
+// and read by the GUI in the same way. Only the last plan published is kept, since every plan
+// replaces the previous one; the GUI reads it when it changes.
+
+#[derive(Default)]
+struct Series {
+    samples: VecDeque<(usize, f64)>, // tick, value
+    published: u64, // including the dropped samples
+}
+
+#[derive(Default)]
+struct Shared {
+    tick: usize,
+    series: BTreeMap<String, Series>,
+    phases: Vec<(usize, String)>, // first tick, name
+    plan: (usize, Vec<String>), // tick, actions
+    plans_published: u64,
+}
+
+/// Named series of values published by the host code (e.g. the length of the robot's plan at
+/// every tick), which the GUI charts in its Telemetry panel as time series, histograms or scatter
+/// plots. Every value is stamped with the tick it was published at. The robot can also mark the
+/// phases of its run (e.g. "exploration", "harvesting"), which color the timeline of the GUI and
+/// break its statistics down, and publish the actions it plans to do, which the Energy planner of the
+/// GUI checks against its remaining energy. Cloning a Telemetry gives a handle to the same series, so one clone
+/// can be passed to `GuiRunnerBuilder::telemetry` and another kept by the robot to publish values.
+///
+/// ```no_run
+/// let telemetry = ragnarok::Telemetry::new();
+/// telemetry.publish("plan length", 12.0);
+/// telemetry.begin_phase("exploration");
+/// telemetry.publish_plan(&["go", "go", "destroy"]);
+/// ```
+#[derive(Clone, Default)]
+pub struct Telemetry {
+    shared: Arc<Mutex<Shared>>,
+}
+impl Telemetry {
+    pub(crate) const MAX_SAMPLES: usize = 50_000;
+
+    /// Constructs a Telemetry without any series.
+    pub fn new() -> Self { Self::default() }
+
+    /// Appends a value to the given series, creating the series if needed.
+    pub fn publish(&self, series: &str, value: f64) {
+        let mut shared = self.shared.lock().unwrap();
+        let tick = shared.tick;
+        let series = shared.series.entry(series.to_string()).or_default();
+        series.samples.push_back((tick, value));
+        series.published += 1;
+        if series.samples.len() > Self::MAX_SAMPLES {
+            series.samples.pop_front();
+        }
+    }
+
+    /// Marks the start of a named phase of the run at the current tick, which lasts until the next
+    /// one begins.
+    pub fn begin_phase(&self, name: &str) {
+        let mut shared = self.shared.lock().unwrap();
+        let tick = shared.tick;
+        shared.phases.push((tick, name.to_string()));
+    }
+
+    /// Publishes the actions the robot plans to do next, in order, replacing the previous plan. Each
+    /// action is named after the interface function doing it (`"go"`, `"destroy"`, `"put"`, ...),
+    /// so that the GUI can estimate its cost from the energy those actions took so far. An empty
+    /// plan means that the robot has none.
+    pub fn publish_plan<S: AsRef<str>>(&self, actions: &[S]) {
+        let mut shared = self.shared.lock().unwrap();
+        let tick = shared.tick;
+        shared.plan = (tick, actions.iter().map(|action| action.as_ref().to_string()).collect());
+        shared.plans_published += 1;
+    }
+
+    // sets the tick the values published from now on are stamped with
+    pub(crate) fn set_tick(&self, tick: usize) {
+        self.shared.lock().unwrap().tick = tick;
+    }
+
+    // the samples of every series published since the last call with the same cursors (the number
+    // of samples of each series seen so far), which are updated
+    pub(crate) fn read_new(&self, cursors: &mut HashMap<String, u64>) -> Vec<(String, Vec<(usize, f64)>)> {
+        let shared = self.shared.lock().unwrap();
+        let mut new_samples = vec![];
+        for (name, series) in shared.series.iter() {
+            let seen = cursors.entry(name.clone()).or_insert(0);
+            let unseen = ((series.published - *seen) as usize).min(series.samples.len());
+            if unseen > 0 {
+                let samples = series.samples.iter().skip(series.samples.len() - unseen).copied().collect();
+                new_samples.push((name.clone(), samples));
+            }
+            *seen = series.published;
+        }
+        new_samples
+    }
+
+    // the phases begun since the last call with the same cursor (the number of phases seen so far),
+    // which is updated
+    pub(crate) fn read_new_phases(&self, cursor: &mut usize) -> Vec<(usize, String)> {
+        let shared = self.shared.lock().unwrap();
+        let new_phases = shared.phases[(*cursor).min(shared.phases.len())..].to_vec();
+        *cursor = shared.phases.len();
+        new_phases
+    }
+
+    // the plan, with the tick it was published at, if one was published since the last call with
+    // the same cursor (the number of plans seen so far), which is updated
+    pub(crate) fn read_new_plan(&self, cursor: &mut u64) -> Option<(usize, Vec<String>)> {
+        let shared = self.shared.lock().unwrap();
+        if *cursor == shared.plans_published {
+            return None;
+        }
+        *cursor = shared.plans_published;
+        Some(shared.plan.clone())
+    }
+}
@@ -0,0 +1,140 @@
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// HealthMonitor is shared (cloned) between the game, worker and gui threads. Each thread
+// periodically calls beat() and holds a HeartbeatGuard for its whole lifetime, which records
+// whether the thread finished normally or panicked when it is dropped. Any thread can then query
+// the status of the others, which allows noticing a stalled or dead thread instead of silently
+// hanging on a channel whose other end has disappeared.
+// The game thread also brackets every game_tick with begin_tick() and end_tick(), so that a tick
+// which takes too long (e.g. an AI stuck in a loop) can be told apart from the thread hanging
+// elsewhere, and reported as such while it is still running.
+// A thread which stops because of an error (rather than a panic) reports it with report_error(),
+// so that the others can tell the user what went wrong.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum MonitoredThread {
+    Game,
+    Worker,
+    Gui,
+}
+impl MonitoredThread {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MonitoredThread::Game => "game thread",
+            MonitoredThread::Worker => "worker thread",
+            MonitoredThread::Gui => "GUI thread",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ThreadStatus {
+    Alive,
+    Stalled(Duration), // time since the last heartbeat
+    Finished,
+    Panicked,
+}
+
+const STATE_ALIVE: u8 = 0;
+const STATE_FINISHED: u8 = 1;
+const STATE_PANICKED: u8 = 2;
+
+struct Heartbeat {
+    last_beat_ms: AtomicU64, // milliseconds since HealthMonitor::epoch
+    state: AtomicU8,
+}
+
+#[derive(Clone)]
+pub(crate) struct HealthMonitor {
+    heartbeats: Arc<[Heartbeat; 3]>,
+    tick_begin_ms: Arc<AtomicU64>, // milliseconds since epoch + 1 at which the running tick began, 0 between ticks
+    errors: Arc<Mutex<[Option<String>; 3]>>,
+    epoch: Instant,
+}
+impl HealthMonitor {
+    // interval at which threads which are waiting on something should still beat
+    pub const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(250);
+
+    pub fn new() -> Self {
+        let new_heartbeat = || Heartbeat { last_beat_ms: AtomicU64::new(0), state: AtomicU8::new(STATE_ALIVE) };
+        Self {
+            heartbeats: Arc::new([new_heartbeat(), new_heartbeat(), new_heartbeat()]),
+            tick_begin_ms: Arc::new(AtomicU64::new(0)),
+            errors: Arc::default(),
+            epoch: Instant::now(),
+        }
+    }
+
+    fn heartbeat(&self, t: MonitoredThread) -> &Heartbeat {
+        &self.heartbeats[t as usize]
+    }
+
+    pub fn beat(&self, t: MonitoredThread) {
+        let now = self.epoch.elapsed().as_millis() as u64;
+        self.heartbeat(t).last_beat_ms.store(now, Ordering::Relaxed);
+    }
+
+    pub fn begin_tick(&self) {
+        let now = self.epoch.elapsed().as_millis() as u64;
+        self.tick_begin_ms.store(now + 1, Ordering::Relaxed);
+    }
+
+    pub fn end_tick(&self) {
+        self.tick_begin_ms.store(0, Ordering::Relaxed);
+    }
+
+    // how long the tick being run by the game thread has been running, None if it isn't in a tick
+    pub fn tick_duration(&self) -> Option<Duration> {
+        match self.tick_begin_ms.load(Ordering::Relaxed) {
+            0 => None,
+            begin => Some(self.epoch.elapsed().saturating_sub(Duration::from_millis(begin - 1))),
+        }
+    }
+
+    pub fn status(&self, t: MonitoredThread, stall_timeout: Duration) -> ThreadStatus {
+        let heartbeat = self.heartbeat(t);
+        match heartbeat.state.load(Ordering::Relaxed) {
+            STATE_FINISHED => ThreadStatus::Finished,
+            STATE_PANICKED => ThreadStatus::Panicked,
+            _ => {
+                let last_beat = Duration::from_millis(heartbeat.last_beat_ms.load(Ordering::Relaxed));
+                let since_last_beat = self.epoch.elapsed().saturating_sub(last_beat);
+                if since_last_beat > stall_timeout {
+                    ThreadStatus::Stalled(since_last_beat)
+                } else {
+                    ThreadStatus::Alive
+                }
+            }
+        }
+    }
+
+    // must be called by a thread stopping because of an error, before it exits
+    pub fn report_error(&self, t: MonitoredThread, error: String) {
+        self.errors.lock().unwrap()[t as usize] = Some(error);
+    }
+
+    // the error the thread stopped because of, if any
+    pub fn error(&self, t: MonitoredThread) -> Option<String> {
+        self.errors.lock().unwrap()[t as usize].clone()
+    }
+
+    // must be kept alive by the monitored thread until it exits
+    pub fn guard(&self, t: MonitoredThread) -> HeartbeatGuard {
+        self.beat(t);
+        HeartbeatGuard { monitor: self.clone(), thread: t }
+    }
+}
+
+pub(crate) struct HeartbeatGuard {
+    monitor: HealthMonitor,
+    thread: MonitoredThread,
+}
+impl Drop for HeartbeatGuard {
+    fn drop(&mut self) {
+        let state = if thread::panicking() { STATE_PANICKED } else { STATE_FINISHED };
+        self.monitor.heartbeat(self.thread).state.store(state, Ordering::Relaxed);
+    }
+}
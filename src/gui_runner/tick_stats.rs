@@ -0,0 +1,63 @@
+use std::collections::{HashMap, HashSet};
+use robotics_lib::world::tile::Content;
+use crate::snapshot::WorldSnapshot;
+use super::GuiRunnerObserver;
+
+// StatsObserver is the observer registered by GuiRunnerBuilder::on_stats: it keeps the last
+// snapshot it saw to derive the TickStats of every tick from the difference with the next one,
+// and hands them to the user's callback.
+
+/// What changed for the robot during a tick, as passed to the callback registered with
+/// `GuiRunnerBuilder::on_stats`.
+#[derive(Clone, Debug)]
+pub struct TickStats {
+    /// The tick (the initialization tick is tick 0, which has no changes).
+    pub tick: usize,
+    /// Position of the robot at the end of the tick, as `(row, col)`.
+    pub position: (u32, u32),
+    /// Energy level of the robot at the end of the tick.
+    pub energy: usize,
+    /// Energy gained (positive) or spent (negative) during the tick.
+    pub energy_delta: i64,
+    /// Contents whose quantity in the backpack changed during the tick, with the change.
+    pub backpack_delta: Vec<(Content, i64)>,
+    /// Number of tiles the robot discovered during the tick.
+    pub tiles_discovered: usize,
+}
+
+pub(crate) struct StatsObserver<F: FnMut(TickStats)> {
+    callback: F,
+    last: Option<(usize, HashMap<Content, usize>, usize)>, // energy, backpack, discovered tiles
+}
+impl<F: FnMut(TickStats)> StatsObserver<F> {
+    pub fn new(callback: F) -> Self {
+        Self { callback, last: None }
+    }
+}
+impl<F: FnMut(TickStats)> GuiRunnerObserver for StatsObserver<F> {
+    fn on_tick(&mut self, snapshot: &WorldSnapshot) {
+        let backpack: HashMap<Content, usize> = snapshot.backpack.iter().cloned().collect();
+        let discovered = snapshot.world.iter().flatten().filter(|tile| tile.is_some()).count();
+        let (last_energy, last_backpack, last_discovered) = self.last.take().unwrap_or_else(|| (snapshot.energy, backpack.clone(), discovered));
+
+        let contents: HashSet<&Content> = backpack.keys().chain(last_backpack.keys()).collect();
+        let backpack_delta: Vec<(Content, i64)> = contents.into_iter()
+            .map(|content| {
+                let now = backpack.get(content).copied().unwrap_or(0) as i64;
+                let before = last_backpack.get(content).copied().unwrap_or(0) as i64;
+                (content.clone(), now - before)
+            })
+            .filter(|(_, delta)| *delta != 0)
+            .collect();
+
+        (self.callback)(TickStats {
+            tick: snapshot.tick,
+            position: snapshot.robot_position,
+            energy: snapshot.energy,
+            energy_delta: snapshot.energy as i64 - last_energy as i64,
+            backpack_delta,
+            tiles_discovered: discovered.saturating_sub(last_discovered),
+        });
+        self.last = Some((snapshot.energy, backpack, discovered));
+    }
+}
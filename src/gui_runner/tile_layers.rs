@@ -0,0 +1,118 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use nalgebra_glm::UVec2;
+
+// TileLayers is shared (cloned) between the host code, which writes any number of named layers of
+// per-tile values from whatever thread it likes (typically the robot's process_tick), and the GUI
+// thread, which renders the selected layer as a heatmap. Vector fields are layers of per-tile
+// directions instead, rendered as arrows. Every write bumps a version, so that the GUI only
+// rebuilds the heatmap or the arrows when something changed.
+
+#[derive(Default)]
+struct Shared {
+    layers: BTreeMap<String, HashMap<UVec2, f32>>, // sorted by name, for the layer selector
+    vector_fields: BTreeMap<String, HashMap<UVec2, [f32; 2]>>,
+    version: u64,
+}
+
+/// Named layers of per-tile values (e.g. the cost map of an AI), which the GUI can render as
+/// heatmaps over the world, and of per-tile directions (e.g. the gradient of a potential field),
+/// which it can render as arrows. Cloning a TileLayers gives a handle to the same layers, so one
+/// clone can be passed to `GuiRunnerBuilder::tile_layers` and another kept by the robot to update
+/// them.
+///
+/// ```no_run
+/// let layers = ragnarok::TileLayers::new();
+/// layers.set("cost", (3, 4), 12.5);
+/// layers.clear("cost");
+/// layers.set_vector("gradient", (3, 4), (0.5, -1.0));
+/// ```
+#[derive(Clone, Default)]
+pub struct TileLayers {
+    shared: Arc<Mutex<Shared>>,
+}
+impl TileLayers {
+    /// Constructs an empty set of layers.
+    pub fn new() -> Self { Self::default() }
+
+    /// Sets the value of the tile at (row, col) in the given layer, creating the layer if needed.
+    pub fn set(&self, layer: &str, (row, col): (usize, usize), value: f32) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.layers.entry(layer.to_string()).or_default().insert(UVec2::new(row as u32, col as u32), value);
+        shared.version += 1;
+    }
+
+    /// Replaces every value of the given layer with those of a matrix indexed by [row][col], as
+    /// the world is. `None` leaves a tile without a value.
+    pub fn set_all(&self, layer: &str, values: &[Vec<Option<f32>>]) {
+        let tiles = values.iter().enumerate()
+            .flat_map(|(row, values)| values.iter().enumerate()
+                .filter_map(move |(col, value)| value.map(|value| (UVec2::new(row as u32, col as u32), value))))
+            .collect();
+        let mut shared = self.shared.lock().unwrap();
+        shared.layers.insert(layer.to_string(), tiles);
+        shared.version += 1;
+    }
+
+    /// Removes the given layer.
+    pub fn clear(&self, layer: &str) {
+        let mut shared = self.shared.lock().unwrap();
+        if shared.layers.remove(layer).is_some() {
+            shared.version += 1;
+        }
+    }
+
+    /// Sets the direction of the tile at (row, col) in the given vector field, as (d_row, d_col),
+    /// creating the field if needed. The arrows drawn are scaled by the length of the vectors.
+    pub fn set_vector(&self, field: &str, (row, col): (usize, usize), (d_row, d_col): (f32, f32)) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.vector_fields.entry(field.to_string()).or_default().insert(UVec2::new(row as u32, col as u32), [d_row, d_col]);
+        shared.version += 1;
+    }
+
+    /// Replaces every direction of the given vector field with those of a matrix indexed by
+    /// [row][col]. `None` leaves a tile without an arrow.
+    pub fn set_all_vectors(&self, field: &str, vectors: &[Vec<Option<(f32, f32)>>]) {
+        let tiles = vectors.iter().enumerate()
+            .flat_map(|(row, vectors)| vectors.iter().enumerate()
+                .filter_map(move |(col, vector)| vector.map(|(d_row, d_col)| (UVec2::new(row as u32, col as u32), [d_row, d_col]))))
+            .collect();
+        let mut shared = self.shared.lock().unwrap();
+        shared.vector_fields.insert(field.to_string(), tiles);
+        shared.version += 1;
+    }
+
+    /// Removes the given vector field.
+    pub fn clear_vectors(&self, field: &str) {
+        let mut shared = self.shared.lock().unwrap();
+        if shared.vector_fields.remove(field).is_some() {
+            shared.version += 1;
+        }
+    }
+
+    pub(crate) fn version(&self) -> u64 {
+        self.shared.lock().unwrap().version
+    }
+
+    pub(crate) fn names(&self) -> Vec<String> {
+        self.shared.lock().unwrap().layers.keys().cloned().collect()
+    }
+
+    // a copy of the values of a layer, and the version they were copied at
+    pub(crate) fn layer(&self, layer: &str) -> (Vec<(UVec2, f32)>, u64) {
+        let shared = self.shared.lock().unwrap();
+        let values = shared.layers.get(layer).map(|tiles| tiles.iter().map(|(pos, value)| (*pos, *value)).collect()).unwrap_or_default();
+        (values, shared.version)
+    }
+
+    pub(crate) fn vector_field_names(&self) -> Vec<String> {
+        self.shared.lock().unwrap().vector_fields.keys().cloned().collect()
+    }
+
+    // a copy of the directions of a vector field, and the version they were copied at
+    pub(crate) fn vector_field(&self, field: &str) -> (Vec<(UVec2, [f32; 2])>, u64) {
+        let shared = self.shared.lock().unwrap();
+        let vectors = shared.vector_fields.get(field).map(|tiles| tiles.iter().map(|(pos, vector)| (*pos, *vector)).collect()).unwrap_or_default();
+        (vectors, shared.version)
+    }
+}
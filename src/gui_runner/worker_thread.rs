@@ -1,67 +1,71 @@
 use std::collections::HashSet;
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
 use std::thread;
-use nalgebra_glm::vec2;
-use robotics_lib::world::tile::Tile;
-use super::PartialWorld;
+use nalgebra_glm::{vec2, UVec2};
+use robotics_lib::world::tile::{Content, Tile};
+use super::{ContentChange, PartialWorld};
+use super::snapshot_history::SnapshotHistory;
+use super::run_statistics::RunStatistics;
+use super::map_merge::{MapMerge, MapMerger};
+use super::thread_health::{HealthMonitor, MonitoredThread};
+use crate::snapshot::WorldSnapshot;
 
 // WorkerThread handles a thread which receives the world information from the game->worker channel
 // and relays it through the worker->gui channel after populating the PartialWorld::tiles_to_refresh
 // field with the positions of tiles that changed since the last PartialWorld received through the
-// game->worker channel.
+// game->worker channel, along with their vicinity (within refresh_radius tiles), and the
+// PartialWorld::changed_tiles field with the positions of the changed tiles alone. The contents
+// which changed on known tiles are listed in PartialWorld::content_changes, where those which no
+// robot and no event was next to are told apart as changed by the world itself. The vicinity of
+// PartialWorld::distant_changes is refreshed with a wider radius, since the tiles around them may
+// have changed without the robot being there to see them being changed.
+// When the game thread tracks the events (PartialWorld::touched_tiles is Some) the changes are
+// only looked for around the tiles the events touched and around the robot, so that a tick costs
+// as much as the events it had rather than as much as the size of the world; the whole map is still
+// diffed every FULL_DIFF_INTERVAL ticks, to catch the changes no event tells about (e.g. tiles
+// discovered through tools).
+// When several robots run, their maps are first merged into the one the GUI shows (see MapMerger),
+// and the changes are also looked for around the tiles the other robots touched and stand on.
+// In live runs every world is also pushed to the SnapshotHistory the GUI rewinds through, and in
+// every run it is accounted for in the RunStatistics, along with the tiles the diff found discovered.
 pub struct WorkerThread {
     game_to_worker_rx: Receiver<PartialWorld>,
     worker_to_gui_tx: Sender<PartialWorld>,
+    refresh_radius: u32,
+    health: HealthMonitor,
+    rewind_history: Option<SnapshotHistory>,
+    statistics: RunStatistics,
+    map_merge: MapMerge,
 }
 impl WorkerThread {
-    pub fn new(game_to_worker_rx: Receiver<PartialWorld>, worker_to_gui_tx: Sender<PartialWorld>) -> Self {
-        Self { game_to_worker_rx, worker_to_gui_tx }
+    pub fn new(game_to_worker_rx: Receiver<PartialWorld>, worker_to_gui_tx: Sender<PartialWorld>, refresh_radius: u32, health: HealthMonitor, rewind_history: Option<SnapshotHistory>, statistics: RunStatistics, map_merge: MapMerge) -> Self {
+        Self { game_to_worker_rx, worker_to_gui_tx, refresh_radius, health, rewind_history, statistics, map_merge }
     }
 
     pub fn start(self) -> thread::JoinHandle<()> {
         thread::spawn(move || {
+            let _heartbeat_guard = self.health.guard(MonitoredThread::Worker);
             let mut world_copy = Option::<Vec<Vec<Option<Tile>>>>::None;
+            let mut worlds_since_full_diff = 0;
+            let mut map_merger = MapMerger::new(self.map_merge.clone());
 
             loop {
-                let new_world = match self.game_to_worker_rx.recv() {
+                self.health.beat(MonitoredThread::Worker);
+                let mut new_world = match self.game_to_worker_rx.recv_timeout(HealthMonitor::HEARTBEAT_INTERVAL) {
                     Ok(w) => w,
-                    Err(_) => return, // if the other end is closed simply terminate this thread
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => return, // if the other end is closed simply terminate this thread
                 };
 
-                let mut tiles_to_refresh = HashSet::new();
-
-                if let Some(world_copy) = &mut world_copy {
-                    for x in 0..new_world.world.len() {
-                        for y in 0..new_world.world.len() {
-                            if world_copy[x][y] != new_world.world[x][y] {
-                                world_copy[x][y] = new_world.world[x][y].clone();
-
-                                for dx in -1..=1 {
-                                    for dy in -1..=1 {
-                                        let x = (x as i32 + dx).clamp(0, new_world.world.len() as i32 - 1) as u32;
-                                        let y = (y as i32 + dy).clamp(0, new_world.world.len() as i32 - 1) as u32;
-                                        tiles_to_refresh.insert(vec2(x, y));
-                                    }
-                                }
-                            }
-                        }
-                    }
-                } else {
-                    world_copy = Some(new_world.world.clone());
-                    let world_size = new_world.world.len();
-                    let x = new_world.robot_position.x;
-                    let y = new_world.robot_position.y;
-                    for dx in -1..=1 {
-                        for dy in -1..=1 {
-                            let x = (x as i32 + dx).clamp(0, world_size as i32 - 1) as u32;
-                            let y = (y as i32 + dy).clamp(0, world_size as i32 - 1) as u32;
-                            tiles_to_refresh.insert(vec2(x, y));
-                        }
-                    }
+                map_merger.merge(&mut new_world);
+                let full_diff = worlds_since_full_diff + 1 >= FULL_DIFF_INTERVAL;
+                worlds_since_full_diff = if full_diff { 0 } else { worlds_since_full_diff + 1 };
+                let diff = diff_world(&mut world_copy, &new_world, self.refresh_radius, full_diff);
+                (new_world.tiles_to_refresh, new_world.changed_tiles, new_world.content_changes) = (diff.tiles_to_refresh, diff.changed_tiles, diff.content_changes);
+                self.statistics.record(&new_world, diff.discovered);
+                if let Some(rewind_history) = &self.rewind_history {
+                    rewind_history.push(WorldSnapshot::from_partial_world(&new_world));
                 }
-
-                let mut new_world = new_world;
-                new_world.tiles_to_refresh = tiles_to_refresh;
                 match self.worker_to_gui_tx.send(new_world) {
                     Ok(()) => {}
                     Err(_) => return, // if the other end is closed simply terminate this thread
@@ -69,4 +73,108 @@ impl WorkerThread {
             }
         })
     }
-}
\ No newline at end of file
+}
+
+const DISTANT_CHANGES_EXTRA_RADIUS: u32 = 2;
+const FULL_DIFF_INTERVAL: usize = 8;
+// the robot sees (and discovers) the tiles next to the ones it moves to
+pub(crate) const TOUCHED_TILES_RADIUS: u32 = 1;
+
+// returns the positions of the tiles of new_world which changed since world_copy (along with their
+// vicinity) and brings world_copy up to date. world_copy is None before the first world is received.
+// unless full_diff is true, only the tiles around new_world.touched_tiles are compared, if known
+pub(crate) fn tiles_to_refresh(world_copy: &mut Option<Vec<Vec<Option<Tile>>>>, new_world: &PartialWorld, refresh_radius: u32, full_diff: bool) -> HashSet<UVec2> {
+    diff_world(world_copy, new_world, refresh_radius, full_diff).tiles_to_refresh
+}
+
+struct WorldDiff {
+    tiles_to_refresh: HashSet<UVec2>,
+    changed_tiles: Vec<UVec2>,
+    content_changes: Vec<(UVec2, ContentChange)>,
+    discovered: usize, // tiles which were unknown in world_copy
+}
+
+// as tiles_to_refresh, also returning the positions of the changed tiles alone, the changes of the
+// contents of the known ones and the number of tiles discovered (all the known ones in the first world)
+fn diff_world(world_copy: &mut Option<Vec<Vec<Option<Tile>>>>, new_world: &PartialWorld, refresh_radius: u32, full_diff: bool) -> WorldDiff {
+    let mut tiles_to_refresh = HashSet::new();
+    let mut changed_tiles = vec![];
+    let mut content_changes = vec![];
+    let mut discovered = 0;
+    let world_size = new_world.world.len();
+
+    if let Some(world_copy) = world_copy {
+        // the tiles the robots may have changed: those they could interact with and those the events touched
+        let mut caused_by_robots = HashSet::new();
+        let robot_positions = std::iter::once(new_world.robot_position).chain(new_world.other_robots.iter().map(|robot| robot.position));
+        let touched_tiles = new_world.touched_tiles.iter().flatten().chain(new_world.distant_changes.iter()).copied();
+        for position in robot_positions.chain(touched_tiles) {
+            insert_vicinity(&mut caused_by_robots, position, TOUCHED_TILES_RADIUS, world_size);
+        }
+
+        let mut refresh_if_changed = |x: usize, y: usize| {
+            if world_copy[x][y] != new_world.world[x][y] {
+                let position = vec2(x as u32, y as u32);
+                if world_copy[x][y].is_none() {
+                    discovered += 1;
+                }
+                if let (Some(old_tile), Some(new_tile)) = (&world_copy[x][y], &new_world.world[x][y]) {
+                    if old_tile.content != new_tile.content {
+                        let change = if caused_by_robots.contains(&position) {
+                            ContentChange::ByRobot
+                        } else if new_tile.content == Content::None {
+                            ContentChange::Decayed
+                        } else {
+                            ContentChange::Respawned
+                        };
+                        content_changes.push((position, change));
+                    }
+                }
+                world_copy[x][y] = new_world.world[x][y].clone();
+                changed_tiles.push(position);
+
+                insert_vicinity(&mut tiles_to_refresh, position, refresh_radius, world_size);
+            }
+        };
+        match &new_world.touched_tiles {
+            Some(touched_tiles) if !full_diff => {
+                let mut candidates = HashSet::new();
+                for touched_tile in touched_tiles.iter().chain(std::iter::once(&new_world.robot_position)) {
+                    insert_vicinity(&mut candidates, *touched_tile, TOUCHED_TILES_RADIUS, world_size);
+                }
+                for candidate in candidates {
+                    refresh_if_changed(candidate.x as usize, candidate.y as usize);
+                }
+            }
+            _ => {
+                for x in 0..new_world.world.len() {
+                    for y in 0..new_world.world.len() {
+                        refresh_if_changed(x, y);
+                    }
+                }
+            }
+        }
+    } else {
+        *world_copy = Some(new_world.world.clone());
+        discovered = new_world.world.iter().flatten().filter(|tile| tile.is_some()).count();
+        insert_vicinity(&mut tiles_to_refresh, new_world.robot_position, refresh_radius, world_size);
+    }
+
+    for distant_change in new_world.distant_changes.iter() {
+        let radius = refresh_radius + DISTANT_CHANGES_EXTRA_RADIUS;
+        insert_vicinity(&mut tiles_to_refresh, *distant_change, radius, world_size);
+    }
+    WorldDiff { tiles_to_refresh, changed_tiles, content_changes, discovered }
+}
+
+// inserts in tiles_to_refresh all the positions within radius of center which are inside the world
+pub(crate) fn insert_vicinity(tiles_to_refresh: &mut HashSet<UVec2>, center: UVec2, radius: u32, world_size: usize) {
+    let radius = radius as i32;
+    for dx in -radius..=radius {
+        for dy in -radius..=radius {
+            let x = (center.x as i32 + dx).clamp(0, world_size as i32 - 1) as u32;
+            let y = (center.y as i32 + dy).clamp(0, world_size as i32 - 1) as u32;
+            tiles_to_refresh.insert(vec2(x, y));
+        }
+    }
+}
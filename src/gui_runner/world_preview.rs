@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, SyncSender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+use nalgebra_glm::UVec2;
+use robotics_lib::world::world_generator::Generator;
+use super::{PartialWorld, RunMode, RunModeChange};
+use super::run_summary::{ExitReason, RunSummary};
+use super::thread_health::{HealthMonitor, MonitoredThread};
+
+// WorldPreview takes the place of GameRunner when previewing a world generator: it generates the
+// world once and sends it, with every tile discovered, through the game->worker channel, then
+// only waits for the GUI to be closed. There is no robot (the robot position is the spawn point,
+// which the GUI uses to place the camera), and the run controls of the GUI have no effect. Having
+// no robot, its RunSummary is empty but for the exit reason.
+
+pub struct WorldPreview {
+    world: PartialWorld,
+    game_to_worker_tx: SyncSender<PartialWorld>,
+    gui_to_game_rx: Receiver<RunModeChange>,
+    health: HealthMonitor,
+}
+impl WorldPreview {
+    pub fn new(generator: &mut impl Generator, game_to_worker_tx: SyncSender<PartialWorld>, gui_to_game_rx: Receiver<RunModeChange>, health: HealthMonitor) -> Self {
+        let (tiles, spawn, env_cond, ..) = generator.gen();
+        let world = PartialWorld {
+            world: tiles.into_iter().map(|row| row.into_iter().map(Some).collect()).collect(),
+            tiles_to_refresh: Default::default(),
+            distant_changes: vec![],
+            touched_tiles: None,
+            stale_tiles: vec![],
+            tick: 0,
+            elapsed: Duration::ZERO,
+            robot_position: UVec2::new(spawn.0 as u32, spawn.1 as u32),
+            energy: 0,
+            backpack: HashMap::new(),
+            backpack_size: None,
+            env_cond,
+            other_robots: vec![],
+            other_maps: vec![],
+            changed_tiles: vec![],
+            content_changes: vec![],
+        };
+        Self { world, game_to_worker_tx, gui_to_game_rx, health }
+    }
+
+    pub fn run(self) -> RunSummary {
+        let _heartbeat_guard = self.health.guard(MonitoredThread::Game);
+        // if the GUI was closed already the loop ends at once, telling whether it terminated the game
+        let _ = self.game_to_worker_tx.send(self.world);
+
+        loop {
+            self.health.beat(MonitoredThread::Game);
+            let exit_reason = match self.gui_to_game_rx.try_recv() {
+                Ok(RunModeChange { run_mode: RunMode::Terminate, source, .. }) => ExitReason::from_source(source),
+                Err(TryRecvError::Disconnected) => ExitReason::GuiExited,
+                Ok(_) | Err(TryRecvError::Empty) => {
+                    thread::sleep(Duration::from_millis(50));
+                    continue;
+                }
+            };
+            return RunSummary::new(0, 0, HashMap::new(), exit_reason);
+        }
+    }
+}
@@ -45,9 +45,38 @@
 /// A wrapper of the Runner struct which runs the game and visualizes it in a GUI.
 ///
 pub use gui_runner::GuiRunner;
+/// Builds a GuiRunner with non-default settings.
+pub use gui_runner::GuiRunnerBuilder;
+/// Configuration and on-disk format of the event journal.
+pub use gui_runner::{EventJournalConfig, JournalEntry};
+/// How robots are marked in the GUI.
+pub use gui_runner::{MarkerIcon, MarkerStyle};
+/// Callbacks receiving the ticks and events of a running game.
+pub use gui_runner::GuiRunnerObserver;
+/// Per-tile values and directions set by the host code, which the GUI can render as heatmaps and arrows.
+pub use gui_runner::TileLayers;
+/// Series of values published by the host code, which the GUI can chart.
+pub use gui_runner::Telemetry;
+/// Drives the run mode of a running GuiRunner from other code.
+pub use gui_runner::ControlHandle;
+/// What changed for the robot during a tick, as passed to `GuiRunnerBuilder::on_stats`.
+pub use gui_runner::TickStats;
+/// What `GuiRunner::run` returns about how the run ended.
+pub use gui_runner::{ExitReason, RunSummary};
+/// What `GuiRunner::run` returns when the run could not go on.
+pub use gui_runner::RagnarokError;
+/// How the maps of several robots are merged into the one shown in the GUI.
+pub use gui_runner::MergePolicy;
+/// The world as known to the robot at the end of a tick.
+pub use snapshot::WorldSnapshot;
 
 
 #[macro_use]
 extern crate glium;
 
 mod gui_runner;
+mod snapshot;
+pub mod replay;
+pub mod video;
+pub mod grid;
+pub mod prelude;
@@ -0,0 +1,9 @@
+//! The types and functions most code using ragnarok needs, to be imported all at once.
+//!
+//! ```
+//! use ragnarok::prelude::*;
+//! ```
+
+pub use crate::{ControlHandle, ExitReason, GuiRunner, GuiRunnerBuilder, GuiRunnerObserver, RagnarokError, RunSummary, Telemetry, TickStats, TileLayers, WorldSnapshot};
+pub use crate::{EventJournalConfig, JournalEntry, MarkerIcon, MarkerStyle};
+pub use crate::grid::{chebyshev_distance, euclidean_distance, manhattan_distance};
@@ -0,0 +1,437 @@
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use robotics_lib::world::environmental_conditions::EnvironmentalConditions;
+use robotics_lib::world::tile::{Content, Tile};
+use crate::{JournalEntry, WorldSnapshot};
+
+// Saving and loading replays, i.e. the sequence of world snapshots and events of a run.
+// A replay file starts with a magic number and a (major, minor) format version, followed by a
+// sequence of frames, each made of a kind byte, a little-endian u32 length and a zstd compressed
+// JSON payload. The first frame holds the metadata of the replay, the others either a snapshot, the
+// events of a tick or the annotations of the replay (markers and camera paths added while viewing
+// it, appended to the file by append_annotations; the last ones win).
+// Since 1.2 most ticks are stored as a delta from the snapshot of the previous tick (the tiles which
+// changed, plus the state of the robot), with a full snapshot every KEYFRAME_INTERVAL ticks. Readers
+// of older minor versions skip the deltas, and so only see the full snapshots. Newer minor versions
+// may only add frame kinds (which are skipped) or fields (which are ignored).
+
+const MAGIC: &[u8; 8] = b"RGNKRPLY";
+const COMPRESSION_LEVEL: i32 = 3;
+// a full snapshot is written every KEYFRAME_INTERVAL ticks, the others are deltas
+const KEYFRAME_INTERVAL: usize = 100;
+
+/// Version of the replay format written by this version of the crate, as `(major, minor)`.
+pub const FORMAT_VERSION: (u16, u16) = (1, 2);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+enum FrameKind {
+    Metadata = 0,
+    Snapshot = 1,
+    Events = 2,
+    Annotations = 3, // since 1.1
+    SnapshotDelta = 4, // since 1.2
+}
+impl FrameKind {
+    fn from_u8(kind: u8) -> Option<Self> {
+        match kind {
+            0 => Some(Self::Metadata),
+            1 => Some(Self::Snapshot),
+            2 => Some(Self::Events),
+            3 => Some(Self::Annotations),
+            4 => Some(Self::SnapshotDelta),
+            _ => None,
+        }
+    }
+}
+
+/// Information about the run a replay was recorded from.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayMetadata {
+    /// Version of ragnarok which recorded the replay.
+    pub crate_version: String,
+    /// Time at which the recording started, in seconds since the unix epoch.
+    pub recorded_at: u64,
+    /// Side length of the (square) world.
+    pub world_size: usize,
+}
+impl ReplayMetadata {
+    /// Metadata of a replay of a world of the given size, recorded now by this version of the crate.
+    pub fn new(world_size: usize) -> Self {
+        let recorded_at = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        Self { crate_version: env!("CARGO_PKG_VERSION").to_string(), recorded_at, world_size }
+    }
+}
+
+/// A recorded run: one snapshot per tick plus every event received by the robot.
+#[derive(Clone)]
+pub struct Replay {
+    pub metadata: ReplayMetadata,
+    /// Snapshots in tick order.
+    pub snapshots: Vec<WorldSnapshot>,
+    /// Events in the order in which they were received.
+    pub events: Vec<JournalEntry>,
+    /// Markers and camera paths added to the replay after it was recorded.
+    pub annotations: ReplayAnnotations,
+}
+
+/// User annotations of a replay, which make up a guided tour of the run.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ReplayAnnotations {
+    #[serde(default)]
+    pub markers: Vec<Marker>,
+    #[serde(default)]
+    pub camera_bookmarks: Vec<CameraBookmark>,
+    #[serde(default)]
+    pub camera_paths: Vec<CameraPath>,
+}
+impl ReplayAnnotations {
+    pub fn is_empty(&self) -> bool {
+        self.markers.is_empty() && self.camera_bookmarks.is_empty() && self.camera_paths.is_empty()
+    }
+}
+
+/// A labelled tile, shown from the given tick onwards.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Marker {
+    pub tick: usize,
+    /// Position of the tile as `(row, col)`.
+    pub tile: (u32, u32),
+    pub label: String,
+}
+
+/// A named camera pose at a given tick.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CameraBookmark {
+    pub name: String,
+    pub tick: usize,
+    pub pose: CameraPose,
+}
+
+/// A camera position and (normalized) viewing direction, in the 3D space of the GUI, where a tile
+/// at `(row, col)` spans `row..row+1` on the x axis and `col..col+1` on the z axis.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct CameraPose {
+    pub position: [f32; 3],
+    pub direction: [f32; 3],
+}
+
+/// A recorded camera movement, played back along with the replay.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CameraPath {
+    pub name: String,
+    /// Keyframes in time order.
+    pub keyframes: Vec<CameraKeyframe>,
+}
+
+impl CameraPath {
+    /// Duration of the path in seconds.
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map(|k| k.time).unwrap_or(0.0)
+    }
+
+    /// Camera pose at `time` seconds since the start of the path (interpolated between keyframes),
+    /// along with the tick of the replay shown at that point. `None` past the end of the path.
+    pub fn sample(&self, time: f32) -> Option<(CameraPose, usize)> {
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        if next >= self.keyframes.len() {
+            return None;
+        }
+        Some(match next {
+            0 => (self.keyframes[0].pose, self.keyframes[0].tick),
+            _ => {
+                let (a, b) = (&self.keyframes[next - 1], &self.keyframes[next]);
+                let t = (time - a.time) / (b.time - a.time).max(f32::EPSILON);
+                (a.pose.lerp(&b.pose, t), a.tick)
+            }
+        })
+    }
+}
+
+impl CameraPose {
+    fn lerp(&self, other: &CameraPose, t: f32) -> CameraPose {
+        let lerp = |a: [f32; 3], b: [f32; 3]| [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t);
+        CameraPose { position: lerp(self.position, other.position), direction: lerp(self.direction, other.direction) }
+    }
+}
+
+/// A sample of a camera path.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct CameraKeyframe {
+    /// Seconds since the start of the path.
+    pub time: f32,
+    /// Tick of the replay shown at this point of the path.
+    pub tick: usize,
+    pub pose: CameraPose,
+}
+
+/// Error returned when saving or loading a replay.
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// The file is not a replay.
+    NotAReplay,
+    /// The file was written with an incompatible major version of the format.
+    UnsupportedVersion { found: (u16, u16), supported: (u16, u16) },
+    /// The file does not start with the metadata frame.
+    MissingMetadata,
+    /// A delta frame does not follow a snapshot of the same world.
+    MissingKeyframe,
+}
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "i/o error: {e}"),
+            Self::Json(e) => write!(f, "malformed frame: {e}"),
+            Self::NotAReplay => write!(f, "not a replay file"),
+            Self::UnsupportedVersion { found, supported } =>
+                write!(f, "unsupported replay format {}.{} (this version of ragnarok reads {}.x)", found.0, found.1, supported.0),
+            Self::MissingMetadata => write!(f, "the replay has no metadata"),
+            Self::MissingKeyframe => write!(f, "a tick is stored as a delta of a missing snapshot"),
+        }
+    }
+}
+impl std::error::Error for ReplayError {}
+impl From<io::Error> for ReplayError {
+    fn from(e: io::Error) -> Self { Self::Io(e) }
+}
+impl From<serde_json::Error> for ReplayError {
+    fn from(e: serde_json::Error) -> Self { Self::Json(e) }
+}
+
+/// Writes a replay to `path`, overwriting it if it exists. The file is zstd compressed, and most
+/// ticks are stored as the tiles which changed since the previous one, so that long runs stay small.
+pub fn save(path: impl AsRef<Path>, replay: &Replay) -> Result<(), ReplayError> {
+    let mut writer = ReplayWriter::create(path, &replay.metadata)?;
+    for snapshot in &replay.snapshots {
+        let events: Vec<_> = replay.events.iter().filter(|e| e.tick == snapshot.tick).cloned().collect();
+        writer.write_tick(snapshot, &events)?;
+    }
+    if !replay.annotations.is_empty() {
+        write_frame(&mut writer.writer, FrameKind::Annotations, &replay.annotations)?;
+    }
+    writer.flush()
+}
+
+/// Stores the annotations in the replay at `path`, replacing the ones it already contains. The
+/// rest of the file is left untouched.
+pub fn append_annotations(path: impl AsRef<Path>, annotations: &ReplayAnnotations) -> Result<(), ReplayError> {
+    read_header(&mut BufReader::new(File::open(&path)?))?;
+
+    let mut writer = BufWriter::new(OpenOptions::new().append(true).open(path)?);
+    write_frame(&mut writer, FrameKind::Annotations, annotations)?;
+    Ok(writer.flush()?)
+}
+
+/// Reads a replay from `path`. A truncated last frame (e.g. because the recording process was
+/// killed) is ignored rather than reported as an error. Replays written by older versions of the
+/// crate can be read, and so can those written by newer ones as long as the major version of their
+/// `FORMAT_VERSION` is the same: whatever the newer minor versions added is ignored.
+pub fn load(path: impl AsRef<Path>) -> Result<Replay, ReplayError> {
+    let mut snapshots = vec![];
+    let mut replay = load_streaming(path, |snapshot| snapshots.push(snapshot))?;
+    replay.snapshots = snapshots;
+    Ok(replay)
+}
+
+// whether the file starts with the magic number of replays, whatever its version
+pub(crate) fn is_replay(path: impl AsRef<Path>) -> bool {
+    let mut magic = [0u8; 8];
+    File::open(path).and_then(|mut file| file.read_exact(&mut magic)).is_ok() && &magic == MAGIC
+}
+
+// like load, but hands every snapshot to on_snapshot as soon as it is read rather than storing it
+// in the returned Replay (whose snapshots are left empty), so that the whole replay never needs to
+// be in memory at once
+pub(crate) fn load_streaming(path: impl AsRef<Path>, mut on_snapshot: impl FnMut(WorldSnapshot)) -> Result<Replay, ReplayError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    read_header(&mut reader)?;
+
+    let metadata = match read_frame(&mut reader)? {
+        Some((Some(FrameKind::Metadata), payload)) => decode(&payload)?,
+        _ => return Err(ReplayError::MissingMetadata),
+    };
+    let mut replay = Replay { metadata, snapshots: vec![], events: vec![], annotations: ReplayAnnotations::default() };
+    let mut last_snapshot: Option<WorldSnapshot> = None; // which the next delta applies to
+    while let Some((kind, payload)) = read_frame(&mut reader)? {
+        match kind {
+            Some(FrameKind::Snapshot) => {
+                let snapshot: WorldSnapshot = decode(&payload)?;
+                last_snapshot = Some(snapshot.clone());
+                on_snapshot(snapshot);
+            }
+            Some(FrameKind::SnapshotDelta) => {
+                let delta: SnapshotDelta = decode(&payload)?;
+                let snapshot = last_snapshot.as_mut().ok_or(ReplayError::MissingKeyframe)?;
+                delta.apply(snapshot)?;
+                on_snapshot(snapshot.clone());
+            }
+            Some(FrameKind::Events) => replay.events.extend(decode::<Vec<JournalEntry>>(&payload)?),
+            Some(FrameKind::Annotations) => replay.annotations = decode(&payload)?,
+            Some(FrameKind::Metadata) | None => {} // only the first metadata frame counts; unknown kinds are from newer minor versions
+        }
+    }
+    Ok(replay)
+}
+
+// SnapshotDelta is a snapshot stored as the tiles which changed since the snapshot of the previous
+// tick; the rest of the snapshot is small, so it is stored whole.
+#[derive(Serialize, Deserialize)]
+struct SnapshotDelta {
+    tick: usize,
+    tiles: Vec<((u32, u32), Option<Tile>)>, // (row, col), tile
+    robot_position: (u32, u32),
+    energy: usize,
+    backpack: Vec<(Content, usize)>,
+    env_cond: EnvironmentalConditions,
+}
+impl SnapshotDelta {
+    // None if the worlds differ in size
+    fn between(previous: &WorldSnapshot, snapshot: &WorldSnapshot) -> Option<Self> {
+        if previous.world.len() != snapshot.world.len() {
+            return None;
+        }
+        let mut tiles = vec![];
+        for (row, (previous_row, new_row)) in previous.world.iter().zip(snapshot.world.iter()).enumerate() {
+            if previous_row.len() != new_row.len() {
+                return None;
+            }
+            for (col, (previous_tile, new_tile)) in previous_row.iter().zip(new_row.iter()).enumerate() {
+                if previous_tile != new_tile {
+                    tiles.push(((row as u32, col as u32), new_tile.clone()));
+                }
+            }
+        }
+        Some(Self {
+            tick: snapshot.tick,
+            tiles,
+            robot_position: snapshot.robot_position,
+            energy: snapshot.energy,
+            backpack: snapshot.backpack.clone(),
+            env_cond: snapshot.env_cond.clone(),
+        })
+    }
+
+    fn apply(self, snapshot: &mut WorldSnapshot) -> Result<(), ReplayError> {
+        for ((row, col), tile) in self.tiles {
+            let slot = snapshot.world.get_mut(row as usize)
+                .and_then(|tiles| tiles.get_mut(col as usize))
+                .ok_or(ReplayError::MissingKeyframe)?;
+            *slot = tile;
+        }
+        snapshot.tick = self.tick;
+        snapshot.robot_position = self.robot_position;
+        snapshot.energy = self.energy;
+        snapshot.backpack = self.backpack;
+        snapshot.env_cond = self.env_cond;
+        Ok(())
+    }
+}
+
+// ReplayWriter writes a replay frame by frame, so that a run can be recorded while it happens
+// without keeping all of its snapshots in memory (only the last one, which the next delta is
+// computed from).
+pub(crate) struct ReplayWriter {
+    writer: BufWriter<File>,
+    last_snapshot: Option<WorldSnapshot>,
+    ticks_since_keyframe: usize,
+}
+impl ReplayWriter {
+    pub fn create(path: impl AsRef<Path>, metadata: &ReplayMetadata) -> Result<Self, ReplayError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.0.to_le_bytes())?;
+        writer.write_all(&FORMAT_VERSION.1.to_le_bytes())?;
+        let mut replay_writer = Self { writer, last_snapshot: None, ticks_since_keyframe: 0 };
+        replay_writer.write_frame(FrameKind::Metadata, metadata)?;
+        Ok(replay_writer)
+    }
+
+    pub fn write_tick(&mut self, snapshot: &WorldSnapshot, events: &[JournalEntry]) -> Result<(), ReplayError> {
+        let delta = self.last_snapshot.as_ref()
+            .filter(|_| self.ticks_since_keyframe + 1 < KEYFRAME_INTERVAL)
+            .and_then(|last_snapshot| SnapshotDelta::between(last_snapshot, snapshot));
+        match delta {
+            Some(delta) => {
+                self.write_frame(FrameKind::SnapshotDelta, &delta)?;
+                self.ticks_since_keyframe += 1;
+            }
+            None => {
+                self.write_frame(FrameKind::Snapshot, snapshot)?;
+                self.ticks_since_keyframe = 0;
+            }
+        }
+        self.last_snapshot = Some(snapshot.clone());
+        if !events.is_empty() {
+            self.write_frame(FrameKind::Events, &events)?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), ReplayError> {
+        Ok(self.writer.flush()?)
+    }
+
+    fn write_frame(&mut self, kind: FrameKind, payload: &impl Serialize) -> Result<(), ReplayError> {
+        write_frame(&mut self.writer, kind, payload)
+    }
+}
+
+fn write_frame(writer: &mut impl Write, kind: FrameKind, payload: &impl Serialize) -> Result<(), ReplayError> {
+    let json = serde_json::to_vec(payload)?;
+    let compressed = zstd::encode_all(json.as_slice(), COMPRESSION_LEVEL)?;
+    let len = u32::try_from(compressed.len()).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+    writer.write_all(&[kind as u8])?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(&compressed)?;
+    Ok(())
+}
+
+// checks the magic number and the format version
+fn read_header(reader: &mut impl Read) -> Result<(), ReplayError> {
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic).map_err(|_| ReplayError::NotAReplay)?;
+    if &magic != MAGIC {
+        return Err(ReplayError::NotAReplay);
+    }
+    let found = (read_u16(reader)?, read_u16(reader)?);
+    if found.0 != FORMAT_VERSION.0 {
+        return Err(ReplayError::UnsupportedVersion { found, supported: FORMAT_VERSION });
+    }
+    Ok(())
+}
+
+// returns None at the end of the file, or if the last frame is truncated
+fn read_frame(reader: &mut impl Read) -> Result<Option<(Option<FrameKind>, Vec<u8>)>, ReplayError> {
+    let mut header = [0u8; 5];
+    match reader.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as usize;
+    let mut compressed = vec![0u8; len];
+    match reader.read_exact(&mut compressed) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    Ok(Some((FrameKind::from_u8(header[0]), zstd::decode_all(compressed.as_slice())?)))
+}
+
+fn decode<T: DeserializeOwned>(payload: &[u8]) -> Result<T, ReplayError> {
+    Ok(serde_json::from_slice(payload)?)
+}
+
+fn read_u16(reader: &mut impl Read) -> Result<u16, ReplayError> {
+    let mut bytes = [0u8; 2];
+    reader.read_exact(&mut bytes).map_err(|_| ReplayError::NotAReplay)?;
+    Ok(u16::from_le_bytes(bytes))
+}
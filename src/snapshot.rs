@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use robotics_lib::world::environmental_conditions::EnvironmentalConditions;
+use robotics_lib::world::tile::{Content, Tile};
+use serde::{Deserialize, Serialize};
+use nalgebra_glm::UVec2;
+use crate::gui_runner::PartialWorld;
+use crate::grid;
+
+// WorldSnapshot is the public, serializable counterpart of PartialWorld: the state of the world as
+// known to the (first) robot at the end of a tick. Only the tick, the map, the state of the robot and
+// the environmental conditions are kept; every other field of PartialWorld only matters to the
+// threads of the GuiRunner (or isn't recorded in replays), and is left empty by to_partial_world.
+
+/// The world as known to the robot at the end of a tick, along with the state of the robot itself.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WorldSnapshot {
+    /// Tick at the end of which the snapshot was taken (the initialization tick is tick 0).
+    pub tick: usize,
+    /// The robot's map, indexed as `world[row][col]`; `None` for undiscovered tiles.
+    pub world: Vec<Vec<Option<Tile>>>,
+    /// Position of the robot as `(row, col)`.
+    pub robot_position: (u32, u32),
+    /// Energy level of the robot.
+    pub energy: usize,
+    /// Contents of the backpack and their quantities.
+    pub backpack: Vec<(Content, usize)>,
+    /// Weather and time of day.
+    pub env_cond: EnvironmentalConditions,
+}
+impl WorldSnapshot {
+    pub(crate) fn from_partial_world(world: &PartialWorld) -> Self {
+        Self {
+            tick: world.tick,
+            world: world.world.clone(),
+            robot_position: (world.robot_position.x, world.robot_position.y),
+            energy: world.energy,
+            backpack: world.backpack.iter().map(|(content, n)| (content.clone(), *n)).collect(),
+            env_cond: world.env_cond.clone(),
+        }
+    }
+
+    /// Side length of the (square) world.
+    pub fn size(&self) -> usize {
+        self.world.len()
+    }
+
+    /// The tile at `(row, col)`, if it is in the world and the robot discovered it.
+    pub fn tile_at(&self, tile: (usize, usize)) -> Option<&Tile> {
+        self.world.get(tile.0)?.get(tile.1)?.as_ref()
+    }
+
+    /// The tile the robot is on.
+    pub fn robot_tile(&self) -> Option<&Tile> {
+        let (row, col) = self.robot_position;
+        self.tile_at((row as usize, col as usize))
+    }
+
+    /// The tiles up, down, left and right of `(row, col)` which are in the world, discovered or not.
+    pub fn neighbors(&self, tile: (usize, usize)) -> impl Iterator<Item = (usize, usize)> {
+        grid::neighbors(tile, self.size())
+    }
+
+    /// The discovered tiles, with their position.
+    pub fn discovered_tiles(&self) -> impl Iterator<Item = ((usize, usize), &Tile)> {
+        self.world.iter().enumerate().flat_map(|(row, tiles)| {
+            tiles.iter().enumerate().filter_map(move |(col, tile)| Some(((row, col), tile.as_ref()?)))
+        })
+    }
+
+    /// The smallest rectangle containing every discovered tile, as its `(row, col)` corners (both
+    /// included); `None` if no tile was discovered.
+    pub fn discovered_bounds(&self) -> Option<((usize, usize), (usize, usize))> {
+        self.discovered_tiles().fold(None, |bounds, ((row, col), _)| match bounds {
+            None => Some(((row, col), (row, col))),
+            Some(((min_row, min_col), (max_row, max_col))) => {
+                Some(((min_row.min(row), min_col.min(col)), (max_row.max(row), max_col.max(col))))
+            }
+        })
+    }
+
+    /// Fraction of the tiles of the world which were discovered, from 0 to 1.
+    pub fn discovered_fraction(&self) -> f32 {
+        self.discovered_tiles().count() as f32 / (self.size() * self.size()).max(1) as f32
+    }
+
+    /// Number of moves (in four directions) from the robot to `(row, col)`, ignoring obstacles.
+    pub fn distance_from_robot(&self, tile: (usize, usize)) -> usize {
+        let (row, col) = self.robot_position;
+        grid::manhattan_distance((row as usize, col as usize), tile)
+    }
+
+    /// The discovered tiles satisfying the predicate, nearest to the robot first.
+    pub fn nearest_tiles(&self, predicate: impl Fn(&Tile) -> bool) -> Vec<((usize, usize), &Tile)> {
+        let mut tiles: Vec<_> = self.discovered_tiles().filter(|(_, tile)| predicate(tile)).collect();
+        tiles.sort_by_key(|(position, _)| self.distance_from_robot(*position));
+        tiles
+    }
+
+    // tiles_to_refresh is left empty: the worker thread (or whoever feeds the GUI) fills it
+    pub(crate) fn to_partial_world(&self) -> PartialWorld {
+        PartialWorld {
+            world: self.world.clone(),
+            tiles_to_refresh: Default::default(),
+            distant_changes: vec![],
+            touched_tiles: None,
+            stale_tiles: vec![],
+            tick: self.tick,
+            elapsed: Duration::ZERO, // not recorded in replays
+            robot_position: UVec2::new(self.robot_position.0, self.robot_position.1),
+            energy: self.energy,
+            backpack: self.backpack.iter().cloned().collect::<HashMap<_, _>>(),
+            backpack_size: None,
+            env_cond: self.env_cond.clone(),
+            other_robots: vec![],
+            other_maps: vec![],
+            changed_tiles: vec![],
+            content_changes: vec![],
+        }
+    }
+}
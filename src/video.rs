@@ -0,0 +1,159 @@
+//! Rendering replays to a sequence of frames without opening a window.
+//!
+//! `render_replay` writes one PNG image per frame (`frame_000000.png`, `frame_000001.png`, ...) to
+//! a directory, which can then be turned into a video with any encoder, e.g.
+//! `ffmpeg -framerate 30 -i frame_%06d.png -pix_fmt yuv420p replay.mp4`.
+//!
+//! No display is needed: on Linux frames are rendered in software through OSMesa, so `libOSMesa`
+//! must be installed on the machine.
+//!
+//! ```no_run
+//! let options = ragnarok::video::VideoOptions {
+//!     resolution: (1920, 1080),
+//!     speed: 1.0,
+//!     camera: ragnarok::video::VideoCamera::Path("overview".to_string()),
+//!     ..Default::default()
+//! };
+//! ragnarok::video::render_replay("run.rgnk", "frames", &options).unwrap();
+//! ```
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::Path;
+use nalgebra_glm::vec3;
+use crate::gui_runner::OffscreenRenderer;
+use crate::replay::{self, ReplayError};
+
+/// Settings of `render_replay`.
+#[derive(Clone, Debug)]
+pub struct VideoOptions {
+    /// Width and height of the frames, in pixels. Defaults to 1280x720.
+    pub resolution: (u32, u32),
+    /// Frames rendered per second of video. Defaults to 30.
+    pub frames_per_second: f32,
+    /// Playback speed: ticks per second of video when following the robot, multiplier of the
+    /// recorded speed when following a camera path. Defaults to 4.
+    pub speed: f32,
+    pub camera: VideoCamera,
+}
+impl Default for VideoOptions {
+    fn default() -> Self {
+        Self { resolution: (1280, 720), frames_per_second: 30.0, speed: 4.0, camera: VideoCamera::default() }
+    }
+}
+
+/// How the camera moves in a rendered replay.
+#[derive(Clone, Debug)]
+pub enum VideoCamera {
+    /// Looks at the robot from the given distance, from the default direction of the GUI. Every
+    /// tick of the replay is shown, at `VideoOptions::speed` ticks per second.
+    FollowRobot { distance: f32 },
+    /// Follows the camera path with the given name, stored in the annotations of the replay. The
+    /// ticks shown are the ones which were shown while the path was recorded, and the speed is a
+    /// multiplier of the recorded one (so the default 4 plays the path 4 times as fast).
+    Path(String),
+}
+impl Default for VideoCamera {
+    fn default() -> Self { Self::FollowRobot { distance: 30.0 } }
+}
+
+/// Error returned by `render_replay`.
+#[derive(Debug)]
+pub enum VideoError {
+    /// The replay could not be loaded.
+    Replay(ReplayError),
+    /// The frames could not be written.
+    Io(io::Error),
+    Png(png::EncodingError),
+    /// No OpenGL context could be created for offscreen rendering.
+    Context(String),
+    /// The replay has no camera path with the given name.
+    UnknownCameraPath(String),
+}
+impl fmt::Display for VideoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Replay(e) => write!(f, "could not load the replay: {e}"),
+            Self::Io(e) => write!(f, "i/o error: {e}"),
+            Self::Png(e) => write!(f, "could not encode a frame: {e}"),
+            Self::Context(e) => write!(f, "{e}"),
+            Self::UnknownCameraPath(name) => write!(f, "the replay has no camera path named \"{name}\""),
+        }
+    }
+}
+impl std::error::Error for VideoError {}
+impl From<ReplayError> for VideoError {
+    fn from(e: ReplayError) -> Self { Self::Replay(e) }
+}
+impl From<io::Error> for VideoError {
+    fn from(e: io::Error) -> Self { Self::Io(e) }
+}
+impl From<png::EncodingError> for VideoError {
+    fn from(e: png::EncodingError) -> Self { Self::Png(e) }
+}
+
+/// Renders the replay at `replay_path` to a sequence of PNG frames in `output_dir` (created if
+/// missing), as described in the module documentation. Returns the number of frames written.
+pub fn render_replay(replay_path: impl AsRef<Path>, output_dir: impl AsRef<Path>, options: &VideoOptions) -> Result<usize, VideoError> {
+    let replay = replay::load(replay_path)?;
+    let (camera_path, follow_distance) = match &options.camera {
+        VideoCamera::FollowRobot { distance } => (None, *distance),
+        VideoCamera::Path(name) => {
+            let path = replay.annotations.camera_paths.iter()
+                .find(|path| &path.name == name)
+                .ok_or_else(|| VideoError::UnknownCameraPath(name.clone()))?;
+            (Some(path), 0.0)
+        }
+    };
+    let Some(first_tick) = replay.snapshots.first().map(|s| s.tick) else { return Ok(0) };
+    let last_tick = replay.snapshots.last().map(|s| s.tick).unwrap_or(first_tick);
+
+    let output_dir = output_dir.as_ref();
+    fs::create_dir_all(output_dir)?;
+    let mut renderer = OffscreenRenderer::new(options.resolution).map_err(VideoError::Context)?;
+
+    let mut shown_snapshot = None;
+    let mut frame = 0;
+    loop {
+        let time = frame as f32 / options.frames_per_second;
+        let (tick, pose) = match camera_path {
+            None => {
+                let tick = first_tick + (time * options.speed) as usize;
+                if tick > last_tick {
+                    break;
+                }
+                (tick, None)
+            }
+            Some(path) => match path.sample(time * options.speed) {
+                Some((pose, tick)) => (tick, Some(pose)),
+                None => break,
+            },
+        };
+
+        // show the last snapshot taken at or before the tick (ticks may be missing from the replay)
+        let snapshot = replay.snapshots.partition_point(|s| s.tick <= tick).saturating_sub(1);
+        if shown_snapshot != Some(snapshot) {
+            renderer.set_world(replay.snapshots[snapshot].to_partial_world());
+            shown_snapshot = Some(snapshot);
+        }
+
+        let (cam_pos, cam_dir) = match pose {
+            Some(pose) => (vec3(pose.position[0], pose.position[1], pose.position[2]),
+                           vec3(pose.direction[0], pose.direction[1], pose.direction[2]).normalize()),
+            None => renderer.robot_camera(follow_distance).unwrap(), // a world was set above
+        };
+        let pixels = renderer.render(cam_pos, cam_dir, time);
+        write_png(&output_dir.join(format!("frame_{frame:06}.png")), options.resolution, &pixels)?;
+        frame += 1;
+    }
+    Ok(frame)
+}
+
+fn write_png(path: &Path, (width, height): (u32, u32), rgba: &[u8]) -> Result<(), VideoError> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(rgba)?;
+    Ok(())
+}